@@ -4,6 +4,7 @@ set -ex
 
 cargo doc --no-deps -p askama
 cargo doc --no-deps -p juniper
+cargo doc --no-deps -p tera
 cargo doc --no-deps -p tungstenite -p tokio-tungstenite
 cargo doc --no-deps -p tokio-rustls
 
@@ -16,6 +17,7 @@ cargo doc --no-deps -p tsukuyomi-askama
 cargo doc --no-deps -p tsukuyomi-cors
 cargo doc --no-deps -p tsukuyomi-juniper
 cargo doc --no-deps -p tsukuyomi-session --all-features
+cargo doc --no-deps -p tsukuyomi-tera
 cargo doc --no-deps -p tsukuyomi-tungstenite
 
 rm -f target/doc/.lock
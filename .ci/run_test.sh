@@ -5,21 +5,34 @@ set -ex
 rustc --version
 cargo --version
 
+# The feature 'tracing' of tsukuyomi (and the example using it) requires
+# a newer compiler than the minimum supported version.
+if [[ "$(rustc --version)" == "rustc 1.31."* ]]; then
+    TSUKUYOMI_FEATURES="full debug-extractor"
+    WORKSPACE_EXCLUDES="--exclude example-tracing"
+else
+    TSUKUYOMI_FEATURES="full debug-extractor tracing"
+    WORKSPACE_EXCLUDES=""
+fi
+
 if cargo fmt --version >/dev/null 2>&1; then
     cargo fmt -- --check
 fi
 
 if cargo clippy --version >/dev/null 2>&1; then
-    cargo clippy --all --all-targets
+    cargo clippy --all $WORKSPACE_EXCLUDES --all-targets
 
-    cargo clippy -p tsukuyomi --all-features --all-targets
+    cargo clippy -p tsukuyomi --features "$TSUKUYOMI_FEATURES" --all-targets
     cargo clippy -p tsukuyomi-session --all-features --all-targets
+    cargo clippy -p tsukuyomi-juniper --all-features --all-targets
 fi
 
-cargo test --all
+cargo test --all $WORKSPACE_EXCLUDES
 
-cargo test -p tsukuyomi --all-features
+cargo test -p tsukuyomi --features "$TSUKUYOMI_FEATURES"
 cargo test -p tsukuyomi --no-default-features
 
 cargo test -p tsukuyomi-session --all-features
 cargo test -p tsukuyomi-session --no-default-features
+
+cargo test -p tsukuyomi-juniper --features subscriptions
@@ -0,0 +1,40 @@
+use {
+    std::{cell::RefCell, net::SocketAddr, rc::Rc},
+    tsukuyomi::{
+        app::LocalApp,
+        config::prelude::*, //
+    },
+    tsukuyomi_server::Server,
+};
+
+fn main() -> tsukuyomi_server::Result<()> {
+    // a non-`Send` state shared among the handlers.
+    let counter = Rc::new(RefCell::new(0));
+
+    // `LocalApp` accepts the handlers which are not `Send`.
+    let app = LocalApp::create(chain![
+        path!("/") //
+            .to(endpoint::get().call({
+                let counter = counter.clone();
+                move || {
+                    let mut counter = counter.borrow_mut();
+                    *counter += 1;
+                    format!("count = {}\n", *counter)
+                }
+            })),
+        path!("/reset") //
+            .to(endpoint::post().call(move || {
+                *counter.borrow_mut() = 0;
+                "reset\n"
+            })),
+    ])?;
+
+    let addr: SocketAddr = "127.0.0.1:4000".parse()?;
+    println!("Listening on http://{}", addr);
+
+    // `LocalApp` must be driven by the single-threaded runtime.
+    Server::new(app) //
+        .bind(addr)
+        .current_thread()
+        .run()
+}
@@ -4,42 +4,48 @@ use {
         sqlite::SqliteConnection,
     },
     failure::Fallible,
-    futures::Future,
-    tsukuyomi::{
-        extractor::Extractor,
-        future::{Futures01CompatExt, TryFuture},
+    std::time::Duration,
+    tsukuyomi::extractor::{
+        pool::{Acquire, Pool as PoolTrait},
+        Extractor,
     },
 };
 
 pub type Conn = PooledConnection<ConnectionManager<SqliteConnection>>;
 
-pub fn extractor<T>(
-    url: T,
-) -> Fallible<
-    impl Extractor<
-        Output = (Conn,), //
-        Error = tsukuyomi::Error,
-        Extract = impl TryFuture<Ok = (Conn,), Error = tsukuyomi::Error> + Send + 'static,
-    >,
->
-where
-    T: Into<String>,
-{
-    let manager = ConnectionManager::<SqliteConnection>::new(url);
-    let pool = Pool::builder().max_size(15).build(manager)?;
+/// The connection pool registered as a shared state.
+pub struct DbPool(Pool<ConnectionManager<SqliteConnection>>);
 
-    Ok(tsukuyomi::extractor::extract(move || {
-        let pool = pool.clone();
-        tsukuyomi_server::rt::blocking(move || pool.get()) //
-            .then(|result| {
-                result
-                    .map_err(tsukuyomi::error::internal_server_error) // <-- BlockingError
-                    .and_then(|result| {
-                        result
-                            .map(|conn| (conn,))
-                            .map_err(tsukuyomi::error::internal_server_error) // <-- r2d2::Error
-                    })
-            })
-            .compat01()
-    }))
+impl DbPool {
+    pub fn new<T>(url: T) -> Fallible<Self>
+    where
+        T: Into<String>,
+    {
+        let manager = ConnectionManager::<SqliteConnection>::new(url);
+        let pool = Pool::builder().max_size(15).build(manager)?;
+        Ok(DbPool(pool))
+    }
+}
+
+impl PoolTrait for DbPool {
+    type Resource = Conn;
+
+    fn get_timeout(&self, timeout: Duration) -> tsukuyomi::Result<Option<Self::Resource>> {
+        // r2d2 reports the timeout of acquisition as an error.
+        Ok(self.0.get_timeout(timeout).ok())
+    }
+}
+
+/// Creates an `Extractor` that acquires a connection from the pool.
+///
+/// If no connection becomes available within `timeout`, the request is
+/// rejected with `503 Service Unavailable` instead of waiting indefinitely.
+pub fn extractor(
+    timeout: Duration,
+) -> impl Extractor<
+    Output = (Conn,), //
+    Error = tsukuyomi::Error,
+    Extract = Acquire<DbPool>,
+> {
+    tsukuyomi::extractor::pool::acquire::<DbPool>(timeout)
 }
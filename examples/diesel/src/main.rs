@@ -14,7 +14,7 @@ use {
     },
     dotenv::dotenv,
     futures::Future,
-    std::{env, sync::Arc},
+    std::{env, sync::Arc, time::Duration},
     tsukuyomi::{
         config::prelude::*, //
         error::Error,
@@ -29,9 +29,11 @@ fn main() -> tsukuyomi_server::Result<()> {
     dotenv()?;
 
     let database_url = env::var("DATABASE_URL")?;
-    let db_conn = crate::conn::extractor(database_url).map(Arc::new)?;
+    let db_pool = crate::conn::DbPool::new(database_url)?;
+    let db_conn = Arc::new(crate::conn::extractor(Duration::from_secs(5)));
 
-    let app = App::create({
+    let app = App::create(chain![
+        tsukuyomi::config::state(db_pool),
         mount("/api/v1/posts").with(chain![
             path!("/") //
                 .to(chain![
@@ -96,8 +98,8 @@ fn main() -> tsukuyomi_server::Result<()> {
                             .map_err(tsukuyomi::error::internal_server_error)
                     })
                     .map(|post_opt| post_opt.map(tsukuyomi::output::json))))
-        ])
-    })?;
+        ]),
+    ])?;
 
     Server::new(app).run()
 }
@@ -30,6 +30,10 @@ fn main() -> tsukuyomi_server::Result<()> {
         path!("/") //
             .to(endpoint::get() //
                 .reply(tsukuyomi_juniper::graphiql_source("/graphql"))),
+        // renders the source of GraphQL Playground.
+        path!("/playground") //
+            .to(endpoint::get() //
+                .reply(tsukuyomi_juniper::playground_source("/graphql", None))),
         // a route which handles GraphQL requests over HTTP.
         path!("/graphql")
             .to(endpoint::allow_only("GET, POST")?
@@ -8,18 +8,18 @@ use {
 };
 
 fn main() -> tsukuyomi_server::Result<()> {
-    let tls_acceptor = build_tls_acceptor()?;
+    let tls_config = build_tls_config()?;
 
     App::create(
         path!("/") //
             .to(endpoint::reply("Hello, Tsukuyomi.\n")),
     ) //
     .map(Server::new)?
-    .acceptor(tls_acceptor)
+    .acceptor(tls_config)
     .run()
 }
 
-fn build_tls_acceptor() -> failure::Fallible<tokio_rustls::TlsAcceptor> {
+fn build_tls_config() -> failure::Fallible<Arc<rustls::ServerConfig>> {
     const CERTS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/private/cert.pem");
     const PRIV_KEY_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/private/key.pem");
 
@@ -34,7 +34,7 @@ fn build_tls_acceptor() -> failure::Fallible<tokio_rustls::TlsAcceptor> {
 
     config.set_protocols(&["h2".into(), "http/1.1".into()]);
 
-    Ok(Arc::new(config).into())
+    Ok(Arc::new(config))
 }
 
 fn load_certs(path: impl AsRef<Path>) -> failure::Fallible<Vec<rustls::Certificate>> {
@@ -0,0 +1,33 @@
+use {
+    std::net::SocketAddr,
+    tsukuyomi::{
+        config::prelude::*, //
+        App,
+    },
+    tsukuyomi_server::Server,
+};
+
+fn main() -> tsukuyomi_server::Result<()> {
+    // install the subscriber that prints the spans and events to stdout.
+    tracing::subscriber::set_global_default(tracing_subscriber::FmtSubscriber::new())?;
+
+    let app = App::create(
+        chain![
+            path!("/") //
+                .to(endpoint::get().reply("Hello, world!\n")),
+            path!("/:name") //
+                .to(endpoint::get().call(|name: String| {
+                    // this event is emitted within the span of the current request.
+                    tracing::info!(name = %name, "greeting");
+                    format!("Hello, {}!\n", name)
+                })),
+        ]
+        .modify(tsukuyomi::modifiers::trace()),
+    )?;
+
+    let addr: SocketAddr = "127.0.0.1:4000".parse()?;
+    println!("Listening on http://{}", addr);
+    Server::new(app) //
+        .bind(addr)
+        .run()
+}
@@ -7,7 +7,7 @@ use {
         App,
     },
     tsukuyomi_server::Server,
-    tsukuyomi_tungstenite::{Message, Ws},
+    tsukuyomi_tungstenite::{CloseCode, Message, Ws},
 };
 
 const STATIC_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/static");
@@ -15,19 +15,43 @@ const STATIC_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/static");
 fn main() -> tsukuyomi_server::Result<()> {
     App::create(chain![
         path!("/ws") //
-            .to(endpoint::get().reply(Ws::new(|stream| {
+            .to(endpoint::get().reply(Ws::new(|stream, _| {
                 let (tx, rx) = stream.split();
                 rx.filter_map(|m| {
                     println!("Message from client: {:?}", m);
                     match m {
                         Message::Ping(p) => Some(Message::Pong(p)),
                         Message::Pong(_) => None,
+                        Message::Close(Some(frame)) => {
+                            println!(
+                                "Closed by client: code={}, reason={:?}",
+                                frame.code, frame.reason
+                            );
+                            None
+                        }
+                        Message::Close(None) => {
+                            println!("Closed by client without status code");
+                            None
+                        }
                         _ => Some(m),
                     }
                 }) //
                 .forward(tx)
                 .then(|_| Ok(()))
             }))),
+        path!("/ws/bye") //
+            .to(endpoint::get().reply(Ws::new(|stream, _| {
+                stream
+                    .send(Message::text("bye"))
+                    .and_then(|stream| stream.close_with(CloseCode::Normal, "bye"))
+                    .then(|result| {
+                        match result {
+                            Ok(frame) => println!("Close acknowledged by client: {:?}", frame),
+                            Err(err) => println!("failed to close the connection: {}", err),
+                        }
+                        Ok(())
+                    })
+            }))),
         path!("/") //
             .to(endpoint::reply(redirect::to("/index.html"))),
         Staticfiles::new(STATIC_PATH)
@@ -27,6 +27,10 @@ use {
 /// An implementor of `Preset` for deriving the implementation of `IntoResponse`
 /// to Askama templates.
 ///
+/// The value of `Content-Type` is guessed from the extension of the template
+/// (e.g. `text/plain` for `.txt`), and `text/html` is used if it is unknown.
+/// If the rendering fails, the error is logged and converted into `500 Internal Server Error`.
+///
 /// # Example
 ///
 /// ```
@@ -73,10 +77,10 @@ where
         .extension()
         .and_then(get_mime_type_str)
         .unwrap_or("text/html; charset=utf-8");
-    let mut response = t
-        .render()
-        .map(Response::new)
-        .map_err(internal_server_error)?;
+    let mut response = t.render().map(Response::new).map_err(|err| {
+        log::error!("failed to render the template: {}", err);
+        internal_server_error(err)
+    })?;
     response
         .headers_mut()
         .insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
@@ -84,6 +88,8 @@ where
 }
 
 /// Creates a `ModifyHandler` that renders the outputs of handlers as Askama template.
+///
+/// The value of `Content-Type` is determined in the same way as `Askama`.
 pub fn renderer() -> Renderer {
     Renderer::default()
 }
@@ -37,6 +37,45 @@ fn test_template_derivation() -> tsukuyomi_server::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_template_content_type_from_extension() -> tsukuyomi_server::Result<()> {
+    #[derive(Template, IntoResponse)]
+    #[template(source = "Hello, {{ name }}.", ext = "txt")]
+    #[response(preset = "tsukuyomi_askama::Askama")]
+    struct Text {
+        name: &'static str,
+    }
+
+    #[derive(Template, IntoResponse)]
+    #[template(source = "<name>{{ name }}</name>", ext = "xml")]
+    #[response(preset = "tsukuyomi_askama::Askama")]
+    struct Xml {
+        name: &'static str,
+    }
+
+    let app = App::create(chain![
+        path!("/text") //
+            .to(endpoint::get() //
+                .call(|| Text { name: "Alice" })),
+        path!("/xml") //
+            .to(endpoint::get() //
+                .call(|| Xml { name: "Alice" })),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/text")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.header("content-type")?, "text/plain");
+    assert_eq!(response.body().to_utf8()?, "Hello, Alice.");
+
+    let response = server.perform("/xml")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.header("content-type")?, "text/xml");
+    assert_eq!(response.body().to_utf8()?, "<name>Alice</name>");
+
+    Ok(())
+}
+
 #[test]
 fn test_template_with_modifier() -> tsukuyomi_server::Result<()> {
     #[derive(Template)]
@@ -60,3 +99,34 @@ fn test_template_with_modifier() -> tsukuyomi_server::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_template_render_error() -> tsukuyomi_server::Result<()> {
+    struct Failing;
+
+    impl std::fmt::Display for Failing {
+        fn fmt(&self, _: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            Err(std::fmt::Error)
+        }
+    }
+
+    #[derive(Template)]
+    #[template(source = "Hello, {{ name }}.", ext = "html")]
+    struct Index {
+        name: Failing,
+    }
+
+    let app = App::create(
+        path!("/") //
+            .to(endpoint::get() //
+                .call(|| Index { name: Failing }))
+            .modify(tsukuyomi_askama::renderer()),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/")?;
+    assert_eq!(response.status(), 500);
+    assert!(!response.body().to_utf8()?.contains("Hello"));
+
+    Ok(())
+}
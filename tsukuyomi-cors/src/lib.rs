@@ -26,6 +26,7 @@ use {
             ACCESS_CONTROL_REQUEST_HEADERS,
             ACCESS_CONTROL_REQUEST_METHOD,
             ORIGIN,
+            VARY,
         },
         HttpTryFrom, Method, Request, Response, StatusCode, Uri,
     },
@@ -49,6 +50,18 @@ impl Builder {
         Self::default()
     }
 
+    /// Allows the requests from any origin.
+    ///
+    /// This is the default behavior, and discards the origins registered so far.
+    /// If the credentials are allowed, the value of `Origin` is sent back as
+    /// `Access-Control-Allow-Origin` instead of the wildcard `*`.
+    pub fn allow_any_origin(self) -> Self {
+        Self {
+            origins: None,
+            ..self
+        }
+    }
+
     #[allow(missing_docs)]
     pub fn allow_origin<U>(mut self, origin: U) -> http::Result<Self>
     where
@@ -478,9 +491,7 @@ impl Inner {
 
         let mut response = Response::default();
         *response.status_mut() = StatusCode::NO_CONTENT;
-        response
-            .headers_mut()
-            .insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin.into());
+        self.append_origin_headers(origin, response.headers_mut());
 
         if let Some(allow_methods) = allow_methods {
             response
@@ -513,6 +524,17 @@ impl Inner {
             return Err(CORSErrorKind::DisallowedRequestMethod.into());
         }
 
+        self.append_origin_headers(origin, hdrs);
+
+        Ok(())
+    }
+
+    fn append_origin_headers(&self, origin: AllowedOrigin, hdrs: &mut HeaderMap) {
+        if let AllowedOrigin::Some(..) = origin {
+            // the response varies depending on the value of `Origin`.
+            tsukuyomi::util::merge_header_value(hdrs, VARY, HeaderValue::from_static("Origin"));
+        }
+
         hdrs.append(ACCESS_CONTROL_ALLOW_ORIGIN, origin.into());
 
         if self.allow_credentials {
@@ -521,8 +543,6 @@ impl Inner {
                 HeaderValue::from_static("true"),
             );
         }
-
-        Ok(())
     }
 
     fn process_request(&self, input: &mut Input<'_>) -> Result<Option<Response<()>>, CORSError> {
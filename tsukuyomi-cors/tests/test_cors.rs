@@ -11,8 +11,9 @@ use {
             COOKIE,
             HOST,
             ORIGIN,
+            VARY,
         },
-        Method, Request,
+        Method, Request, Response,
     },
     tsukuyomi::{
         config::prelude::*, //
@@ -156,6 +157,66 @@ fn simple_request_with_allow_credentials() -> tsukuyomi_server::Result<()> {
         "http://example.com"
     );
     assert_eq!(response.header(ACCESS_CONTROL_ALLOW_CREDENTIALS)?, "true",);
+    assert_eq!(response.header(VARY)?, "Origin");
+
+    Ok(())
+}
+
+#[test]
+fn simple_request_merges_vary() -> tsukuyomi_server::Result<()> {
+    let cors = CORS::builder() //
+        .allow_credentials(true)
+        .build();
+
+    let app = App::create(
+        path!("/") //
+            .to(endpoint::get() //
+                .call(|| {
+                    Response::builder()
+                        .header(VARY, "Accept-Encoding")
+                        .body("hello")
+                        .unwrap()
+                }))
+            .modify(cors),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform(
+        Request::get("/")
+            .header(HOST, "localhost")
+            .header(ORIGIN, "http://example.com"),
+    )?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "hello");
+    assert_eq!(response.headers().get_all(VARY).iter().count(), 1);
+    assert_eq!(response.header(VARY)?, "Accept-Encoding, Origin");
+
+    Ok(())
+}
+
+#[test]
+fn simple_request_with_allow_any_origin() -> tsukuyomi_server::Result<()> {
+    let cors = CORS::builder()
+        .allow_origin("http://example.com")?
+        .allow_any_origin()
+        .build();
+
+    let app = App::create(
+        path!("/") //
+            .to(endpoint::get() //
+                .call(|| "hello"))
+            .modify(cors),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform(
+        Request::get("/")
+            .header(HOST, "localhost")
+            .header(ORIGIN, "http://example.org"),
+    )?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.header(ACCESS_CONTROL_ALLOW_ORIGIN)?, "*");
+    assert!(!response.headers().contains_key(VARY));
 
     Ok(())
 }
@@ -245,6 +306,37 @@ fn preflight_with_allow_origin() -> tsukuyomi_server::Result<()> {
     Ok(())
 }
 
+#[test]
+fn preflight_with_allow_credentials() -> tsukuyomi_server::Result<()> {
+    let cors = CORS::builder() //
+        .allow_credentials(true)
+        .build();
+
+    let app = App::create(
+        path!("/") //
+            .to(endpoint::get() //
+                .call(|| "hello"))
+            .modify(cors),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform(
+        Request::options("/")
+            .header(HOST, "localhost")
+            .header(ORIGIN, "http://example.com")
+            .header(ACCESS_CONTROL_REQUEST_METHOD, "GET"),
+    )?;
+    assert_eq!(response.status(), 204);
+    assert_eq!(
+        response.header(ACCESS_CONTROL_ALLOW_ORIGIN)?,
+        "http://example.com"
+    );
+    assert_eq!(response.header(ACCESS_CONTROL_ALLOW_CREDENTIALS)?, "true");
+    assert_eq!(response.header(VARY)?, "Origin");
+
+    Ok(())
+}
+
 #[test]
 fn preflight_with_allow_method() -> tsukuyomi_server::Result<()> {
     let cors = CORS::builder() //
@@ -6,17 +6,62 @@ use {
 
 /// Creates a handler function which returns a GraphiQL source.
 pub fn graphiql_source(url: impl AsRef<str> + 'static) -> impl IntoResponse + Clone {
-    GraphiQLSource {
+    HtmlSource {
         source: juniper::http::graphiql::graphiql_source(url.as_ref()).into(),
     }
 }
 
+/// Creates a handler function which returns a GraphQL Playground source.
+///
+/// If `subscriptions_url` is specified, the playground connects to it
+/// in order to execute the operations over WebSocket.
+pub fn playground_source(
+    url: impl AsRef<str> + 'static,
+    subscriptions_url: Option<&str>,
+) -> impl IntoResponse + Clone {
+    let mut options = format!("endpoint: {}", js_string(url.as_ref()));
+    if let Some(subscriptions_url) = subscriptions_url {
+        options += &format!(", subscriptionEndpoint: {}", js_string(subscriptions_url));
+    }
+    HtmlSource {
+        source: PLAYGROUND_SOURCE.replace("{options}", &options).into(),
+    }
+}
+
+/// Converts a string into a JavaScript string literal which can be embedded in a `<script>`.
+fn js_string(s: &str) -> String {
+    serde_json::to_string(s)
+        .expect("should be a valid JSON string")
+        .replace("</", "<\\/")
+}
+
+const PLAYGROUND_SOURCE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+  <meta charset="utf-8" />
+  <meta name="viewport" content="user-scalable=no, initial-scale=1.0, minimum-scale=1.0, maximum-scale=1.0, minimal-ui">
+  <title>GraphQL Playground</title>
+  <link rel="stylesheet" href="//cdn.jsdelivr.net/npm/graphql-playground-react/build/static/css/index.css" />
+  <link rel="shortcut icon" href="//cdn.jsdelivr.net/npm/graphql-playground-react/build/favicon.png" />
+  <script src="//cdn.jsdelivr.net/npm/graphql-playground-react/build/static/js/middleware.js"></script>
+</head>
+<body>
+  <div id="root"></div>
+  <script>
+    window.addEventListener('load', function (event) {
+      GraphQLPlayground.init(document.getElementById('root'), { {options} });
+    });
+  </script>
+</body>
+</html>
+"#;
+
 #[derive(Debug, Clone)]
-struct GraphiQLSource {
+struct HtmlSource {
     source: Bytes,
 }
 
-impl IntoResponse for GraphiQLSource {
+impl IntoResponse for HtmlSource {
     type Body = Bytes;
     type Error = tsukuyomi::util::Never;
 
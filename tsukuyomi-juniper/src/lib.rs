@@ -14,13 +14,18 @@
 mod error;
 mod graphiql;
 mod request;
+#[cfg(feature = "subscriptions")]
+mod subscriptions;
 
 pub use crate::{
     error::{capture_errors, CaptureErrors},
-    graphiql::graphiql_source,
+    graphiql::{graphiql_source, playground_source},
     request::{request, GraphQLRequest, GraphQLResponse},
 };
 
+#[cfg(feature = "subscriptions")]
+pub use crate::subscriptions::{subscriptions, subscriptions_with, Operation, GRAPHQL_WS};
+
 use {
     juniper::{DefaultScalarValue, GraphQLType, RootNode, ScalarRefValue, ScalarValue},
     std::sync::Arc,
@@ -0,0 +1,308 @@
+use {
+    crate::Schema,
+    futures::{
+        sync::{mpsc, oneshot},
+        Future, Sink, Stream,
+    },
+    juniper::{http::GraphQLRequest, ScalarRefValue, ScalarValue},
+    serde::Deserialize,
+    serde_json::json,
+    std::collections::HashMap,
+    tsukuyomi_tungstenite::{Handshake, Message, WebSocketStream, Ws},
+};
+
+/// The name of subprotocol negotiated by `subscriptions`.
+pub const GRAPHQL_WS: &str = "graphql-ws";
+
+/// Information about an operation started by the client.
+///
+/// The value of this type is passed to the function specified by `subscriptions_with`
+/// in order to select the source of events that triggers the re-execution of the operation.
+#[derive(Debug)]
+pub struct Operation<'a> {
+    id: &'a str,
+    query: &'a str,
+    operation_name: Option<&'a str>,
+}
+
+impl<'a> Operation<'a> {
+    /// Returns the ID of this operation assigned by the client.
+    pub fn id(&self) -> &'a str {
+        self.id
+    }
+
+    /// Returns the query string of this operation.
+    pub fn query(&self) -> &'a str {
+        self.query
+    }
+
+    /// Returns the name of operation to be executed, if specified.
+    pub fn operation_name(&self) -> Option<&'a str> {
+        self.operation_name
+    }
+}
+
+/// Creates a `Responder` that handles the GraphQL operations over WebSocket,
+/// using the `graphql-ws` protocol.
+///
+/// The operations sent with `start` messages are executed using the specified schema
+/// and context, and the results are sent back as a `data` message followed by `complete`.
+///
+/// This function is equivalent to `subscriptions_with` without any event sources.
+pub fn subscriptions<T, CtxT, S>(
+    schema: T,
+    context: CtxT,
+) -> Ws<
+    impl Fn(WebSocketStream, Handshake) -> Box<dyn Future<Item = (), Error = ()> + Send + 'static>
+        + Clone
+        + Send
+        + 'static,
+>
+where
+    T: Schema<S> + Clone + Send + 'static,
+    CtxT: AsRef<T::Context> + Clone + Send + 'static,
+    S: ScalarValue + Send + 'static,
+    for<'a> &'a S: ScalarRefValue<'a>,
+{
+    subscriptions_with(schema, context, |_: &Operation<'_>| {
+        None::<futures::stream::Empty<(), ()>>
+    })
+}
+
+/// Creates a `Responder` that handles the GraphQL operations over WebSocket,
+/// with the specified function that selects the source of events for each operation.
+///
+/// Since the current version of `juniper` does not support the execution of
+/// subscription operations, the subscriptions are emulated by re-executing the query
+/// every time the event stream returned from `events` yields an item.
+/// The result of each execution is sent as a `data` message, and `complete` is sent
+/// when the event stream is terminated or the client sends `stop`.
+/// If `events` returns `None`, the operation is executed only once.
+pub fn subscriptions_with<T, CtxT, S, F, St>(
+    schema: T,
+    context: CtxT,
+    events: F,
+) -> Ws<
+    impl Fn(WebSocketStream, Handshake) -> Box<dyn Future<Item = (), Error = ()> + Send + 'static>
+        + Clone
+        + Send
+        + 'static,
+>
+where
+    T: Schema<S> + Clone + Send + 'static,
+    CtxT: AsRef<T::Context> + Clone + Send + 'static,
+    S: ScalarValue + Send + 'static,
+    for<'a> &'a S: ScalarRefValue<'a>,
+    F: Fn(&Operation<'_>) -> Option<St> + Clone + Send + 'static,
+    St: Stream<Item = (), Error = ()> + Send + 'static,
+{
+    Ws::new(move |stream: WebSocketStream, _: Handshake| {
+        let mut connection = Connection {
+            schema: schema.clone(),
+            context: context.clone(),
+            events: events.clone(),
+            operations: HashMap::new(),
+            _marker: std::marker::PhantomData,
+        };
+
+        let (sink, stream) = stream.split();
+        let (tx, rx) = mpsc::unbounded();
+
+        let incoming = stream
+            .map_err(|_| ())
+            .for_each(move |message| connection.handle_message(message, &tx));
+        let outgoing = rx.forward(sink.sink_map_err(|_| ())).map(|_| ());
+
+        let task = incoming.select(outgoing).then(|_| Ok(()));
+        Box::new(task) as Box<dyn Future<Item = (), Error = ()> + Send + 'static>
+    })
+    .protocols(vec![GRAPHQL_WS])
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    // The payload of `connection_init` is not used currently.
+    ConnectionInit,
+    Start {
+        id: String,
+        payload: serde_json::Value,
+    },
+    Stop {
+        id: String,
+    },
+    ConnectionTerminate,
+}
+
+#[derive(Debug, Deserialize)]
+struct StartPayload<'a> {
+    query: &'a str,
+    #[serde(rename = "operationName")]
+    operation_name: Option<&'a str>,
+}
+
+struct Connection<T, CtxT, S, F> {
+    schema: T,
+    context: CtxT,
+    events: F,
+    // The handles for cancelling the operations running in the background.
+    operations: HashMap<String, oneshot::Sender<()>>,
+    _marker: std::marker::PhantomData<fn(S)>,
+}
+
+impl<T, CtxT, S, F, St> Connection<T, CtxT, S, F>
+where
+    T: Schema<S> + Clone + Send + 'static,
+    CtxT: AsRef<T::Context> + Clone + Send + 'static,
+    S: ScalarValue + Send + 'static,
+    for<'a> &'a S: ScalarRefValue<'a>,
+    F: Fn(&Operation<'_>) -> Option<St>,
+    St: Stream<Item = (), Error = ()> + Send + 'static,
+{
+    /// Handles a message from the client.
+    ///
+    /// The returned error means that the connection should be terminated.
+    fn handle_message(
+        &mut self,
+        message: Message,
+        tx: &mpsc::UnboundedSender<Message>,
+    ) -> Result<(), ()> {
+        let text = match message {
+            Message::Text(text) => text,
+            _ => return Ok(()),
+        };
+
+        match serde_json::from_str::<ClientMessage>(&text) {
+            Ok(ClientMessage::ConnectionInit) => {
+                send(tx, json!({ "type": "connection_ack" }));
+            }
+            Ok(ClientMessage::Start { id, payload }) => self.start(id, &payload, tx),
+            Ok(ClientMessage::Stop { id }) => {
+                // `complete` has already been sent if the operation has been finished.
+                if let Some(cancel) = self.operations.remove(&id) {
+                    if cancel.send(()).is_ok() {
+                        send(tx, json!({ "type": "complete", "id": id }));
+                    }
+                }
+            }
+            Ok(ClientMessage::ConnectionTerminate) => return Err(()),
+            Err(err) => send(
+                tx,
+                json!({
+                    "type": "connection_error",
+                    "payload": { "message": err.to_string() },
+                }),
+            ),
+        }
+
+        Ok(())
+    }
+
+    fn start(
+        &mut self,
+        id: String,
+        payload: &serde_json::Value,
+        tx: &mpsc::UnboundedSender<Message>,
+    ) {
+        // Forget the operations that have already been completed.
+        self.operations.retain(|_, cancel| !cancel.is_canceled());
+        if let Some(cancel) = self.operations.remove(&id) {
+            let _ = cancel.send(());
+        }
+
+        let parsed = StartPayload::deserialize(payload)
+            .map_err(|err| err.to_string())
+            .and_then(|start| {
+                let request = GraphQLRequest::<S>::deserialize(payload) //
+                    .map_err(|err| err.to_string())?;
+                Ok((start, request))
+            });
+        let (start, request) = match parsed {
+            Ok(parsed) => parsed,
+            Err(message) => {
+                send(
+                    tx,
+                    json!({
+                        "type": "error",
+                        "id": id,
+                        "payload": { "message": message },
+                    }),
+                );
+                return;
+            }
+        };
+
+        let events = (self.events)(&Operation {
+            id: &id,
+            query: start.query,
+            operation_name: start.operation_name,
+        });
+
+        send(tx, execute(&self.schema, &self.context, &id, &request));
+
+        let events = match events {
+            Some(events) => events,
+            None => {
+                send(tx, json!({ "type": "complete", "id": id }));
+                return;
+            }
+        };
+
+        let (cancel_tx, cancel_rx) = oneshot::channel();
+        self.operations.insert(id.clone(), cancel_tx);
+
+        let schema = self.schema.clone();
+        let context = self.context.clone();
+        let tx = tx.clone();
+        let task = events
+            .for_each({
+                let id = id.clone();
+                let tx = tx.clone();
+                move |()| {
+                    let data = execute(&schema, &context, &id, &request);
+                    tx.unbounded_send(reply(data)).map_err(|_| ())
+                }
+            })
+            .then(move |_| {
+                send(&tx, json!({ "type": "complete", "id": id }));
+                Ok::<(), ()>(())
+            })
+            // The operation is cancelled when `stop` is received or the connection is closed.
+            .select2(cancel_rx)
+            .then(|_| Ok(()));
+
+        tsukuyomi_server::rt::spawn(task);
+    }
+}
+
+fn execute<T, CtxT, S>(
+    schema: &T,
+    context: &CtxT,
+    id: &str,
+    request: &GraphQLRequest<S>,
+) -> serde_json::Value
+where
+    T: Schema<S>,
+    CtxT: AsRef<T::Context>,
+    S: ScalarValue,
+    for<'a> &'a S: ScalarRefValue<'a>,
+{
+    let response = request.execute(schema.as_root_node(), context.as_ref());
+    match serde_json::to_value(&response) {
+        Ok(payload) => json!({ "type": "data", "id": id, "payload": payload }),
+        Err(err) => json!({
+            "type": "error",
+            "id": id,
+            "payload": { "message": err.to_string() },
+        }),
+    }
+}
+
+fn send(tx: &mpsc::UnboundedSender<Message>, value: serde_json::Value) {
+    // The error means that the connection has already been closed.
+    let _ = tx.unbounded_send(reply(value));
+}
+
+fn reply(value: serde_json::Value) -> Message {
+    Message::text(value.to_string())
+}
@@ -101,3 +101,165 @@ fn make_test_response(response: &Response<TestOutput>) -> http_tests::TestRespon
         body: Some(body),
     }
 }
+
+#[test]
+fn playground_source() -> tsukuyomi_server::Result<()> {
+    let app = App::create(
+        path!("/") //
+            .to(endpoint::get().reply(tsukuyomi_juniper::playground_source(
+                "/graphql",
+                Some("ws://localhost:4000/subscriptions"),
+            ))),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "text/html; charset=utf-8"
+    );
+    let body = response.body().to_utf8()?;
+    assert!(body.contains(r#"endpoint: "/graphql""#));
+    assert!(body.contains(r#"subscriptionEndpoint: "ws://localhost:4000/subscriptions""#));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "subscriptions")]
+fn subscriptions_over_websocket() -> tsukuyomi_server::Result<()> {
+    use {
+        futures::{Future, Sink, Stream},
+        serde_json::json,
+        tsukuyomi_tungstenite::{test::ServerExt, Message},
+    };
+
+    let database = Arc::new(Database::new());
+    let schema = Arc::new(RootNode::new(
+        Database::new(),
+        EmptyMutation::<Database>::new(),
+    ));
+
+    let app = App::create(
+        path!("/subscriptions") //
+            .to(endpoint::get().reply(tsukuyomi_juniper::subscriptions(schema, database))),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let ws = server.ws("/subscriptions")?;
+
+    let ws = ws
+        .send(Message::text(r#"{"type":"connection_init","payload":{}}"#))
+        .wait()?;
+    let (message, ws) = ws.into_future().wait().map_err(|(err, _)| err)?;
+    let message: serde_json::Value = serde_json::from_str(message.unwrap().to_text()?)?;
+    assert_eq!(message, json!({ "type": "connection_ack" }));
+
+    let ws = ws
+        .send(Message::text(
+            r#"{"type":"start","id":"1","payload":{"query":"{ hero { name } }"}}"#,
+        ))
+        .wait()?;
+    let (message, ws) = ws.into_future().wait().map_err(|(err, _)| err)?;
+    let message: serde_json::Value = serde_json::from_str(message.unwrap().to_text()?)?;
+    assert_eq!(
+        message,
+        json!({
+            "type": "data",
+            "id": "1",
+            "payload": { "data": { "hero": { "name": "R2-D2" } } },
+        })
+    );
+    let (message, _ws) = ws.into_future().wait().map_err(|(err, _)| err)?;
+    let message: serde_json::Value = serde_json::from_str(message.unwrap().to_text()?)?;
+    assert_eq!(message, json!({ "type": "complete", "id": "1" }));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "subscriptions")]
+fn subscriptions_triggered_by_events() -> tsukuyomi_server::Result<()> {
+    use {
+        futures::{Future, Sink, Stream},
+        serde_json::json,
+        tsukuyomi_juniper::Operation,
+        tsukuyomi_tungstenite::{test::ServerExt, Message},
+    };
+
+    type Events = Box<dyn Stream<Item = (), Error = ()> + Send>;
+
+    fn recv<S>(ws: S) -> tsukuyomi_server::Result<(serde_json::Value, S)>
+    where
+        S: Stream<Item = Message>,
+        tsukuyomi_server::Error: From<S::Error>,
+    {
+        let (message, ws) = ws.into_future().wait().map_err(|(err, _)| err)?;
+        let message = serde_json::from_str(message.unwrap().to_text()?)?;
+        Ok((message, ws))
+    }
+
+    let database = Arc::new(Database::new());
+    let schema = Arc::new(RootNode::new(
+        Database::new(),
+        EmptyMutation::<Database>::new(),
+    ));
+
+    let app = App::create(
+        path!("/subscriptions") //
+            .to(endpoint::get().reply(tsukuyomi_juniper::subscriptions_with(
+                schema,
+                database,
+                |operation: &Operation<'_>| match operation.id() {
+                    // yields two events, and then terminates.
+                    "ticks" => Some(Box::new(futures::stream::iter_ok(vec![(), ()])) as Events),
+                    // never yields any events until stopped.
+                    "pending" => Some(Box::new(futures::future::empty().into_stream()) as Events),
+                    _ => None,
+                },
+            ))),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let data = |id: &str| {
+        json!({
+            "type": "data",
+            "id": id,
+            "payload": { "data": { "hero": { "name": "R2-D2" } } },
+        })
+    };
+
+    let ws = server.ws("/subscriptions")?;
+
+    // The query is re-executed every time the event is triggered.
+    let ws = ws
+        .send(Message::text(
+            r#"{"type":"start","id":"ticks","payload":{"query":"{ hero { name } }"}}"#,
+        ))
+        .wait()?;
+    let (message, ws) = recv(ws)?;
+    assert_eq!(message, data("ticks"));
+    let (message, ws) = recv(ws)?;
+    assert_eq!(message, data("ticks"));
+    let (message, ws) = recv(ws)?;
+    assert_eq!(message, data("ticks"));
+    let (message, ws) = recv(ws)?;
+    assert_eq!(message, json!({ "type": "complete", "id": "ticks" }));
+
+    // The operation is cancelled by `stop`.
+    let ws = ws
+        .send(Message::text(
+            r#"{"type":"start","id":"pending","payload":{"query":"{ hero { name } }"}}"#,
+        ))
+        .wait()?;
+    let (message, ws) = recv(ws)?;
+    assert_eq!(message, data("pending"));
+    let ws = ws
+        .send(Message::text(r#"{"type":"stop","id":"pending"}"#))
+        .wait()?;
+    let (message, _ws) = recv(ws)?;
+    assert_eq!(message, json!({ "type": "complete", "id": "pending" }));
+
+    Ok(())
+}
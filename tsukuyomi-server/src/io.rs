@@ -1,7 +1,15 @@
 use {
     crate::CritError,
-    futures::{Future, IntoFuture, Stream},
-    tokio::io::{AsyncRead, AsyncWrite},
+    futures::{Async, Future, IntoFuture, Poll, Stream},
+    std::{
+        io,
+        net::SocketAddr,
+        time::{Duration, Instant},
+    },
+    tokio::{
+        io::{AsyncRead, AsyncWrite},
+        timer::Delay,
+    },
 };
 
 /// A trait that represents the low-level I/O.
@@ -12,6 +20,84 @@ pub trait Listener {
 
     /// Creates a `Stream` of asynchronous I/Os.
     fn listen(self) -> Result<Self::Incoming, Self::Error>;
+
+    /// Creates a `Stream` of asynchronous I/Os with the specified TCP options.
+    ///
+    /// The default implementation ignores the options and calls `listen`.
+    fn listen_with(self, options: &TcpOptions) -> Result<Self::Incoming, Self::Error>
+    where
+        Self: Sized,
+    {
+        let _ = options;
+        self.listen()
+    }
+
+    /// Returns the address of the remote peer of the specified connection, if available.
+    ///
+    /// The returned address is inserted into the extensions of each request received
+    /// from the connection. The default implementation returns `None`.
+    fn peer_addr(conn: &Self::Conn) -> Option<SocketAddr> {
+        let _ = conn;
+        None
+    }
+}
+
+/// Creates a `Stream` of asynchronous I/Os paired with the addresses of their remote peers.
+pub(crate) fn incoming_with_peer_addr<T>(
+    listener: T,
+    options: &TcpOptions,
+) -> Result<impl Stream<Item = (T::Conn, Option<SocketAddr>), Error = T::Error>, T::Error>
+where
+    T: Listener,
+{
+    let incoming = listener.listen_with(options)?;
+    Ok(incoming.map(|conn| {
+        let peer_addr = T::peer_addr(&conn);
+        (conn, peer_addr)
+    }))
+}
+
+/// A set of socket-level options applied to the TCP listeners and the accepted connections.
+///
+/// # Platform differences
+///
+/// * `SO_REUSEADDR` is always enabled on Unix platforms by the underlying runtime,
+///   regardless of the value of `reuse_address`. On Windows, the option has the
+///   different semantics and allows to bind the address that is already in use.
+/// * `SO_REUSEPORT` is available only on Unix platforms. Enabling it on the other platforms
+///   causes an error when the server starts.
+/// * The options applied before binding (`reuse_address` and `reuse_port`) are ignored
+///   if the listener has already been bound, e.g. `std::net::TcpListener`.
+#[derive(Debug, Clone, Default)]
+pub struct TcpOptions {
+    pub(crate) nodelay: Option<bool>,
+    pub(crate) reuse_address: bool,
+    pub(crate) reuse_port: bool,
+    pub(crate) keepalive: Option<Option<Duration>>,
+}
+
+impl TcpOptions {
+    /// Returns the value of `TCP_NODELAY` set to the accepted connections, if specified.
+    pub fn nodelay(&self) -> Option<bool> {
+        self.nodelay
+    }
+
+    /// Returns whether to enable `SO_REUSEADDR` on the listener.
+    pub fn reuse_address(&self) -> bool {
+        self.reuse_address
+    }
+
+    /// Returns whether to enable `SO_REUSEPORT` on the listener.
+    pub fn reuse_port(&self) -> bool {
+        self.reuse_port
+    }
+
+    /// Returns the value of `SO_KEEPALIVE` set to the accepted connections, if specified.
+    ///
+    /// The inner `None` means that the keepalive is disabled.
+    pub fn keepalive(&self) -> Option<Option<Duration>> {
+        self.keepalive
+    }
 }
 
 /// A trait that represents the conversion of asynchronous I/Os.
@@ -23,6 +109,15 @@ pub trait Acceptor<T> {
     type Accept: Future<Item = Self::Conn, Error = Self::Error>;
 
     fn accept(&self, io: T) -> Self::Accept;
+
+    /// Returns whether the connections established by this acceptor are secure.
+    ///
+    /// If this method returns `true`, the server inserts `tsukuyomi_service::Secure`
+    /// into the extensions of each request received from the connections.
+    /// The default implementation returns `false`.
+    fn is_secure(&self) -> bool {
+        false
+    }
 }
 
 impl<F, T, R> Acceptor<T> for F
@@ -55,58 +150,264 @@ where
     }
 }
 
+/// A wrapper of I/O that fails when reading or writing is blocked longer than the timeout.
+#[derive(Debug)]
+pub(crate) struct TimeoutIo<T> {
+    io: T,
+    read: TimeoutState,
+    write: TimeoutState,
+}
+
+impl<T> TimeoutIo<T> {
+    pub(crate) fn new(
+        io: T,
+        read_timeout: Option<Duration>,
+        write_timeout: Option<Duration>,
+    ) -> Self {
+        Self {
+            io,
+            read: TimeoutState::new(read_timeout),
+            write: TimeoutState::new(write_timeout),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TimeoutState {
+    timeout: Option<Duration>,
+    delay: Option<Delay>,
+}
+
+impl TimeoutState {
+    fn new(timeout: Option<Duration>) -> Self {
+        Self {
+            timeout,
+            delay: None,
+        }
+    }
+
+    fn track<T>(&mut self, result: io::Result<T>) -> io::Result<T> {
+        match result {
+            Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => {}
+            result => {
+                self.delay = None;
+                return result;
+            }
+        }
+
+        if let Some(timeout) = self.timeout {
+            let delay = self
+                .delay
+                .get_or_insert_with(|| Delay::new(Instant::now() + timeout));
+            match delay.poll() {
+                Ok(Async::Ready(())) => {
+                    return Err(io::Error::new(io::ErrorKind::TimedOut, "I/O timed out"));
+                }
+                Ok(Async::NotReady) => {}
+                Err(err) => return Err(io::Error::new(io::ErrorKind::Other, err)),
+            }
+        }
+
+        Err(io::ErrorKind::WouldBlock.into())
+    }
+}
+
+impl<T: io::Read> io::Read for TimeoutIo<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let result = self.io.read(buf);
+        self.read.track(result)
+    }
+}
+
+impl<T: io::Write> io::Write for TimeoutIo<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let result = self.io.write(buf);
+        self.write.track(result)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let result = self.io.flush();
+        self.write.track(result)
+    }
+}
+
+impl<T: AsyncRead> AsyncRead for TimeoutIo<T> {
+    unsafe fn prepare_uninitialized_buffer(&self, buf: &mut [u8]) -> bool {
+        self.io.prepare_uninitialized_buffer(buf)
+    }
+}
+
+impl<T: AsyncWrite> AsyncWrite for TimeoutIo<T> {
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        self.io.shutdown()
+    }
+}
+
 mod tcp {
     use {
-        super::Listener,
-        std::{io, net::SocketAddr},
+        super::{Listener, TcpOptions},
+        futures::{Async, Poll, Stream},
+        net2::TcpBuilder,
+        std::{io, net::SocketAddr, time::Duration},
         tokio::{
             net::{tcp::Incoming, TcpListener, TcpStream},
             reactor::Handle,
         },
     };
 
+    /// A `Stream` of TCP connections which applies `TcpOptions` to the accepted connections.
+    #[derive(Debug)]
+    pub struct TcpIncoming {
+        inner: Incoming,
+        nodelay: Option<bool>,
+        keepalive: Option<Option<Duration>>,
+    }
+
+    impl TcpIncoming {
+        fn new(listener: TcpListener, options: &TcpOptions) -> Self {
+            Self {
+                inner: listener.incoming(),
+                nodelay: options.nodelay,
+                keepalive: options.keepalive,
+            }
+        }
+    }
+
+    impl Stream for TcpIncoming {
+        type Item = TcpStream;
+        type Error = io::Error;
+
+        fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+            let stream = futures::try_ready!(self.inner.poll());
+            if let (Some(ref stream), Some(nodelay)) = (&stream, self.nodelay) {
+                if let Err(err) = stream.set_nodelay(nodelay) {
+                    log::warn!("failed to set TCP_NODELAY: {}", err);
+                }
+            }
+            if let (Some(ref stream), Some(keepalive)) = (&stream, self.keepalive) {
+                if let Err(err) = stream.set_keepalive(keepalive) {
+                    log::warn!("failed to set SO_KEEPALIVE: {}", err);
+                }
+            }
+            Ok(Async::Ready(stream))
+        }
+    }
+
+    fn bind(addr: &SocketAddr, options: &TcpOptions) -> io::Result<TcpListener> {
+        if !options.reuse_address && !options.reuse_port {
+            return TcpListener::bind(addr);
+        }
+
+        let builder = match addr {
+            SocketAddr::V4(..) => TcpBuilder::new_v4()?,
+            SocketAddr::V6(..) => TcpBuilder::new_v6()?,
+        };
+        if options.reuse_address {
+            builder.reuse_address(true)?;
+        }
+        if options.reuse_port {
+            set_reuse_port(&builder)?;
+        }
+        builder.bind(addr)?;
+        let listener = builder.listen(1024)?;
+
+        TcpListener::from_std(listener, &Handle::current())
+    }
+
+    #[cfg(unix)]
+    fn set_reuse_port(builder: &TcpBuilder) -> io::Result<()> {
+        use net2::unix::UnixTcpBuilderExt;
+        builder.reuse_port(true).map(|_| ())
+    }
+
+    #[cfg(not(unix))]
+    fn set_reuse_port(_: &TcpBuilder) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "SO_REUSEPORT is not supported on this platform",
+        ))
+    }
+
     impl Listener for SocketAddr {
         type Conn = TcpStream;
         type Error = io::Error;
-        type Incoming = Incoming;
+        type Incoming = TcpIncoming;
 
         #[inline]
         fn listen(self) -> io::Result<Self::Incoming> {
             (&self).listen()
         }
+
+        #[inline]
+        fn listen_with(self, options: &TcpOptions) -> io::Result<Self::Incoming> {
+            (&self).listen_with(options)
+        }
+
+        #[inline]
+        fn peer_addr(conn: &Self::Conn) -> Option<SocketAddr> {
+            conn.peer_addr().ok()
+        }
     }
 
     impl<'a> Listener for &'a SocketAddr {
         type Conn = TcpStream;
         type Error = io::Error;
-        type Incoming = Incoming;
+        type Incoming = TcpIncoming;
 
         #[inline]
         fn listen(self) -> io::Result<Self::Incoming> {
-            Ok(TcpListener::bind(self)?.incoming())
+            self.listen_with(&TcpOptions::default())
+        }
+
+        fn listen_with(self, options: &TcpOptions) -> io::Result<Self::Incoming> {
+            Ok(TcpIncoming::new(bind(self, options)?, options))
+        }
+
+        #[inline]
+        fn peer_addr(conn: &Self::Conn) -> Option<SocketAddr> {
+            conn.peer_addr().ok()
         }
     }
 
     impl Listener for std::net::TcpListener {
         type Conn = TcpStream;
         type Error = io::Error;
-        type Incoming = Incoming;
+        type Incoming = TcpIncoming;
 
         #[inline]
         fn listen(self) -> io::Result<Self::Incoming> {
+            self.listen_with(&TcpOptions::default())
+        }
+
+        fn listen_with(self, options: &TcpOptions) -> io::Result<Self::Incoming> {
             let listener = TcpListener::from_std(self, &Handle::current())?;
-            Ok(listener.incoming())
+            Ok(TcpIncoming::new(listener, options))
+        }
+
+        #[inline]
+        fn peer_addr(conn: &Self::Conn) -> Option<SocketAddr> {
+            conn.peer_addr().ok()
         }
     }
 
     impl Listener for TcpListener {
         type Conn = TcpStream;
         type Error = io::Error;
-        type Incoming = Incoming;
+        type Incoming = TcpIncoming;
 
         #[inline]
         fn listen(self) -> io::Result<Self::Incoming> {
-            Ok(self.incoming())
+            self.listen_with(&TcpOptions::default())
+        }
+
+        #[inline]
+        fn listen_with(self, options: &TcpOptions) -> io::Result<Self::Incoming> {
+            Ok(TcpIncoming::new(self, options))
+        }
+
+        #[inline]
+        fn peer_addr(conn: &Self::Conn) -> Option<SocketAddr> {
+            conn.peer_addr().ok()
         }
     }
 }
@@ -201,6 +502,11 @@ mod navite_tls {
         fn accept(&self, io: T) -> Self::Accept {
             self.accept(io)
         }
+
+        #[inline]
+        fn is_secure(&self) -> bool {
+            true
+        }
     }
 }
 
@@ -208,7 +514,8 @@ mod navite_tls {
 mod rustls {
     use {
         super::Acceptor,
-        rustls::ServerSession,
+        rustls::{ServerConfig, ServerSession},
+        std::sync::Arc,
         tokio::io::{AsyncRead, AsyncWrite},
         tokio_rustls::{Accept, TlsAcceptor, TlsStream},
     };
@@ -225,6 +532,34 @@ mod rustls {
         fn accept(&self, io: T) -> Self::Accept {
             self.accept(io)
         }
+
+        #[inline]
+        fn is_secure(&self) -> bool {
+            true
+        }
+    }
+
+    /// The shared configuration of rustls can be used as an acceptor directly.
+    ///
+    /// The protocols set by `ServerConfig::set_protocols` are negotiated by ALPN
+    /// during the handshake.
+    impl<T> Acceptor<T> for Arc<ServerConfig>
+    where
+        T: AsyncRead + AsyncWrite,
+    {
+        type Conn = TlsStream<T, ServerSession>;
+        type Error = std::io::Error;
+        type Accept = Accept<T>;
+
+        #[inline]
+        fn accept(&self, io: T) -> Self::Accept {
+            TlsAcceptor::from(self.clone()).accept(io)
+        }
+
+        #[inline]
+        fn is_secure(&self) -> bool {
+            true
+        }
     }
 }
 
@@ -249,5 +584,10 @@ mod openssl {
         fn accept(&self, io: T) -> Self::Accept {
             self.accept_async(io)
         }
+
+        #[inline]
+        fn is_secure(&self) -> bool {
+            true
+        }
     }
 }
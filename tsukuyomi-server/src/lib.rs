@@ -20,9 +20,11 @@ pub mod test;
 
 pub use crate::{
     error::{Error, Result},
-    io::{Acceptor, Listener},
+    io::{Acceptor, Listener, TcpOptions},
 };
 
+use crate::io::TimeoutIo;
+
 use {
     futures::{Future, Poll, Stream},
     http::{Request, Response},
@@ -30,19 +32,28 @@ use {
         body::{Body, Payload},
         server::conn::Http,
     },
-    std::{marker::PhantomData, net::SocketAddr, rc::Rc, sync::Arc},
+    std::{marker::PhantomData, net::SocketAddr, rc::Rc, sync::Arc, time::Duration},
     tsukuyomi_service::{MakeServiceRef, Service},
 };
 
 type CritError = Box<dyn std::error::Error + Send + Sync + 'static>;
 
 /// An HTTP server.
+///
+/// The address of the remote peer returned from `Listener::peer_addr` (e.g. for the TCP
+/// connections) is inserted into the extensions of each request as a `SocketAddr`.
+/// If the acceptor establishes the secure connections (e.g. TLS), a value of
+/// `tsukuyomi_service::Secure` is also inserted into the extensions.
 #[derive(Debug)]
 pub struct Server<S, L = SocketAddr, A = (), R = tokio::runtime::Runtime> {
     make_service: S,
     listener: L,
     acceptor: A,
     protocol: Http,
+    tcp_options: TcpOptions,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+    shutdown_timeout: Option<Duration>,
     runtime: Option<R>,
 }
 
@@ -54,6 +65,10 @@ impl<S> Server<S> {
             listener: ([127, 0, 0, 1], 4000).into(),
             acceptor: (),
             protocol: Http::new(),
+            tcp_options: TcpOptions::default(),
+            read_timeout: None,
+            write_timeout: None,
+            shutdown_timeout: None,
             runtime: None,
         }
     }
@@ -72,6 +87,10 @@ impl<S, L, A, R> Server<S, L, A, R> {
             listener,
             acceptor: self.acceptor,
             protocol: self.protocol,
+            tcp_options: self.tcp_options,
+            read_timeout: self.read_timeout,
+            write_timeout: self.write_timeout,
+            shutdown_timeout: self.shutdown_timeout,
             runtime: self.runtime,
         }
     }
@@ -90,6 +109,10 @@ impl<S, L, A, R> Server<S, L, A, R> {
             listener: self.listener,
             acceptor,
             protocol: self.protocol,
+            tcp_options: self.tcp_options,
+            read_timeout: self.read_timeout,
+            write_timeout: self.write_timeout,
+            shutdown_timeout: self.shutdown_timeout,
             runtime: self.runtime,
         }
     }
@@ -101,6 +124,114 @@ impl<S, L, A, R> Server<S, L, A, R> {
         Self { protocol, ..self }
     }
 
+    /// Sets whether to serve all connections with HTTP/2.
+    ///
+    /// When enabled, the clients must start the connection with the HTTP/2 connection
+    /// preface, and the connections speaking HTTP/1 are rejected. This is useful when
+    /// the server is placed behind a reverse proxy that always speaks cleartext HTTP/2 (h2c).
+    ///
+    /// Note that the protocol selected by TLS ALPN is not taken into account by this option.
+    /// If the acceptor advertises `http/1.1` in addition to `h2`, the clients negotiating
+    /// HTTP/1.1 will fail to connect.
+    pub fn http2_only(mut self, enabled: bool) -> Self {
+        self.protocol.http2_only(enabled);
+        self
+    }
+
+    /// Sets whether to accept the HTTP/2 connections with prior knowledge.
+    ///
+    /// By default, the server speaks HTTP/1 and switches to HTTP/2 when the client sends
+    /// the HTTP/2 connection preface, so that both the h2c clients with prior knowledge and
+    /// the clients negotiating `h2` via TLS ALPN are served. If disabled, the server always
+    /// speaks HTTP/1 regardless of the protocol negotiated by ALPN.
+    ///
+    /// This option and `http2_only` overwrite each other, and the last one wins.
+    pub fn http2_prior_knowledge(mut self, enabled: bool) -> Self {
+        self.protocol.http1_only(!enabled);
+        self
+    }
+
+    /// Sets whether to enable `TCP_NODELAY` on the accepted TCP connections.
+    ///
+    /// By default, the option is left as the platform default (that is, disabled).
+    pub fn tcp_nodelay(mut self, enabled: bool) -> Self {
+        self.tcp_options.nodelay = Some(enabled);
+        self
+    }
+
+    /// Sets whether to enable `SO_REUSEADDR` on the TCP listener.
+    ///
+    /// See [`TcpOptions`](./struct.TcpOptions.html) for the platform differences.
+    pub fn reuse_address(mut self, enabled: bool) -> Self {
+        self.tcp_options.reuse_address = enabled;
+        self
+    }
+
+    /// Sets whether to enable `SO_REUSEPORT` on the TCP listener.
+    ///
+    /// This option allows multiple processes to bind the same address, and is
+    /// available only on Unix platforms. See [`TcpOptions`](./struct.TcpOptions.html)
+    /// for details.
+    pub fn reuse_port(mut self, enabled: bool) -> Self {
+        self.tcp_options.reuse_port = enabled;
+        self
+    }
+
+    /// Sets whether to enable `SO_KEEPALIVE` on the accepted TCP connections,
+    /// with the specified interval.
+    ///
+    /// If `None` is given, the keepalive is disabled. By default, the option
+    /// is left as the platform default.
+    pub fn keep_alive(mut self, keepalive: Option<Duration>) -> Self {
+        self.tcp_options.keepalive = Some(keepalive);
+        self
+    }
+
+    /// Sets the maximum buffer size for the HTTP/1 connections.
+    ///
+    /// The default value is the one used by hyper (about 400KB).
+    pub fn http1_max_buf_size(mut self, max: usize) -> Self {
+        self.protocol.max_buf_size(max);
+        self
+    }
+
+    /// Sets the maximum duration that reading from a connection can be blocked.
+    ///
+    /// The connection is closed if no data is received within the specified duration
+    /// while the server is waiting for it, including the time waiting for the next
+    /// request on a keep-alive connection. It protects the server from the clients
+    /// sending the requests slowly. By default, the timeout is disabled.
+    ///
+    /// Note that the timeout is also applied to the upgraded connections.
+    pub fn read_timeout(self, timeout: Duration) -> Self {
+        Self {
+            read_timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Sets the maximum duration that writing to a connection can be blocked.
+    ///
+    /// The connection is closed if the client does not receive the response within
+    /// the specified duration. By default, the timeout is disabled.
+    pub fn write_timeout(self, timeout: Duration) -> Self {
+        Self {
+            write_timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Sets the maximum duration to wait for the completion of the in-flight connections
+    /// after the shutdown signal passed to `run_until` is received.
+    ///
+    /// By default, the server waits until all of the connections are closed.
+    pub fn shutdown_timeout(self, timeout: Duration) -> Self {
+        Self {
+            shutdown_timeout: Some(timeout),
+            ..self
+        }
+    }
+
     /// Sets the instance of runtime to the specified `runtime`.
     pub fn runtime<R2>(self, runtime: R2) -> Server<S, L, A, R2> {
         Server {
@@ -108,6 +239,10 @@ impl<S, L, A, R> Server<S, L, A, R> {
             listener: self.listener,
             acceptor: self.acceptor,
             protocol: self.protocol,
+            tcp_options: self.tcp_options,
+            read_timeout: self.read_timeout,
+            write_timeout: self.write_timeout,
+            shutdown_timeout: self.shutdown_timeout,
             runtime: Some(runtime),
         }
     }
@@ -121,57 +256,122 @@ impl<S, L, A, R> Server<S, L, A, R> {
             listener: self.listener,
             acceptor: self.acceptor,
             protocol: self.protocol,
+            tcp_options: self.tcp_options,
+            read_timeout: self.read_timeout,
+            write_timeout: self.write_timeout,
+            shutdown_timeout: self.shutdown_timeout,
             runtime: None,
         }
     }
 }
 
 /// A macro for creating a server task from the specified components.
+///
+/// The created task stops accepting the new connections when `signal` completes,
+/// and then waits for the completion of the in-flight connections.
 macro_rules! serve {
     (
         make_service: $make_service:expr,
         listener: $listener:expr,
         acceptor: $acceptor:expr,
         protocol: $protocol:expr,
+        tcp_options: $tcp_options:expr,
+        io_timeouts: $io_timeouts:expr,
+        shutdown_timeout: $shutdown_timeout:expr,
+        signal: $signal:expr,
         spawn: $spawn:expr,
     ) => {{
         let make_service = $make_service;
         let listener = $listener;
         let acceptor = $acceptor;
         let protocol = $protocol;
+        let tcp_options = $tcp_options;
+        let (read_timeout, write_timeout) = $io_timeouts;
+        let shutdown_timeout = $shutdown_timeout;
+        let signal = $signal.shared();
         let spawn = $spawn;
 
-        let incoming = listener
-            .listen()
+        // Each connection task holds a sender, and the receiver is notified
+        // when all of the connections are closed.
+        let (drain_tx, drain_rx) = futures::sync::mpsc::channel::<()>(0);
+
+        let secure = acceptor.is_secure();
+        let incoming = crate::io::incoming_with_peer_addr(listener, &tcp_options)
             .map_err(|err| failure::Error::from_boxed_compat(err.into()))?;
-        incoming
-            .map_err(|e| log::error!("transport error: {}", e.into()))
-            .for_each(move |io| {
-                let accept = acceptor
-                    .accept(io)
-                    .map_err(|e| log::error!("acceptor error: {}", e.into()));
-
-                let protocol = protocol.clone();
-                let make_service = make_service.clone();
-                let task = accept.and_then(move |io| {
-                    let service = make_service
-                        .make_service_ref(&io)
-                        .map_err(|e| log::error!("make_service error: {}", e.into()));
-                    service
-                        .and_then(|service| {
-                            ReadyService(Some(service), PhantomData)
-                                .map_err(|e| log::error!("service error: {}", e.into()))
-                        })
-                        .and_then(move |service| {
-                            protocol
-                                .serve_connection(io, LiftedHttpService { service })
-                                .with_upgrades()
+        let accept_loop = {
+            let signal = signal.clone();
+            incoming
+                .map_err(|e| log::error!("transport error: {}", e.into()))
+                .for_each(move |(io, remote_addr)| {
+                    let accept = acceptor
+                        .accept(io)
+                        .map_err(|e| log::error!("acceptor error: {}", e.into()));
+
+                    let protocol = protocol.clone();
+                    let make_service = make_service.clone();
+                    let signal = signal.clone();
+                    let drain_tx = drain_tx.clone();
+                    let task = accept.and_then(move |io| {
+                        let service = make_service
+                            .make_service_ref(&io)
+                            .map_err(|e| log::error!("make_service error: {}", e.into()));
+                        service
+                            .and_then(|service| {
+                                ReadyService(Some(service), PhantomData)
+                                    .map_err(|e| log::error!("service error: {}", e.into()))
+                            })
+                            .and_then(move |service| {
+                                let io = TimeoutIo::new(io, read_timeout, write_timeout);
+                                let mut conn = protocol
+                                    .serve_connection(
+                                        io,
+                                        LiftedHttpService {
+                                            service,
+                                            remote_addr,
+                                            secure,
+                                        },
+                                    )
+                                    .with_upgrades();
+                                let mut signal = signal;
+                                let mut shutting_down = false;
+                                futures::future::poll_fn(move || {
+                                    let _ = &drain_tx;
+                                    if !shutting_down {
+                                        match signal.poll() {
+                                            Ok(futures::Async::NotReady) => {}
+                                            _ => {
+                                                shutting_down = true;
+                                                conn.graceful_shutdown();
+                                            }
+                                        }
+                                    }
+                                    conn.poll()
+                                })
                                 .map_err(|e| log::error!("HTTP protocol error: {}", e))
-                        })
-                });
-                spawn(task);
-                Ok(())
-            })
+                            })
+                    });
+                    spawn(task);
+                    Ok(())
+                })
+        };
+
+        accept_loop.select2(signal).then(move |result| {
+            // drop the listener and the sender owned by the accept loop.
+            drop(result);
+            log::debug!("stop accepting the new connections");
+            let drained = drain_rx.for_each(|_| Ok(()));
+            match shutdown_timeout {
+                Some(timeout) => futures::future::Either::A(
+                    tokio::timer::Timeout::new(drained, timeout).then(|result| {
+                        if result.is_err() {
+                            log::warn!("the shutdown timeout has elapsed");
+                        }
+                        Ok(())
+                    }),
+                ),
+                None => futures::future::Either::B(drained),
+            }
+        })
     }};
 }
 
@@ -194,7 +394,20 @@ where
     A::Error: Into<crate::CritError>,
     A::Accept: Send + 'static,
 {
+    /// Runs the server until the process is terminated.
     pub fn run(self) -> crate::Result<()> {
+        self.run_until(futures::future::empty())
+    }
+
+    /// Runs the server until the specified signal completes, and then shuts down gracefully.
+    ///
+    /// When `signal` completes (or fails), the server stops accepting the new connections
+    /// and waits for the in-flight connections to complete, within the duration specified
+    /// by `shutdown_timeout`. The idle keep-alive connections are closed immediately.
+    pub fn run_until<F>(self, signal: F) -> crate::Result<()>
+    where
+        F: Future<Item = (), Error = ()> + Send + 'static,
+    {
         let mut runtime = match self.runtime {
             Some(rt) => rt,
             None => tokio::runtime::Runtime::new()?,
@@ -207,11 +420,19 @@ where
             protocol: Arc::new(
                 self.protocol.with_executor(tokio::executor::DefaultExecutor::current())
             ),
+            tcp_options: self.tcp_options,
+            io_timeouts: (self.read_timeout, self.write_timeout),
+            shutdown_timeout: self.shutdown_timeout,
+            signal: signal,
             spawn: |future| crate::rt::spawn(future),
         };
 
-        runtime.spawn(serve);
-        runtime.shutdown_on_idle().wait().unwrap();
+        let _ = runtime.block_on(serve);
+        if self.shutdown_timeout.is_some() {
+            runtime.shutdown_now().wait().unwrap();
+        } else {
+            runtime.shutdown_on_idle().wait().unwrap();
+        }
 
         Ok(())
     }
@@ -233,7 +454,18 @@ where
     A::Error: Into<crate::CritError>,
     A::Accept: 'static,
 {
+    /// Runs the server until the process is terminated.
     pub fn run(self) -> crate::Result<()> {
+        self.run_until(futures::future::empty())
+    }
+
+    /// Runs the server until the specified signal completes, and then shuts down gracefully.
+    ///
+    /// See the documentation of the multi-threaded version for details.
+    pub fn run_until<F>(self, signal: F) -> crate::Result<()>
+    where
+        F: Future<Item = (), Error = ()> + 'static,
+    {
         let mut runtime = match self.runtime {
             Some(rt) => rt,
             None => tokio::runtime::current_thread::Runtime::new()?,
@@ -246,11 +478,17 @@ where
             protocol: Rc::new(
                 self.protocol.with_executor(tokio::runtime::current_thread::TaskExecutor::current())
             ),
+            tcp_options: self.tcp_options,
+            io_timeouts: (self.read_timeout, self.write_timeout),
+            shutdown_timeout: self.shutdown_timeout,
+            signal: signal,
             spawn: |future| tokio::runtime::current_thread::spawn(future),
         };
 
         let _ = runtime.block_on(serve);
-        runtime.run()?;
+        if self.shutdown_timeout.is_none() {
+            runtime.run()?;
+        }
 
         Ok(())
     }
@@ -259,6 +497,8 @@ where
 #[allow(missing_debug_implementations)]
 struct LiftedHttpService<S> {
     service: S,
+    remote_addr: Option<SocketAddr>,
+    secure: bool,
 }
 
 impl<S, Bd> hyper::service::Service for LiftedHttpService<S>
@@ -273,7 +513,13 @@ where
     type Future = S::Future;
 
     #[inline]
-    fn call(&mut self, request: Request<Body>) -> Self::Future {
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        if let Some(remote_addr) = self.remote_addr {
+            request.extensions_mut().insert(remote_addr);
+        }
+        if self.secure {
+            request.extensions_mut().insert(tsukuyomi_service::Secure);
+        }
         self.service.call(request)
     }
 }
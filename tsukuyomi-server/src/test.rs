@@ -5,14 +5,20 @@ mod output;
 mod server;
 
 pub use self::{
-    input::{Input, IntoRequestBody},
+    input::{BodyStream, Input, IntoRequestBody},
     output::Output,
     server::{Server, Session},
 };
 
+#[doc(no_inline)]
+pub use hyper::upgrade::Upgraded;
+
 use {
+    futures::Stream,
     http::{Request, Response},
-    hyper::body::Payload,
+    hyper::{body::Payload, Chunk},
+    serde::de::DeserializeOwned,
+    std::{io, str::FromStr},
     tsukuyomi_service::{MakeService, Service},
 };
 
@@ -26,9 +32,23 @@ pub trait ResponseExt {
     fn header<H>(&self, name: H) -> crate::Result<&http::header::HeaderValue>
     where
         H: http::header::AsHeaderName + std::fmt::Display;
+
+    /// Parses the value of header field with the specified name into `T`.
+    ///
+    /// If the header field does not exist or its value is invalid, this method will return an `Err`.
+    fn header_typed<T, H>(&self, name: H) -> crate::Result<T>
+    where
+        T: FromStr,
+        T::Err: Into<failure::Error>,
+        H: http::header::AsHeaderName + std::fmt::Display;
+
+    /// Deserializes the response body as a JSON value.
+    fn json<T>(&self) -> crate::Result<T>
+    where
+        T: DeserializeOwned;
 }
 
-impl<T> ResponseExt for http::Response<T> {
+impl ResponseExt for http::Response<Output> {
     fn header<H>(&self, name: H) -> crate::Result<&http::header::HeaderValue>
     where
         H: http::header::AsHeaderName + std::fmt::Display,
@@ -38,6 +58,59 @@ impl<T> ResponseExt for http::Response<T> {
             .get(name)
             .ok_or_else(|| crate::Error::from(err))
     }
+
+    fn header_typed<T, H>(&self, name: H) -> crate::Result<T>
+    where
+        T: FromStr,
+        T::Err: Into<failure::Error>,
+        H: http::header::AsHeaderName + std::fmt::Display,
+    {
+        let value = self.header(name)?.to_str()?;
+        value.parse::<T>().map_err(crate::Error::from)
+    }
+
+    fn json<T>(&self) -> crate::Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        serde_json::from_slice(&*self.body().to_bytes()).map_err(Into::into)
+    }
+}
+
+/// Creates a request body that transfers the chunks yielded by the specified `Stream`.
+///
+/// The body is passed to the service incrementally, without the `Content-Length` header.
+/// It is useful for testing the handlers that process the request body in a streaming manner.
+pub fn body_stream<S>(stream: S) -> BodyStream<S>
+where
+    S: Stream + Send + 'static,
+    S::Error: Into<crate::CritError>,
+    Chunk: From<S::Item>,
+{
+    BodyStream(stream)
+}
+
+/// Creates a request body that transfers the specified chunks and then fails,
+/// as if the client disconnected in the middle of sending the request body.
+///
+/// It is useful for testing that the handlers and extractors release the resources
+/// held during the extraction (e.g. temporary files) when they are aborted.
+pub fn body_disconnected<I>(
+    chunks: I,
+) -> BodyStream<impl Stream<Item = Chunk, Error = io::Error> + Send + 'static>
+where
+    I: IntoIterator,
+    I::IntoIter: Send + 'static,
+    Chunk: From<I::Item>,
+{
+    BodyStream(
+        futures::stream::iter_ok(chunks.into_iter().map(Chunk::from)).chain(futures::stream::once(
+            Err(io::Error::new(
+                io::ErrorKind::ConnectionAborted,
+                "the client has disconnected",
+            )),
+        )),
+    )
 }
 
 /// Creates a test server using the specified service factory.
@@ -1,6 +1,8 @@
 use {
+    futures::Stream,
     http::{header::HeaderValue, Request},
-    hyper::body::Body,
+    hyper::{body::Body, Chunk},
+    std::fmt,
 };
 
 // ==== traits ====
@@ -75,6 +77,13 @@ impl<'a> InputImpl for &'a mut http::request::Builder {
     }
 }
 
+impl IntoRequestBody for Body {}
+impl IntoRequestBodyImpl for Body {
+    fn into_request_body(self) -> Body {
+        self
+    }
+}
+
 impl IntoRequestBody for () {}
 impl IntoRequestBodyImpl for () {
     fn into_request_body(self) -> Body {
@@ -115,3 +124,32 @@ impl IntoRequestBodyImpl for Vec<u8> {
         self.into()
     }
 }
+
+/// A request body that transfers the chunks yielded by a `Stream`.
+///
+/// The value of this type is created by [`body_stream`](./fn.body_stream.html).
+pub struct BodyStream<S>(pub(super) S);
+
+impl<S> fmt::Debug for BodyStream<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("BodyStream").finish()
+    }
+}
+
+impl<S> IntoRequestBody for BodyStream<S>
+where
+    S: Stream + Send + 'static,
+    S::Error: Into<crate::CritError>,
+    Chunk: From<S::Item>,
+{
+}
+impl<S> IntoRequestBodyImpl for BodyStream<S>
+where
+    S: Stream + Send + 'static,
+    S::Error: Into<crate::CritError>,
+    Chunk: From<S::Item>,
+{
+    fn into_request_body(self) -> Body {
+        Body::wrap_stream(self.0)
+    }
+}
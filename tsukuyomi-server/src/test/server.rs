@@ -9,11 +9,11 @@ use {
     cookie::Cookie,
     futures::{Future, Poll},
     http::{
-        header::{COOKIE, SET_COOKIE},
-        Request, Response,
+        header::{HeaderMap, CONTENT_LENGTH, CONTENT_TYPE, COOKIE, LOCATION, SET_COOKIE},
+        Method, Request, Response, StatusCode, Uri,
     },
     hyper::body::Payload,
-    std::{collections::HashMap, mem},
+    std::{collections::HashMap, fmt, mem, sync::Arc},
     tsukuyomi_service::{MakeService, Service},
 };
 
@@ -22,6 +22,7 @@ use {
 pub struct Server<S, Rt = tokio::runtime::Runtime> {
     make_service: S,
     runtime: Rt,
+    extensions: Extensions,
 }
 
 impl<S, Rt> Server<S, Rt>
@@ -33,6 +34,43 @@ where
         Self {
             make_service,
             runtime,
+            extensions: Extensions::default(),
+        }
+    }
+
+    /// Registers a value to be inserted into the extensions of every request
+    /// sent from this server.
+    ///
+    /// This is useful for replacing the components that the service looks up
+    /// from the request extensions, e.g. the clock in Tsukuyomi.
+    pub fn extension<T>(mut self, value: T) -> Self
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.extensions.0.push(Arc::new(move |extensions| {
+            extensions.insert(value.clone());
+        }));
+        self
+    }
+}
+
+/// A set of functions that insert values into the extensions of requests.
+#[derive(Clone, Default)]
+#[allow(clippy::type_complexity)]
+struct Extensions(Vec<Arc<dyn Fn(&mut http::Extensions) + Send + Sync + 'static>>);
+
+impl fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.0.len())
+            .finish()
+    }
+}
+
+impl Extensions {
+    fn apply<T>(&self, request: &mut Request<T>) {
+        for insert in &self.0 {
+            insert(request.extensions_mut());
         }
     }
 }
@@ -44,17 +82,19 @@ pub struct Session<'a, S, Rt: 'a> {
     service: S,
     cookies: Option<HashMap<String, String>>,
     runtime: &'a mut Rt,
+    extensions: Extensions,
 }
 
 impl<'a, S, Rt> Session<'a, S, Rt>
 where
     S: Service<Request<hyper::Body>>,
 {
-    fn new(service: S, runtime: &'a mut Rt) -> Self {
+    fn new(service: S, runtime: &'a mut Rt, extensions: Extensions) -> Self {
         Session {
             service,
             runtime,
             cookies: None,
+            extensions,
         }
     }
 
@@ -84,6 +124,7 @@ where
         T: Input,
     {
         let mut request = input.build_request()?;
+        self.extensions.apply(&mut request);
         if let Some(cookies) = &self.cookies {
             for (k, v) in cookies {
                 request.headers_mut().append(
@@ -115,8 +156,11 @@ where
 mod threadpool {
     use {
         super::*,
+        crate::LiftedHttpService,
+        futures::future::Either,
+        hyper::{server::conn::Http, upgrade::Upgraded},
         std::panic::{resume_unwind, AssertUnwindSafe},
-        tokio::runtime::Runtime,
+        tokio::{reactor::Handle, runtime::Runtime},
     };
 
     fn block_on<F>(runtime: &mut Runtime, future: F) -> Result<F::Item, F::Error>
@@ -148,7 +192,11 @@ mod threadpool {
             )
             .map_err(failure::Error::from_boxed_compat)?;
 
-            Ok(Session::new(service, &mut self.runtime))
+            Ok(Session::new(
+                service,
+                &mut self.runtime,
+                self.extensions.clone(),
+            ))
         }
 
         pub fn perform<T>(&mut self, input: T) -> crate::Result<Response<Output>>
@@ -159,6 +207,84 @@ mod threadpool {
             let mut session = self.new_session()?;
             session.perform(input)
         }
+
+        /// Applies an HTTP request and follows the redirects.
+        ///
+        /// See `Session::perform_follow` for details.
+        pub fn perform_follow<T>(
+            &mut self,
+            input: T,
+            max_redirects: usize,
+        ) -> crate::Result<(Response<Output>, Vec<StatusCode>)>
+        where
+            T: Input,
+            <S::Service as Service<Request<hyper::Body>>>::Future: Send + 'static,
+        {
+            let mut session = self.new_session()?;
+            session.perform_follow(input, max_redirects)
+        }
+
+        /// Applies an HTTP request that upgrades the connection, and returns the response
+        /// with the upgraded I/O.
+        ///
+        /// Unlike `perform`, the request is sent over a loopback TCP connection in order
+        /// to allow the service to take over the connection after the response.
+        /// This method fails if the response status is not `101 Switching Protocols`.
+        pub fn upgrade<T>(&mut self, input: T) -> crate::Result<(Response<()>, Upgraded)>
+        where
+            T: Input,
+            <S::Service as Service<Request<hyper::Body>>>::Future: Send + 'static,
+        {
+            let mut request = input.build_request()?;
+            self.extensions.apply(&mut request);
+
+            let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+            let client_io = std::net::TcpStream::connect(listener.local_addr()?)?;
+            let (server_io, _) = listener.accept()?;
+
+            let service = block_on(
+                &mut self.runtime,
+                self.make_service.make_service(()).map_err(Into::into),
+            )
+            .map_err(failure::Error::from_boxed_compat)?;
+
+            let future = futures::future::lazy(move || -> Result<_, CritError> {
+                let server_io = tokio::net::TcpStream::from_std(server_io, &Handle::current())?;
+                let client_io = tokio::net::TcpStream::from_std(client_io, &Handle::current())?;
+
+                tokio::spawn(
+                    Http::new()
+                        .serve_connection(server_io, LiftedHttpService { service })
+                        .with_upgrades()
+                        .map_err(|e| log::error!("connection error: {}", e)),
+                );
+
+                Ok(hyper::client::conn::handshake(client_io).map_err(CritError::from))
+            })
+            .flatten()
+            .and_then(|(mut send_request, conn)| {
+                tokio::spawn(conn.map_err(|e| log::error!("connection error: {}", e)));
+                send_request.send_request(request).map_err(CritError::from)
+            })
+            .and_then(|response| {
+                let (parts, body) = response.into_parts();
+                if parts.status != StatusCode::SWITCHING_PROTOCOLS {
+                    let err = failure::format_err!(
+                        "the connection was not upgraded: status = {}",
+                        parts.status
+                    );
+                    return Either::A(futures::future::err(CritError::from(err.compat())));
+                }
+                Either::B(
+                    body.on_upgrade()
+                        .map(move |upgraded| (Response::from_parts(parts, ()), upgraded))
+                        .map_err(CritError::from),
+                )
+            });
+
+            block_on(&mut self.runtime, future)
+                .map_err(|err| failure::Error::from_boxed_compat(err).into())
+        }
     }
 
     impl<'a, S, Bd> Session<'a, S, Runtime>
@@ -182,6 +308,44 @@ mod threadpool {
 
             Ok(response)
         }
+
+        /// Applies an HTTP request to this client, and follows the redirects until
+        /// a non-redirect response is returned.
+        ///
+        /// The returned value contains the final response and the statuses of
+        /// the intermediate redirect responses. The request is re-issued with the method
+        /// `GET` and without the body on `301`, `302` and `303`, and with the original
+        /// method on `307` and `308` (the body is not re-sent). If the number of redirects
+        /// exceeds `max_redirects`, this method returns an `Err`.
+        pub fn perform_follow<T>(
+            &mut self,
+            input: T,
+            max_redirects: usize,
+        ) -> crate::Result<(Response<Output>, Vec<StatusCode>)>
+        where
+            T: Input,
+        {
+            let mut request = input.build_request()?;
+            let mut statuses = vec![];
+            loop {
+                let head = RequestHead::new(&request);
+                let response = self.perform(request)?;
+                match head.redirect(&response)? {
+                    Some(next) => {
+                        if statuses.len() >= max_redirects {
+                            return Err(failure::format_err!(
+                                "too many redirects (max_redirects = {})",
+                                max_redirects
+                            )
+                            .into());
+                        }
+                        statuses.push(response.status());
+                        request = next;
+                    }
+                    None => return Ok((response, statuses)),
+                }
+            }
+        }
     }
 }
 
@@ -201,7 +365,11 @@ mod current_thread {
                 .runtime
                 .block_on(self.make_service.make_service(()))
                 .map_err(|err| failure::Error::from_boxed_compat(err.into()))?;
-            Ok(Session::new(service, &mut self.runtime))
+            Ok(Session::new(
+                service,
+                &mut self.runtime,
+                self.extensions.clone(),
+            ))
         }
 
         pub fn perform<T>(&mut self, input: T) -> crate::Result<Response<Output>>
@@ -211,6 +379,21 @@ mod current_thread {
             let mut session = self.new_session()?;
             session.perform(input)
         }
+
+        /// Applies an HTTP request and follows the redirects.
+        ///
+        /// See `Session::perform_follow` for details.
+        pub fn perform_follow<T>(
+            &mut self,
+            input: T,
+            max_redirects: usize,
+        ) -> crate::Result<(Response<Output>, Vec<StatusCode>)>
+        where
+            T: Input,
+        {
+            let mut session = self.new_session()?;
+            session.perform_follow(input, max_redirects)
+        }
     }
 
     impl<'a, S, Bd> Session<'a, S, Runtime>
@@ -235,6 +418,103 @@ mod current_thread {
 
             Ok(response)
         }
+
+        /// Applies an HTTP request to this client, and follows the redirects until
+        /// a non-redirect response is returned.
+        ///
+        /// The returned value contains the final response and the statuses of
+        /// the intermediate redirect responses. The request is re-issued with the method
+        /// `GET` and without the body on `301`, `302` and `303`, and with the original
+        /// method on `307` and `308` (the body is not re-sent). If the number of redirects
+        /// exceeds `max_redirects`, this method returns an `Err`.
+        pub fn perform_follow<T>(
+            &mut self,
+            input: T,
+            max_redirects: usize,
+        ) -> crate::Result<(Response<Output>, Vec<StatusCode>)>
+        where
+            T: Input,
+        {
+            let mut request = input.build_request()?;
+            let mut statuses = vec![];
+            loop {
+                let head = RequestHead::new(&request);
+                let response = self.perform(request)?;
+                match head.redirect(&response)? {
+                    Some(next) => {
+                        if statuses.len() >= max_redirects {
+                            return Err(failure::format_err!(
+                                "too many redirects (max_redirects = {})",
+                                max_redirects
+                            )
+                            .into());
+                        }
+                        statuses.push(response.status());
+                        request = next;
+                    }
+                    None => return Ok((response, statuses)),
+                }
+            }
+        }
+    }
+}
+
+/// The head of a request, used for building the request following a redirect.
+#[derive(Debug)]
+struct RequestHead {
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+}
+
+impl RequestHead {
+    fn new<T>(request: &Request<T>) -> Self {
+        Self {
+            method: request.method().clone(),
+            uri: request.uri().clone(),
+            headers: request.headers().clone(),
+        }
+    }
+
+    fn redirect(&self, response: &Response<Output>) -> crate::Result<Option<Request<hyper::Body>>> {
+        let method = match response.status() {
+            StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND | StatusCode::SEE_OTHER => {
+                if self.method == Method::HEAD {
+                    Method::HEAD
+                } else {
+                    Method::GET
+                }
+            }
+            StatusCode::TEMPORARY_REDIRECT | StatusCode::PERMANENT_REDIRECT => self.method.clone(),
+            _ => return Ok(None),
+        };
+
+        let location = response
+            .headers()
+            .get(LOCATION)
+            .ok_or_else(|| failure::format_err!("missing header field: `Location'"))?
+            .to_str()?;
+        let uri = self.resolve(location)?;
+
+        let mut request = Request::new(hyper::Body::empty());
+        *request.method_mut() = method;
+        *request.uri_mut() = uri;
+        *request.headers_mut() = self.headers.clone();
+        request.headers_mut().remove(CONTENT_LENGTH);
+        request.headers_mut().remove(CONTENT_TYPE);
+        Ok(Some(request))
+    }
+
+    fn resolve(&self, location: &str) -> crate::Result<Uri> {
+        let uri: Uri = location.parse()?;
+        if uri.scheme_part().is_some() || location.starts_with('/') {
+            return Ok(uri);
+        }
+
+        // relative reference: resolve against the directory of the current path.
+        let path = self.uri.path();
+        let base = &path[..path.rfind('/').map_or(0, |pos| pos + 1)];
+        Ok(format!("{}{}", base, location).parse()?)
     }
 }
 
@@ -2,3 +2,522 @@
 fn test_version_sync() {
     version_sync::assert_html_root_url_updated!("src/lib.rs");
 }
+
+#[test]
+fn test_http2_prior_knowledge() -> tsukuyomi_server::Result<()> {
+    use {
+        futures::{sync::oneshot, Future, Stream},
+        http::{Request, Response, Version},
+        hyper::{Body, Client},
+        tokio::net::TcpStream,
+        tsukuyomi_server::Server,
+        tsukuyomi_service::{make_service_ref, service_fn},
+    };
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+
+    let make_service = make_service_ref(|_: &TcpStream| {
+        Ok::<_, std::io::Error>(service_fn(|request: Request<Body>| {
+            let body = format!("{:?}", request.version());
+            Ok::<_, std::io::Error>(Response::new(Body::from(body)))
+        }))
+    });
+
+    let (tx_shutdown, rx_shutdown) = oneshot::channel::<()>();
+    let server = std::thread::spawn(move || {
+        Server::new(make_service)
+            .bind(listener)
+            .http2_prior_knowledge(true)
+            .run_until(rx_shutdown.map_err(|_| ()))
+    });
+
+    let mut runtime = tokio::runtime::Runtime::new()?;
+    let client = Client::builder().http2_only(true).build_http::<Body>();
+    let response = runtime.block_on(client.get(format!("http://{}/", addr).parse()?))?;
+    assert_eq!(response.version(), Version::HTTP_2);
+    let body = runtime.block_on(response.into_body().concat2())?;
+    assert_eq!(&*body, &b"HTTP/2.0"[..]);
+
+    let _ = tx_shutdown.send(());
+    server.join().expect("the server thread panicked")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_remote_addr() -> tsukuyomi_server::Result<()> {
+    use {
+        futures::{sync::oneshot, Future, Stream},
+        http::{Request, Response},
+        hyper::{Body, Client},
+        std::net::SocketAddr,
+        tokio::net::TcpStream,
+        tsukuyomi_server::Server,
+        tsukuyomi_service::{make_service_ref, service_fn},
+    };
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+
+    let make_service = make_service_ref(|_: &TcpStream| {
+        Ok::<_, std::io::Error>(service_fn(|request: Request<Body>| {
+            let body = match request.extensions().get::<SocketAddr>() {
+                Some(remote_addr) => remote_addr.ip().to_string(),
+                None => "<none>".into(),
+            };
+            Ok::<_, std::io::Error>(Response::new(Body::from(body)))
+        }))
+    });
+
+    let (tx_shutdown, rx_shutdown) = oneshot::channel::<()>();
+    let server = std::thread::spawn(move || {
+        Server::new(make_service)
+            .bind(listener)
+            .run_until(rx_shutdown.map_err(|_| ()))
+    });
+
+    let mut runtime = tokio::runtime::Runtime::new()?;
+    let client = Client::new();
+    let response = runtime.block_on(client.get(format!("http://{}/", addr).parse()?))?;
+    let body = runtime.block_on(response.into_body().concat2())?;
+    assert_eq!(&*body, &b"127.0.0.1"[..]);
+
+    let _ = tx_shutdown.send(());
+    server.join().expect("the server thread panicked")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_secure_acceptor() -> tsukuyomi_server::Result<()> {
+    use {
+        futures::{future::FutureResult, sync::oneshot, Future, Stream},
+        http::{Request, Response},
+        hyper::{Body, Client},
+        tokio::net::TcpStream,
+        tsukuyomi_server::{Acceptor, Server},
+        tsukuyomi_service::{make_service_ref, service_fn, Secure},
+    };
+
+    // An acceptor that marks the raw connections as secure, in place of a TLS acceptor.
+    struct SecureAcceptor;
+
+    impl Acceptor<TcpStream> for SecureAcceptor {
+        type Conn = TcpStream;
+        type Error = std::io::Error;
+        type Accept = FutureResult<Self::Conn, Self::Error>;
+
+        fn accept(&self, io: TcpStream) -> Self::Accept {
+            futures::future::ok(io)
+        }
+
+        fn is_secure(&self) -> bool {
+            true
+        }
+    }
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+
+    let make_service = make_service_ref(|_: &TcpStream| {
+        Ok::<_, std::io::Error>(service_fn(|request: Request<Body>| {
+            let secure = request.extensions().get::<Secure>().is_some();
+            Ok::<_, std::io::Error>(Response::new(Body::from(secure.to_string())))
+        }))
+    });
+
+    let (tx_shutdown, rx_shutdown) = oneshot::channel::<()>();
+    let server = std::thread::spawn(move || {
+        Server::new(make_service)
+            .bind(listener)
+            .acceptor(SecureAcceptor)
+            .run_until(rx_shutdown.map_err(|_| ()))
+    });
+
+    let mut runtime = tokio::runtime::Runtime::new()?;
+    let client = Client::new();
+    let response = runtime.block_on(client.get(format!("http://{}/", addr).parse()?))?;
+    let body = runtime.block_on(response.into_body().concat2())?;
+    assert_eq!(&*body, &b"true"[..]);
+
+    let _ = tx_shutdown.send(());
+    server.join().expect("the server thread panicked")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_tcp_nodelay() -> tsukuyomi_server::Result<()> {
+    use {
+        futures::{sync::oneshot, Future, Stream},
+        http::{Request, Response},
+        hyper::{Body, Client},
+        tokio::net::TcpStream,
+        tsukuyomi_server::Server,
+        tsukuyomi_service::{make_service_ref, service_fn},
+    };
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+
+    let make_service = make_service_ref(|conn: &TcpStream| {
+        let nodelay = conn.nodelay()?;
+        Ok::<_, std::io::Error>(service_fn(move |_: Request<Body>| {
+            Ok::<_, std::io::Error>(Response::new(Body::from(nodelay.to_string())))
+        }))
+    });
+
+    let (tx_shutdown, rx_shutdown) = oneshot::channel::<()>();
+    let server = std::thread::spawn(move || {
+        Server::new(make_service)
+            .bind(listener)
+            .tcp_nodelay(true)
+            .run_until(rx_shutdown.map_err(|_| ()))
+    });
+
+    let mut runtime = tokio::runtime::Runtime::new()?;
+    let client = Client::new();
+    let response = runtime.block_on(client.get(format!("http://{}/", addr).parse()?))?;
+    let body = runtime.block_on(response.into_body().concat2())?;
+    assert_eq!(&*body, &b"true"[..]);
+
+    let _ = tx_shutdown.send(());
+    server.join().expect("the server thread panicked")?;
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_reuse_port() -> tsukuyomi_server::Result<()> {
+    use {
+        futures::{sync::oneshot, Future, Stream},
+        http::{Request, Response},
+        hyper::{Body, Client},
+        std::net::SocketAddr,
+        tokio::net::TcpStream,
+        tsukuyomi_server::Server,
+        tsukuyomi_service::{make_service_ref, service_fn},
+    };
+
+    // Find a free port and release it so that the servers bind it by themselves.
+    let addr: SocketAddr = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+
+    let spawn_server = move |name: &'static str| {
+        let make_service = make_service_ref(move |_: &TcpStream| {
+            Ok::<_, std::io::Error>(service_fn(move |_: Request<Body>| {
+                Ok::<_, std::io::Error>(Response::new(Body::from(name)))
+            }))
+        });
+        let (tx_shutdown, rx_shutdown) = oneshot::channel::<()>();
+        let server = std::thread::spawn(move || {
+            Server::new(make_service)
+                .bind(addr)
+                .reuse_address(true)
+                .reuse_port(true)
+                .run_until(rx_shutdown.map_err(|_| ()))
+        });
+        (tx_shutdown, server)
+    };
+
+    // Both servers can bind the same address, since SO_REUSEPORT is enabled.
+    let (tx_shutdown1, server1) = spawn_server("server1");
+    let (tx_shutdown2, server2) = spawn_server("server2");
+
+    let mut runtime = tokio::runtime::Runtime::new()?;
+    let client = Client::new();
+    let mut retries = 0;
+    let response = loop {
+        match runtime.block_on(client.get(format!("http://{}/", addr).parse()?)) {
+            Ok(response) => break response,
+            Err(ref err) if err.is_connect() && retries < 10 => {
+                retries += 1;
+                std::thread::sleep(std::time::Duration::from_millis(50));
+            }
+            Err(err) => return Err(err.into()),
+        }
+    };
+    let body = runtime.block_on(response.into_body().concat2())?;
+    assert!(&*body == b"server1" || &*body == b"server2");
+
+    let _ = tx_shutdown1.send(());
+    let _ = tx_shutdown2.send(());
+    server1.join().expect("the server thread panicked")?;
+    server2.join().expect("the server thread panicked")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_run_until_graceful_shutdown() -> tsukuyomi_server::Result<()> {
+    use {
+        futures::{sync::oneshot, Future, Stream},
+        http::{Request, Response},
+        hyper::{Body, Client},
+        std::{
+            sync::{Arc, Mutex},
+            time::{Duration, Instant},
+        },
+        tokio::{net::TcpStream, timer::Delay},
+        tsukuyomi_server::Server,
+        tsukuyomi_service::{make_service_ref, service_fn},
+    };
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+
+    // Notifies that the request has been received by the server.
+    let (tx_received, rx_received) = oneshot::channel::<()>();
+    let tx_received = Arc::new(Mutex::new(Some(tx_received)));
+
+    let make_service = make_service_ref(move |_: &TcpStream| {
+        let tx_received = tx_received.clone();
+        Ok::<_, std::io::Error>(service_fn(move |_: Request<Body>| {
+            if let Some(tx) = tx_received.lock().unwrap().take() {
+                let _ = tx.send(());
+            }
+            Delay::new(Instant::now() + Duration::from_millis(300))
+                .map(|()| Response::new(Body::from("done")))
+        }))
+    });
+
+    let (tx_shutdown, rx_shutdown) = oneshot::channel::<()>();
+    let server = std::thread::spawn(move || {
+        Server::new(make_service)
+            .bind(listener)
+            .run_until(rx_shutdown.map_err(|_| ()))
+    });
+
+    let mut runtime = tokio::runtime::Runtime::new()?;
+    let client = Client::new();
+    let response = oneshot::spawn(
+        client.get(format!("http://{}/", addr).parse()?),
+        &runtime.executor(),
+    );
+
+    // The signal is sent while the request is still being handled.
+    let _ = runtime.block_on(rx_received);
+    let _ = tx_shutdown.send(());
+
+    // The in-flight request is completed before the server shuts down.
+    let response = runtime.block_on(response)?;
+    let body = runtime.block_on(response.into_body().concat2())?;
+    assert_eq!(&*body, &b"done"[..]);
+
+    server.join().expect("the server thread panicked")?;
+
+    // The server no longer accepts the new connections.
+    assert!(std::net::TcpStream::connect(addr).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_run_until_shutdown_timeout() -> tsukuyomi_server::Result<()> {
+    use {
+        futures::{sync::oneshot, Future},
+        http::{Request, Response},
+        hyper::{Body, Client},
+        std::{
+            sync::{Arc, Mutex},
+            time::{Duration, Instant},
+        },
+        tokio::{net::TcpStream, timer::Delay},
+        tsukuyomi_server::Server,
+        tsukuyomi_service::{make_service_ref, service_fn},
+    };
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+
+    let (tx_received, rx_received) = oneshot::channel::<()>();
+    let tx_received = Arc::new(Mutex::new(Some(tx_received)));
+
+    let make_service = make_service_ref(move |_: &TcpStream| {
+        let tx_received = tx_received.clone();
+        Ok::<_, std::io::Error>(service_fn(move |_: Request<Body>| {
+            if let Some(tx) = tx_received.lock().unwrap().take() {
+                let _ = tx.send(());
+            }
+            Delay::new(Instant::now() + Duration::from_secs(10))
+                .map(|()| Response::new(Body::from("done")))
+        }))
+    });
+
+    let (tx_shutdown, rx_shutdown) = oneshot::channel::<()>();
+    let server = std::thread::spawn(move || {
+        Server::new(make_service)
+            .bind(listener)
+            .shutdown_timeout(Duration::from_millis(100))
+            .run_until(rx_shutdown.map_err(|_| ()))
+    });
+
+    let mut runtime = tokio::runtime::Runtime::new()?;
+    let client = Client::new();
+    let response = oneshot::spawn(
+        client.get(format!("http://{}/", addr).parse()?),
+        &runtime.executor(),
+    );
+
+    let _ = runtime.block_on(rx_received);
+    let start = Instant::now();
+    let _ = tx_shutdown.send(());
+
+    // The server gives up the in-flight request after the timeout elapsed.
+    server.join().expect("the server thread panicked")?;
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert!(runtime.block_on(response).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_keep_alive() -> tsukuyomi_server::Result<()> {
+    use {
+        futures::{sync::oneshot, Future, Stream},
+        http::{Request, Response},
+        hyper::{Body, Client},
+        std::time::Duration,
+        tokio::net::TcpStream,
+        tsukuyomi_server::Server,
+        tsukuyomi_service::{make_service_ref, service_fn},
+    };
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+
+    let make_service = make_service_ref(|conn: &TcpStream| {
+        let keepalive = conn.keepalive()?;
+        Ok::<_, std::io::Error>(service_fn(move |_: Request<Body>| {
+            let body = keepalive.is_some().to_string();
+            Ok::<_, std::io::Error>(Response::new(Body::from(body)))
+        }))
+    });
+
+    let (tx_shutdown, rx_shutdown) = oneshot::channel::<()>();
+    let server = std::thread::spawn(move || {
+        Server::new(make_service)
+            .bind(listener)
+            .keep_alive(Some(Duration::from_secs(10)))
+            .run_until(rx_shutdown.map_err(|_| ()))
+    });
+
+    let mut runtime = tokio::runtime::Runtime::new()?;
+    let client = Client::new();
+    let response = runtime.block_on(client.get(format!("http://{}/", addr).parse()?))?;
+    let body = runtime.block_on(response.into_body().concat2())?;
+    assert_eq!(&*body, &b"true"[..]);
+
+    let _ = tx_shutdown.send(());
+    server.join().expect("the server thread panicked")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_read_timeout() -> tsukuyomi_server::Result<()> {
+    use {
+        futures::{sync::oneshot, Future},
+        http::{Request, Response},
+        hyper::Body,
+        std::{
+            io::{self, Read, Write},
+            time::Duration,
+        },
+        tokio::net::TcpStream,
+        tsukuyomi_server::Server,
+        tsukuyomi_service::{make_service_ref, service_fn},
+    };
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+
+    let make_service = make_service_ref(|_: &TcpStream| {
+        Ok::<_, std::io::Error>(service_fn(|_: Request<Body>| {
+            Ok::<_, std::io::Error>(Response::new(Body::empty()))
+        }))
+    });
+
+    let (tx_shutdown, rx_shutdown) = oneshot::channel::<()>();
+    let server = std::thread::spawn(move || {
+        Server::new(make_service)
+            .bind(listener)
+            .read_timeout(Duration::from_millis(100))
+            .run_until(rx_shutdown.map_err(|_| ()))
+    });
+
+    // A slow client that never completes the request header.
+    let mut stream = std::net::TcpStream::connect(addr)?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n")?;
+
+    // The server closes the connection without waiting for the rest of request.
+    let mut buf = vec![];
+    match stream.read_to_end(&mut buf) {
+        Ok(..) => {}
+        Err(ref err) if err.kind() == io::ErrorKind::ConnectionReset => {}
+        Err(err) => panic!("the connection is not closed by the server: {}", err),
+    }
+    assert!(!buf.starts_with(b"HTTP/1.1 200"));
+
+    let _ = tx_shutdown.send(());
+    server.join().expect("the server thread panicked")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_http1_max_buf_size() -> tsukuyomi_server::Result<()> {
+    use {
+        futures::{sync::oneshot, Future},
+        http::{Request, Response},
+        hyper::Body,
+        std::{
+            io::{self, Read, Write},
+            time::Duration,
+        },
+        tokio::net::TcpStream,
+        tsukuyomi_server::Server,
+        tsukuyomi_service::{make_service_ref, service_fn},
+    };
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+
+    let make_service = make_service_ref(|_: &TcpStream| {
+        Ok::<_, std::io::Error>(service_fn(|_: Request<Body>| {
+            Ok::<_, std::io::Error>(Response::new(Body::empty()))
+        }))
+    });
+
+    let (tx_shutdown, rx_shutdown) = oneshot::channel::<()>();
+    let server = std::thread::spawn(move || {
+        Server::new(make_service)
+            .bind(listener)
+            .http1_max_buf_size(8192)
+            .run_until(rx_shutdown.map_err(|_| ()))
+    });
+
+    // The request header larger than the buffer is rejected.
+    let mut stream = std::net::TcpStream::connect(addr)?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let request = format!(
+        "GET / HTTP/1.1\r\nHost: localhost\r\nX-Large: {}\r\n\r\n",
+        "a".repeat(16 * 1024)
+    );
+    let _ = stream.write_all(request.as_bytes());
+
+    let mut buf = vec![];
+    match stream.read_to_end(&mut buf) {
+        Ok(..) => {}
+        Err(ref err) if err.kind() == io::ErrorKind::ConnectionReset => {}
+        Err(err) => panic!("the connection is not closed by the server: {}", err),
+    }
+    assert!(!buf.starts_with(b"HTTP/1.1 200"));
+
+    let _ = tx_shutdown.send(());
+    server.join().expect("the server thread panicked")?;
+
+    Ok(())
+}
@@ -56,6 +56,13 @@ where
     ServiceFn(f)
 }
 
+/// A marker value indicating that the request is received over a secure connection.
+///
+/// The server inserts this value into the extensions of each request received from
+/// the connection established by the secure (e.g. TLS) acceptor.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Secure;
+
 /// A trait representing a factory of `Service`s.
 ///
 /// The signature of this trait imitates `tower_util::MakeService`,
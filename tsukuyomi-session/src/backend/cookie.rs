@@ -6,18 +6,21 @@ use {
     tsukuyomi::{
         error::{Error, Result},
         future::{Poll, TryFuture},
-        input::{Cookies, Input},
+        input::Input,
     },
 };
 
 #[cfg(feature = "secure")]
-use cookie::Key;
+use {cookie::Key, tsukuyomi::input::state::States};
 
+/// The secret key used for signing or encrypting the Cookie entry.
+///
+/// If the key is not specified, the one registered in the scope as a shared state is used.
 #[cfg(feature = "secure")]
 enum Security {
     Plain,
-    Signed(Key),
-    Private(Key),
+    Signed(Option<Key>),
+    Private(Option<Key>),
 }
 
 #[cfg(not(feature = "secure"))]
@@ -31,34 +34,60 @@ impl fmt::Debug for Security {
         match self {
             Security::Plain => f.debug_tuple("Plain").finish(),
             #[cfg(feature = "secure")]
-            Security::Signed(..) => f.debug_tuple("Signed").field(&"<secret key>").finish(),
+            Security::Signed(ref key) => f
+                .debug_tuple("Signed")
+                .field(&key.as_ref().map(|_| "<secret key>"))
+                .finish(),
             #[cfg(feature = "secure")]
-            Security::Private(..) => f.debug_tuple("Private").field(&"<secret key>").finish(),
+            Security::Private(ref key) => f
+                .debug_tuple("Private")
+                .field(&key.as_ref().map(|_| "<secret key>"))
+                .finish(),
         }
     }
 }
 
 impl Security {
-    fn get(&self, name: &str, cookies: &mut Cookies<'_>) -> Result<Option<Cookie<'static>>> {
+    fn get(&self, name: &str, input: &mut Input<'_>) -> Result<Option<Cookie<'static>>> {
         match self {
-            Security::Plain => Ok(cookies.jar()?.get(name).cloned()),
+            Security::Plain => Ok(input.cookies.jar()?.get(name).cloned()),
             #[cfg(feature = "secure")]
-            Security::Signed(ref key) => Ok(cookies.signed_jar(key)?.get(name)),
+            Security::Signed(ref key) => {
+                let key = Self::key(key, input.states)?;
+                Ok(input.cookies.signed_jar(key)?.get(name))
+            }
             #[cfg(feature = "secure")]
-            Security::Private(ref key) => Ok(cookies.private_jar(key)?.get(name)),
+            Security::Private(ref key) => {
+                let key = Self::key(key, input.states)?;
+                Ok(input.cookies.private_jar(key)?.get(name))
+            }
         }
     }
 
-    fn add(&self, cookie: Cookie<'static>, cookies: &mut Cookies<'_>) -> Result<()> {
+    fn add(&self, cookie: Cookie<'static>, input: &mut Input<'_>) -> Result<()> {
         match self {
-            Security::Plain => cookies.jar()?.add(cookie),
+            Security::Plain => input.cookies.jar()?.add(cookie),
             #[cfg(feature = "secure")]
-            Security::Signed(ref key) => cookies.signed_jar(key)?.add(cookie),
+            Security::Signed(ref key) => {
+                let key = Self::key(key, input.states)?;
+                input.cookies.signed_jar(key)?.add(cookie)
+            }
             #[cfg(feature = "secure")]
-            Security::Private(ref key) => cookies.private_jar(key)?.add(cookie),
+            Security::Private(ref key) => {
+                let key = Self::key(key, input.states)?;
+                input.cookies.private_jar(key)?.add(cookie)
+            }
         }
         Ok(())
     }
+
+    #[cfg(feature = "secure")]
+    fn key<'a>(key: &'a Option<Key>, states: &'a States) -> Result<&'a Key> {
+        match key {
+            Some(ref key) => Ok(key),
+            None => states.try_get::<Key>(),
+        }
+    }
 }
 
 /// A `Backend` using a Cookie entry for storing the session data.
@@ -90,13 +119,31 @@ impl CookieBackend {
     /// Create a new `CookieBackend` that signs the cookie entry with the specified `Key`.
     #[cfg(feature = "secure")]
     pub fn signed(secret_key: Key) -> Self {
-        Self::new(Security::Signed(secret_key))
+        Self::new(Security::Signed(Some(secret_key)))
+    }
+
+    /// Create a new `CookieBackend` that signs the cookie entry with the `Key`
+    /// registered in the scope as a shared state.
+    ///
+    /// If the `Key` is not registered, the session fails with `500 Internal Server Error`.
+    #[cfg(feature = "secure")]
+    pub fn signed_from_state() -> Self {
+        Self::new(Security::Signed(None))
     }
 
     /// Create a new `CookieBackend` that encrypts the cookie entry with the specified `Key`.
     #[cfg(feature = "secure")]
     pub fn private(secret_key: Key) -> Self {
-        Self::new(Security::Private(secret_key))
+        Self::new(Security::Private(Some(secret_key)))
+    }
+
+    /// Create a new `CookieBackend` that encrypts the cookie entry with the `Key`
+    /// registered in the scope as a shared state.
+    ///
+    /// If the `Key` is not registered, the session fails with `500 Internal Server Error`.
+    #[cfg(feature = "secure")]
+    pub fn private_from_state() -> Self {
+        Self::new(Security::Private(None))
     }
 
     /// Sets the name of Cookie entry to be used for storing the session data.
@@ -143,7 +190,7 @@ impl CookieBackendInner {
     }
 
     fn read(&self, input: &mut Input<'_>) -> tsukuyomi::Result<Inner> {
-        match self.security.get(&*self.cookie_name, input.cookies)? {
+        match self.security.get(&*self.cookie_name, input)? {
             Some(cookie) => {
                 let map = self.deserialize(cookie.value())?;
                 Ok(Inner::Some(map))
@@ -159,7 +206,7 @@ impl CookieBackendInner {
                 let value = self.serialize(&map);
                 let cookie =
                     (self.builder)(Cookie::build(self.cookie_name.clone(), value)).finish();
-                self.security.add(cookie, input.cookies)?;
+                self.security.add(cookie, input)?;
             }
             Inner::Clear => {
                 input
@@ -0,0 +1,144 @@
+use {
+    super::store::SessionStore,
+    futures::future::{self, FutureResult},
+    std::{
+        collections::HashMap,
+        fmt,
+        sync::{Arc, Mutex},
+        time::{Duration, SystemTime},
+    },
+    tsukuyomi::util::Never,
+};
+
+/// A `SessionStore` that stores the session data in the memory of the process.
+///
+/// Since the session data is not shared among processes, this store is mainly
+/// intended for development and testing, or for the applications running as
+/// a single instance.
+#[derive(Debug, Clone)]
+pub struct MemoryStore {
+    inner: Arc<MemoryStoreInner>,
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryStore {
+    /// Create a new `MemoryStore`.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(MemoryStoreInner {
+                sessions: Mutex::new(Sessions {
+                    entries: HashMap::new(),
+                    next_purge: None,
+                }),
+                timeout: None,
+            }),
+        }
+    }
+
+    fn inner_mut(&mut self) -> &mut MemoryStoreInner {
+        Arc::get_mut(&mut self.inner).expect("the value has already been shared")
+    }
+
+    /// Sets the period of time until the stored session data expires.
+    ///
+    /// The expiration time is reset each time the session data is stored,
+    /// and it is compared with the time returned from `Input::clock`.
+    /// By default, the session data never expires.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.inner_mut().timeout = Some(timeout);
+        self
+    }
+}
+
+struct MemoryStoreInner {
+    sessions: Mutex<Sessions>,
+    timeout: Option<Duration>,
+}
+
+#[cfg_attr(tarpaulin, skip)]
+impl fmt::Debug for MemoryStoreInner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryStoreInner")
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+struct Sessions {
+    entries: HashMap<String, Entry>,
+    // The time when the expired entries are purged next time.
+    next_purge: Option<SystemTime>,
+}
+
+struct Entry {
+    data: HashMap<String, String>,
+    expires_at: Option<SystemTime>,
+}
+
+impl Entry {
+    fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires_at
+            .map_or(false, |expires_at| expires_at <= now)
+    }
+}
+
+impl MemoryStoreInner {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Sessions> {
+        self.sessions.lock().expect("the lock is poisoned")
+    }
+}
+
+impl SessionStore for MemoryStore {
+    type Error = Never;
+    type Load = FutureResult<Option<HashMap<String, String>>, Never>;
+    type Store = FutureResult<(), Never>;
+    type Destroy = FutureResult<(), Never>;
+
+    fn load(&self, id: &str, now: SystemTime) -> Self::Load {
+        let mut sessions = self.inner.lock();
+        let data = match sessions.entries.get(id) {
+            Some(entry) if !entry.is_expired(now) => Some(entry.data.clone()),
+            Some(..) => {
+                sessions.entries.remove(id);
+                None
+            }
+            None => None,
+        };
+        future::ok(data)
+    }
+
+    fn store(&self, id: &str, data: HashMap<String, String>, now: SystemTime) -> Self::Store {
+        let mut sessions = self.inner.lock();
+
+        // Purge the expired entries at most once per timeout period, rather than
+        // scanning all of the entries every time the session data is stored.
+        if let Some(timeout) = self.inner.timeout {
+            if sessions
+                .next_purge
+                .map_or(true, |next_purge| next_purge <= now)
+            {
+                sessions.entries.retain(|_, entry| !entry.is_expired(now));
+                sessions.next_purge = Some(now + timeout);
+            }
+        }
+
+        sessions.entries.insert(
+            id.to_owned(),
+            Entry {
+                data,
+                expires_at: self.inner.timeout.map(|timeout| now + timeout),
+            },
+        );
+        future::ok(())
+    }
+
+    fn destroy(&self, id: &str) -> Self::Destroy {
+        self.inner.lock().entries.remove(id);
+        future::ok(())
+    }
+}
@@ -1,8 +1,14 @@
 //! The definition of session backends
 
 mod cookie;
+mod memory;
 mod redis;
+mod store;
 
-pub use self::cookie::CookieBackend;
 #[cfg(feature = "use-redis")]
-pub use self::redis::RedisBackend;
+pub use self::redis::{RedisBackend, RedisStore};
+pub use self::{
+    cookie::CookieBackend,
+    memory::MemoryStore,
+    store::{SessionStore, StoreBackend},
+};
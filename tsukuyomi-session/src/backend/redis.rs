@@ -1,11 +1,12 @@
 #![cfg(feature = "use-redis")]
 
 use {
+    super::store::SessionStore,
     crate::{Backend, RawSession},
     cookie::Cookie,
-    futures::try_ready,
+    futures::{try_ready, Future},
     redis::{r#async::Connection, Client, RedisFuture},
-    std::time::Duration,
+    std::time::{Duration, SystemTime},
     std::{borrow::Cow, collections::HashMap, mem, sync::Arc},
     tsukuyomi::{
         error::{Error, Result},
@@ -357,3 +358,113 @@ impl TryFuture for WriteSession {
         }
     }
 }
+
+/// A `SessionStore` using Redis.
+#[derive(Debug, Clone)]
+pub struct RedisStore {
+    inner: Arc<RedisStoreInner>,
+}
+
+#[derive(Debug)]
+struct RedisStoreInner {
+    client: Client,
+    key_prefix: Cow<'static, str>,
+    timeout: Option<Duration>,
+}
+
+impl RedisStore {
+    /// Create a new `RedisStore` from the specified Redis client.
+    pub fn new(client: Client) -> Self {
+        Self {
+            inner: Arc::new(RedisStoreInner {
+                client,
+                key_prefix: "tsukuyomi-session".into(),
+                timeout: None,
+            }),
+        }
+    }
+
+    fn inner_mut(&mut self) -> &mut RedisStoreInner {
+        Arc::get_mut(&mut self.inner).expect("the value has already been shared")
+    }
+
+    /// Sets the prefix of key name used at storing the session data in Redis.
+    ///
+    /// The default value is `"tsukuyomi-session"`.
+    pub fn key_prefix(mut self, prefix: impl Into<Cow<'static, str>>) -> Self {
+        self.inner_mut().key_prefix = prefix.into();
+        self
+    }
+
+    /// Sets the timeout to be used at storing the session data in Redis.
+    ///
+    /// By default, the timeout is not set.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.inner_mut().timeout = Some(timeout);
+        self
+    }
+
+    fn redis_key(&self, id: &str) -> String {
+        format!("{}:{}", self.inner.key_prefix, id)
+    }
+}
+
+impl SessionStore for RedisStore {
+    type Error = Error;
+    type Load = Box<dyn Future<Item = Option<HashMap<String, String>>, Error = Error> + Send>;
+    type Store = Box<dyn Future<Item = (), Error = Error> + Send>;
+    type Destroy = Box<dyn Future<Item = (), Error = Error> + Send>;
+
+    fn load(&self, id: &str, _: SystemTime) -> Self::Load {
+        let redis_key = self.redis_key(id);
+        Box::new(
+            self.inner
+                .client
+                .get_async_connection()
+                .and_then(move |conn| redis::cmd("GET").arg(redis_key).query_async(conn))
+                .map_err(tsukuyomi::error::internal_server_error)
+                .and_then(|(_conn, value): (Connection, Option<String>)| match value {
+                    Some(value) => serde_json::from_str(&value)
+                        .map(Some)
+                        .map_err(tsukuyomi::error::internal_server_error),
+                    None => Ok(None),
+                }),
+        )
+    }
+
+    fn store(&self, id: &str, data: HashMap<String, String>, _: SystemTime) -> Self::Store {
+        let redis_key = self.redis_key(id);
+        let value = serde_json::to_string(&data).expect("should be successed");
+        let timeout = self.inner.timeout;
+        Box::new(
+            self.inner
+                .client
+                .get_async_connection()
+                .and_then(move |conn| match timeout {
+                    Some(timeout) => redis::cmd("SETEX")
+                        .arg(redis_key)
+                        .arg(timeout.as_secs())
+                        .arg(value)
+                        .query_async(conn),
+                    None => redis::cmd("SET")
+                        .arg(redis_key)
+                        .arg(value)
+                        .query_async(conn),
+                })
+                .map(|(_conn, ()): (Connection, ())| ())
+                .map_err(tsukuyomi::error::internal_server_error),
+        )
+    }
+
+    fn destroy(&self, id: &str) -> Self::Destroy {
+        let redis_key = self.redis_key(id);
+        Box::new(
+            self.inner
+                .client
+                .get_async_connection()
+                .and_then(move |conn| redis::cmd("DEL").arg(redis_key).query_async(conn))
+                .map(|(_conn, ()): (Connection, ())| ())
+                .map_err(tsukuyomi::error::internal_server_error),
+        )
+    }
+}
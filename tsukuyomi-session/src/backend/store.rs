@@ -0,0 +1,367 @@
+use {
+    crate::{Backend, RawSession},
+    cookie::{Cookie, CookieBuilder},
+    futures::Future,
+    std::{borrow::Cow, collections::HashMap, fmt, mem, sync::Arc, time::SystemTime},
+    tsukuyomi::{
+        error::{Error, Result},
+        future::{try_ready, Async, Poll, TryFuture},
+        input::{state::States, Input},
+    },
+    uuid::Uuid,
+};
+
+/// A trait representing the storage of session data, identified by the session ID.
+///
+/// The argument `now` is the time returned from `Input::clock`, and is used by
+/// the stores that manage the expiration of session data by themselves.
+pub trait SessionStore: Send + Sync + 'static {
+    /// The type of errors which will occur when accessing the store.
+    type Error: Into<Error>;
+    /// The type of `Future` to load the session data.
+    type Load: Future<Item = Option<HashMap<String, String>>, Error = Self::Error> + Send + 'static;
+    /// The type of `Future` to save the session data.
+    type Store: Future<Item = (), Error = Self::Error> + Send + 'static;
+    /// The type of `Future` to remove the session data.
+    type Destroy: Future<Item = (), Error = Self::Error> + Send + 'static;
+
+    /// Loads the session data associated with the specified ID, if exists.
+    fn load(&self, id: &str, now: SystemTime) -> Self::Load;
+
+    /// Saves the session data with associating the specified ID.
+    fn store(&self, id: &str, data: HashMap<String, String>, now: SystemTime) -> Self::Store;
+
+    /// Removes the session data associated with the specified ID.
+    fn destroy(&self, id: &str) -> Self::Destroy;
+}
+
+/// A `Backend` that saves the session data into a `SessionStore`.
+///
+/// Only the session ID is stored in the Cookie entry.
+pub struct StoreBackend<S> {
+    inner: Arc<StoreBackendInner<S>>,
+}
+
+impl<S> Clone for StoreBackend<S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+#[cfg_attr(tarpaulin, skip)]
+impl<S> fmt::Debug for StoreBackend<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StoreBackend")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<S> StoreBackend<S>
+where
+    S: SessionStore,
+{
+    fn new_inner(store: Option<S>) -> Self {
+        Self {
+            inner: Arc::new(StoreBackendInner {
+                store,
+                cookie_name: "session-id".into(),
+                builder: Box::new(|cookie| cookie),
+            }),
+        }
+    }
+
+    fn inner_mut(&mut self) -> &mut StoreBackendInner<S> {
+        Arc::get_mut(&mut self.inner).expect("the value has already been shared")
+    }
+
+    /// Create a new `StoreBackend` using the specified store.
+    pub fn new(store: S) -> Self {
+        Self::new_inner(Some(store))
+    }
+
+    /// Create a new `StoreBackend` using the store registered in the scope
+    /// as a shared state.
+    ///
+    /// If the store is not registered, the session fails with `500 Internal Server Error`.
+    pub fn from_state() -> Self {
+        Self::new_inner(None)
+    }
+
+    /// Sets the name of Cookie entry for storing the session ID.
+    ///
+    /// The default value is `"session-id"`.
+    pub fn cookie_name(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.inner_mut().cookie_name = name.into();
+        self
+    }
+
+    /// Sets the functions for modifying the saved Cookie entry.
+    ///
+    /// The Cookie entry is marked as `HttpOnly` before applying this function.
+    pub fn builder(
+        mut self,
+        builder: impl Fn(CookieBuilder) -> CookieBuilder + Send + Sync + 'static,
+    ) -> Self {
+        self.inner_mut().builder = Box::new(builder);
+        self
+    }
+}
+
+struct StoreBackendInner<S> {
+    store: Option<S>,
+    cookie_name: Cow<'static, str>,
+    builder: Box<dyn Fn(CookieBuilder) -> CookieBuilder + Send + Sync + 'static>,
+}
+
+#[cfg_attr(tarpaulin, skip)]
+impl<S> fmt::Debug for StoreBackendInner<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StoreBackendInner")
+            .field("from_state", &self.store.is_none())
+            .field("cookie_name", &self.cookie_name)
+            .finish()
+    }
+}
+
+impl<S> StoreBackendInner<S>
+where
+    S: SessionStore,
+{
+    fn store<'a>(&'a self, states: &'a States) -> Result<&'a S> {
+        match self.store {
+            Some(ref store) => Ok(store),
+            None => states.try_get::<S>(),
+        }
+    }
+
+    fn get_session_id(&self, input: &mut Input<'_>) -> Result<Option<String>> {
+        // The malformed session ID is regarded as missing, since it may be sent
+        // from the client that holds a stale or broken Cookie.
+        Ok(input
+            .cookies
+            .jar()?
+            .get(&self.cookie_name)
+            .and_then(|cookie| cookie.value().parse::<Uuid>().ok())
+            .map(|session_id| session_id.to_string()))
+    }
+
+    fn set_session_id(&self, input: &mut Input<'_>, session_id: &str) -> Result<()> {
+        let cookie = Cookie::build(self.cookie_name.clone(), session_id.to_owned()) //
+            .http_only(true);
+        input.cookies.jar()?.add((self.builder)(cookie).finish());
+        Ok(())
+    }
+
+    fn remove_session_id(&self, input: &mut Input<'_>) -> Result<()> {
+        input
+            .cookies
+            .jar()?
+            .remove(Cookie::named(self.cookie_name.clone()));
+        Ok(())
+    }
+}
+
+impl<S> Backend for StoreBackend<S>
+where
+    S: SessionStore,
+{
+    type Session = StoreSession<S>;
+    type ReadError = Error;
+    type ReadSession = ReadSession<S>;
+
+    fn read(&self) -> Self::ReadSession {
+        ReadSession {
+            backend: self.clone(),
+            state: ReadSessionState::Init,
+        }
+    }
+}
+
+#[doc(hidden)]
+#[allow(missing_debug_implementations)]
+pub struct ReadSession<S: SessionStore> {
+    backend: StoreBackend<S>,
+    state: ReadSessionState<S::Load>,
+}
+
+enum ReadSessionState<F> {
+    Init,
+    Load { future: F, session_id: String },
+    Done,
+}
+
+impl<S> TryFuture for ReadSession<S>
+where
+    S: SessionStore,
+{
+    type Ok = StoreSession<S>;
+    type Error = Error;
+
+    fn poll_ready(&mut self, input: &mut Input<'_>) -> Poll<Self::Ok, Self::Error> {
+        loop {
+            let data = match self.state {
+                ReadSessionState::Init => {
+                    let inner = &self.backend.inner;
+                    match inner.get_session_id(input)? {
+                        Some(session_id) => {
+                            let future = inner
+                                .store(input.states)?
+                                .load(&session_id, input.clock().now());
+                            self.state = ReadSessionState::Load { future, session_id };
+                            continue;
+                        }
+                        None => None,
+                    }
+                }
+                ReadSessionState::Load { ref mut future, .. } => {
+                    try_ready!(future.poll().map_err(Into::<Error>::into))
+                }
+                ReadSessionState::Done => panic!("the future has already been polled"),
+            };
+
+            let session_id = match mem::replace(&mut self.state, ReadSessionState::Done) {
+                ReadSessionState::Load { session_id, .. } => Some(session_id),
+                _ => None,
+            };
+
+            // The session ID is discarded if the corresponding session data is missing,
+            // in order to prevent from reusing the ID specified by the client.
+            let (inner, session_id) = match data {
+                Some(data) => (Inner::Some(data), session_id),
+                None => (Inner::Empty, None),
+            };
+
+            return Ok(Async::Ready(StoreSession {
+                inner,
+                backend: self.backend.clone(),
+                session_id,
+            }));
+        }
+    }
+}
+
+#[doc(hidden)]
+#[allow(missing_debug_implementations)]
+pub struct StoreSession<S> {
+    inner: Inner,
+    backend: StoreBackend<S>,
+    session_id: Option<String>,
+}
+
+#[derive(Debug)]
+enum Inner {
+    Empty,
+    Some(HashMap<String, String>),
+    Clear,
+}
+
+impl<S> RawSession for StoreSession<S>
+where
+    S: SessionStore,
+{
+    type WriteSession = WriteSession<S>;
+    type WriteError = Error;
+
+    fn get(&self, name: &str) -> Option<&str> {
+        match self.inner {
+            Inner::Some(ref map) => map.get(name).map(|s| &**s),
+            _ => None,
+        }
+    }
+
+    fn set(&mut self, name: &str, value: String) {
+        match self.inner {
+            Inner::Empty => {}
+            Inner::Some(ref mut map) => {
+                map.insert(name.to_owned(), value);
+                return;
+            }
+            Inner::Clear => return,
+        }
+
+        match std::mem::replace(&mut self.inner, Inner::Empty) {
+            Inner::Empty => {
+                self.inner = Inner::Some({
+                    let mut map = HashMap::new();
+                    map.insert(name.to_owned(), value);
+                    map
+                });
+            }
+            Inner::Some(..) | Inner::Clear => unreachable!(),
+        }
+    }
+
+    fn remove(&mut self, name: &str) {
+        if let Inner::Some(ref mut map) = self.inner {
+            map.remove(name);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.inner = Inner::Clear;
+    }
+
+    fn write(self) -> Self::WriteSession {
+        WriteSession::Init(Some(self))
+    }
+}
+
+#[doc(hidden)]
+#[allow(missing_debug_implementations)]
+pub enum WriteSession<S: SessionStore> {
+    Init(Option<StoreSession<S>>),
+    Store(S::Store),
+    Destroy(S::Destroy),
+}
+
+impl<S> TryFuture for WriteSession<S>
+where
+    S: SessionStore,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn poll_ready(&mut self, input: &mut Input<'_>) -> Poll<Self::Ok, Self::Error> {
+        loop {
+            *self = match self {
+                WriteSession::Init(ref mut session) => {
+                    let StoreSession {
+                        inner,
+                        backend,
+                        session_id,
+                    } = session.take().expect("the future has already been polled");
+                    let backend = &backend.inner;
+
+                    match inner {
+                        Inner::Empty => return Ok(Async::Ready(())),
+                        Inner::Some(data) => {
+                            let session_id = session_id //
+                                .unwrap_or_else(|| Uuid::new_v4().to_string());
+                            backend.set_session_id(input, &session_id)?;
+                            let now = input.clock().now();
+                            WriteSession::Store(backend.store(input.states)?.store(
+                                &session_id,
+                                data,
+                                now,
+                            ))
+                        }
+                        Inner::Clear => match session_id {
+                            Some(session_id) => {
+                                backend.remove_session_id(input)?;
+                                WriteSession::Destroy(
+                                    backend.store(input.states)?.destroy(&session_id),
+                                )
+                            }
+                            None => return Ok(Async::Ready(())),
+                        },
+                    }
+                }
+                WriteSession::Store(ref mut future) => return future.poll().map_err(Into::into),
+                WriteSession::Destroy(ref mut future) => return future.poll().map_err(Into::into),
+            };
+        }
+    }
+}
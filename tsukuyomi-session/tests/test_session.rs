@@ -75,3 +75,174 @@ fn smoketest() -> tsukuyomi_server::Result<()> {
 
     Ok(())
 }
+
+#[cfg(feature = "secure")]
+#[test]
+fn signed_with_key_from_state() -> tsukuyomi_server::Result<()> {
+    use cookie::Key;
+
+    let session = std::sync::Arc::new(session(
+        CookieBackend::signed_from_state().cookie_name("session"),
+    ));
+
+    let app = App::create(chain![
+        config::state(Key::generate()),
+        path!("/counter").to(chain![
+            endpoint::get() //
+                .extract(session.clone())
+                .call_async(|session: Session<_>| -> tsukuyomi::Result<_> {
+                    let counter: Option<i64> = session.get("counter")?;
+                    Ok(session.finish(format!("{:?}", counter)))
+                }),
+            endpoint::put() //
+                .extract(session.clone())
+                .call_async(|mut session: Session<_>| -> tsukuyomi::Result<_> {
+                    let counter: i64 = session.get("counter")?.unwrap_or_default();
+                    session.set("counter", counter + 1)?;
+                    Ok(session.finish(format!("{}", counter)))
+                }),
+        ]),
+    ])?;
+
+    let mut server = tsukuyomi_server::test::server(app)?;
+    {
+        let mut session = server.new_session()?.save_cookies(true);
+
+        session.perform(Request::put("/counter"))?;
+        let cookie = session.cookie("session").expect("should be saved");
+        assert_ne!(cookie, r#"{"counter":"1"}"#);
+
+        let response = session.perform(Request::get("/counter"))?;
+        assert_eq!(response.body().to_utf8()?, "Some(1)");
+    }
+
+    // the tampered entry is ignored.
+    let response =
+        server.perform(Request::get("/counter").header("cookie", r#"session={"counter":"42"}"#))?;
+    assert_eq!(response.body().to_utf8()?, "None");
+
+    Ok(())
+}
+
+#[cfg(feature = "secure")]
+#[test]
+fn signed_without_key_in_state() -> tsukuyomi_server::Result<()> {
+    let session = session(CookieBackend::signed_from_state());
+
+    let app = App::create(
+        path!("/") //
+            .to(endpoint::get() //
+                .extract(session)
+                .call(|session: Session<_>| session.finish("dummy"))),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/")?;
+    assert_eq!(response.status(), 500);
+
+    Ok(())
+}
+
+#[test]
+fn memory_store() -> tsukuyomi_server::Result<()> {
+    use {
+        std::{
+            sync::{Arc, Mutex},
+            time::{Duration, SystemTime},
+        },
+        tsukuyomi::input::clock::Clock,
+        tsukuyomi_session::backend::{MemoryStore, StoreBackend},
+    };
+
+    #[derive(Debug, Clone)]
+    struct ManualClock(Arc<Mutex<SystemTime>>);
+
+    impl Clock for ManualClock {
+        fn now(&self) -> SystemTime {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    let clock = ManualClock(Arc::new(Mutex::new(SystemTime::now())));
+    let store = MemoryStore::new().timeout(Duration::from_secs(60));
+    let backend = StoreBackend::<MemoryStore>::from_state() //
+        .cookie_name("session-id");
+    let session = Arc::new(session(backend));
+
+    let app = App::create(chain![
+        config::clock(clock.clone()),
+        config::state(store),
+        path!("/counter").to(chain![
+            endpoint::get() //
+                .extract(session.clone())
+                .call_async(|session: Session<_>| -> tsukuyomi::Result<_> {
+                    let counter: Option<i64> = session.get("counter")?;
+                    Ok(session.finish(format!("{:?}", counter)))
+                }),
+            endpoint::put() //
+                .extract(session.clone())
+                .call_async(|mut session: Session<_>| -> tsukuyomi::Result<_> {
+                    let counter: i64 = session.get("counter")?.unwrap_or_default();
+                    session.set("counter", counter + 1)?;
+                    Ok(session.finish(format!("{}", counter)))
+                }),
+        ]),
+        path!("/clear").to(endpoint::put()
+            .extract(session)
+            .call(|mut session: Session<_>| {
+                session.clear();
+                session.finish("cleared")
+            }))
+    ])?;
+
+    let mut server = tsukuyomi_server::test::server(app)?;
+    let mut session = server.new_session()?.save_cookies(true);
+
+    // create
+    let response = session.perform(Request::get("/counter"))?;
+    assert!(!response.headers().contains_key("set-cookie"));
+    assert_eq!(response.body().to_utf8()?, "None");
+
+    let response = session.perform(Request::put("/counter"))?;
+    assert!(response.headers()["set-cookie"]
+        .to_str()?
+        .contains("HttpOnly"));
+    let session_id = session
+        .cookie("session-id")
+        .expect("should be saved")
+        .to_owned();
+    assert!(!session_id.contains("counter"));
+
+    // read
+    let response = session.perform(Request::get("/counter"))?;
+    assert_eq!(response.body().to_utf8()?, "Some(1)");
+
+    // update
+    session.perform(Request::put("/counter"))?;
+    assert_eq!(session.cookie("session-id"), Some(&*session_id));
+    let response = session.perform(Request::get("/counter"))?;
+    assert_eq!(response.body().to_utf8()?, "Some(2)");
+
+    // expire
+    *clock.0.lock().unwrap() += Duration::from_secs(61);
+    let response = session.perform(Request::get("/counter"))?;
+    assert_eq!(response.body().to_utf8()?, "None");
+
+    // clear
+    session.perform(Request::put("/counter"))?;
+    assert_ne!(session.cookie("session-id"), Some(&*session_id));
+    session.perform(Request::put("/clear"))?;
+    assert!(session.cookie("session-id").is_none());
+    let response = session.perform(Request::get("/counter"))?;
+    assert_eq!(response.body().to_utf8()?, "None");
+
+    // the malformed session ID is regarded as missing.
+    let response = server.perform(
+        Request::get("/counter") //
+            .header("cookie", "session-id=garbage"),
+    )?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "None");
+
+    Ok(())
+}
@@ -0,0 +1,198 @@
+//! Tera integration for Tsukuyomi.
+
+#![doc(html_root_url = "https://docs.rs/tsukuyomi-tera/0.1.0")]
+#![deny(
+    missing_debug_implementations,
+    nonstandard_style,
+    rust_2018_idioms,
+    rust_2018_compatibility,
+    unused
+)]
+#![forbid(clippy::unimplemented)]
+
+use {
+    http::{
+        header::{HeaderValue, CONTENT_TYPE},
+        Response,
+    },
+    mime_guess::get_mime_type_str,
+    serde::Serialize,
+    std::path::Path,
+    tera::Tera,
+    tsukuyomi::{
+        error::internal_server_error,
+        handler::{Handler, ModifyHandler},
+        input::Input,
+        responder::Responder,
+    },
+};
+
+/// A trait representing the values rendered by Tera.
+///
+/// The engine used for rendering is taken from the shared state
+/// of type `tera::Tera` registered in the scope.
+///
+/// # Example
+///
+/// ```
+/// # use serde::Serialize;
+/// #[derive(Serialize)]
+/// struct Index {
+///     name: String,
+/// }
+///
+/// impl tsukuyomi_tera::Template for Index {
+///     type Context = Self;
+///
+///     fn template_name(&self) -> &str {
+///         "index.html"
+///     }
+///
+///     fn context(&self) -> &Self::Context {
+///         self
+///     }
+/// }
+/// # fn main() {}
+/// ```
+pub trait Template {
+    /// The type of context passed to the template.
+    type Context: Serialize;
+
+    /// Returns the name of template to be rendered.
+    fn template_name(&self) -> &str;
+
+    /// Returns the context passed to the template.
+    fn context(&self) -> &Self::Context;
+}
+
+impl<T> Template for (T, tera::Context)
+where
+    T: AsRef<str>,
+{
+    type Context = tera::Context;
+
+    #[inline]
+    fn template_name(&self) -> &str {
+        self.0.as_ref()
+    }
+
+    #[inline]
+    fn context(&self) -> &Self::Context {
+        &self.1
+    }
+}
+
+/// Creates a `Responder` that renders the specified template.
+///
+/// The value of `Content-Type` is guessed from the extension of the template name
+/// (e.g. `text/plain` for `.txt`), and `text/html` is used if it is unknown.
+/// If the rendering fails, the error is logged and converted into `500 Internal Server Error`.
+pub fn render<T>(
+    template: T,
+) -> impl Responder<Response = Response<String>, Error = tsukuyomi::Error>
+where
+    T: Template,
+{
+    tsukuyomi::responder::oneshot(move |input| render_template(&template, input))
+}
+
+/// Creates a `ModifyHandler` that renders the outputs of handlers as Tera template.
+///
+/// The value of `Content-Type` is determined in the same way as `render`.
+pub fn renderer() -> Renderer {
+    Renderer::default()
+}
+
+#[derive(Debug, Default)]
+pub struct Renderer(());
+
+impl<H> ModifyHandler<H> for Renderer
+where
+    H: Handler,
+    H::Output: Template,
+{
+    type Output = Response<String>;
+    type Handler = self::renderer::RenderedHandler<H>; // private
+
+    fn modify(&self, inner: H) -> Self::Handler {
+        self::renderer::RenderedHandler { inner }
+    }
+}
+
+fn render_template<T>(template: &T, input: &mut Input<'_>) -> tsukuyomi::Result<Response<String>>
+where
+    T: Template,
+{
+    let engine = input.states.try_get::<Tera>()?;
+    let name = template.template_name();
+    let content_type = Path::new(name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(get_mime_type_str)
+        .unwrap_or("text/html; charset=utf-8");
+    let mut response = engine
+        .render(name, template.context())
+        .map(Response::new)
+        .map_err(|err| {
+            log::error!("failed to render the template `{}`: {}", name, err);
+            internal_server_error(err.to_string())
+        })?;
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    Ok(response)
+}
+
+mod renderer {
+    use {
+        super::Template,
+        http::Response,
+        tsukuyomi::{
+            error::Error,
+            future::{Poll, TryFuture},
+            handler::{AllowedMethods, Handler},
+            input::Input,
+        },
+    };
+
+    #[allow(missing_debug_implementations)]
+    pub struct RenderedHandler<H> {
+        pub(super) inner: H,
+    }
+
+    impl<H> Handler for RenderedHandler<H>
+    where
+        H: Handler,
+        H::Output: Template,
+    {
+        type Output = Response<String>;
+        type Error = Error;
+        type Handle = RenderedHandle<H::Handle>;
+
+        fn allowed_methods(&self) -> Option<&AllowedMethods> {
+            self.inner.allowed_methods()
+        }
+
+        fn handle(&self) -> Self::Handle {
+            RenderedHandle(self.inner.handle())
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    pub struct RenderedHandle<H>(H);
+
+    impl<H> TryFuture for RenderedHandle<H>
+    where
+        H: TryFuture,
+        H::Ok: Template,
+    {
+        type Ok = Response<String>;
+        type Error = Error;
+
+        #[inline]
+        fn poll_ready(&mut self, input: &mut Input<'_>) -> Poll<Self::Ok, Self::Error> {
+            let ctx = tsukuyomi::future::try_ready!(self.0.poll_ready(input).map_err(Into::into));
+            super::render_template(&ctx, input).map(Into::into)
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use {
+    serde::Serialize,
+    tera::{Context, Tera},
+    tsukuyomi::{
+        config::prelude::*, //
+        App,
+    },
+    tsukuyomi_server::test::ResponseExt,
+};
+
+fn engine() -> Tera {
+    let mut engine = Tera::default();
+    engine
+        .add_raw_templates(vec![
+            ("index.html", "Hello, {{ name }}."),
+            ("index.txt", "Hello, {{ name }}."),
+        ])
+        .expect("should be valid templates");
+    engine
+}
+
+#[test]
+fn test_version_sync() {
+    version_sync::assert_html_root_url_updated!("src/lib.rs");
+}
+
+#[test]
+fn test_render() -> tsukuyomi_server::Result<()> {
+    let app = App::create(chain![
+        config::state(engine()),
+        path!("/") //
+            .to(endpoint::get() //
+                .call(|| {
+                    let mut context = Context::new();
+                    context.insert("name", &"Alice");
+                    tsukuyomi_tera::render(("index.html", context))
+                })),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.header("content-type")?, "text/html");
+    assert_eq!(response.body().to_utf8()?, "Hello, Alice.");
+
+    Ok(())
+}
+
+#[test]
+fn test_template_with_modifier() -> tsukuyomi_server::Result<()> {
+    #[derive(Serialize)]
+    struct Index {
+        name: &'static str,
+    }
+
+    impl tsukuyomi_tera::Template for Index {
+        type Context = Self;
+
+        fn template_name(&self) -> &str {
+            "index.txt"
+        }
+
+        fn context(&self) -> &Self::Context {
+            self
+        }
+    }
+
+    let app = App::create(chain![
+        config::state(engine()),
+        path!("/") //
+            .to(endpoint::get() //
+                .call(|| Index { name: "Alice" }))
+            .modify(tsukuyomi_tera::renderer()),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.header("content-type")?, "text/plain");
+    assert_eq!(response.body().to_utf8()?, "Hello, Alice.");
+
+    Ok(())
+}
+
+#[test]
+fn test_render_error() -> tsukuyomi_server::Result<()> {
+    let app = App::create(chain![
+        config::state(engine()),
+        path!("/missing-variable") //
+            .to(endpoint::get() //
+                .call(|| tsukuyomi_tera::render(("index.html", Context::new())))),
+        path!("/missing-template") //
+            .to(endpoint::get() //
+                .call(|| tsukuyomi_tera::render(("missing.html", Context::new())))),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/missing-variable")?;
+    assert_eq!(response.status(), 500);
+
+    let response = server.perform("/missing-template")?;
+    assert_eq!(response.status(), 500);
+
+    Ok(())
+}
@@ -12,36 +12,324 @@
 #![doc(test(attr(deny(deprecated, unused,))))]
 #![forbid(clippy::unimplemented)]
 
+pub mod test;
+
 use {
-    futures::IntoFuture,
-    http::Response,
+    futures::{Async, AsyncSink, Future, IntoFuture, Poll, Sink, StartSend, Stream},
+    http::{Request, Response},
+    std::{borrow::Cow, fmt, io, time::Duration},
+    tokio_timer::Interval,
     tsukuyomi::{error::Error, input::body::UpgradedIo, responder::Responder},
 };
 
 #[doc(no_inline)]
-pub use tungstenite::protocol::{Message, WebSocketConfig};
+pub use tungstenite::protocol::{
+    frame::coding::CloseCode, //
+    CloseFrame,
+    Message,
+    WebSocketConfig,
+};
 
 /// A transport for exchanging data frames with the peer.
-pub type WebSocketStream = tokio_tungstenite::WebSocketStream<UpgradedIo>;
+///
+/// If the interval of automatic ping is configured, the ping frames are sent
+/// to the peer while this stream is being polled, and the stream fails with
+/// a timeout error when the peer does not respond to the previous ping.
+pub struct WebSocketStream {
+    inner: tokio_tungstenite::WebSocketStream<UpgradedIo>,
+    ping: Option<AutoPing>,
+}
+
+struct AutoPing {
+    interval: Interval,
+    awaiting_pong: bool,
+    pending: bool,
+}
+
+impl fmt::Debug for WebSocketStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebSocketStream")
+            .field("auto_ping", &self.ping.is_some())
+            .finish()
+    }
+}
+
+impl WebSocketStream {
+    fn new(
+        inner: tokio_tungstenite::WebSocketStream<UpgradedIo>,
+        auto_ping_interval: Option<Duration>,
+    ) -> Self {
+        Self {
+            inner,
+            ping: auto_ping_interval.map(|interval| AutoPing {
+                interval: Interval::new_interval(interval),
+                awaiting_pong: false,
+                pending: false,
+            }),
+        }
+    }
+
+    /// Initiates the closing handshake with the specified close code and reason.
+    ///
+    /// The returned future completes after the close frame has been flushed and
+    /// the peer has acknowledged it, with the close frame sent back from the peer.
+    /// The incoming data messages received in the meantime are discarded.
+    pub fn close_with(self, code: CloseCode, reason: impl Into<Cow<'static, str>>) -> CloseWith {
+        CloseWith {
+            stream: self,
+            frame: Some(CloseFrame {
+                code,
+                reason: reason.into(),
+            }),
+            peer_frame: None,
+        }
+    }
+
+    fn poll_ping(&mut self) -> Result<(), tungstenite::Error> {
+        if let Some(ref mut ping) = self.ping {
+            while let Async::Ready(Some(..)) = ping
+                .interval
+                .poll()
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?
+            {
+                if ping.awaiting_pong {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "the peer did not respond to the ping",
+                    )
+                    .into());
+                }
+                ping.awaiting_pong = true;
+                ping.pending = true;
+            }
+
+            if ping.pending {
+                if let AsyncSink::Ready = self.inner.start_send(Message::Ping(vec![]))? {
+                    ping.pending = false;
+                }
+                self.inner.poll_complete()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Stream for WebSocketStream {
+    type Item = Message;
+    type Error = tungstenite::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        self.poll_ping()?;
+
+        match self.inner.poll() {
+            Ok(Async::Ready(Some(Message::Pong(payload)))) => {
+                if let Some(ref mut ping) = self.ping {
+                    ping.awaiting_pong = false;
+                }
+                Ok(Async::Ready(Some(Message::Pong(payload))))
+            }
+            Err(tungstenite::Error::Capacity(reason)) => {
+                // Notify the peer that the message is too big, and give up the connection.
+                let frame = CloseFrame {
+                    code: CloseCode::Size,
+                    reason: reason.clone(),
+                };
+                let _ = self.inner.start_send(Message::Close(Some(frame)));
+                let _ = self.inner.poll_complete();
+                Err(tungstenite::Error::Capacity(reason))
+            }
+            polled => polled,
+        }
+    }
+}
+
+/// A `Future` that completes the closing handshake of a WebSocket connection.
+///
+/// The value of this type is created by `WebSocketStream::close_with`.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct CloseWith {
+    stream: WebSocketStream,
+    frame: Option<CloseFrame<'static>>,
+    peer_frame: Option<CloseFrame<'static>>,
+}
+
+impl Future for CloseWith {
+    type Item = Option<CloseFrame<'static>>;
+    type Error = tungstenite::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if let Some(frame) = self.frame.take() {
+            if let AsyncSink::NotReady(Message::Close(frame)) =
+                self.stream.inner.start_send(Message::Close(Some(frame)))?
+            {
+                self.frame = frame;
+                return Ok(Async::NotReady);
+            }
+        }
+
+        futures::try_ready!(self.stream.inner.poll_complete());
+
+        loop {
+            match self.stream.inner.poll() {
+                Ok(Async::Ready(Some(Message::Close(frame)))) => {
+                    self.peer_frame = frame;
+                }
+                Ok(Async::Ready(Some(..))) => {}
+                Ok(Async::Ready(None))
+                | Err(tungstenite::Error::ConnectionClosed(..))
+                | Err(tungstenite::Error::AlreadyClosed) => {
+                    return Ok(Async::Ready(self.peer_frame.take()));
+                }
+                Ok(Async::NotReady) => return Ok(Async::NotReady),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+impl Sink for WebSocketStream {
+    type SinkItem = Message;
+    type SinkError = tungstenite::Error;
+
+    #[inline]
+    fn start_send(&mut self, item: Self::SinkItem) -> StartSend<Self::SinkItem, Self::SinkError> {
+        self.inner.start_send(item)
+    }
+
+    #[inline]
+    fn poll_complete(&mut self) -> Poll<(), Self::SinkError> {
+        self.inner.poll_complete()
+    }
+
+    #[inline]
+    fn close(&mut self) -> Poll<(), Self::SinkError> {
+        self.inner.close()
+    }
+}
+
+/// The information determined by the WebSocket handshake.
+#[derive(Debug)]
+pub struct Handshake {
+    request: Request<()>,
+    protocol: Option<String>,
+    config: WebSocketConfig,
+    auto_ping_interval: Option<Duration>,
+}
+
+impl Handshake {
+    /// Returns the head of the request that initiated the handshake.
+    ///
+    /// The request method, URI, version and headers are captured at the time of upgrade.
+    /// Note that the extensions of the original request are not preserved.
+    pub fn request(&self) -> &Request<()> {
+        &self.request
+    }
+
+    /// Returns the subprotocol selected by the handshake, if any.
+    pub fn protocol(&self) -> Option<&str> {
+        self.protocol.as_ref().map(|protocol| protocol.as_str())
+    }
+
+    /// Returns the configuration applied to the upgraded connection.
+    pub fn config(&self) -> &WebSocketConfig {
+        &self.config
+    }
+
+    /// Returns the interval of automatic ping, if enabled.
+    pub fn auto_ping_interval(&self) -> Option<Duration> {
+        self.auto_ping_interval
+    }
+}
 
 /// A `Responder` that handles an WebSocket connection.
 #[derive(Debug, Clone)]
 pub struct Ws<F> {
     on_upgrade: F,
     config: Option<WebSocketConfig>,
+    protocols: Vec<String>,
+    require_protocol: bool,
+    auto_ping_interval: Option<Duration>,
 }
 
 impl<F, R> Ws<F>
 where
-    F: Fn(WebSocketStream) -> R + Send + 'static,
+    F: Fn(WebSocketStream, Handshake) -> R + Send + 'static,
     R: IntoFuture<Item = (), Error = ()>,
     R::Future: Send + 'static,
 {
     /// Crates a `Ws` with the specified closure.
+    ///
+    /// The closure is called with the upgraded transport and the result of
+    /// the handshake, after the response has been sent to the client.
     pub fn new(on_upgrade: F) -> Self {
         Self {
             on_upgrade,
             config: None,
+            protocols: vec![],
+            require_protocol: false,
+            auto_ping_interval: None,
+        }
+    }
+
+    /// Sets the maximum size of an incoming message.
+    ///
+    /// When the peer sends a larger message, the connection is closed with
+    /// the close code `1009` (Message Too Big). The default value is determined by
+    /// `tungstenite` (64 MiB).
+    pub fn max_message_size(mut self, max: usize) -> Self {
+        self.config
+            .get_or_insert_with(WebSocketConfig::default)
+            .max_message_size = Some(max);
+        self
+    }
+
+    /// Sets the maximum size of an incoming frame.
+    ///
+    /// The default value is determined by `tungstenite` (16 MiB).
+    pub fn max_frame_size(mut self, max: usize) -> Self {
+        self.config
+            .get_or_insert_with(WebSocketConfig::default)
+            .max_frame_size = Some(max);
+        self
+    }
+
+    /// Sets the interval of automatic ping to the peer.
+    ///
+    /// The connection is considered to be dead if the peer does not respond
+    /// with a pong until the next ping. By default, the automatic ping is disabled.
+    pub fn auto_ping_interval(self, interval: Duration) -> Self {
+        Self {
+            auto_ping_interval: Some(interval),
+            ..self
+        }
+    }
+
+    /// Sets the list of subprotocols supported by the server.
+    ///
+    /// The first subprotocol in the client's `Sec-WebSocket-Protocol` that is contained
+    /// in this list is selected and returned to the client.
+    pub fn protocols<I>(self, protocols: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        Self {
+            protocols: protocols.into_iter().map(Into::into).collect(),
+            ..self
+        }
+    }
+
+    /// Sets whether to reject the handshake if none of the subprotocols requested
+    /// by the client is supported.
+    ///
+    /// If disabled, the connection is established without a subprotocol in such case.
+    /// The handshake from the client that does not request any subprotocol is always
+    /// accepted. The default value is `false`.
+    pub fn require_protocol(self, enabled: bool) -> Self {
+        Self {
+            require_protocol: enabled,
+            ..self
         }
     }
 
@@ -56,7 +344,7 @@ where
 
 impl<F, R> Responder for Ws<F>
 where
-    F: Fn(WebSocketStream) -> R + Send + 'static,
+    F: Fn(WebSocketStream, Handshake) -> R + Send + 'static,
     R: IntoFuture<Item = (), Error = ()>,
     R::Future: Send + 'static,
 {
@@ -71,13 +359,14 @@ where
 
 mod imp {
     use {
-        super::{WebSocketStream, Ws},
+        super::{Handshake, WebSocketStream, Ws},
         futures::{Future, IntoFuture},
         http::{
             header::{
                 CONNECTION, //
                 SEC_WEBSOCKET_ACCEPT,
                 SEC_WEBSOCKET_KEY,
+                SEC_WEBSOCKET_PROTOCOL,
                 SEC_WEBSOCKET_VERSION,
                 UPGRADE,
             },
@@ -102,7 +391,7 @@ mod imp {
 
     impl<F, R> TryFuture for WsRespond<F>
     where
-        F: FnOnce(WebSocketStream) -> R + Send + 'static,
+        F: FnOnce(WebSocketStream, Handshake) -> R + Send + 'static,
         R: IntoFuture<Item = (), Error = ()>,
         R::Future: Send + 'static,
     {
@@ -110,10 +399,29 @@ mod imp {
         type Error = tsukuyomi::Error;
 
         fn poll_ready(&mut self, input: &mut Input<'_>) -> Poll<Self::Ok, Self::Error> {
-            let Ws { on_upgrade, config } =
-                self.0.take().expect("the future has already been polled");
+            let Ws {
+                on_upgrade,
+                config,
+                protocols,
+                require_protocol,
+                auto_ping_interval,
+            } = self.0.take().expect("the future has already been polled");
 
             let accept_hash = handshake(input)?;
+            let protocol = negotiate_protocol(input, &protocols, require_protocol)?;
+            let handshake = Handshake {
+                request: {
+                    let mut request = Request::new(());
+                    *request.method_mut() = input.request.method().clone();
+                    *request.uri_mut() = input.request.uri().clone();
+                    *request.version_mut() = input.request.version();
+                    *request.headers_mut() = input.request.headers().clone();
+                    request
+                },
+                protocol: protocol.clone(),
+                config: config.unwrap_or_default(),
+                auto_ping_interval,
+            };
 
             let body = RequestBody::take_from(input.locals) //
                 .ok_or_else(|| {
@@ -126,19 +434,32 @@ mod imp {
                 .on_upgrade()
                 .map_err(|e| log::error!("failed to upgrade the request: {}", e))
                 .and_then(move |io: UpgradedIo| {
-                    let transport = WebSocketStream::from_raw_socket(io, Role::Server, config);
-                    on_upgrade(transport).into_future()
+                    let transport = WebSocketStream::new(
+                        tokio_tungstenite::WebSocketStream::from_raw_socket(
+                            io,
+                            Role::Server,
+                            config,
+                        ),
+                        auto_ping_interval,
+                    );
+                    on_upgrade(transport, handshake).into_future()
                 });
 
             DefaultExecutor::current()
                 .spawn(Box::new(task))
                 .map_err(tsukuyomi::error::internal_server_error)?;
 
-            Ok(Response::builder()
+            let mut response = Response::builder();
+            response
                 .status(StatusCode::SWITCHING_PROTOCOLS)
                 .header(UPGRADE, "websocket")
                 .header(CONNECTION, "upgrade")
-                .header(SEC_WEBSOCKET_ACCEPT, &*accept_hash)
+                .header(SEC_WEBSOCKET_ACCEPT, &*accept_hash);
+            if let Some(protocol) = protocol {
+                response.header(SEC_WEBSOCKET_PROTOCOL, &*protocol);
+            }
+
+            Ok(response
                 .body(())
                 .expect("should be a valid response")
                 .into())
@@ -158,6 +479,9 @@ mod imp {
 
         #[fail(display = "The value of `Sec-WebSocket-Version` must be equal to '13'")]
         InvalidSecWebSocketVersion,
+
+        #[fail(display = "None of the requested subprotocols is supported")]
+        UnsupportedProtocol,
     }
 
     impl HttpError for HandshakeError {
@@ -202,18 +526,47 @@ mod imp {
                     Err(HandshakeError::InvalidSecWebSocketKey)?;
                 }
 
-                let mut m = Sha1::new();
-                m.input(h.as_bytes());
-                m.input(&b"258EAFA5-E914-47DA-95CA-C5AB0DC85B11"[..]);
-                base64::encode(&*m.result())
+                accept_hash(h.as_bytes())
             }
             None => Err(HandshakeError::MissingHeader {
                 name: "Sec-WebSocket-Key",
             })?,
         };
 
-        // TODO: Sec-WebSocket-Protocol, Sec-WebSocket-Extension
+        // TODO: Sec-WebSocket-Extension
 
         Ok(accept_hash)
     }
+
+    pub(crate) fn accept_hash(key: &[u8]) -> String {
+        let mut m = Sha1::new();
+        m.input(key);
+        m.input(&b"258EAFA5-E914-47DA-95CA-C5AB0DC85B11"[..]);
+        base64::encode(&*m.result())
+    }
+
+    fn negotiate_protocol(
+        input: &mut Input<'_>,
+        protocols: &[String],
+        require_protocol: bool,
+    ) -> Result<Option<String>, HandshakeError> {
+        let mut requested = false;
+        for h in input.request.headers().get_all(SEC_WEBSOCKET_PROTOCOL) {
+            let h = h.to_str().map_err(|_| HandshakeError::InvalidHeader {
+                name: "Sec-WebSocket-Protocol",
+            })?;
+            for candidate in h.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+                requested = true;
+                if let Some(protocol) = protocols.iter().find(|p| *p == candidate) {
+                    return Ok(Some(protocol.clone()));
+                }
+            }
+        }
+
+        if requested && require_protocol {
+            Err(HandshakeError::UnsupportedProtocol)
+        } else {
+            Ok(None)
+        }
+    }
 }
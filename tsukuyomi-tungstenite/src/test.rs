@@ -0,0 +1,59 @@
+//! Utilities for testing WebSocket endpoints.
+
+use {
+    http::{
+        header::{
+            CONNECTION, //
+            HOST,
+            SEC_WEBSOCKET_ACCEPT,
+            SEC_WEBSOCKET_KEY,
+            SEC_WEBSOCKET_VERSION,
+            UPGRADE,
+        },
+        Request,
+    },
+    tsukuyomi::App,
+    tsukuyomi_server::test::{Server, Upgraded},
+    tungstenite::protocol::Role,
+};
+
+/// A client-side transport connected to the test server.
+///
+/// This is a `Stream` and `Sink` of `Message`s. The blocking operations, such as
+/// `Sink::wait` and `Stream::wait`, can be used within the test cases.
+pub type TestWebSocket = tokio_tungstenite::WebSocketStream<Upgraded>;
+
+/// A set of extension methods of the test server used for testing WebSocket endpoints.
+pub trait ServerExt {
+    /// Performs the WebSocket handshake with the specified URI, and returns
+    /// the client-side transport of the established connection.
+    ///
+    /// If the server rejects the handshake, this method will return an `Err`.
+    fn ws(&mut self, uri: &str) -> tsukuyomi_server::Result<TestWebSocket>;
+}
+
+impl ServerExt for Server<App> {
+    fn ws(&mut self, uri: &str) -> tsukuyomi_server::Result<TestWebSocket> {
+        const KEY: &str = "dGhlIHNhbXBsZSBub25jZQ==";
+
+        let (response, io) = self.upgrade(
+            Request::get(uri)
+                .header(HOST, "localhost")
+                .header(CONNECTION, "upgrade")
+                .header(UPGRADE, "websocket")
+                .header(SEC_WEBSOCKET_VERSION, "13")
+                .header(SEC_WEBSOCKET_KEY, KEY),
+        )?;
+
+        let accept_hash = crate::imp::accept_hash(KEY.as_bytes());
+        match response.headers().get(SEC_WEBSOCKET_ACCEPT) {
+            Some(h) if h.as_bytes() == accept_hash.as_bytes() => {}
+            Some(..) => Err(failure::format_err!("invalid Sec-WebSocket-Accept"))?,
+            None => Err(failure::format_err!(
+                "missing header field: `Sec-WebSocket-Accept'"
+            ))?,
+        }
+
+        Ok(TestWebSocket::from_raw_socket(io, Role::Client, None))
+    }
+}
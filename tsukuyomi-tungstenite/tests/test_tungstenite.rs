@@ -10,6 +10,7 @@ use {
             HOST,
             SEC_WEBSOCKET_ACCEPT,
             SEC_WEBSOCKET_KEY,
+            SEC_WEBSOCKET_PROTOCOL,
             SEC_WEBSOCKET_VERSION,
             UPGRADE,
         },
@@ -20,7 +21,7 @@ use {
         App,
     },
     tsukuyomi_server::test::ResponseExt,
-    tsukuyomi_tungstenite::Ws,
+    tsukuyomi_tungstenite::{test::ServerExt, Message, Ws},
 };
 
 #[test]
@@ -32,7 +33,7 @@ fn test_version_sync() {
 fn test_handshake() -> tsukuyomi_server::Result<()> {
     let app = App::create(
         path!("/ws") //
-            .to(endpoint::get().reply(Ws::new(|_| Ok(())))),
+            .to(endpoint::get().reply(Ws::new(|_, _| Ok(())))),
     )?;
     let mut server = tsukuyomi_server::test::server(app)?;
 
@@ -55,4 +56,205 @@ fn test_handshake() -> tsukuyomi_server::Result<()> {
     Ok(())
 }
 
-// TODO: add check whether the task to handle upgraded connection is spawned
+#[test]
+fn test_handshake_with_protocol() -> tsukuyomi_server::Result<()> {
+    let app = App::create(
+        path!("/ws") //
+            .to(endpoint::get().reply(
+                Ws::new(|_, _| Ok(())) //
+                    .protocols(vec!["graphql-ws", "chat"])
+                    .require_protocol(true),
+            )),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let handshake = |protocols: &'static str| {
+        Request::get("/ws")
+            .header(HOST, "localhost:4000")
+            .header(CONNECTION, "upgrade")
+            .header(UPGRADE, "websocket")
+            .header(SEC_WEBSOCKET_VERSION, "13")
+            .header(SEC_WEBSOCKET_KEY, "dGhlIHNhbXBsZSBub25jZQ==")
+            .header(SEC_WEBSOCKET_PROTOCOL, protocols)
+            .body("")
+    };
+
+    let response = server.perform(handshake("superchat, chat, graphql-ws"))?;
+    assert_eq!(response.status(), 101);
+    assert_eq!(response.header(SEC_WEBSOCKET_PROTOCOL)?, "chat");
+
+    let response = server.perform(handshake("superchat"))?;
+    assert_eq!(response.status(), 400);
+
+    Ok(())
+}
+
+#[test]
+fn test_echo() -> tsukuyomi_server::Result<()> {
+    use futures::{Future, Sink, Stream};
+
+    let app = App::create(
+        path!("/ws") //
+            .to(endpoint::get().reply(Ws::new(|stream, _| {
+                let (tx, rx) = stream.split();
+                rx.forward(tx).then(|_| Ok(()))
+            }))),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let ws = server.ws("/ws")?;
+    let ws = ws.send(Message::text("hello")).wait()?;
+    let (message, _ws) = ws.into_future().wait().map_err(|(err, _)| err)?;
+    assert_eq!(message, Some(Message::text("hello")));
+
+    assert!(server.ws("/not_found").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_max_message_size() -> tsukuyomi_server::Result<()> {
+    use {
+        futures::{Future, Sink, Stream},
+        tsukuyomi_tungstenite::CloseCode,
+    };
+
+    let app = App::create(
+        path!("/ws") //
+            .to(endpoint::get().reply(
+                Ws::new(|stream, handshake| {
+                    // Notify the client of the configured limit before echoing.
+                    let max = format!("{:?}", handshake.config().max_message_size);
+                    stream
+                        .send(Message::text(max))
+                        .and_then(|stream| {
+                            let (tx, rx) = stream.split();
+                            rx.forward(tx)
+                        })
+                        .then(|_| Ok(()))
+                })
+                .max_message_size(16),
+            )),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let ws = server.ws("/ws")?;
+    let (message, ws) = ws.into_future().wait().map_err(|(err, _)| err)?;
+    assert_eq!(message, Some(Message::text("Some(16)")));
+
+    let ws = ws.send(Message::text("a".repeat(64))).wait()?;
+    let (message, _ws) = ws.into_future().wait().map_err(|(err, _)| err)?;
+    match message {
+        Some(Message::Close(Some(frame))) => assert_eq!(frame.code, CloseCode::Size),
+        message => panic!("unexpected message: {:?}", message),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_auto_ping() -> tsukuyomi_server::Result<()> {
+    use {
+        futures::{Future, Stream},
+        std::time::Duration,
+    };
+
+    let app = App::create(
+        path!("/ws") //
+            .to(endpoint::get().reply(
+                Ws::new(|stream, _| stream.for_each(|_| Ok(())).then(|_| Ok(())))
+                    .auto_ping_interval(Duration::from_millis(50)),
+            )),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    // The server sends a ping without any messages from the client.
+    let ws = server.ws("/ws")?;
+    let (message, _ws) = ws.into_future().wait().map_err(|(err, _)| err)?;
+    match message {
+        Some(Message::Ping(..)) => {}
+        message => panic!("unexpected message: {:?}", message),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_handshake_request() -> tsukuyomi_server::Result<()> {
+    use futures::{Future, Sink, Stream};
+
+    let app = App::create(
+        path!("/ws") //
+            .to(endpoint::get().reply(Ws::new(|stream, handshake| {
+                let request = handshake.request();
+                let text = format!(
+                    "{} {} {:?}",
+                    request.method(),
+                    request.uri(),
+                    request.headers().get(HOST),
+                );
+                stream.send(Message::text(text)).then(|_| Ok(()))
+            }))),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let ws = server.ws("/ws?room=lobby")?;
+    let (message, _ws) = ws.into_future().wait().map_err(|(err, _)| err)?;
+    assert_eq!(
+        message,
+        Some(Message::text(r#"GET /ws?room=lobby Some("localhost")"#))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_close_with() -> tsukuyomi_server::Result<()> {
+    use {
+        futures::{Future, Stream},
+        std::{
+            sync::{mpsc, Arc, Mutex},
+            time::Duration,
+        },
+        tsukuyomi_tungstenite::CloseCode,
+    };
+
+    // Receives the close frame acknowledged by the client.
+    let (tx_ack, rx_ack) = mpsc::channel();
+    let tx_ack = Arc::new(Mutex::new(tx_ack));
+
+    let app = App::create(
+        path!("/ws") //
+            .to(endpoint::get().reply(Ws::new(move |stream, _| {
+                let tx_ack = tx_ack.lock().unwrap().clone();
+                stream
+                    .close_with(CloseCode::Away, "going away")
+                    .then(move |result| {
+                        let _ = tx_ack.send(result.map_err(|err| err.to_string()));
+                        Ok(())
+                    })
+            }))),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let ws = server.ws("/ws")?;
+    let (message, ws) = ws.into_future().wait().map_err(|(err, _)| err)?;
+    match message {
+        Some(Message::Close(Some(ref frame))) => {
+            assert_eq!(frame.code, CloseCode::Away);
+            assert_eq!(frame.reason, "going away");
+        }
+        message => panic!("unexpected message: {:?}", message),
+    }
+
+    // The client replies the close frame and the connection is closed.
+    let _ = ws.into_future().wait();
+
+    let ack = rx_ack
+        .recv_timeout(Duration::from_secs(5))
+        .expect("the closing handshake is not completed")
+        .map_err(failure::err_msg)?;
+    assert_eq!(ack.map(|frame| frame.code), Some(CloseCode::Away));
+
+    Ok(())
+}
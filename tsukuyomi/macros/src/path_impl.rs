@@ -36,6 +36,7 @@ pub fn path_impl(input: TokenStream) -> parse::Result<TokenStream> {
 #[derive(Debug, Copy, Clone, PartialEq)]
 enum Param<'a> {
     Single(&'a str),
+    Optional(&'a str),
     CatchAll(&'a str),
 }
 
@@ -60,6 +61,20 @@ fn parse_literal(path: &str, span: Span) -> parse::Result<Vec<Param<'_>>> {
 
     while let Some(segment) = iter.next() {
         match segment.split_at(1) {
+            (":", name) if name.ends_with('?') => {
+                let name = &name[..name.len() - 1];
+                if !names.insert(name) {
+                    return spanned_err(
+                        span,
+                        format!("detected duplicate parameter name: '{}'", name),
+                    );
+                }
+                params.push(Param::Optional(name));
+                if iter.next().is_some() {
+                    return spanned_err(span, "the optional parameter must be at the end of path");
+                }
+                break;
+            }
             (":", name) => {
                 if !names.insert(name) {
                     return spanned_err(
@@ -113,6 +128,7 @@ impl<'a> ToTokens for PathImplOutput<'a> {
         let PercentEncoded = quote!(#module::PercentEncoded);
         let FromPercentEncoded = quote!(#module::FromPercentEncoded);
         let Error = quote!(#module::Error);
+        let invalid_param = quote!(#module::invalid_param);
 
         if self.params.is_empty() {
             tokens.append_all(quote!(
@@ -139,22 +155,63 @@ impl<'a> ToTokens for PathImplOutput<'a> {
         };
         let where_clause = &where_clause;
 
+        let output_types: Vec<_> = self
+            .params
+            .iter()
+            .zip(type_idents)
+            .map(|(param, ty)| match param {
+                Param::Optional(..) => quote!(Option<#ty>),
+                Param::Single(..) | Param::CatchAll(..) => quote!(#ty),
+            })
+            .collect();
+        let output_types = &output_types[..];
+
         let extract = self.params.iter().zip(type_idents).map(|(param, ty)| {
-            let extract_raw = match param {
-                Param::Single(name) => quote!(params.name(#name).expect("missing parameter")),
-                Param::CatchAll(..) => {
-                    quote!(params.catch_all().expect("missing catch-all parameter"))
+            let (name, extract_raw) = match param {
+                Param::Single(name) => (
+                    *name,
+                    quote!(params.name(#name).expect("missing parameter")),
+                ),
+                Param::Optional(name) => {
+                    // the parameter is missing if the route is matched to the path without
+                    // the trailing segment.
+                    return quote!(
+                        let #ty = match params.name(#name) {
+                            Some(raw) => {
+                                let raw = unsafe { #PercentEncoded::new_unchecked(raw) };
+                                Some(
+                                    <#ty as #FromPercentEncoded>::from_percent_encoded(raw)
+                                        .map_err(|cause| #invalid_param(#name, raw, cause))?
+                                )
+                            }
+                            None => None,
+                        };
+                    );
                 }
+                Param::CatchAll(name) => (
+                    if name.is_empty() { "*" } else { *name },
+                    quote!(params.catch_all().expect("missing catch-all parameter")),
+                ),
             };
             quote!(
-                let #ty = <#ty as #FromPercentEncoded>::from_percent_encoded(
-                    unsafe { #PercentEncoded::new_unchecked(#extract_raw) }
-                ).map_err(Into::into)?;
+                let #ty = {
+                    let raw = unsafe { #PercentEncoded::new_unchecked(#extract_raw) };
+                    <#ty as #FromPercentEncoded>::from_percent_encoded(raw)
+                        .map_err(|cause| #invalid_param(#name, raw, cause))?
+                };
             )
         });
 
+        let (path, optional) = match self.params.last() {
+            Some(Param::Optional(name)) => (
+                path[..path.len() - 1].to_owned(),
+                quote!(.optional_tail(#name)),
+            ),
+            _ => (path.to_owned(), quote!()),
+        };
+
         tokens.append_all(quote! {
-            fn call<#(#type_idents),*>() -> #Path<impl #PathExtractor<Output = (#(#type_idents,)*)>>
+            fn call<#(#type_idents),*>() -> #Path<impl #PathExtractor<Output = (#(#output_types,)*)>>
             #where_clause
             {
                 #[allow(missing_debug_implementations)]
@@ -165,7 +222,7 @@ impl<'a> ToTokens for PathImplOutput<'a> {
                 impl<#(#type_idents),*> #PathExtractor for __Extractor<#(#type_idents),*>
                 #where_clause
                 {
-                    type Output = (#(#type_idents,)*);
+                    type Output = (#(#output_types,)*);
 
                     #[allow(nonstandard_style)]
                     fn extract(params: Option<&#Params<'_>>)
@@ -177,7 +234,7 @@ impl<'a> ToTokens for PathImplOutput<'a> {
                     }
                 }
 
-                #Path::<__Extractor<#(#type_idents),*>>::new(#path)
+                #Path::<__Extractor<#(#type_idents),*>>::new(#path) #optional
             }
         });
     }
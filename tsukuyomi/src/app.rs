@@ -11,18 +11,35 @@ mod tests;
 pub(crate) use self::recognizer::Captures;
 pub use self::{
     config::{Error, Result},
+    scope::ScopeId,
     service::AppService,
 };
 
 use {
     self::{
-        config::Concurrency,
+        config::{Concurrency, Guard},
         recognizer::{RecognizeError, Recognizer},
-        scope::{Scope, ScopeId, Scopes},
+        scope::{Scope, Scopes},
+    },
+    crate::{
+        handler::AllowedMethods,
+        input::{body::RequestBody, clock::Clock, method::MethodSemantics, state::States, Input},
+        output::ResponseBody,
+        uri::Uri,
+        util::Never,
+    },
+    http::{
+        header::{self, HeaderValue},
+        Method, Request, Response, StatusCode,
+    },
+    regex::Regex,
+    std::{
+        fmt,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
     },
-    crate::{input::body::RequestBody, uri::Uri, util::Never},
-    http::Request,
-    std::{fmt, sync::Arc},
     tsukuyomi_service::{MakeService, Service},
 };
 
@@ -36,6 +53,41 @@ impl<C> AppBase<C>
 where
     C: Concurrency,
 {
+    /// Marks this application as draining.
+    ///
+    /// Once this method is called, the incoming requests are no longer processed
+    /// and the draining response (by default, `503 Service Unavailable`) is returned
+    /// with the header field `Connection: close`, so that the clients fail fast and
+    /// reconnect to another instance. This method is intended to be called when
+    /// the graceful shutdown of the server begins.
+    ///
+    /// The state is shared among the clones of this value and the services created from it.
+    pub fn start_draining(&self) {
+        self.inner.draining.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns `true` if this application is draining.
+    pub fn is_draining(&self) -> bool {
+        self.inner.draining.load(Ordering::SeqCst)
+    }
+
+    /// Returns the information of all routes registered in this application,
+    /// in the order of registration.
+    ///
+    /// The default handlers registered by `path!("*")` are not included.
+    pub fn routes(&self) -> Vec<RouteInfo> {
+        self.inner
+            .recognizer
+            .iter()
+            .flat_map(|endpoints| endpoints.iter())
+            .map(|endpoint| RouteInfo {
+                uri: endpoint.uri.as_str().to_owned(),
+                allowed_methods: endpoint.allowed_methods.clone(),
+                scope_id: endpoint.scope,
+            })
+            .collect()
+    }
+
     /// Converts itself into a `MakeService` with the specified `ModifyService`.
     pub fn with_modify_service<M>(
         self,
@@ -96,13 +148,109 @@ mod with_modify_service {
     }
 }
 
+/// The information of a route registered in `App`, returned by `AppBase::routes`.
+#[derive(Debug, Clone)]
+pub struct RouteInfo {
+    uri: String,
+    allowed_methods: Option<AllowedMethods>,
+    scope_id: ScopeId,
+}
+
+impl RouteInfo {
+    /// Returns the URI template of this route, including the prefixes of its scopes.
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// Returns the set of methods that this route accepts.
+    ///
+    /// The return value is `None` if the route accepts all methods.
+    pub fn allowed_methods(&self) -> Option<&AllowedMethods> {
+        self.allowed_methods.as_ref()
+    }
+
+    /// Returns the identifier of the scope that this route belongs to.
+    pub fn scope_id(&self) -> ScopeId {
+        self.scope_id
+    }
+}
+
+/// An HTTP application that can be shared among multiple threads.
 pub type App = AppBase<self::config::ThreadSafe>;
+
+/// An HTTP application whose handlers are not required to be `Send`.
+///
+/// The handlers registered in `LocalApp` can hold the values which cannot be sent
+/// across threads, such as `Rc<RefCell<T>>`. Instead, the application must be
+/// driven on a single-threaded runtime, e.g. by using `Server::current_thread`
+/// in `tsukuyomi-server`:
+///
+/// ```no_run
+/// # use {std::{cell::RefCell, rc::Rc}, tsukuyomi::{app::LocalApp, config::prelude::*}};
+/// # fn main() -> tsukuyomi_server::Result<()> {
+/// let counter = Rc::new(RefCell::new(0));
+/// let app = LocalApp::create(
+///     path!("/") //
+///         .to(endpoint::get().call(move || {
+///             *counter.borrow_mut() += 1;
+///             format!("{}", *counter.borrow())
+///         })),
+/// )?;
+///
+/// tsukuyomi_server::Server::new(app)
+///     .current_thread()
+///     .run()
+/// # }
+/// ```
+///
+/// Note that the components depending on the blocking API of `tokio-threadpool`
+/// (e.g. `fs::NamedFile`) do not work on the single-threaded runtime.
 pub type LocalApp = AppBase<self::config::CurrentThread>;
 
 #[derive(Debug)]
 struct AppInner<C: Concurrency> {
-    recognizer: Recognizer<Arc<Endpoint<C>>>,
+    recognizer: Recognizer<Vec<Arc<Endpoint<C>>>>,
     scopes: Scopes<ScopeData<C>>,
+    options: AppOptions,
+    draining: AtomicBool,
+}
+
+type DrainingResponseFn = dyn Fn(&Request<()>) -> Response<ResponseBody> + Send + Sync + 'static;
+
+type ErrorRendererFn =
+    dyn Fn(crate::error::Error, &mut Input<'_>) -> Response<ResponseBody> + Send + Sync + 'static;
+
+/// The application-wide options.
+#[derive(Default)]
+struct AppOptions {
+    redirect_trailing_slash: bool,
+    auto_options: bool,
+    method_semantics: MethodSemantics,
+    clock: Option<Arc<dyn Clock>>,
+    draining_response: Option<Box<DrainingResponseFn>>,
+    error_renderer: Option<Box<ErrorRendererFn>>,
+}
+
+impl fmt::Debug for AppOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AppOptions")
+            .field("redirect_trailing_slash", &self.redirect_trailing_slash)
+            .field("auto_options", &self.auto_options)
+            .field("method_semantics", &self.method_semantics)
+            .field("clock", &self.clock)
+            .field(
+                "draining_response",
+                &self
+                    .draining_response
+                    .as_ref()
+                    .map(|_| "<draining response>"),
+            )
+            .field(
+                "error_renderer",
+                &self.error_renderer.as_ref().map(|_| "<error renderer>"),
+            )
+            .finish()
+    }
 }
 
 impl<C: Concurrency> AppInner<C> {
@@ -136,7 +284,7 @@ impl<C: Concurrency> AppInner<C> {
             .and_then(|ancestors| {
                 ancestors
                     .into_iter()
-                    .find(|&&scope| self.scope(scope).data.prefix.as_str().starts_with(path)) //
+                    .find(|&&scope| self.scope(scope).data.prefix.starts_with_path(path)) //
                     .or_else(|| ancestors.last())
                     .cloned()
             })
@@ -145,16 +293,46 @@ impl<C: Concurrency> AppInner<C> {
         self.scope(node_id)
     }
 
-    fn find_default_handler(&self, start: ScopeId) -> Option<&C::Handler> {
+    /// Finds the deepest scope whose prefix matches the path, from the specified scope
+    /// and its descendants.
+    ///
+    /// The scopes without any routes (e.g. the ones having only the default handler)
+    /// cannot be inferred from the candidates of endpoints, so they are looked up by
+    /// their prefixes.
+    fn find_nearest_scope<'a>(
+        &'a self,
+        path: &str,
+        scope: &'a Scope<ScopeData<C>>,
+    ) -> &'a Scope<ScopeData<C>> {
+        self.scopes
+            .iter()
+            .filter(|s| s.ancestors().contains(&scope.id()))
+            .filter(|s| {
+                let prefix = s.data.prefix.as_str().trim_end_matches('/');
+                crate::uri::match_prefix(prefix, path).is_some()
+            })
+            .max_by_key(|s| s.ancestors().len())
+            .unwrap_or(scope)
+    }
+
+    /// Finds the default handler from the specified scope and its ancestors,
+    /// with the identifier of the scope that it belongs to.
+    fn find_default_handler(&self, start: ScopeId) -> Option<(ScopeId, &C::Handler)> {
         let scope = self.scope(start);
         if let Some(ref f) = scope.data.default_handler {
-            return Some(f);
+            return Some((start, f));
         }
         scope
             .ancestors()
             .into_iter()
             .rev()
-            .filter_map(|&id| self.scope(id).data.default_handler.as_ref())
+            .filter_map(|&id| {
+                self.scope(id)
+                    .data
+                    .default_handler
+                    .as_ref()
+                    .map(|f| (id, f))
+            })
             .next()
     }
 
@@ -162,23 +340,117 @@ impl<C: Concurrency> AppInner<C> {
         &self,
         path: &str,
         captures: &mut Option<Captures>,
-    ) -> std::result::Result<&Arc<Endpoint<C>>, &Scope<ScopeData<C>>> {
+    ) -> std::result::Result<&[Arc<Endpoint<C>>], &Scope<ScopeData<C>>> {
         match self.recognizer.recognize(path, captures) {
-            Ok(endpoint) => Ok(endpoint),
+            Ok(endpoints) => Ok(endpoints),
             Err(RecognizeError::NotMatched) => Err(self.scope(ScopeId::root())),
             Err(RecognizeError::PartiallyMatched(candidates)) => Err(self.infer_scope(
                 path,
                 candidates
                     .iter()
-                    .filter_map(|i| self.recognizer.get(i).map(|e| &**e)),
+                    .filter_map(|i| self.recognizer.get(i))
+                    .flat_map(|endpoints| endpoints.iter().map(|e| &**e)),
             )),
         }
     }
+
+    /// Creates the response returned while the application is draining.
+    fn draining_response(&self, request: &Request<()>) -> Response<ResponseBody> {
+        let mut response = match self.options.draining_response {
+            Some(ref f) => f(request),
+            None => Response::builder()
+                .status(StatusCode::SERVICE_UNAVAILABLE)
+                .body(ResponseBody::empty())
+                .expect("should be a valid response"),
+        };
+        response
+            .headers_mut()
+            .insert(header::CONNECTION, HeaderValue::from_static("close"));
+        response
+    }
+
+    /// Creates the response to `OPTIONS` from the methods allowed by the endpoints
+    /// which accept the path.
+    ///
+    /// The return value is `None` if the option is disabled, no endpoint accepts the path,
+    /// or one of the endpoints handles `OPTIONS` by itself.
+    fn auto_options_response(
+        &self,
+        path: &str,
+        endpoints: &[Arc<Endpoint<C>>],
+        captures: Option<&Captures>,
+    ) -> Option<Response<ResponseBody>> {
+        if !self.options.auto_options {
+            return None;
+        }
+
+        let mut allowed_methods: Option<AllowedMethods> = None;
+        for endpoint in endpoints.iter().filter(|e| e.accepts(path, captures)) {
+            match endpoint.allowed_methods {
+                Some(ref methods) if !methods.contains(&Method::OPTIONS) => allowed_methods
+                    .get_or_insert_with(|| std::iter::empty().collect())
+                    .extend(methods.iter().cloned()),
+                _ => return None,
+            }
+        }
+
+        let mut allowed_methods = allowed_methods?;
+        let fallbacks: Vec<_> = self
+            .options
+            .method_semantics
+            .fallbacks()
+            .filter(|(_, fallback)| allowed_methods.contains(fallback))
+            .map(|(method, _)| method)
+            .collect();
+        allowed_methods.extend(fallbacks);
+        allowed_methods.extend(Some(Method::OPTIONS));
+
+        Some(
+            Response::builder()
+                .status(StatusCode::NO_CONTENT)
+                .header(header::ALLOW, allowed_methods.to_header_value())
+                .body(ResponseBody::empty())
+                .expect("should be a valid response"),
+        )
+    }
+
+    /// Returns the location to be redirected if the path with or without
+    /// the trailing slash matches a route.
+    fn find_trailing_slash_redirect(&self, uri: &http::Uri) -> Option<String> {
+        if !self.options.redirect_trailing_slash {
+            return None;
+        }
+
+        let path = uri.path();
+        if path == "/" || path == "*" {
+            return None;
+        }
+        let location = if path.ends_with('/') {
+            path[..path.len() - 1].to_owned()
+        } else {
+            format!("{}/", path)
+        };
+
+        let mut captures = None;
+        match self.recognizer.recognize(&location, &mut captures) {
+            Ok(endpoints)
+                if endpoints
+                    .iter()
+                    .any(|e| e.accepts(&location, captures.as_ref())) => {}
+            _ => return None,
+        }
+
+        Some(match uri.query() {
+            Some(query) => format!("{}?{}", location, query),
+            None => location,
+        })
+    }
 }
 
 struct ScopeData<C: Concurrency> {
     prefix: Uri,
     default_handler: Option<C::Handler>,
+    states: States,
 }
 
 impl<C: Concurrency> fmt::Debug for ScopeData<C> {
@@ -189,6 +461,7 @@ impl<C: Concurrency> fmt::Debug for ScopeData<C> {
                 "default_handler",
                 &self.default_handler.as_ref().map(|_| "<default handler>"),
             )
+            .field("states", &self.states)
             .finish()
     }
 }
@@ -198,15 +471,50 @@ struct Endpoint<C: Concurrency> {
     scope: ScopeId,
     ancestors: Vec<ScopeId>,
     uri: Uri,
+    constraints: Vec<(usize, Regex)>,
+    guards: Vec<Guard>,
+    allowed_methods: Option<AllowedMethods>,
     handler: C::Handler,
 }
 
+impl<C: Concurrency> Endpoint<C> {
+    /// Returns `true` if the captured parameters satisfy the constraints of this endpoint.
+    fn accepts(&self, path: &str, captures: Option<&Captures>) -> bool {
+        self.constraints.iter().all(|&(pos, ref regex)| {
+            captures
+                .and_then(|captures| {
+                    captures
+                        .params()
+                        .get(pos)
+                        .cloned()
+                        .or_else(|| captures.wildcard())
+                })
+                .and_then(|(start, end)| path.get(start..end))
+                .map_or(false, |value| regex.is_match(value))
+        })
+    }
+
+    /// Returns `true` if this endpoint accepts the specified request method,
+    /// including the fallback specified by `MethodSemantics` (e.g. `HEAD` to `GET`).
+    fn allows(&self, method: &Method, method_semantics: &MethodSemantics) -> bool {
+        self.allowed_methods.as_ref().map_or(true, |methods| {
+            methods.contains(method)
+                || method_semantics
+                    .fallback(method)
+                    .map_or(false, |fallback| methods.contains(&fallback))
+        })
+    }
+}
+
 impl<C: Concurrency> fmt::Debug for Endpoint<C> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Endpoint")
             .field("scope", &self.scope)
             .field("ancestors", &self.ancestors)
             .field("uri", &self.uri)
+            .field("constraints", &self.constraints)
+            .field("guards", &self.guards)
+            .field("allowed_methods", &self.allowed_methods)
             .finish()
     }
 }
@@ -2,14 +2,23 @@ use {
     super::{
         recognizer::Recognizer,
         scope::{ScopeId, Scopes},
-        AppBase, AppInner, Endpoint, ScopeData, Uri,
+        AppBase, AppInner, AppOptions, Endpoint, ScopeData, Uri,
     },
     crate::{
         handler::{Handler, ModifyHandler},
+        input::{clock::Clock, method::MethodSemantics, state::States, Input},
+        output::ResponseBody,
         util::{Chain, Never},
     },
     failure::Fail,
-    std::{marker::PhantomData, rc::Rc, sync::Arc},
+    http::{Request, Response},
+    regex::Regex,
+    std::{
+        fmt,
+        marker::PhantomData,
+        rc::Rc,
+        sync::{atomic::AtomicBool, Arc},
+    },
 };
 
 /// A type alias of `Result<T, E>` whose error type is restricted to `AppError`.
@@ -39,6 +48,56 @@ impl Error {
     }
 }
 
+/// A predicate checked after the path of a route is matched.
+#[derive(Clone)]
+pub(crate) struct Guard(Arc<dyn Fn(&Input<'_>) -> bool + Send + Sync + 'static>);
+
+impl fmt::Debug for Guard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Guard").field(&"<guard>").finish()
+    }
+}
+
+impl Guard {
+    pub(crate) fn new<F>(f: F) -> Self
+    where
+        F: Fn(&Input<'_>) -> bool + Send + Sync + 'static,
+    {
+        Guard(Arc::new(f))
+    }
+
+    pub(crate) fn check(&self, input: &Input<'_>) -> bool {
+        (self.0)(input)
+    }
+}
+
+/// The additional conditions of a route, checked after its path is matched.
+#[derive(Debug, Default)]
+pub(crate) struct Conditions {
+    /// The pairs of parameter name and regex pattern.
+    pub(crate) constraints: Vec<(String, String)>,
+    pub(crate) guards: Vec<Guard>,
+}
+
+impl Conditions {
+    fn is_empty(&self) -> bool {
+        self.constraints.is_empty() && self.guards.is_empty()
+    }
+
+    /// Returns a copy of the conditions, excluding the constraints on the specified parameter.
+    pub(crate) fn without_param(&self, name: &str) -> Self {
+        Self {
+            constraints: self
+                .constraints
+                .iter()
+                .filter(|(n, _)| n != name)
+                .cloned()
+                .collect(),
+            guards: self.guards.clone(),
+        }
+    }
+}
+
 /// A trait to specify the concurrency of trait objects inside of `AppBase`.
 pub trait Concurrency: self::imp::ConcurrencyImpl {}
 
@@ -249,19 +308,30 @@ where
         let mut scopes = Scopes::new(ScopeData {
             prefix: Uri::root(),
             default_handler: None,
+            states: States::default(),
         });
+        let mut options = AppOptions::default();
         config
             .configure(&mut Scope {
                 recognizer: &mut recognizer,
                 scopes: &mut scopes,
+                options: &mut options,
                 scope_id: ScopeId::root(),
                 modifier: &(),
                 _marker: PhantomData,
             })
             .map_err(Into::into)?;
 
+        // make the states in ancestor scopes visible from the descendants.
+        scopes.propagate(|parent, child| child.states.inherit(&parent.states));
+
         Ok(Self {
-            inner: Arc::new(AppInner { recognizer, scopes }),
+            inner: Arc::new(AppInner {
+                recognizer,
+                scopes,
+                options,
+                draining: AtomicBool::new(false),
+            }),
         })
     }
 }
@@ -269,8 +339,9 @@ where
 /// A type representing the contextual information in `Config::configure`.
 #[derive(Debug)]
 pub struct Scope<'a, M, T: Concurrency> {
-    recognizer: &'a mut Recognizer<Arc<Endpoint<T>>>,
+    recognizer: &'a mut Recognizer<Vec<Arc<Endpoint<T>>>>,
     scopes: &'a mut Scopes<ScopeData<T>>,
+    options: &'a mut AppOptions,
     modifier: &'a M,
     scope_id: ScopeId,
     _marker: PhantomData<Rc<()>>,
@@ -282,6 +353,39 @@ where
 {
     /// Adds a route onto the current scope.
     pub fn route<H>(&mut self, path: impl AsRef<str>, handler: H) -> Result<()>
+    where
+        H: Handler,
+        M: ModifyHandler<H>,
+        M::Handler: Into<T::Handler>,
+    {
+        self.add_route(path, Conditions::default(), handler)
+    }
+
+    /// Sets the default handler of the current scope.
+    ///
+    /// The default handler is called when the request path does not match any route,
+    /// and the nearest one is chosen from the scope where the path belongs and its
+    /// ancestors. That is, the default handler of a sub-scope overrides the one of
+    /// its parent, and the request is replied with `404 Not Found` if none of them
+    /// has the default handler. This is equivalent to registering the route with `*`.
+    pub fn default_handler<H>(&mut self, handler: H) -> Result<()>
+    where
+        H: Handler,
+        M: ModifyHandler<H>,
+        M::Handler: Into<T::Handler>,
+    {
+        self.add_route("*", Conditions::default(), handler)
+    }
+
+    /// Adds a route with the additional conditions checked after its path is matched.
+    ///
+    /// The routes with the same path are grouped and tried in the order of registration.
+    pub(crate) fn add_route<H>(
+        &mut self,
+        path: impl AsRef<str>,
+        conditions: Conditions,
+        handler: H,
+    ) -> Result<()>
     where
         H: Handler,
         M: ModifyHandler<H>,
@@ -299,33 +403,209 @@ where
                 .join(&uri)
                 .map_err(Error::custom)?;
 
-            let scope = &self.scopes[self.scope_id];
-            self.recognizer
-                .insert(
-                    uri.as_str(),
-                    Arc::new(Endpoint {
-                        scope: scope.id(),
-                        ancestors: scope
-                            .ancestors()
-                            .into_iter()
-                            .cloned()
-                            .chain(Some(scope.id()))
-                            .collect(),
-                        uri: uri.clone(),
-                        handler: self.modifier.modify(handler).into(),
-                    }),
-                )
+            let constraints = conditions
+                .constraints
+                .into_iter()
+                .map(|(name, pattern)| {
+                    let pos = uri
+                        .capture_names()
+                        .and_then(|names| names.position(&name))
+                        .ok_or_else(|| {
+                            failure::format_err!("unknown parameter name: '{}'", name)
+                        })?;
+                    let regex = Regex::new(&format!("^(?:{})$", pattern))?;
+                    Ok((pos, regex))
+                })
+                .collect::<std::result::Result<_, failure::Error>>()
                 .map_err(Error::custom)?;
+
+            let handler = self.modifier.modify(handler);
+            let allowed_methods = handler.allowed_methods().cloned();
+            let scope = &self.scopes[self.scope_id];
+            let endpoint = Endpoint {
+                scope: scope.id(),
+                ancestors: scope
+                    .ancestors()
+                    .into_iter()
+                    .cloned()
+                    .chain(Some(scope.id()))
+                    .collect(),
+                uri,
+                constraints,
+                guards: conditions.guards,
+                allowed_methods,
+                handler: handler.into(),
+            };
+            self.insert_endpoint(endpoint)?;
         } else {
+            if !conditions.is_empty() {
+                return Err(Error::custom(failure::format_err!(
+                    "the default handler cannot have the parameter constraints or guards"
+                )));
+            }
             self.scopes[self.scope_id].data.default_handler =
                 Some(self.modifier.modify(handler).into());
         }
         Ok(())
     }
 
+    fn insert_endpoint(&mut self, endpoint: Endpoint<T>) -> Result<()> {
+        if let Some(endpoints) = self.recognizer.get_mut_by_path(endpoint.uri.as_str()) {
+            if endpoints
+                .iter()
+                .any(|e| e.constraints.is_empty() && e.guards.is_empty())
+            {
+                return Err(Error::custom(failure::format_err!(
+                    "the route '{}' is unreachable since the route registered earlier \
+                     on the same path has no guards",
+                    endpoint.uri
+                )));
+            }
+            endpoints.push(Arc::new(endpoint));
+        } else {
+            let uri = endpoint.uri.clone();
+            self.recognizer
+                .insert(uri.as_str(), vec![Arc::new(endpoint)])
+                .map_err(Error::custom)?;
+        }
+        Ok(())
+    }
+
+    /// Registers a shared state onto the current scope.
+    ///
+    /// The registered state is visible from the handlers in the current scope
+    /// and its sub-scopes, regardless of the order of registration. If a state of
+    /// the same type has already been registered in the current scope, it is replaced.
+    pub fn state<S>(&mut self, state: S)
+    where
+        S: Send + Sync + 'static,
+    {
+        self.scopes[self.scope_id].data.states.insert(state);
+    }
+
+    /// Sets whether to redirect the requests whose path differs from a registered route
+    /// only in the trailing slash.
+    ///
+    /// If enabled, a request that does not match any route is redirected to the path
+    /// with (or without) the trailing slash with `308 Permanent Redirect`, when the
+    /// path matches a route. The query string is preserved. The default value is `false`.
+    ///
+    /// Note that this option is applied to the entire application, regardless of
+    /// the scope where it is specified.
+    pub fn redirect_trailing_slash(&mut self, enabled: bool) {
+        self.options.redirect_trailing_slash = enabled;
+    }
+
+    /// Sets whether to reply to `OPTIONS` automatically.
+    ///
+    /// If enabled, the request with `OPTIONS` to a path that matches some routes is
+    /// replied with `204 No Content` and the header field `Allow`, which contains
+    /// the methods allowed by all routes registered on the path. The routes that
+    /// accept `OPTIONS` explicitly (or accept any method) take precedence, that is,
+    /// the request is dispatched to the routes as usual. The default value is `false`.
+    ///
+    /// Note that this option is applied to the entire application, regardless of
+    /// the scope where it is specified.
+    pub fn auto_options(&mut self, enabled: bool) {
+        self.options.auto_options = enabled;
+    }
+
+    /// Sets the semantics of request methods used in the application.
+    ///
+    /// The value is visible from the handlers through `Input::method_semantics`.
+    /// By default, the semantics defined in RFC 7231 are used.
+    ///
+    /// Note that this option is applied to the entire application, regardless of
+    /// the scope where it is specified.
+    pub fn method_semantics(&mut self, semantics: MethodSemantics) {
+        self.options.method_semantics = semantics;
+    }
+
+    /// Sets the clock used in the application.
+    ///
+    /// The value is visible from the handlers through `Input::clock`.
+    /// By default, the clock returning the time of the system is used.
+    ///
+    /// Note that this option is applied to the entire application, regardless of
+    /// the scope where it is specified.
+    pub fn clock<T>(&mut self, clock: T)
+    where
+        T: Clock,
+    {
+        self.options.clock = Some(Arc::new(clock));
+    }
+
+    /// Sets the function that creates the response returned while the application is draining.
+    ///
+    /// The header field `Connection: close` is always added to the returned response.
+    /// By default, an empty response with the status `503 Service Unavailable` is returned.
+    ///
+    /// Note that this option is applied to the entire application, regardless of
+    /// the scope where it is specified.
+    pub fn draining_response<F>(&mut self, f: F)
+    where
+        F: Fn(&Request<()>) -> Response<ResponseBody> + Send + Sync + 'static,
+    {
+        self.options.draining_response = Some(Box::new(f));
+    }
+
+    /// Sets the function that renders the errors occurred in the application.
+    ///
+    /// The function is called with the errors returned from the handlers and the ones
+    /// created by the application itself (e.g. `404 Not Found` for the unmatched paths),
+    /// and is useful for rendering the branded error pages or JSON responses.
+    /// The original response can be obtained by `Error::into_response`.
+    /// The header fields provided by the error (e.g. `Allow` in `405 Method Not Allowed`
+    /// or `WWW-Authenticate` in `401 Unauthorized`) are merged into the rendered response,
+    /// except for the ones already set by the function.
+    /// By default, the response created by `Error::into_response` is returned as it is.
+    ///
+    /// Note that this option is applied to the entire application, regardless of
+    /// the scope where it is specified.
+    pub fn error_renderer<F>(&mut self, f: F)
+    where
+        F: Fn(crate::error::Error, &mut Input<'_>) -> Response<ResponseBody>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.options.error_renderer = Some(Box::new(f));
+    }
+
+    /// Sets the handler called when the request does not match any route.
+    ///
+    /// The handler is registered as the default handler of the root scope, and hence
+    /// it is used when no default handler is found in the scope where the request path
+    /// belongs and its ancestors. By default, the request is replied with `404 Not Found`.
+    /// Note that this option replaces the default handler registered in the root scope.
+    ///
+    /// This option is applied to the entire application, regardless of the scope
+    /// where it is specified.
+    pub fn not_found_handler<H>(&mut self, handler: H)
+    where
+        H: Handler,
+        M: ModifyHandler<H>,
+        M::Handler: Into<T::Handler>,
+    {
+        self.scopes[ScopeId::root()].data.default_handler =
+            Some(self.modifier.modify(handler).into());
+    }
+
     /// Creates a sub-scope with the provided prefix onto the current scope.
+    ///
+    /// The prefix may contain the parameter segments, e.g. `/tenants/:tenant`.
+    /// The captured values are visible from all routes in the sub-scope through `Params`,
+    /// as with the parameters declared in the path of each route.
     pub fn mount(&mut self, prefix: impl AsRef<str>, config: impl Config<M, T>) -> Result<()> {
         let prefix: Uri = prefix.as_ref().parse().map_err(Error::custom)?;
+        if prefix
+            .capture_names()
+            .map_or(false, |names| names.has_wildcard())
+        {
+            return Err(Error::custom(failure::format_err!(
+                "the prefix of a scope cannot contain the catch-all parameter"
+            )));
+        }
 
         let scope_id = self
             .scopes
@@ -334,6 +614,7 @@ where
                 ScopeData {
                     prefix: parent.prefix.join(&prefix).map_err(Error::custom)?,
                     default_handler: None,
+                    states: States::default(),
                 }
             })
             .map_err(Error::custom)?;
@@ -342,6 +623,7 @@ where
             .configure(&mut Scope {
                 recognizer: &mut *self.recognizer,
                 scopes: &mut *self.scopes,
+                options: &mut *self.options,
                 scope_id,
                 modifier: &*self.modifier,
                 _marker: PhantomData,
@@ -351,18 +633,100 @@ where
         Ok(())
     }
 
+    /// Merges the routes and scopes of an independently built application into
+    /// the current scope, as a sub-scope with the provided prefix.
+    ///
+    /// The sub-scopes, default handlers and shared states of `app` are preserved,
+    /// and the shared states in the current scope are inherited by them as with `mount`.
+    /// Note that the modifiers applied to the current scope and the application-wide
+    /// options of `app` are ignored, since the handlers of `app` have already been built.
+    ///
+    /// This method fails if one of the routes in `app` conflicts with the registered routes,
+    /// or `app` has already been shared (e.g. cloned or converted into a service).
+    pub fn merge(&mut self, prefix: impl AsRef<str>, app: AppBase<T>) -> Result<()> {
+        let prefix: Uri = prefix.as_ref().parse().map_err(Error::custom)?;
+        if prefix
+            .capture_names()
+            .map_or(false, |names| names.has_wildcard())
+        {
+            return Err(Error::custom(failure::format_err!(
+                "the prefix of a scope cannot contain the catch-all parameter"
+            )));
+        }
+        let prefix = self.scopes[self.scope_id]
+            .data
+            .prefix
+            .join(&prefix)
+            .map_err(Error::custom)?;
+        let num_prefix_params = prefix.capture_names().map_or(0, |names| names.num_params());
+
+        let AppInner {
+            recognizer, scopes, ..
+        } = Arc::try_unwrap(app.inner).map_err(|_| {
+            Error::custom(failure::format_err!(
+                "the merged application has already been shared"
+            ))
+        })?;
+
+        let scope_id = self
+            .scopes
+            .append(self.scope_id, scopes, |data| {
+                Ok(ScopeData {
+                    prefix: prefix.join(&data.prefix)?,
+                    ..data
+                })
+            })
+            .map_err(Error::custom)?;
+
+        for (_, endpoints) in recognizer.into_entries() {
+            for endpoint in endpoints {
+                let endpoint = Arc::try_unwrap(endpoint).map_err(|_| {
+                    Error::custom(failure::format_err!(
+                        "the merged application has already been shared"
+                    ))
+                })?;
+                let scope = &self.scopes[scope_id(endpoint.scope)];
+                let endpoint = Endpoint {
+                    scope: scope.id(),
+                    ancestors: scope
+                        .ancestors()
+                        .into_iter()
+                        .cloned()
+                        .chain(Some(scope.id()))
+                        .collect(),
+                    uri: prefix.join(&endpoint.uri).map_err(Error::custom)?,
+                    constraints: endpoint
+                        .constraints
+                        .into_iter()
+                        .map(|(pos, regex)| (pos + num_prefix_params, regex))
+                        .collect(),
+                    ..endpoint
+                };
+                self.insert_endpoint(endpoint)?;
+            }
+        }
+
+        Ok(())
+    }
+
     /// Applies the specified configuration with a `ModifyHandler` on the current scope.
+    ///
+    /// The modifiers applied on the outer scopes wrap the ones on the inner scopes.
+    /// That is, when a request is handled, the `Handle` created by the outermost modifier
+    /// is polled first and completes last, and the one created by the innermost modifier
+    /// is polled last and completes first.
     pub fn modify<M2>(
         &mut self,
         modifier: M2,
-        config: impl Config<Chain<&'a M, M2>, T>,
+        config: impl Config<Chain<M2, &'a M>, T>,
     ) -> Result<()> {
         config
             .configure(&mut Scope {
                 recognizer: &mut *self.recognizer,
                 scopes: &mut *self.scopes,
+                options: &mut *self.options,
                 scope_id: self.scope_id,
-                modifier: &Chain::new(self.modifier, modifier),
+                modifier: &Chain::new(modifier, self.modifier),
                 _marker: PhantomData,
             })
             .map_err(Into::into)
@@ -104,6 +104,22 @@ impl<T> Recognizer<T> {
     pub fn get(&self, index: usize) -> Option<&T> {
         Some(self.inner.get_index(index)?.1)
     }
+
+    /// Returns an iterator over the registered values, in the order of registration.
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = &'a T> + 'a {
+        self.inner.values()
+    }
+
+    /// Consumes itself and returns the pairs of registered paths and values,
+    /// in the order of registration.
+    pub fn into_entries(self) -> impl Iterator<Item = (String, T)> {
+        self.inner.into_iter()
+    }
+
+    /// Returns a mutable reference to the value registered with exactly the same path.
+    pub fn get_mut_by_path(&mut self, path: &str) -> Option<&mut T> {
+        self.inner.get_mut(path)
+    }
 }
 
 #[derive(Clone, PartialEq)]
@@ -3,12 +3,13 @@ use {
     std::ops::{Index, IndexMut},
 };
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub(super) struct ScopeId {
+/// The identifier of a scope in `App`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ScopeId {
     inner: ScopeIdInner,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 enum ScopeIdInner {
     Root,
     Index(usize),
@@ -60,6 +61,56 @@ impl<T> Scopes<T> {
     }
 }
 
+impl<T> Scopes<T> {
+    /// Moves all scopes in `other` into this tree, as the descendants of `parent`.
+    ///
+    /// The root of `other` becomes a new child of `parent`. The data of each scope
+    /// is converted by `f`, and the returned function maps the identifiers of scopes
+    /// in `other` to the new ones.
+    pub(super) fn append<U>(
+        &mut self,
+        parent: ScopeId,
+        other: Scopes<U>,
+        mut f: impl FnMut(U) -> Result<T, Error>,
+    ) -> Result<impl Fn(ScopeId) -> ScopeId, Error> {
+        let root = self.add_node(parent, f(other.root.data)?)?;
+        let mut nodes = Vec::with_capacity(other.nodes.len());
+        for node in other.nodes {
+            // the parent always precedes its children.
+            let parent = match node.ancestors.last().map(|id| id.inner) {
+                Some(ScopeIdInner::Index(i)) => nodes[i],
+                _ => root,
+            };
+            nodes.push(self.add_node(parent, f(node.data)?)?);
+        }
+        Ok(move |id: ScopeId| match id.inner {
+            ScopeIdInner::Root => root,
+            ScopeIdInner::Index(i) => nodes[i],
+        })
+    }
+
+    /// Returns an iterator over all scopes, in the order of registration.
+    pub(super) fn iter(&self) -> impl Iterator<Item = &Scope<T>> {
+        Some(&self.root).into_iter().chain(&self.nodes)
+    }
+
+    /// Applies the specified function to each pair of parent and child scopes.
+    ///
+    /// The function is called with the parents before their children, so that
+    /// the values propagated from the parent are transitively visible.
+    pub(super) fn propagate(&mut self, f: impl Fn(&T, &mut T)) {
+        for i in 0..self.nodes.len() {
+            let (init, rest) = self.nodes.split_at_mut(i);
+            let node = &mut rest[0];
+            let parent = match node.ancestors.last().map(|id| id.inner) {
+                Some(ScopeIdInner::Index(j)) => &init[j],
+                _ => &self.root,
+            };
+            f(&parent.data, &mut node.data);
+        }
+    }
+}
+
 impl<T> Index<ScopeId> for Scopes<T> {
     type Output = Scope<T>;
 
@@ -1,23 +1,31 @@
 use {
-    super::{config::Concurrency, recognizer::Captures, AppInner, Endpoint},
+    super::{config::Concurrency, recognizer::Captures, scope::ScopeId, AppInner, Endpoint},
     crate::{
+        error::Rejection,
         input::{
             body::RequestBody,
+            clock::{Clock, FixedClock, SystemClock},
             localmap::{LocalData, LocalMap},
             param::Params,
             Cookies, Input,
         },
-        output::ResponseBody,
+        output::{redirect, IntoResponse, ResponseBody},
         util::Never,
     },
     cookie::CookieJar,
+    either::Either,
     futures01::{Async, Future, Poll},
     http::{
         header::{self, HeaderMap, HeaderValue},
         Request, Response,
     },
     hyper::body::Payload,
-    std::{fmt, marker::PhantomData, sync::Arc},
+    std::{
+        fmt,
+        marker::PhantomData,
+        sync::{atomic::Ordering, Arc},
+        time::UNIX_EPOCH,
+    },
     tsukuyomi_service::Service,
 };
 
@@ -66,11 +74,26 @@ where
             locals,
             endpoint: None,
             captures: None,
+            scope: None,
             state: AppFutureState::Init,
         }
     }
 }
 
+/// Returns the clock used in the current request.
+///
+/// A `FixedClock` stored in the extensions of request (e.g. by the test server)
+/// takes precedence over the clock configured in the application.
+fn clock<'a>(request: &'a Request<()>, configured: &'a Option<Arc<dyn Clock>>) -> &'a dyn Clock {
+    if let Some(clock) = request.extensions().get::<FixedClock>() {
+        return clock;
+    }
+    match *configured {
+        Some(ref clock) => &**clock,
+        None => &SystemClock,
+    }
+}
+
 /// A future that manages an HTTP request, created by `AppService`.
 #[must_use = "futures do nothing unless polled"]
 #[derive(Debug)]
@@ -82,6 +105,7 @@ pub struct AppFuture<C: Concurrency> {
     locals: LocalMap,
     endpoint: Option<Arc<Endpoint<C>>>,
     captures: Option<Captures>,
+    scope: Option<ScopeId>,
     state: AppFutureState<C>,
 }
 
@@ -112,6 +136,16 @@ macro_rules! input {
                         names: endpoint.uri.capture_names(),
                         captures: $self.captures.as_ref(),
                     })
+                } else if let Some(names) = match $self.scope {
+                    Some(id) => $self.inner.scope(id).data.prefix.capture_names(),
+                    None => None,
+                } {
+                    // the default handlers can access the parameters in the scope prefix.
+                    Some(Params {
+                        path: $self.request.uri().path(),
+                        names: Some(names),
+                        captures: $self.captures.as_ref(),
+                    })
                 } else {
                     None
                 }
@@ -119,35 +153,137 @@ macro_rules! input {
             cookies: &mut Cookies::new(&mut $self.cookie_jar, &$self.request),
             locals: &mut $self.locals,
             response_headers: &mut $self.response_headers,
+            states: &$self
+                .inner
+                .scope($self.scope.unwrap_or_else(ScopeId::root))
+                .data
+                .states,
+            scope_prefix: match $self.scope {
+                Some(id) => Some($self.inner.scope(id).data.prefix.as_str()),
+                None => None,
+            },
+            route_pattern: $self
+                .endpoint
+                .as_ref()
+                .map(|endpoint| endpoint.uri.as_str()),
+            method_semantics: &$self.inner.options.method_semantics,
+            clock: clock(&$self.request, &$self.inner.options.clock),
             _marker: PhantomData,
         }
     };
 }
 
 impl<C: Concurrency> AppFuture<C> {
-    fn process_recognize(&mut self) -> Result<C::Handle, crate::Error> {
+    fn process_recognize(
+        &mut self,
+    ) -> Result<Either<C::Handle, Response<ResponseBody>>, crate::Error> {
         self.endpoint = None;
         self.captures = None;
+        self.scope = None;
 
-        match self
-            .inner
-            .find_endpoint(self.request.uri().path(), &mut self.captures)
-        {
-            Ok(endpoint) => {
-                self.endpoint = Some(endpoint.clone());
-                Ok(C::handle(&endpoint.handler))
+        if self.inner.draining.load(Ordering::SeqCst) {
+            return Ok(Either::Right(self.inner.draining_response(&self.request)));
+        }
+
+        // reject the expectations other than `100-continue`. The interim response for
+        // `100-continue` is sent by hyper when the handler starts to read the request body,
+        // so the requests rejected before reading the body never receive it.
+        if let Some(expect) = self.request.headers().get(header::EXPECT) {
+            if !expect.as_bytes().eq_ignore_ascii_case(b"100-continue") {
+                return Err(http::StatusCode::EXPECTATION_FAILED.into());
             }
-            Err(scope) => match self.inner.find_default_handler(scope.id()) {
-                Some(fallback) => Ok(C::handle(fallback)),
-                None => Err(http::StatusCode::NOT_FOUND.into()),
-            },
+        }
+
+        let inner = self.inner.clone();
+        let scope = match inner.find_endpoint(self.request.uri().path(), &mut self.captures) {
+            Ok(endpoints) => {
+                if self.request.method() == http::Method::OPTIONS {
+                    if let Some(response) = inner.auto_options_response(
+                        self.request.uri().path(),
+                        endpoints,
+                        self.captures.as_ref(),
+                    ) {
+                        return Ok(Either::Right(response));
+                    }
+                }
+
+                // try the endpoints with the same path in the order of registration.
+                // The endpoints accepting the request method are tried first, and the rest
+                // are tried after them so that the request is rejected by the handler
+                // (e.g. with `405 Method Not Allowed`) if none of the former is chosen.
+                for &allowed in &[true, false] {
+                    for endpoint in endpoints {
+                        if !endpoint.accepts(self.request.uri().path(), self.captures.as_ref())
+                            || endpoint
+                                .allows(self.request.method(), &inner.options.method_semantics)
+                                != allowed
+                        {
+                            continue;
+                        }
+                        self.endpoint = Some(endpoint.clone());
+                        self.scope = Some(endpoint.scope);
+                        if endpoint
+                            .guards
+                            .iter()
+                            .all(|guard| guard.check(input!(self)))
+                        {
+                            return Ok(Either::Left(C::handle(&endpoint.handler)));
+                        }
+                    }
+                }
+                self.endpoint = None;
+                self.scope = None;
+                inner.infer_scope(
+                    self.request.uri().path(),
+                    endpoints.iter().map(|endpoint| &**endpoint),
+                )
+            }
+            Err(scope) => scope,
+        };
+
+        if let Some(location) = self.inner.find_trailing_slash_redirect(self.request.uri()) {
+            let response = redirect::permanent_redirect(location)
+                .into_response(&self.request)
+                .unwrap_or_else(|never| match never {});
+            return Ok(Either::Right(response.map(Into::into)));
+        }
+
+        let scope = inner.find_nearest_scope(self.request.uri().path(), scope);
+        match self.inner.find_default_handler(scope.id()) {
+            Some((id, fallback)) => {
+                self.scope = Some(id);
+                Ok(Either::Left(C::handle(fallback)))
+            }
+            None => Err(http::StatusCode::NOT_FOUND.into()),
         }
     }
 
+    #[allow(clippy::cast_possible_wrap)]
     fn process_before_reply(&mut self, output: &mut Response<ResponseBody>) {
         // append Cookie entries.
+        // If the clock is replaced (e.g. `FixedClock` in tests), the expiration date of
+        // the cookies with `Max-Age` is computed from the clock, unless it is set explicitly.
+        // The expiration date of removal cookies, which is computed from the system time
+        // by `CookieJar::remove`, is also recomputed. Otherwise, the cookies are not modified.
         if let Some(ref jar) = self.cookie_jar {
+            let is_custom_clock = self.request.extensions().get::<FixedClock>().is_some()
+                || self.inner.options.clock.is_some();
+            let now = clock(&self.request, &self.inner.options.clock)
+                .now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| time::Timespec::new(d.as_secs() as i64, d.subsec_nanos() as i32))
+                .unwrap_or_else(|_| time::Timespec::new(0, 0));
             for cookie in jar.delta() {
+                let mut cookie = cookie.clone();
+                match cookie.max_age() {
+                    Some(max_age) if is_custom_clock && max_age <= time::Duration::zero() => {
+                        cookie.set_expires(time::at_utc(now - time::Duration::days(365)));
+                    }
+                    Some(max_age) if is_custom_clock && cookie.expires().is_none() => {
+                        cookie.set_expires(time::at_utc(now + max_age));
+                    }
+                    _ => {}
+                }
                 output.headers_mut().append(
                     header::SET_COOKIE,
                     cookie.encoded().to_string().parse().unwrap(),
@@ -158,12 +294,28 @@ impl<C: Concurrency> AppFuture<C> {
         // append supplemental response headers.
         if let Some(mut hdrs) = self.response_headers.take() {
             for (k, v) in hdrs.drain() {
-                output.headers_mut().extend(v.map(|v| (k.clone(), v)));
+                if k == header::VARY {
+                    // merge into the `Vary` set by the handler, rather than splitting it.
+                    for v in v {
+                        crate::util::merge_header_value(output.headers_mut(), k.clone(), v);
+                    }
+                } else {
+                    output.headers_mut().extend(v.map(|v| (k.clone(), v)));
+                }
             }
         }
 
         // append the value of Content-Length to the response header if missing.
-        if let Some(len) = output.body().content_length() {
+        // The responses with 1xx, 204 or 304 must not have the header field.
+        let status = output.status();
+        let forbids_content_length = status.is_informational()
+            || status == http::StatusCode::NO_CONTENT
+            || status == http::StatusCode::NOT_MODIFIED;
+        if let Some(len) = output
+            .body()
+            .content_length()
+            .filter(|_| !forbids_content_length)
+        {
             output
                 .headers_mut()
                 .entry(header::CONTENT_LENGTH)
@@ -174,6 +326,17 @@ impl<C: Concurrency> AppFuture<C> {
                     unsafe { HeaderValue::from_shared_unchecked(len.to_string().into()) }
                 });
         }
+
+        // drop the response body to HEAD, after the header fields are computed
+        // (e.g. when the request falls back to the endpoint accepting GET).
+        if self
+            .inner
+            .options
+            .method_semantics
+            .omits_response_body(self.request.method())
+        {
+            *output.body_mut() = ResponseBody::empty();
+        }
     }
 }
 
@@ -185,7 +348,8 @@ impl<C: Concurrency> Future for AppFuture<C> {
         let polled = loop {
             self.state = match self.state {
                 AppFutureState::Init => match self.process_recognize() {
-                    Ok(in_flight) => AppFutureState::InFlight(in_flight),
+                    Ok(Either::Left(in_flight)) => AppFutureState::InFlight(in_flight),
+                    Ok(Either::Right(response)) => break Ok(response),
                     Err(err) => break Err(err),
                 },
                 AppFutureState::InFlight(ref mut in_flight) => {
@@ -198,7 +362,20 @@ impl<C: Concurrency> Future for AppFuture<C> {
 
         let mut output = match polled {
             Ok(output) => output,
-            Err(err) => err.into_response(&self.request),
+            Err(err) => {
+                let inner = self.inner.clone();
+                match inner.options.error_renderer {
+                    Some(ref error_renderer) if !err.is::<Rejection>() => {
+                        let headers = err.headers();
+                        let mut response = error_renderer(err, input!(self));
+                        if let Some(headers) = headers {
+                            crate::error::merge_headers(&mut response, &headers);
+                        }
+                        response
+                    }
+                    _ => err.into_response(&self.request),
+                }
+            }
         };
 
         self.process_before_reply(&mut output);
@@ -20,14 +20,14 @@ fn route_single_method() -> Result<()> {
 
     assert_matches!(
         app.inner.find_endpoint("/", &mut None),
-        Ok(endpoint) if endpoint.uri == "/"
+        Ok(endpoints) if endpoints[0].uri == "/"
     );
 
     assert_matches!(app.inner.find_endpoint("/path/to", &mut None), Err(..));
 
     assert_matches!(
         app.inner.find_endpoint("/", &mut None),
-        Ok(endpoint) if endpoint.uri == "/"
+        Ok(endpoints) if endpoints[0].uri == "/"
     );
 
     Ok(())
@@ -46,19 +46,19 @@ fn scope_simple() -> Result<()> {
 
     assert_matches!(
         app.inner.find_endpoint("/a", &mut None),
-        Ok(endpoint) if endpoint.uri == "/a"
+        Ok(endpoints) if endpoints[0].uri == "/a"
     );
     assert_matches!(
         app.inner.find_endpoint("/b", &mut None),
-        Ok(endpoint) if endpoint.uri == "/b"
+        Ok(endpoints) if endpoints[0].uri == "/b"
     );
     assert_matches!(
         app.inner.find_endpoint("/foo", &mut None),
-        Ok(endpoint) if endpoint.uri == "/foo"
+        Ok(endpoints) if endpoints[0].uri == "/foo"
     );
     assert_matches!(
         app.inner.find_endpoint("/c/d", &mut None),
-        Ok(endpoint) if endpoint.uri == "/c/d"
+        Ok(endpoints) if endpoints[0].uri == "/c/d"
     );
 
     Ok(())
@@ -86,27 +86,27 @@ fn scope_nested() -> Result<()> {
 
     assert_matches!(
         app.inner.find_endpoint("/foo", &mut None),
-        Ok(endpoint) if endpoint.uri == "/foo"
+        Ok(endpoints) if endpoints[0].uri == "/foo"
     );
     assert_matches!(
         app.inner.find_endpoint("/bar", &mut None),
-        Ok(endpoint) if endpoint.uri == "/bar"
+        Ok(endpoints) if endpoints[0].uri == "/bar"
     );
     assert_matches!(
         app.inner.find_endpoint("/foo", &mut None),
-        Ok(endpoint) if endpoint.uri == "/foo"
+        Ok(endpoints) if endpoints[0].uri == "/foo"
     );
     assert_matches!(
         app.inner.find_endpoint("/baz", &mut None),
-        Ok(endpoint) if endpoint.uri == "/baz"
+        Ok(endpoints) if endpoints[0].uri == "/baz"
     );
     assert_matches!(
         app.inner.find_endpoint("/baz/foobar", &mut None),
-        Ok(endpoint) if endpoint.uri == "/baz/foobar"
+        Ok(endpoints) if endpoints[0].uri == "/baz/foobar"
     );
     assert_matches!(
         app.inner.find_endpoint("/hoge", &mut None),
-        Ok(endpoint) if endpoint.uri == "/hoge"
+        Ok(endpoints) if endpoints[0].uri == "/hoge"
     );
 
     assert_matches!(app.inner.find_endpoint("/baz/", &mut None), Err(..));
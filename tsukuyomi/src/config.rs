@@ -13,8 +13,8 @@ pub mod prelude {
     pub mod endpoint {
         #[doc(no_inline)]
         pub use super::super::endpoint::{
-            allow_only, any, call, call_async, connect, delete, get, head, options, patch, post,
-            put, reply, trace,
+            allow_only, any, call, call_async, connect, delete, get, head, methods, options, patch,
+            post, put, reply, trace,
         };
     }
 }
@@ -24,10 +24,16 @@ pub use crate::app::config::{Config, Error, Result, Scope};
 
 use {
     crate::{
-        app::config::Concurrency,
+        app::{
+            config::{Concurrency, Conditions},
+            AppBase,
+        },
         handler::{Handler, ModifyHandler},
-        util::Chain,
+        input::{clock::Clock, method::MethodSemantics, Input},
+        output::ResponseBody,
+        util::{Chain, Never},
     },
+    http::{Request, Response},
     std::borrow::Cow,
 };
 
@@ -71,7 +77,296 @@ where
     }
 }
 
+/// Creates a `Config` that merges an independently built application into the current scope.
+///
+/// See also [`Scope::merge`](./struct.Scope.html#method.merge).
+pub fn merge<P, C>(prefix: P, app: AppBase<C>) -> Merge<P, C>
+where
+    P: AsRef<str>,
+    C: Concurrency,
+{
+    Merge { prefix, app }
+}
+
+/// A `Config` that merges an independently built application into the current scope.
+#[derive(Debug)]
+pub struct Merge<P, C: Concurrency> {
+    prefix: P,
+    app: AppBase<C>,
+}
+
+impl<P, M, C> Config<M, C> for Merge<P, C>
+where
+    P: AsRef<str>,
+    C: Concurrency,
+{
+    type Error = Error;
+
+    fn configure(self, scope: &mut Scope<'_, M, C>) -> std::result::Result<(), Self::Error> {
+        scope.merge(self.prefix, self.app)
+    }
+}
+
+/// Creates a `Config` that sets the default handler of the current scope.
+///
+/// See also [`Scope::default_handler`](./struct.Scope.html#method.default_handler).
+pub fn default_handler<H>(handler: H) -> DefaultHandler<H>
+where
+    H: Handler,
+{
+    DefaultHandler { handler }
+}
+
+/// A `Config` that sets the default handler of the current scope.
+#[derive(Debug)]
+pub struct DefaultHandler<H> {
+    handler: H,
+}
+
+impl<H, M, C> Config<M, C> for DefaultHandler<H>
+where
+    H: Handler,
+    M: ModifyHandler<H>,
+    M::Handler: Into<C::Handler>,
+    C: Concurrency,
+{
+    type Error = Error;
+
+    fn configure(self, scope: &mut Scope<'_, M, C>) -> std::result::Result<(), Self::Error> {
+        scope.default_handler(self.handler)
+    }
+}
+
+/// Creates a `Config` that sets the handler called when the request does not match any route.
+///
+/// See also [`Scope::not_found_handler`](./struct.Scope.html#method.not_found_handler).
+pub fn not_found_handler<H>(handler: H) -> NotFoundHandler<H>
+where
+    H: Handler,
+{
+    NotFoundHandler { handler }
+}
+
+/// A `Config` that sets the handler called when the request does not match any route.
+#[derive(Debug)]
+pub struct NotFoundHandler<H> {
+    handler: H,
+}
+
+impl<H, M, C> Config<M, C> for NotFoundHandler<H>
+where
+    H: Handler,
+    M: ModifyHandler<H>,
+    M::Handler: Into<C::Handler>,
+    C: Concurrency,
+{
+    type Error = Never;
+
+    fn configure(self, scope: &mut Scope<'_, M, C>) -> std::result::Result<(), Self::Error> {
+        scope.not_found_handler(self.handler);
+        Ok(())
+    }
+}
+
+/// Creates a `Config` that registers a shared state onto the current scope.
+///
+/// See also [`Scope::state`](./struct.Scope.html#method.state).
+pub fn state<S>(state: S) -> State<S>
+where
+    S: Send + Sync + 'static,
+{
+    State { state }
+}
+
+/// A `Config` that registers a shared state onto the current scope.
+#[derive(Debug)]
+pub struct State<S> {
+    state: S,
+}
+
+impl<S, M, C> Config<M, C> for State<S>
+where
+    S: Send + Sync + 'static,
+    C: Concurrency,
+{
+    type Error = Never;
+
+    fn configure(self, scope: &mut Scope<'_, M, C>) -> std::result::Result<(), Self::Error> {
+        scope.state(self.state);
+        Ok(())
+    }
+}
+
+/// Creates a `Config` that sets whether to redirect the requests which differ
+/// from a registered route only in the trailing slash.
+///
+/// See also [`Scope::redirect_trailing_slash`](./struct.Scope.html#method.redirect_trailing_slash).
+pub fn redirect_trailing_slash(enabled: bool) -> RedirectTrailingSlash {
+    RedirectTrailingSlash { enabled }
+}
+
+/// A `Config` that sets whether to redirect the requests which differ
+/// from a registered route only in the trailing slash.
+#[derive(Debug)]
+pub struct RedirectTrailingSlash {
+    enabled: bool,
+}
+
+impl<M, C> Config<M, C> for RedirectTrailingSlash
+where
+    C: Concurrency,
+{
+    type Error = Never;
+
+    fn configure(self, scope: &mut Scope<'_, M, C>) -> std::result::Result<(), Self::Error> {
+        scope.redirect_trailing_slash(self.enabled);
+        Ok(())
+    }
+}
+
+/// Creates a `Config` that sets whether to reply to `OPTIONS` automatically.
+///
+/// See also [`Scope::auto_options`](./struct.Scope.html#method.auto_options).
+pub fn auto_options(enabled: bool) -> AutoOptions {
+    AutoOptions { enabled }
+}
+
+/// A `Config` that sets whether to reply to `OPTIONS` automatically.
+#[derive(Debug)]
+pub struct AutoOptions {
+    enabled: bool,
+}
+
+impl<M, C> Config<M, C> for AutoOptions
+where
+    C: Concurrency,
+{
+    type Error = Never;
+
+    fn configure(self, scope: &mut Scope<'_, M, C>) -> std::result::Result<(), Self::Error> {
+        scope.auto_options(self.enabled);
+        Ok(())
+    }
+}
+
+/// Creates a `Config` that sets the semantics of request methods used in the application.
+///
+/// See also [`Scope::method_semantics`](./struct.Scope.html#method.method_semantics).
+pub fn method_semantics(semantics: MethodSemantics) -> SetMethodSemantics {
+    SetMethodSemantics { semantics }
+}
+
+/// A `Config` that sets the semantics of request methods used in the application.
+#[derive(Debug)]
+pub struct SetMethodSemantics {
+    semantics: MethodSemantics,
+}
+
+impl<M, C> Config<M, C> for SetMethodSemantics
+where
+    C: Concurrency,
+{
+    type Error = Never;
+
+    fn configure(self, scope: &mut Scope<'_, M, C>) -> std::result::Result<(), Self::Error> {
+        scope.method_semantics(self.semantics);
+        Ok(())
+    }
+}
+
+/// Creates a `Config` that sets the clock used in the application.
+///
+/// See also [`Scope::clock`](./struct.Scope.html#method.clock).
+pub fn clock<T>(clock: T) -> SetClock<T>
+where
+    T: Clock,
+{
+    SetClock { clock }
+}
+
+/// A `Config` that sets the clock used in the application.
+#[derive(Debug)]
+pub struct SetClock<T> {
+    clock: T,
+}
+
+impl<T, M, C> Config<M, C> for SetClock<T>
+where
+    T: Clock,
+    C: Concurrency,
+{
+    type Error = Never;
+
+    fn configure(self, scope: &mut Scope<'_, M, C>) -> std::result::Result<(), Self::Error> {
+        scope.clock(self.clock);
+        Ok(())
+    }
+}
+
+/// Creates a `Config` that sets the function creating the response returned
+/// while the application is draining.
+///
+/// See also [`Scope::draining_response`](./struct.Scope.html#method.draining_response).
+pub fn draining_response<F>(f: F) -> DrainingResponse<F>
+where
+    F: Fn(&Request<()>) -> Response<ResponseBody> + Send + Sync + 'static,
+{
+    DrainingResponse { f }
+}
+
+/// A `Config` that sets the function creating the response returned
+/// while the application is draining.
+#[derive(Debug)]
+pub struct DrainingResponse<F> {
+    f: F,
+}
+
+impl<F, M, C> Config<M, C> for DrainingResponse<F>
+where
+    F: Fn(&Request<()>) -> Response<ResponseBody> + Send + Sync + 'static,
+    C: Concurrency,
+{
+    type Error = Never;
+
+    fn configure(self, scope: &mut Scope<'_, M, C>) -> std::result::Result<(), Self::Error> {
+        scope.draining_response(self.f);
+        Ok(())
+    }
+}
+
+/// Creates a `Config` that sets the function rendering the errors occurred in the application.
+///
+/// See also [`Scope::error_renderer`](./struct.Scope.html#method.error_renderer).
+pub fn error_renderer<F>(f: F) -> ErrorRenderer<F>
+where
+    F: Fn(crate::error::Error, &mut Input<'_>) -> Response<ResponseBody> + Send + Sync + 'static,
+{
+    ErrorRenderer { f }
+}
+
+/// A `Config` that sets the function rendering the errors occurred in the application.
+#[derive(Debug)]
+pub struct ErrorRenderer<F> {
+    f: F,
+}
+
+impl<F, M, C> Config<M, C> for ErrorRenderer<F>
+where
+    F: Fn(crate::error::Error, &mut Input<'_>) -> Response<ResponseBody> + Send + Sync + 'static,
+    C: Concurrency,
+{
+    type Error = Never;
+
+    fn configure(self, scope: &mut Scope<'_, M, C>) -> std::result::Result<(), Self::Error> {
+        scope.error_renderer(self.f);
+        Ok(())
+    }
+}
+
 /// Crates a `Config` that wraps a config with a `ModifyHandler`.
+///
+/// See also [`Scope::modify`](./struct.Scope.html#method.modify) for the order
+/// in which the nested modifiers are applied.
 pub fn modify<M, T>(modifier: M, config: T) -> Modify<M, T> {
     Modify { modifier, config }
 }
@@ -85,7 +380,7 @@ pub struct Modify<M, T> {
 
 impl<M, T, M2, C> Config<M2, C> for Modify<M, T>
 where
-    for<'a> T: Config<Chain<&'a M2, M>, C>,
+    for<'a> T: Config<Chain<M, &'a M2>, C>,
     C: Concurrency,
 {
     type Error = Error;
@@ -108,7 +403,10 @@ impl<T> ConfigExt for T {}
 #[derive(Debug)]
 pub struct Route<H> {
     path: Cow<'static, str>,
+    conditions: Conditions,
     handler: H,
+    /// The route registered on the path without the optional trailing parameter.
+    trimmed: Option<Box<Route<H>>>,
 }
 
 impl<H> Route<H>
@@ -119,7 +417,9 @@ where
     pub fn new(path: impl Into<Cow<'static, str>>, handler: H) -> Self {
         Self {
             path: path.into(),
+            conditions: Conditions::default(),
             handler,
+            trimmed: None,
         }
     }
 }
@@ -134,6 +434,9 @@ where
     type Error = Error;
 
     fn configure(self, scope: &mut Scope<'_, M, C>) -> std::result::Result<(), Self::Error> {
-        scope.route(self.path, self.handler)
+        if let Some(trimmed) = self.trimmed {
+            (*trimmed).configure(scope)?;
+        }
+        scope.add_route(self.path, self.conditions, self.handler)
     }
 }
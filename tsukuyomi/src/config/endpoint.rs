@@ -19,6 +19,13 @@ pub fn allow_only(methods: impl TryInto<AllowedMethods>) -> super::Result<Builde
     Builder::allow_only(methods)
 }
 
+/// Creates a `Builder` that accepts only the specified HTTP methods.
+///
+/// Unlike `allow_only`, this function never fails since the methods are already parsed.
+pub fn methods(methods: impl IntoIterator<Item = Method>) -> Builder {
+    Builder::methods(methods)
+}
+
 macro_rules! define_builder_with_allowing_sigle_method {
     ($(
         $(#[$m:meta])*
@@ -50,6 +57,21 @@ pub fn get_or_head() -> Builder {
     Builder::allow_only(vec![Method::GET, Method::HEAD]).expect("should be valid methods")
 }
 
+/// Returns whether the method of the request is accepted by the endpoint.
+///
+/// If the method is not accepted explicitly, the request falls back to the endpoint
+/// accepting the method specified by `MethodSemantics::fallback` (e.g. `HEAD` to `GET`).
+fn is_allowed(allowed_methods: Option<&AllowedMethods>, cx: &ApplyContext<'_, '_>) -> bool {
+    allowed_methods.map_or(true, |methods| {
+        let method = cx.method();
+        methods.contains(method)
+            || cx
+                .method_semantics()
+                .fallback(method)
+                .map_or(false, |fallback| methods.contains(&fallback))
+    })
+}
+
 /// A builder of `Endpoint`.
 #[derive(Debug)]
 pub struct Builder<E: Extractor = ()> {
@@ -73,6 +95,14 @@ impl Builder {
             allowed_methods: methods.try_into().map(Some).map_err(super::Error::custom)?,
         })
     }
+
+    /// Creates a `Builder` that accepts only the specified HTTP methods.
+    pub fn methods(methods: impl IntoIterator<Item = Method>) -> Self {
+        Self {
+            extractor: (),
+            allowed_methods: Some(methods.into_iter().collect()),
+        }
+    }
 }
 
 impl<E> Builder<E>
@@ -109,10 +139,7 @@ where
             let allowed_methods = self.allowed_methods.clone();
             let extractor = self.extractor;
             move |args: T, cx: &mut ApplyContext<'_, '_>| {
-                if allowed_methods
-                    .as_ref()
-                    .map_or(false, |methods| !methods.contains(cx.method()))
-                {
+                if !is_allowed(allowed_methods.as_ref(), cx) {
                     return Err((args, ApplyError::method_not_allowed()));
                 }
                 Ok(self::call::CallFuture {
@@ -145,10 +172,7 @@ where
             let allowed_methods = self.allowed_methods.clone();
             let extractor = self.extractor;
             move |args: T, cx: &mut ApplyContext<'_, '_>| {
-                if allowed_methods
-                    .as_ref()
-                    .map_or(false, |methods| !methods.contains(cx.method()))
-                {
+                if !is_allowed(allowed_methods.as_ref(), cx) {
                     return Err((args, ApplyError::method_not_allowed()));
                 }
 
@@ -1,11 +1,12 @@
 use {
     super::Route,
     crate::{
+        app::config::{Conditions, Guard},
         endpoint::Endpoint, //
         error::Error,
         generic::Tuple,
         handler::Handler,
-        input::param::Params,
+        input::{param::Params, Input},
     },
     std::{marker::PhantomData, sync::Arc},
 };
@@ -30,6 +31,11 @@ impl PathExtractor for () {
 
 /// A macro for generating the code that creates a [`Path`] from the provided tokens.
 ///
+/// The last segment of the path can be marked as optional by appending `?` to
+/// the parameter name (e.g. `path!("/items/:page?")`). The value of such a parameter
+/// is extracted as `Option<T>`, and the route matches both the paths with and without
+/// the trailing segment (`/items/2` and `/items` in this case).
+///
 /// [`Path`]: ./app/config/route/struct.Path.html
 #[macro_export]
 macro_rules! path {
@@ -52,11 +58,27 @@ pub mod internal {
             input::param::{FromPercentEncoded, Params, PercentEncoded},
         },
     };
+
+    /// Creates an error representing that the captured value of a parameter
+    /// could not be converted into the specified type.
+    ///
+    /// The error is rendered as `400 Bad Request`, with the message that contains
+    /// the name of parameter and its (percent-encoded) raw value.
+    pub fn invalid_param(name: &str, value: &PercentEncoded, cause: impl Into<Error>) -> Error {
+        crate::error::bad_request(format!(
+            "invalid value '{}' for parameter '{}': {}",
+            value.as_str(),
+            name,
+            cause.into()
+        ))
+    }
 }
 
 #[derive(Debug)]
 pub struct Path<E: PathExtractor = ()> {
     path: &'static str,
+    conditions: Conditions,
+    optional_tail: Option<&'static str>,
     _marker: PhantomData<E>,
 }
 
@@ -68,10 +90,54 @@ where
     pub fn new(path: &'static str) -> Self {
         Self {
             path,
+            conditions: Conditions::default(),
+            optional_tail: None,
             _marker: PhantomData,
         }
     }
 
+    /// Marks the parameter at the end of path as optional.
+    ///
+    /// This method is used by the macro `path!` and is not intended to be called directly.
+    #[doc(hidden)]
+    pub fn optional_tail(self, name: &'static str) -> Self {
+        Self {
+            optional_tail: Some(name),
+            ..self
+        }
+    }
+
+    /// Restricts the value of the specified parameter to the ones matching the regular expression.
+    ///
+    /// The pattern is matched against the whole of the (percent-encoded) segment.
+    /// If the captured value does not match, the route is treated as if it was not matched
+    /// and the request is forwarded to the default handler of the corresponding scope,
+    /// rather than being rejected with `400 Bad Request` by the parameter extraction.
+    ///
+    /// The registration of the route fails if the parameter name is not declared
+    /// in the path or the pattern is not a valid regular expression.
+    pub fn param_regex(mut self, name: impl Into<String>, pattern: impl Into<String>) -> Self {
+        self.conditions
+            .constraints
+            .push((name.into(), pattern.into()));
+        self
+    }
+
+    /// Appends a predicate that will be checked after the path of this route is matched.
+    ///
+    /// Multiple routes can be registered on the same path as long as the routes
+    /// registered earlier have guards. The guards are checked in the order of registration,
+    /// and the first route whose guards all return `true` is selected. If no route is
+    /// selected, the request is forwarded to the default handler of the corresponding scope
+    /// (or rejected with `404 Not Found`).
+    pub fn guard<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&Input<'_>) -> bool + Send + Sync + 'static,
+    {
+        self.conditions.guards.push(Guard::new(f));
+        self
+    }
+
     /// Creates a `Route` with this path configuration and the specified `Endpoint`.
     pub fn to<T>(
         self,
@@ -86,16 +152,42 @@ where
     where
         T: Endpoint<E::Output>,
     {
-        let Self { path, .. } = self;
+        let Self {
+            path,
+            conditions,
+            optional_tail,
+            ..
+        } = self;
         let endpoint = Arc::new(endpoint);
         let allowed_methods = endpoint.allowed_methods();
+        let make_handler = || {
+            let endpoint = endpoint.clone();
+            crate::handler::handler(
+                move || self::handle::RouteHandle::new(endpoint.clone()),
+                allowed_methods.clone(),
+            )
+        };
+
+        // The route with an optional trailing parameter is registered twice,
+        // on the paths with and without the last segment.
+        let trimmed = optional_tail.map(|name| {
+            let trimmed_path = match path.rfind('/') {
+                Some(0) | None => "/",
+                Some(pos) => &path[..pos],
+            };
+            Box::new(Route {
+                path: trimmed_path.into(),
+                conditions: conditions.without_param(name),
+                handler: make_handler(),
+                trimmed: None,
+            })
+        });
 
         Route {
             path: path.into(),
-            handler: crate::handler::handler(
-                move || self::handle::RouteHandle::new(endpoint.clone()),
-                allowed_methods,
-            ),
+            conditions,
+            handler: make_handler(),
+            trimmed,
         }
     }
 }
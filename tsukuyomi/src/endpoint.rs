@@ -1,7 +1,12 @@
 //! Definition of `Endpoint`.
 
 use {
-    crate::{error::Error, future::TryFuture, handler::AllowedMethods, input::Input},
+    crate::{
+        error::Error,
+        future::TryFuture,
+        handler::AllowedMethods,
+        input::{method::MethodSemantics, Input},
+    },
     http::{Method, StatusCode},
 };
 
@@ -40,6 +45,12 @@ impl<'a, 'task> ApplyContext<'a, 'task> {
     pub fn method(&self) -> &Method {
         self.input.request.method()
     }
+
+    /// Returns the semantics of request methods configured in the application.
+    #[inline]
+    pub fn method_semantics(&self) -> &MethodSemantics {
+        self.input.method_semantics
+    }
 }
 
 #[derive(Debug)]
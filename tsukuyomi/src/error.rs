@@ -9,7 +9,10 @@
 
 use {
     crate::{output::ResponseBody, util::Never},
-    http::{Request, Response, StatusCode},
+    http::{
+        header::{HeaderMap, HeaderValue, WWW_AUTHENTICATE},
+        Request, Response, StatusCode,
+    },
     std::{any::Any, fmt, io},
 };
 
@@ -28,6 +31,24 @@ pub trait HttpError: fmt::Display + fmt::Debug + Send + 'static + Sized {
 
     /// Consumes itself and creates an HTTP response from its value.
     fn into_response(self, request: &Request<()>) -> Response<Self::Body>;
+
+    /// Returns the header fields to be added to the response created by this error.
+    ///
+    /// The header fields are merged into the response after calling `into_response`,
+    /// except for the ones already set in the response. This is useful for the errors
+    /// that require specific header fields, e.g. `WWW-Authenticate` in `401 Unauthorized`.
+    /// The default implementation returns `None`.
+    fn headers(&self) -> Option<HeaderMap> {
+        None
+    }
+
+    /// Appends the additional members of the problem details object defined in RFC 7807.
+    ///
+    /// This method is used by [`modifiers::problem_json`] when rendering the error.
+    /// The default implementation does nothing.
+    ///
+    /// [`modifiers::problem_json`]: ../modifiers/fn.problem_json.html
+    fn problem_details(&self, _: &mut serde_json::Map<String, serde_json::Value>) {}
 }
 
 impl HttpError for StatusCode {
@@ -155,6 +176,23 @@ where
     error_response(response)
 }
 
+/// Creates an `Error` from the specified status code, without the response body.
+#[inline]
+pub fn from_status(status: StatusCode) -> Error {
+    status.into()
+}
+
+/// Creates an `Error` from the specified status code and message.
+///
+/// The message is used as the response body. It is equivalent to `custom(status, msg)`.
+#[inline]
+pub fn from_status_with<D>(status: StatusCode, msg: D) -> Error
+where
+    D: fmt::Debug + fmt::Display + Send + 'static,
+{
+    self::custom(status, msg)
+}
+
 macro_rules! define_errors {
     ($(
         $(#[$m:meta])*
@@ -191,6 +229,92 @@ define_errors! {
     internal_server_error => INTERNAL_SERVER_ERROR,
 }
 
+/// Creates an `Error` representing `401 Unauthorized` with the challenge of Basic authentication.
+///
+/// The response has the header field `WWW-Authenticate: Basic realm="<realm>"`.
+pub fn unauthorized_basic(realm: impl Into<String>) -> Error {
+    BasicChallenge {
+        realm: realm.into(),
+    }
+    .into()
+}
+
+#[derive(Debug)]
+struct BasicChallenge {
+    realm: String,
+}
+
+impl fmt::Display for BasicChallenge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Basic authentication is required (realm = {:?})",
+            self.realm
+        )
+    }
+}
+
+impl HttpError for BasicChallenge {
+    type Body = ();
+
+    fn into_response(self, _: &Request<()>) -> Response<Self::Body> {
+        let mut response = Response::new(());
+        *response.status_mut() = StatusCode::UNAUTHORIZED;
+        response
+    }
+
+    fn headers(&self) -> Option<HeaderMap> {
+        let realm = self.realm.replace('\\', "\\\\").replace('"', "\\\"");
+        let challenge = HeaderValue::from_str(&format!("Basic realm=\"{}\"", realm)).ok()?;
+        let mut headers = HeaderMap::new();
+        headers.insert(WWW_AUTHENTICATE, challenge);
+        Some(headers)
+    }
+}
+
+/// An error value that holds a complete response, created by rejecting the request.
+///
+/// Unlike the other errors, the response is returned to the client as it is.
+/// The rendering of errors configured in the application (such as `error_renderer`
+/// and `modifiers::problem_json`) is not applied to this value. It is mainly
+/// used by `ExtractorExt::recover` to short-circuit a route with a custom response
+/// (e.g. a redirect to the login page).
+#[derive(Debug)]
+pub struct Rejection {
+    response: Response<ResponseBody>,
+}
+
+impl Rejection {
+    /// Creates a `Rejection` from the specified response.
+    pub fn new<T>(response: Response<T>) -> Self
+    where
+        T: Into<ResponseBody>,
+    {
+        Self {
+            response: response.map(Into::into),
+        }
+    }
+
+    /// Consumes itself and returns the inner response.
+    pub fn into_inner(self) -> Response<ResponseBody> {
+        self.response
+    }
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "rejected with the status {}", self.response.status())
+    }
+}
+
+impl HttpError for Rejection {
+    type Body = ResponseBody;
+
+    fn into_response(self, _: &Request<()>) -> Response<Self::Body> {
+        self.response
+    }
+}
+
 // ==== Error ====
 
 type AnyObj = dyn Any + Send + 'static;
@@ -201,6 +325,8 @@ pub struct Error {
     fmt_debug_fn: fn(&AnyObj, &mut fmt::Formatter<'_>) -> fmt::Result,
     fmt_display_fn: fn(&AnyObj, &mut fmt::Formatter<'_>) -> fmt::Result,
     into_response_fn: fn(Box<AnyObj>, &Request<()>) -> Response<ResponseBody>,
+    headers_fn: fn(&AnyObj) -> Option<HeaderMap>,
+    problem_details_fn: fn(&AnyObj, &mut serde_json::Map<String, serde_json::Value>),
 }
 
 impl fmt::Debug for Error {
@@ -241,7 +367,25 @@ impl Error {
             request: &Request<()>,
         ) -> Response<ResponseBody> {
             let this = *this.downcast::<E>().expect("the wrong type id");
-            HttpError::into_response(this, request).map(Into::into)
+            let headers = HttpError::headers(&this);
+            let mut response = HttpError::into_response(this, request).map(Into::into);
+            if let Some(headers) = headers {
+                merge_headers(&mut response, &headers);
+            }
+            response
+        }
+
+        fn headers<E: HttpError>(this: &AnyObj) -> Option<HeaderMap> {
+            let this = this.downcast_ref::<E>().expect("the wrong type id");
+            HttpError::headers(this)
+        }
+
+        fn problem_details<E: HttpError>(
+            this: &AnyObj,
+            members: &mut serde_json::Map<String, serde_json::Value>,
+        ) {
+            let this = this.downcast_ref::<E>().expect("the wrong type id");
+            HttpError::problem_details(this, members)
         }
 
         Error {
@@ -249,6 +393,8 @@ impl Error {
             fmt_debug_fn: fmt_debug::<E>,
             fmt_display_fn: fmt_display::<E>,
             into_response_fn: into_response::<E>,
+            headers_fn: headers::<E>,
+            problem_details_fn: problem_details::<E>,
         }
     }
 
@@ -280,8 +426,32 @@ impl Error {
         }
     }
 
+    /// Returns the header fields to be added to the response, provided by the inner error value.
+    ///
+    /// The header fields are merged into the response created by `into_response` automatically.
+    pub fn headers(&self) -> Option<HeaderMap> {
+        (self.headers_fn)(&*self.obj)
+    }
+
+    /// Appends the members of the problem details object provided by the inner error value.
+    pub fn problem_details(&self, members: &mut serde_json::Map<String, serde_json::Value>) {
+        (self.problem_details_fn)(&*self.obj, members)
+    }
+
     /// Consumes itself and creates an HTTP response from its value.
     pub fn into_response(self, request: &Request<()>) -> Response<ResponseBody> {
         (self.into_response_fn)(self.obj, request)
     }
 }
+
+/// Appends the header fields to the response, except for the ones already set.
+pub(crate) fn merge_headers(response: &mut Response<ResponseBody>, headers: &HeaderMap) {
+    for name in headers.keys() {
+        if response.headers().contains_key(name) {
+            continue;
+        }
+        for value in headers.get_all(name) {
+            response.headers_mut().append(name.clone(), value.clone());
+        }
+    }
+}
@@ -1,10 +1,14 @@
 //! Definition of `Extractor` and its implementors.
 
+pub mod auth;
 pub mod body;
+pub mod cookie;
 pub mod ext;
 pub mod header;
 pub mod local;
 pub mod method;
+pub mod pool;
+pub mod query;
 
 pub use self::ext::ExtractorExt;
 
@@ -34,6 +38,17 @@ pub trait Extractor {
     ///
     /// Note that the actual extraction process is started when the value
     /// of `Extract` is polled.
+    ///
+    /// # Cancellation
+    ///
+    /// The value of `Extract` may be dropped before completion, e.g. when the client
+    /// disconnects while the request body is being received. The implementors that
+    /// hold external resources during the extraction (such as temporary files) must
+    /// release them in the implementation of `Drop` rather than after the completion.
+    /// The temporary files created by `body::temp_file` and `Field::spool` in
+    /// `body::multipart` are removed when the future is dropped in the middle of
+    /// the extraction, and the other extractors provided by this crate only hold
+    /// in-memory buffers, which are released together with the future.
     fn extract(&self) -> Self::Extract;
 }
 
@@ -245,6 +260,9 @@ pub fn version() -> impl Extractor<
 }
 
 /// Creates an `Extractor` that parses the value of query string to `T`.
+///
+/// The unknown fields in the query string are silently ignored.
+/// Use `query::strict()` in order to reject them.
 pub fn query<T>() -> impl Extractor<
     Output = (T,), //
     Error = Error,
@@ -265,6 +283,25 @@ where
     })
 }
 
+/// Creates an `Extractor` that clones the shared state of the specified type.
+///
+/// The state is resolved from the scope that the matched handler belongs to.
+/// If the state is not registered in that scope, the nearest ancestor scope
+/// that has the state of the same type is used. Note that the handlers registered
+/// by `path!("*")` (the default handlers) belong to the scope where they are registered.
+///
+/// The extractor fails with `500 Internal Server Error` if the state is not found.
+pub fn state<T>() -> impl Extractor<
+    Output = (T,), //
+    Error = Error,
+    Extract = impl TryFuture<Ok = (T,), Error = Error> + Send + 'static,
+>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    self::ready(|input| input.states.try_get::<T>().map(|state| (state.clone(),)))
+}
+
 /// Creates an `Extractor` that returns the value of extension of the specified type.
 pub fn extension<T>() -> impl Extractor<
     Output = (T,), //
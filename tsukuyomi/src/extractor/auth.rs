@@ -0,0 +1,207 @@
+//! Extractors for the authentication of requests.
+
+use {
+    super::Extractor,
+    crate::{error::Error, future::TryFuture},
+    http::header::{HeaderValue, AUTHORIZATION},
+    std::fmt,
+};
+
+/// The credentials of Basic authentication, extracted by `basic`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("username", &self.username)
+            .field("password", &"<password>")
+            .finish()
+    }
+}
+
+/// Creates an `Extractor` that parses the credentials of Basic authentication
+/// in the header field `Authorization`.
+///
+/// If the header field is missing or malformed, the extraction fails with the error
+/// created by `error::unauthorized_basic`, that is, `401 Unauthorized` with the
+/// header field `WWW-Authenticate: Basic realm="<realm>"`. The verification of the
+/// credentials is left to the handler.
+pub fn basic(
+    realm: impl Into<String>,
+) -> impl Extractor<
+    Output = (Credentials,), //
+    Error = Error,
+    Extract = impl TryFuture<Ok = (Credentials,), Error = Error> + Send + 'static,
+> {
+    let realm = realm.into();
+    super::ready(move |input| {
+        input
+            .request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(parse_basic)
+            .map(|credentials| (credentials,))
+            .ok_or_else(|| crate::error::unauthorized_basic(realm.clone()))
+    })
+}
+
+fn parse_basic(value: &HeaderValue) -> Option<Credentials> {
+    let decoded = base64::decode(parse_authorization(value, "Basic")?).ok()?;
+    let decoded = String::from_utf8(decoded).ok()?;
+    let mut parts = decoded.splitn(2, ':');
+    let username = parts.next()?.to_owned();
+    let password = parts.next()?.to_owned();
+    Some(Credentials { username, password })
+}
+
+/// Returns the credentials in the value of `Authorization` if its scheme is the specified one.
+fn parse_authorization<'a>(value: &'a HeaderValue, scheme: &str) -> Option<&'a str> {
+    let mut parts = value.to_str().ok()?.splitn(2, ' ');
+    if !parts.next()?.eq_ignore_ascii_case(scheme) {
+        return None;
+    }
+    parts
+        .next()
+        .map(str::trim)
+        .filter(|credentials| !credentials.is_empty())
+}
+
+#[cfg(feature = "jwt")]
+mod bearer {
+    use {
+        super::parse_authorization,
+        crate::{
+            error::{Error, HttpError},
+            extractor::Extractor,
+            future::TryFuture,
+            input::Input,
+        },
+        http::{
+            header::{HeaderMap, HeaderValue, AUTHORIZATION, WWW_AUTHENTICATE},
+            Request, Response, StatusCode,
+        },
+        jsonwebtoken::Validation,
+        serde::de::DeserializeOwned,
+        std::fmt,
+    };
+
+    /// The key used for verifying the bearer tokens, registered as a shared state in the scope.
+    #[derive(Debug, Clone)]
+    pub struct DecodingKey {
+        secret: Vec<u8>,
+        validation: Validation,
+    }
+
+    impl DecodingKey {
+        /// Creates a `DecodingKey` from the specified secret.
+        ///
+        /// The tokens are validated using the default `Validation`, that is,
+        /// the signature algorithm is `HS256` and the expiration time is checked.
+        pub fn from_secret(secret: impl Into<Vec<u8>>) -> Self {
+            Self {
+                secret: secret.into(),
+                validation: Validation::default(),
+            }
+        }
+
+        /// Sets the validation rules of the tokens.
+        pub fn validation(self, validation: Validation) -> Self {
+            Self { validation, ..self }
+        }
+    }
+
+    /// Creates an `Extractor` that verifies the bearer token in the header field
+    /// `Authorization` and decodes its claims into `T`.
+    ///
+    /// The `DecodingKey` used for the verification is taken from the shared state
+    /// registered in the scope. If the token is missing or invalid, the extraction
+    /// fails with `401 Unauthorized` and the header field `WWW-Authenticate: Bearer`.
+    pub fn bearer<T>() -> impl Extractor<
+        Output = (T,), //
+        Error = Error,
+        Extract = impl TryFuture<Ok = (T,), Error = Error> + Send + 'static,
+    >
+    where
+        T: DeserializeOwned,
+    {
+        crate::extractor::ready(|input| match decode_bearer(input)? {
+            Some(claims) => Ok((claims,)),
+            None => Err(Error::from(BearerError::Missing)),
+        })
+    }
+
+    /// Creates an `Extractor` that verifies the bearer token and decodes its claims into `T`, if exists.
+    ///
+    /// Unlike `bearer`, the missing token is not treated as an error. The invalid
+    /// token is still rejected with `401 Unauthorized`.
+    pub fn optional_bearer<T>() -> impl Extractor<
+        Output = (Option<T>,), //
+        Error = Error,
+        Extract = impl TryFuture<Ok = (Option<T>,), Error = Error> + Send + 'static,
+    >
+    where
+        T: DeserializeOwned,
+    {
+        crate::extractor::ready(|input| decode_bearer(input).map(|claims| (claims,)))
+    }
+
+    fn decode_bearer<T>(input: &mut Input<'_>) -> Result<Option<T>, Error>
+    where
+        T: DeserializeOwned,
+    {
+        let token = match input.request.headers().get(AUTHORIZATION) {
+            Some(value) => parse_authorization(value, "Bearer").ok_or(BearerError::Missing)?,
+            None => return Ok(None),
+        };
+        let key = input.states.try_get::<DecodingKey>()?;
+        jsonwebtoken::decode(token, &key.secret, &key.validation)
+            .map(|data| Some(data.claims))
+            .map_err(|err| Error::from(BearerError::InvalidToken(err.to_string())))
+    }
+
+    #[derive(Debug)]
+    enum BearerError {
+        Missing,
+        InvalidToken(String),
+    }
+
+    impl fmt::Display for BearerError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                BearerError::Missing => f.write_str("missing bearer token"),
+                BearerError::InvalidToken(cause) => write!(f, "invalid bearer token: {}", cause),
+            }
+        }
+    }
+
+    impl HttpError for BearerError {
+        type Body = String;
+
+        fn into_response(self, _: &Request<()>) -> Response<Self::Body> {
+            Response::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .body(self.to_string())
+                .expect("should be a valid response")
+        }
+
+        fn headers(&self) -> Option<HeaderMap> {
+            let challenge = match self {
+                BearerError::Missing => "Bearer",
+                BearerError::InvalidToken(..) => r#"Bearer error="invalid_token""#,
+            };
+            let mut headers = HeaderMap::new();
+            headers.insert(WWW_AUTHENTICATE, HeaderValue::from_static(challenge));
+            Some(headers)
+        }
+    }
+}
+
+#[cfg(feature = "jwt")]
+pub use {
+    self::bearer::{bearer, optional_bearer, DecodingKey},
+    jsonwebtoken::Validation,
+};
@@ -1,5 +1,12 @@
 //! Extractors for parsing message body.
 
+mod multipart;
+pub mod urlencoded;
+
+pub use self::multipart::{Field, Multipart, MultipartConfig, Spool, Spooled, TempFile};
+
+use self::multipart::SpoolBody;
+
 use {
     super::Extractor,
     crate::{
@@ -7,11 +14,12 @@ use {
         future::{Poll, TryFuture},
         input::{body::RequestBody, header::ContentType, localmap::LocalData, Input},
     },
-    bytes::Bytes,
+    bytes::{Bytes, BytesMut},
     futures01::{Future, Stream},
+    http::{header::CONTENT_LENGTH, StatusCode},
     mime::Mime,
     serde::de::DeserializeOwned,
-    std::{marker::PhantomData, str},
+    std::{borrow::Cow, marker::PhantomData, str},
 };
 
 #[derive(Debug, failure::Fail)]
@@ -31,13 +39,20 @@ enum ExtractBodyError {
     #[fail(display = "charset in `Content-type` must be equal to `utf-8`")]
     NotUtf8Charset,
 
+    #[fail(display = "charset in `Content-type` is not supported: {}", charset)]
+    UnsupportedCharset { charset: String },
+
     #[fail(display = "the content of message body is invalid: {}", cause)]
     InvalidContent { cause: failure::Error },
 }
 
+fn unsupported_media_type(err: ExtractBodyError) -> Error {
+    crate::error::custom(StatusCode::UNSUPPORTED_MEDIA_TYPE, err)
+}
+
 trait Decoder<T> {
-    fn validate_mime(mime: Option<&Mime>) -> Result<(), ExtractBodyError>;
-    fn decode(data: &[u8]) -> Result<T, ExtractBodyError>;
+    fn validate_mime(mime: Option<&Mime>) -> Result<(), Error>;
+    fn decode(mime: Option<&Mime>, data: &[u8]) -> Result<T, Error>;
 }
 
 fn decode<T, D>() -> impl Extractor<
@@ -94,16 +109,15 @@ where
                 self.state = match self.state {
                     State::Init => {
                         let mime_opt = crate::input::header::parse::<ContentType>(input)?;
-                        D::validate_mime(mime_opt).map_err(crate::error::bad_request)?;
+                        D::validate_mime(mime_opt)?;
                         RequestBody::take_from(input.locals)
                             .map(|body| State::ReadAll(body.concat2()))
                             .ok_or_else(stolen_payload)?
                     }
                     State::ReadAll(ref mut read_all) => {
                         let data = futures01::try_ready!(read_all.poll());
-                        return D::decode(&*data)
-                            .map(|out| (out,).into())
-                            .map_err(crate::error::bad_request);
+                        let mime_opt = crate::input::header::parse::<ContentType>(input)?;
+                        return D::decode(mime_opt, &*data).map(|out| (out,).into());
                     }
                 };
             }
@@ -131,31 +145,35 @@ where
     where
         T: DeserializeOwned,
     {
-        fn validate_mime(mime: Option<&Mime>) -> Result<(), ExtractBodyError> {
+        fn validate_mime(mime: Option<&Mime>) -> Result<(), Error> {
             if let Some(mime) = mime {
                 if mime.type_() != mime::TEXT || mime.subtype() != mime::PLAIN {
-                    return Err(ExtractBodyError::UnexpectedContentType {
-                        expected: "text/plain",
-                    });
+                    return Err(crate::error::bad_request(
+                        ExtractBodyError::UnexpectedContentType {
+                            expected: "text/plain",
+                        },
+                    ));
                 }
                 if let Some(charset) = mime.get_param("charset") {
                     if charset != "utf-8" {
-                        return Err(ExtractBodyError::NotUtf8Charset);
+                        return Err(crate::error::bad_request(ExtractBodyError::NotUtf8Charset));
                     }
                 }
             }
             Ok(())
         }
 
-        fn decode(data: &[u8]) -> Result<T, ExtractBodyError> {
+        fn decode(_: Option<&Mime>, data: &[u8]) -> Result<T, Error> {
             let s = str::from_utf8(&*data) //
                 .map_err(|cause| ExtractBodyError::InvalidContent {
                     cause: cause.into(),
-                })?;
+                })
+                .map_err(crate::error::bad_request)?;
             serde_plain::from_str(s) //
                 .map_err(|cause| ExtractBodyError::InvalidContent {
                     cause: cause.into(),
                 })
+                .map_err(crate::error::bad_request)
         }
     }
 
@@ -163,6 +181,11 @@ where
 }
 
 /// Creates an `Extractor` that parses the entire of request body into `T` as JSON data.
+///
+/// The media type of request body must be `application/json` or have the suffix `+json`
+/// (e.g. `application/vnd.api+json`). Otherwise, the extractor fails with
+/// `415 Unsupported Media Type`. The body encoded in UTF-16 is also accepted if specified
+/// by the parameter `charset`.
 pub fn json<T>() -> impl Extractor<
     Output = (T,),
     Error = Error,
@@ -178,60 +201,409 @@ where
     where
         T: DeserializeOwned,
     {
-        fn validate_mime(mime: Option<&Mime>) -> Result<(), ExtractBodyError> {
-            let mime = mime.ok_or_else(|| ExtractBodyError::MissingContentType)?;
-            if *mime != mime::APPLICATION_JSON {
-                return Err(ExtractBodyError::UnexpectedContentType {
-                    expected: "application/json",
-                });
-            }
-            Ok(())
+        fn validate_mime(mime: Option<&Mime>) -> Result<(), Error> {
+            validate_json_mime(mime).map(|_| ())
         }
 
-        fn decode(data: &[u8]) -> Result<T, ExtractBodyError> {
-            serde_json::from_slice(&*data).map_err(|cause| ExtractBodyError::InvalidContent {
-                cause: cause.into(),
-            })
+        fn decode(mime: Option<&Mime>, data: &[u8]) -> Result<T, Error> {
+            let charset = validate_json_mime(mime)?;
+            let data = charset.decode(data).map_err(crate::error::bad_request)?;
+            serde_json::from_slice(&*data)
+                .map_err(|cause| ExtractBodyError::InvalidContent {
+                    cause: cause.into(),
+                })
+                .map_err(crate::error::bad_request)
         }
     }
 
     decode::<T, JsonDecoder>()
 }
 
-/// Creates an `Extractor` that parses the entire of request body into `T` as url-encoded data.
-pub fn urlencoded<T>() -> impl Extractor<
-    Output = (T,),
+/// The character encodings accepted by the JSON extractors.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum JsonCharset {
+    Utf8,
+    /// UTF-16 with the byte order detected from BOM (defaults to big endian).
+    Utf16,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl JsonCharset {
+    /// Converts the specified data into UTF-8.
+    fn decode(self, data: &[u8]) -> Result<Cow<'_, [u8]>, ExtractBodyError> {
+        let (data, big_endian) = match self {
+            JsonCharset::Utf8 => return Ok(Cow::Borrowed(data)),
+            JsonCharset::Utf16 => {
+                if data.starts_with(&[0xFE, 0xFF]) {
+                    (&data[2..], true)
+                } else if data.starts_with(&[0xFF, 0xFE]) {
+                    (&data[2..], false)
+                } else {
+                    (data, true)
+                }
+            }
+            JsonCharset::Utf16Le => (data, false),
+            JsonCharset::Utf16Be => (data, true),
+        };
+        if data.len() % 2 != 0 {
+            return Err(ExtractBodyError::InvalidContent {
+                cause: failure::err_msg("the length of UTF-16 data must be even"),
+            });
+        }
+        let units: Vec<u16> = data
+            .chunks(2)
+            .map(|b| {
+                if big_endian {
+                    (u16::from(b[0]) << 8) | u16::from(b[1])
+                } else {
+                    (u16::from(b[1]) << 8) | u16::from(b[0])
+                }
+            })
+            .collect();
+        String::from_utf16(&units)
+            .map(|s| Cow::Owned(s.into_bytes()))
+            .map_err(|cause| ExtractBodyError::InvalidContent {
+                cause: cause.into(),
+            })
+    }
+}
+
+fn validate_json_mime(mime: Option<&Mime>) -> Result<JsonCharset, Error> {
+    let mime = mime.ok_or_else(|| unsupported_media_type(ExtractBodyError::MissingContentType))?;
+    let is_json = mime.type_() == mime::APPLICATION
+        && (mime.subtype() == mime::JSON || mime.suffix() == Some(mime::JSON));
+    if !is_json {
+        return Err(unsupported_media_type(
+            ExtractBodyError::UnexpectedContentType {
+                expected: "application/json",
+            },
+        ));
+    }
+    match mime.get_param(mime::CHARSET) {
+        None => Ok(JsonCharset::Utf8),
+        Some(charset) => match &*charset.as_str().to_ascii_lowercase() {
+            "utf-8" => Ok(JsonCharset::Utf8),
+            "utf-16" => Ok(JsonCharset::Utf16),
+            "utf-16le" => Ok(JsonCharset::Utf16Le),
+            "utf-16be" => Ok(JsonCharset::Utf16Be),
+            _ => Err(unsupported_media_type(
+                ExtractBodyError::UnsupportedCharset {
+                    charset: charset.as_str().to_owned(),
+                },
+            )),
+        },
+    }
+}
+
+/// The default value of the maximal length of an element buffered by `JsonStream`.
+pub const JSON_STREAM_DEFAULT_LIMIT: u64 = 256 * 1024;
+
+/// Creates an `Extractor` that parses the request body as a JSON array incrementally.
+///
+/// The returned `JsonStream` yields the elements of the top-level array one by one
+/// as the chunks of request body are received, without buffering the entire of body.
+/// The length of each element is limited by `JSON_STREAM_DEFAULT_LIMIT`.
+pub fn json_stream<T>() -> impl Extractor<
+    Output = (JsonStream<T>,),
     Error = Error,
-    Extract = impl TryFuture<Ok = (T,), Error = Error> + Send + 'static,
+    Extract = impl TryFuture<Ok = (JsonStream<T>,), Error = Error> + Send + 'static,
 >
 where
-    T: DeserializeOwned + 'static,
+    T: DeserializeOwned,
 {
-    #[allow(missing_debug_implementations)]
-    struct UrlencodedDecoder(());
+    json_stream_with_limit(JSON_STREAM_DEFAULT_LIMIT)
+}
 
-    impl<T> Decoder<T> for UrlencodedDecoder
-    where
-        T: DeserializeOwned,
-    {
-        fn validate_mime(mime: Option<&Mime>) -> Result<(), ExtractBodyError> {
-            let mime = mime.ok_or_else(|| ExtractBodyError::MissingContentType)?;
-            if *mime != mime::APPLICATION_WWW_FORM_URLENCODED {
-                return Err(ExtractBodyError::UnexpectedContentType {
-                    expected: "application/x-www-form-urlencoded",
-                });
+/// Creates an `Extractor` that parses the request body as a JSON array incrementally,
+/// with the specified limit of the length of each element.
+///
+/// If the length of an element exceeds the limit, the stream fails with
+/// `413 Payload Too Large`.
+pub fn json_stream_with_limit<T>(
+    limit: u64,
+) -> impl Extractor<
+    Output = (JsonStream<T>,),
+    Error = Error,
+    Extract = impl TryFuture<Ok = (JsonStream<T>,), Error = Error> + Send + 'static,
+>
+where
+    T: DeserializeOwned,
+{
+    super::ready(move |input| {
+        let mime_opt = crate::input::header::parse::<ContentType>(input)?;
+        if validate_json_mime(mime_opt)? != JsonCharset::Utf8 {
+            return Err(unsupported_media_type(ExtractBodyError::NotUtf8Charset));
+        }
+        let body = RequestBody::take_from(input.locals).ok_or_else(stolen_payload)?;
+        Ok((JsonStream {
+            body,
+            buf: BytesMut::new(),
+            eof: false,
+            limit,
+            state: JsonStreamState::Start,
+            scanner: ElementScanner::default(),
+            _marker: PhantomData,
+        },))
+    })
+}
+
+/// A `Stream` that parses the elements of a JSON array from the request body.
+#[derive(Debug)]
+pub struct JsonStream<T> {
+    body: RequestBody,
+    buf: BytesMut,
+    eof: bool,
+    limit: u64,
+    state: JsonStreamState,
+    scanner: ElementScanner,
+    _marker: PhantomData<fn() -> T>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum JsonStreamState {
+    Start,
+    Element { first: bool },
+    Separator,
+    End,
+}
+
+/// The state for finding the end of an element, which is kept across the chunks
+/// so that the buffered bytes are scanned only once.
+#[derive(Debug, Default)]
+struct ElementScanner {
+    offset: usize,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+}
+
+impl ElementScanner {
+    /// Scans the buffer from the last position and returns the length of the element
+    /// located at the beginning of the buffer, if it has been terminated.
+    fn scan(&mut self, buf: &[u8], eof: bool) -> Option<usize> {
+        while self.offset < buf.len() {
+            let b = buf[self.offset];
+            self.offset += 1;
+            if self.in_string {
+                match b {
+                    _ if self.escaped => self.escaped = false,
+                    b'\\' => self.escaped = true,
+                    b'"' => {
+                        self.in_string = false;
+                        if self.depth == 0 {
+                            return Some(self.offset);
+                        }
+                    }
+                    _ => {}
+                }
+                continue;
             }
-            Ok(())
+            match b {
+                b'"' => self.in_string = true,
+                b'{' | b'[' => self.depth += 1,
+                b'}' | b']' if self.depth > 0 => {
+                    self.depth -= 1;
+                    if self.depth == 0 {
+                        return Some(self.offset);
+                    }
+                }
+                // the end of a scalar value (e.g. numbers) at the top level.
+                b'}' | b']' | b',' | b' ' | b'\t' | b'\r' | b'\n' if self.depth == 0 => {
+                    return Some(self.offset - 1);
+                }
+                _ => {}
+            }
+        }
+
+        if eof && self.depth == 0 && !self.in_string {
+            Some(buf.len())
+        } else {
+            None
         }
+    }
+}
 
-        fn decode(data: &[u8]) -> Result<T, ExtractBodyError> {
-            serde_urlencoded::from_bytes(&*data).map_err(|cause| ExtractBodyError::InvalidContent {
+impl<T> JsonStream<T>
+where
+    T: DeserializeOwned,
+{
+    /// Removes the leading whitespaces in the buffer and returns the first byte, if available.
+    fn peek_byte(&mut self) -> Option<u8> {
+        let n = self
+            .buf
+            .iter()
+            .position(|b| !b" \t\r\n".contains(b))
+            .unwrap_or_else(|| self.buf.len());
+        self.buf.advance(n);
+        self.buf.first().cloned()
+    }
+
+    /// Tries to parse an element from the buffer.
+    ///
+    /// Returns `Ok(None)` if the buffered data is not enough to determine the value.
+    fn parse_element(&mut self) -> Result<Option<T>, Error> {
+        let len = match self.scanner.scan(&*self.buf, self.eof) {
+            Some(len) if len as u64 <= self.limit => len,
+            Some(..) => return Err(element_too_large()),
+            None if self.buf.len() as u64 > self.limit => return Err(element_too_large()),
+            None => return Ok(None),
+        };
+        self.scanner = ElementScanner::default();
+        let data = self.buf.split_to(len);
+        serde_json::from_slice(&*data).map(Some).map_err(|cause| {
+            crate::error::bad_request(ExtractBodyError::InvalidContent {
                 cause: cause.into(),
             })
+        })
+    }
+
+    /// Receives the next chunk of request body into the buffer.
+    fn fill_buf(&mut self) -> Poll<(), Error> {
+        if self.eof {
+            return Err(crate::error::bad_request(
+                ExtractBodyError::InvalidContent {
+                    cause: failure::format_err!("unexpected EOF while parsing a JSON array"),
+                },
+            ));
         }
+        match futures01::try_ready!(self.body.poll()) {
+            Some(chunk) => self.buf.extend_from_slice(&*chunk),
+            None => self.eof = true,
+        }
+        Ok(().into())
     }
+}
+
+impl<T> Stream for JsonStream<T>
+where
+    T: DeserializeOwned,
+{
+    type Item = T;
+    type Error = Error;
 
-    decode::<T, UrlencodedDecoder>()
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        loop {
+            match self.state {
+                JsonStreamState::Start => match self.peek_byte() {
+                    Some(b'[') => {
+                        self.buf.advance(1);
+                        self.state = JsonStreamState::Element { first: true };
+                        continue;
+                    }
+                    Some(b) => return Err(unexpected_char(b)),
+                    None => {}
+                },
+                JsonStreamState::Element { first } => match self.peek_byte() {
+                    Some(b']') if first => {
+                        self.buf.advance(1);
+                        self.state = JsonStreamState::End;
+                        continue;
+                    }
+                    Some(..) => {
+                        if let Some(value) = self.parse_element()? {
+                            self.state = JsonStreamState::Separator;
+                            return Ok(Some(value).into());
+                        }
+                    }
+                    None => {}
+                },
+                JsonStreamState::Separator => match self.peek_byte() {
+                    Some(b',') => {
+                        self.buf.advance(1);
+                        self.state = JsonStreamState::Element { first: false };
+                        continue;
+                    }
+                    Some(b']') => {
+                        self.buf.advance(1);
+                        self.state = JsonStreamState::End;
+                        continue;
+                    }
+                    Some(b) => return Err(unexpected_char(b)),
+                    None => {}
+                },
+                // only the whitespaces are allowed after the end of array.
+                JsonStreamState::End => match self.peek_byte() {
+                    Some(b) => return Err(unexpected_char(b)),
+                    None if self.eof => return Ok(None.into()),
+                    None => {}
+                },
+            }
+
+            futures01::try_ready!(self.fill_buf());
+        }
+    }
+}
+
+fn element_too_large() -> Error {
+    crate::error::custom(
+        StatusCode::PAYLOAD_TOO_LARGE,
+        "the length of an element in the JSON array exceeds the limit",
+    )
+}
+
+fn unexpected_char(found: u8) -> Error {
+    crate::error::bad_request(ExtractBodyError::InvalidContent {
+        cause: failure::format_err!("unexpected character in a JSON array: {:?}", found as char),
+    })
+}
+
+/// Creates an `Extractor` that parses the entire of request body into `T` as url-encoded data.
+///
+/// The extractor fails with `415 Unsupported Media Type` if the value of `Content-type`
+/// is not `application/x-www-form-urlencoded`, and with `413 Payload Too Large` if the
+/// length of request body exceeds `urlencoded::DEFAULT_LIMIT`.
+/// Use `urlencoded::with_limit()` in order to change the limit.
+pub fn urlencoded<T>() -> impl Extractor<
+    Output = (T,),
+    Error = Error,
+    Extract = impl TryFuture<Ok = (T,), Error = Error> + Send + 'static,
+>
+where
+    T: DeserializeOwned + 'static,
+{
+    self::urlencoded::with_limit(self::urlencoded::DEFAULT_LIMIT)
+}
+
+/// Creates an `Extractor` that parses the request body as `multipart/form-data`.
+///
+/// The returned `Multipart` yields the fields in the body as they are received.
+pub fn multipart() -> impl Extractor<
+    Output = (Multipart,),
+    Error = Error,
+    Extract = impl TryFuture<Ok = (Multipart,), Error = Error> + Send + 'static,
+> {
+    multipart_with_config(MultipartConfig::default())
+}
+
+/// Creates an `Extractor` that parses the request body as `multipart/form-data`,
+/// with the specified configuration.
+pub fn multipart_with_config(
+    config: MultipartConfig,
+) -> impl Extractor<
+    Output = (Multipart,),
+    Error = Error,
+    Extract = impl TryFuture<Ok = (Multipart,), Error = Error> + Send + 'static,
+> {
+    super::ready(move |input| {
+        let boundary = {
+            let mime = crate::input::header::parse::<ContentType>(input)?
+                .ok_or_else(|| unsupported_media_type(ExtractBodyError::MissingContentType))?;
+            if mime.type_() != mime::MULTIPART || mime.subtype() != mime::FORM_DATA {
+                return Err(unsupported_media_type(
+                    ExtractBodyError::UnexpectedContentType {
+                        expected: "multipart/form-data",
+                    },
+                ));
+            }
+            mime.get_param(mime::BOUNDARY)
+                .ok_or_else(|| crate::error::bad_request("missing boundary in `Content-type`"))?
+                .as_str()
+                .to_owned()
+        };
+        let body = RequestBody::take_from(input.locals).ok_or_else(stolen_payload)?;
+        Ok((Multipart::new(body, &boundary, config.clone()),))
+    })
 }
 
 /// Creates an extractor that reads the entire of request body as a single byte sequence.
@@ -258,6 +630,61 @@ pub fn read_all() -> impl Extractor<
     })
 }
 
+/// The default value of the maximal length of request body received by `temp_file()`.
+pub const DEFAULT_TEMP_FILE_LIMIT: u64 = 64 * 1024 * 1024;
+
+/// Creates an `Extractor` that writes out the entire of request body to a temporary file.
+///
+/// The temporary file is removed when the returned `TempFile` is dropped, unless it is
+/// moved by `TempFile::persist`. The partially written file is also removed if the
+/// extraction is aborted, e.g. because the client disconnected. If the length of request body exceeds
+/// `DEFAULT_TEMP_FILE_LIMIT`, the extractor fails with `413 Payload Too Large`.
+/// Use `temp_file_with_limit()` in order to change the limit.
+pub fn temp_file() -> impl Extractor<
+    Output = (TempFile,),
+    Error = Error,
+    Extract = impl TryFuture<Ok = (TempFile,), Error = Error> + Send + 'static,
+> {
+    temp_file_with_limit(DEFAULT_TEMP_FILE_LIMIT)
+}
+
+/// Creates an `Extractor` that writes out the entire of request body to a temporary file,
+/// with the specified limit of the body length.
+pub fn temp_file_with_limit(
+    limit: u64,
+) -> impl Extractor<
+    Output = (TempFile,),
+    Error = Error,
+    Extract = impl TryFuture<Ok = (TempFile,), Error = Error> + Send + 'static,
+> {
+    super::extract(move || {
+        let mut spool: Option<SpoolBody> = None;
+        crate::future::poll_fn(move |input| loop {
+            if let Some(ref mut spool) = spool {
+                return spool.poll().map(|x| x.map(|file| (file,)));
+            }
+
+            let content_length = input
+                .request
+                .headers()
+                .get(CONTENT_LENGTH)
+                .and_then(|h| h.to_str().ok())
+                .and_then(|s| s.parse::<u64>().ok());
+            if content_length.map_or(false, |len| len > limit) {
+                return Err(crate::error::custom(
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    "the length of request body exceeds the limit",
+                ));
+            }
+
+            spool = Some(SpoolBody::new(
+                RequestBody::take_from(input.locals).ok_or_else(stolen_payload)?,
+                limit,
+            ));
+        })
+    })
+}
+
 /// Creates an `Extractor` that takes the raw instance of request body.
 pub fn stream() -> impl Extractor<
     Output = (RequestBody,), //
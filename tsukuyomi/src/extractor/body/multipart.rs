@@ -0,0 +1,642 @@
+//! Components for parsing `multipart/form-data` request bodies.
+
+use {
+    super::ExtractBodyError,
+    crate::{error::Error, fs::blocking_io, input::body::RequestBody},
+    bytes::{Bytes, BytesMut},
+    futures01::{Async, Future, Poll, Stream},
+    http::{
+        header::{self, HeaderMap, HeaderName, HeaderValue},
+        StatusCode,
+    },
+    mime::Mime,
+    std::{
+        fs::{self, File, OpenOptions},
+        io::{self, Read, Seek, SeekFrom, Write},
+        path::{Path, PathBuf},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex, MutexGuard,
+        },
+    },
+};
+
+/// The maximal size of header section in each part.
+const MAX_HEADERS_SIZE: usize = 16 * 1024;
+
+/// A set of configuration used in the multipart extractor.
+#[derive(Debug, Default, Clone)]
+pub struct MultipartConfig {
+    /// The maximal size of the content in each field.
+    ///
+    /// If the size of a field exceeds this value, reading from the field
+    /// fails with `413 Payload Too Large`.
+    pub max_field_size: Option<u64>,
+
+    /// The maximal size of the entire of request body.
+    ///
+    /// If the size of received body exceeds this value, reading from the
+    /// multipart stream fails with `413 Payload Too Large`.
+    pub max_total_size: Option<u64>,
+
+    /// The maximal size of field content buffered in memory by `Field::spool`.
+    ///
+    /// The content exceeding this value is written out to a temporary file.
+    /// If this value is `None`, the content is always buffered in memory.
+    pub spool_threshold: Option<u64>,
+
+    /// Whether to keep the temporary files which were being written by `Field::spool`
+    /// when the spooling was aborted, e.g. because the client disconnected.
+    ///
+    /// By default, such partial files are removed when the `Spool` is dropped.
+    /// Enabling this option is useful for inspecting or resuming the interrupted uploads,
+    /// but the application is then responsible for removing the files left behind.
+    /// The temporary files returned from `Spool` are always removed when dropped,
+    /// unless they are moved by `TempFile::persist`.
+    pub keep_partial_files: bool,
+}
+
+/// A `Stream` that yields the fields in a `multipart/form-data` request body.
+///
+/// The fields must be read in order. When the next field is requested,
+/// the remaining content of the current field is skipped.
+#[derive(Debug)]
+pub struct Multipart {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl Multipart {
+    pub(super) fn new(body: RequestBody, boundary: &str, config: MultipartConfig) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Inner {
+                body,
+                buf: BytesMut::new(),
+                eof: false,
+                delimiter: format!("\r\n--{}", boundary).into_bytes(),
+                state: State::Preamble,
+                field_id: 0,
+                field_size: 0,
+                total_size: 0,
+                config,
+            })),
+        }
+    }
+}
+
+impl Stream for Multipart {
+    type Item = Field;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let mut inner = lock(&self.inner);
+        let head = match futures01::try_ready!(inner.poll_next_field()) {
+            Some(head) => head,
+            None => return Ok(Async::Ready(None)),
+        };
+        Ok(Async::Ready(Some(Field {
+            id: inner.field_id,
+            headers: head.headers,
+            name: head.name,
+            filename: head.filename,
+            content_type: head.content_type,
+            spool_threshold: inner.config.spool_threshold,
+            keep_partial_files: inner.config.keep_partial_files,
+            inner: self.inner.clone(),
+        })))
+    }
+}
+
+/// A field in `multipart/form-data`.
+///
+/// The content of field can be received as a `Stream` of `Bytes`.
+#[derive(Debug)]
+pub struct Field {
+    id: usize,
+    headers: HeaderMap,
+    name: String,
+    filename: Option<String>,
+    content_type: Option<Mime>,
+    spool_threshold: Option<u64>,
+    keep_partial_files: bool,
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl Field {
+    /// Returns the name of this field.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the file name of this field, if specified.
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_ref().map(String::as_str)
+    }
+
+    /// Returns the value of `Content-Type` of this field, if specified.
+    pub fn content_type(&self) -> Option<&Mime> {
+        self.content_type.as_ref()
+    }
+
+    /// Returns the reference to the header map of this field.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Creates a `Future` that receives the entire of content in this field.
+    ///
+    /// The content is buffered in memory until its size exceeds `spool_threshold`
+    /// in the configuration, and then written out to a temporary file.
+    /// If the returned `Spool` is dropped before completion, the temporary file
+    /// is removed unless `keep_partial_files` in the configuration is enabled.
+    pub fn spool(self) -> Spool {
+        Spool {
+            threshold: self.spool_threshold,
+            keep_partial_files: self.keep_partial_files,
+            field: self,
+            buf: BytesMut::new(),
+            file: None,
+            eof: false,
+        }
+    }
+}
+
+impl Stream for Field {
+    type Item = Bytes;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        lock(&self.inner).poll_field_data(self.id)
+    }
+}
+
+/// A `Future` that receives the content of a field, created by `Field::spool`.
+#[derive(Debug)]
+pub struct Spool {
+    field: Field,
+    threshold: Option<u64>,
+    keep_partial_files: bool,
+    buf: BytesMut,
+    file: Option<TempFile>,
+    eof: bool,
+}
+
+impl Drop for Spool {
+    fn drop(&mut self) {
+        // The file is still held only if the spooling has not been completed.
+        if self.keep_partial_files {
+            if let Some(ref mut file) = self.file {
+                file.path.0.take();
+            }
+        }
+    }
+}
+
+impl Future for Spool {
+    type Item = Spooled;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        while !self.eof {
+            if self.file.is_none() && self.threshold.map_or(false, |n| self.buf.len() as u64 > n) {
+                self.file = Some(futures01::try_ready!(blocking_io(TempFile::create)));
+            }
+            if let Some(ref mut file) = self.file {
+                if !self.buf.is_empty() {
+                    let buf = &self.buf;
+                    futures01::try_ready!(blocking_io(|| file.file.write_all(&*buf)));
+                    self.buf.clear();
+                }
+            }
+            match futures01::try_ready!(self.field.poll()) {
+                Some(chunk) => self.buf.extend_from_slice(&*chunk),
+                None => self.eof = true,
+            }
+        }
+
+        if let Some(ref mut file) = self.file {
+            let buf = &self.buf;
+            futures01::try_ready!(blocking_io(|| {
+                file.file.write_all(&*buf)?;
+                file.file.flush()?;
+                file.file.seek(SeekFrom::Start(0)).map(|_| ())
+            }));
+            self.buf.clear();
+        }
+
+        Ok(Async::Ready(match self.file.take() {
+            Some(file) => Spooled::File(file),
+            None => Spooled::Memory(self.buf.take().freeze()),
+        }))
+    }
+}
+
+/// The content of a field received by `Spool`.
+#[derive(Debug)]
+pub enum Spooled {
+    /// The content is buffered in memory.
+    Memory(Bytes),
+
+    /// The content is written out to a temporary file.
+    File(TempFile),
+}
+
+/// A `Future` that writes out the entire of request body to a temporary file.
+#[derive(Debug)]
+pub(super) struct SpoolBody {
+    body: RequestBody,
+    limit: u64,
+    size: u64,
+    chunk: Option<Bytes>,
+    file: Option<TempFile>,
+}
+
+impl SpoolBody {
+    pub(super) fn new(body: RequestBody, limit: u64) -> Self {
+        Self {
+            body,
+            limit,
+            size: 0,
+            chunk: None,
+            file: None,
+        }
+    }
+}
+
+impl Future for SpoolBody {
+    type Item = TempFile;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if self.file.is_none() {
+            self.file = Some(futures01::try_ready!(blocking_io(TempFile::create)));
+        }
+
+        loop {
+            let file = self.file.as_mut().expect("the file should be available");
+            if let Some(ref chunk) = self.chunk {
+                futures01::try_ready!(blocking_io(|| file.file.write_all(&*chunk)));
+            }
+            self.chunk = None;
+
+            match futures01::try_ready!(self.body.poll()) {
+                Some(chunk) => {
+                    self.size += chunk.len() as u64;
+                    if self.size > self.limit {
+                        return Err(payload_too_large("the request body is too large"));
+                    }
+                    self.chunk = Some(chunk.into_bytes());
+                }
+                None => break,
+            }
+        }
+
+        {
+            let file = self.file.as_mut().expect("the file should be available");
+            futures01::try_ready!(blocking_io(|| {
+                file.file.flush()?;
+                file.file.seek(SeekFrom::Start(0)).map(|_| ())
+            }));
+        }
+
+        Ok(Async::Ready(
+            self.file.take().expect("the file should be available"),
+        ))
+    }
+}
+
+/// A temporary file that is removed when the value is dropped.
+#[derive(Debug)]
+pub struct TempFile {
+    file: File,
+    path: TempPath,
+}
+
+impl TempFile {
+    fn create() -> io::Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "tsukuyomi-tmp-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        Ok(Self {
+            file,
+            path: TempPath(Some(path)),
+        })
+    }
+
+    /// Returns the path of this temporary file.
+    pub fn path(&self) -> &Path {
+        self.path.0.as_ref().expect("the path should be available")
+    }
+
+    /// Moves this temporary file to the specified path, rather than removing it.
+    pub fn persist(self, to: impl AsRef<Path>) -> io::Result<()> {
+        let Self { file, mut path } = self;
+        drop(file);
+        let from = path.0.take().expect("the path should be available");
+        if let Err(err) = fs::rename(&from, to) {
+            let _ = fs::remove_file(&from);
+            return Err(err);
+        }
+        Ok(())
+    }
+}
+
+impl Read for TempFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Seek for TempFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+#[derive(Debug)]
+struct TempPath(Option<PathBuf>);
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        if let Some(path) = self.0.take() {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+fn lock(inner: &Mutex<Inner>) -> MutexGuard<'_, Inner> {
+    inner
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum State {
+    Preamble,
+    AfterDelimiter,
+    Headers,
+    Body,
+    End,
+}
+
+#[derive(Debug)]
+struct FieldHead {
+    headers: HeaderMap,
+    name: String,
+    filename: Option<String>,
+    content_type: Option<Mime>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    body: RequestBody,
+    buf: BytesMut,
+    eof: bool,
+    delimiter: Vec<u8>,
+    state: State,
+    field_id: usize,
+    field_size: u64,
+    total_size: u64,
+    config: MultipartConfig,
+}
+
+impl Inner {
+    /// Receives the next chunk of request body into the buffer.
+    fn fill_buf(&mut self) -> Poll<(), Error> {
+        if self.eof {
+            return Err(invalid_content("unexpected EOF in the multipart body"));
+        }
+        match futures01::try_ready!(self.body.poll()) {
+            Some(chunk) => {
+                self.total_size += chunk.len() as u64;
+                if self
+                    .config
+                    .max_total_size
+                    .map_or(false, |max| self.total_size > max)
+                {
+                    return Err(payload_too_large("the multipart body is too large"));
+                }
+                self.buf.extend_from_slice(&*chunk);
+            }
+            None => self.eof = true,
+        }
+        Ok(Async::Ready(()))
+    }
+
+    fn poll_next_field(&mut self) -> Poll<Option<FieldHead>, Error> {
+        loop {
+            match self.state {
+                State::Preamble => {
+                    // The first delimiter may not be preceded by CRLF.
+                    let dash_boundary = &self.delimiter[2..];
+                    if let Some(pos) = find(&self.buf, dash_boundary) {
+                        self.buf.advance(pos + dash_boundary.len());
+                        self.state = State::AfterDelimiter;
+                        continue;
+                    }
+                }
+                State::Body => {
+                    // skip the remaining content of the current field.
+                    let id = self.field_id;
+                    while futures01::try_ready!(self.poll_field_data(id)).is_some() {}
+                    continue;
+                }
+                State::AfterDelimiter => {
+                    if self.buf.len() >= 2 {
+                        if self.buf.starts_with(b"--") {
+                            self.state = State::End;
+                        } else if self.buf.starts_with(b"\r\n") {
+                            self.buf.advance(2);
+                            self.state = State::Headers;
+                        } else {
+                            return Err(invalid_content("invalid delimiter"));
+                        }
+                        continue;
+                    }
+                }
+                State::Headers => {
+                    let headers_end = if self.buf.starts_with(b"\r\n") {
+                        Some((0, 2))
+                    } else {
+                        find(&self.buf, b"\r\n\r\n").map(|pos| (pos, pos + 4))
+                    };
+                    if let Some((end, consumed)) = headers_end {
+                        let head = parse_headers(&self.buf[..end])?;
+                        self.buf.advance(consumed);
+                        self.state = State::Body;
+                        self.field_id += 1;
+                        self.field_size = 0;
+                        return Ok(Async::Ready(Some(head)));
+                    }
+                    if self.buf.len() > MAX_HEADERS_SIZE {
+                        return Err(payload_too_large(
+                            "the header section of field is too large",
+                        ));
+                    }
+                }
+                State::End => return Ok(Async::Ready(None)),
+            }
+
+            futures01::try_ready!(self.fill_buf());
+        }
+    }
+
+    fn poll_field_data(&mut self, id: usize) -> Poll<Option<Bytes>, Error> {
+        if self.field_id != id || self.state != State::Body {
+            return Ok(Async::Ready(None));
+        }
+
+        loop {
+            let chunk = match find(&self.buf, &self.delimiter) {
+                Some(0) => {
+                    let n = self.delimiter.len();
+                    self.buf.advance(n);
+                    self.state = State::AfterDelimiter;
+                    return Ok(Async::Ready(None));
+                }
+                Some(pos) => Some(self.buf.split_to(pos).freeze()),
+                None => {
+                    // keep the tail of buffer which may be a part of delimiter.
+                    let n = self
+                        .buf
+                        .len()
+                        .saturating_sub(self.delimiter.len().saturating_sub(1));
+                    if n > 0 {
+                        Some(self.buf.split_to(n).freeze())
+                    } else {
+                        None
+                    }
+                }
+            };
+
+            if let Some(chunk) = chunk {
+                self.field_size += chunk.len() as u64;
+                if self
+                    .config
+                    .max_field_size
+                    .map_or(false, |max| self.field_size > max)
+                {
+                    return Err(payload_too_large("the content of field is too large"));
+                }
+                return Ok(Async::Ready(Some(chunk)));
+            }
+
+            futures01::try_ready!(self.fill_buf());
+        }
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.len() > haystack.len() {
+        return None;
+    }
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn parse_headers(raw: &[u8]) -> Result<FieldHead, Error> {
+    let mut headers = HeaderMap::new();
+    for line in raw.split(|&b| b == b'\n') {
+        let line = if line.ends_with(b"\r") {
+            &line[..line.len() - 1]
+        } else {
+            line
+        };
+        if line.is_empty() {
+            continue;
+        }
+        let pos = line
+            .iter()
+            .position(|&b| b == b':')
+            .ok_or_else(|| invalid_content("invalid header line in the field"))?;
+        let name = HeaderName::from_bytes(&line[..pos])
+            .map_err(|_| invalid_content("invalid header name in the field"))?;
+        let value = HeaderValue::from_bytes(trim(&line[pos + 1..]))
+            .map_err(|_| invalid_content("invalid header value in the field"))?;
+        headers.append(name, value);
+    }
+
+    let (name, filename) = {
+        let disposition = headers
+            .get(header::CONTENT_DISPOSITION)
+            .ok_or_else(|| invalid_content("missing Content-Disposition in the field"))?
+            .to_str()
+            .map_err(|_| invalid_content("invalid Content-Disposition in the field"))?;
+        parse_content_disposition(disposition)?
+    };
+
+    let content_type = match headers.get(header::CONTENT_TYPE) {
+        Some(h) => Some(
+            h.to_str()
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .ok_or_else(|| invalid_content("invalid Content-Type in the field"))?,
+        ),
+        None => None,
+    };
+
+    Ok(FieldHead {
+        headers,
+        name,
+        filename,
+        content_type,
+    })
+}
+
+/// Parses the value of `Content-Disposition` and returns the parameters `name` and `filename`.
+fn parse_content_disposition(s: &str) -> Result<(String, Option<String>), Error> {
+    let mut params = s.split(';').map(str::trim);
+    match params.next() {
+        Some(kind) if kind.eq_ignore_ascii_case("form-data") => {}
+        _ => return Err(invalid_content("the disposition type must be form-data")),
+    }
+
+    let mut name = None;
+    let mut filename = None;
+    for param in params {
+        let mut kv = param.splitn(2, '=');
+        let key = kv.next().unwrap_or("").trim();
+        let value = kv.next().unwrap_or("").trim();
+        let value = if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+            &value[1..value.len() - 1]
+        } else {
+            value
+        };
+        if key.eq_ignore_ascii_case("name") {
+            name = Some(value.to_owned());
+        } else if key.eq_ignore_ascii_case("filename") {
+            filename = Some(value.to_owned());
+        }
+    }
+
+    let name = name.ok_or_else(|| invalid_content("missing the field name"))?;
+    Ok((name, filename))
+}
+
+fn trim(mut s: &[u8]) -> &[u8] {
+    while s.first().map_or(false, |&b| b == b' ' || b == b'\t') {
+        s = &s[1..];
+    }
+    while s.last().map_or(false, |&b| b == b' ' || b == b'\t') {
+        s = &s[..s.len() - 1];
+    }
+    s
+}
+
+fn invalid_content(msg: &'static str) -> Error {
+    crate::error::bad_request(ExtractBodyError::InvalidContent {
+        cause: failure::err_msg(msg),
+    })
+}
+
+fn payload_too_large(msg: &'static str) -> Error {
+    crate::error::custom(StatusCode::PAYLOAD_TOO_LARGE, msg)
+}
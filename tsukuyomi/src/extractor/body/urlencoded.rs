@@ -0,0 +1,168 @@
+//! Extractors for parsing `application/x-www-form-urlencoded` request bodies.
+
+use {
+    super::{stolen_payload, unsupported_media_type, ExtractBodyError},
+    crate::{
+        error::Error,
+        extractor::Extractor,
+        future::{Async, Poll, TryFuture},
+        input::{body::RequestBody, header::ContentType, Input},
+    },
+    bytes::BytesMut,
+    futures01::Stream,
+    http::{header::CONTENT_LENGTH, StatusCode},
+    serde::de::DeserializeOwned,
+    std::marker::PhantomData,
+};
+
+/// The default value of the maximal length of request body.
+pub const DEFAULT_LIMIT: u64 = 256 * 1024;
+
+/// Creates an `Extractor` that parses the request body as url-encoded data,
+/// with the specified limit of the body length.
+///
+/// If the length of request body exceeds the limit, the extractor fails
+/// with `413 Payload Too Large`.
+pub fn with_limit<T>(
+    limit: u64,
+) -> impl Extractor<
+    Output = (T,),
+    Error = Error,
+    Extract = impl TryFuture<Ok = (T,), Error = Error> + Send + 'static,
+>
+where
+    T: DeserializeOwned + 'static,
+{
+    crate::extractor::extract(move || {
+        let mut future = UrlencodedFuture::<T>::new(limit, false);
+        crate::future::poll_fn(move |input| {
+            let (value,) = futures01::try_ready!(future.poll_ready(input));
+            let value = value.ok_or_else(|| invalid_content("empty body"))?;
+            Ok(Async::Ready((value,)))
+        })
+    })
+}
+
+/// Creates an `Extractor` that parses the request body as url-encoded data,
+/// and returns `None` if the request body is empty.
+///
+/// The header field `Content-type` is not validated if the request body is empty.
+pub fn optional<T>() -> impl Extractor<
+    Output = (Option<T>,),
+    Error = Error,
+    Extract = impl TryFuture<Ok = (Option<T>,), Error = Error> + Send + 'static,
+>
+where
+    T: DeserializeOwned + 'static,
+{
+    crate::extractor::extract(|| UrlencodedFuture::<T>::new(DEFAULT_LIMIT, true))
+}
+
+#[allow(missing_debug_implementations)]
+struct UrlencodedFuture<T> {
+    limit: u64,
+    optional: bool,
+    state: State,
+    _marker: PhantomData<fn() -> T>,
+}
+
+#[allow(missing_debug_implementations)]
+enum State {
+    Init,
+    Receiving(RequestBody, BytesMut),
+}
+
+impl<T> UrlencodedFuture<T>
+where
+    T: DeserializeOwned,
+{
+    fn new(limit: u64, optional: bool) -> Self {
+        Self {
+            limit,
+            optional,
+            state: State::Init,
+            _marker: PhantomData,
+        }
+    }
+
+    fn check_content_length(&self, input: &mut Input<'_>) -> Result<(), Error> {
+        let content_length = input
+            .request
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|s| s.parse::<u64>().ok());
+        match content_length {
+            Some(len) if len > self.limit => Err(payload_too_large()),
+            _ => Ok(()),
+        }
+    }
+
+    fn decode(&self, input: &mut Input<'_>, data: &[u8]) -> Result<Option<T>, Error> {
+        if data.is_empty() && self.optional {
+            return Ok(None);
+        }
+        validate_mime(input)?;
+        serde_urlencoded::from_bytes(data)
+            .map(Some)
+            .map_err(invalid_content)
+    }
+}
+
+impl<T> TryFuture for UrlencodedFuture<T>
+where
+    T: DeserializeOwned,
+{
+    type Ok = (Option<T>,);
+    type Error = Error;
+
+    fn poll_ready(&mut self, input: &mut Input<'_>) -> Poll<Self::Ok, Self::Error> {
+        loop {
+            self.state = match self.state {
+                State::Init => {
+                    if !self.optional {
+                        validate_mime(input)?;
+                    }
+                    self.check_content_length(input)?;
+                    let body = RequestBody::take_from(input.locals).ok_or_else(stolen_payload)?;
+                    State::Receiving(body, BytesMut::new())
+                }
+                State::Receiving(ref mut body, ref mut buf) => {
+                    while let Some(chunk) = futures01::try_ready!(body.poll()) {
+                        if (buf.len() + chunk.len()) as u64 > self.limit {
+                            return Err(payload_too_large());
+                        }
+                        buf.extend_from_slice(&*chunk);
+                    }
+                    let data = buf.take();
+                    return self.decode(input, &*data).map(|value| (value,).into());
+                }
+            };
+        }
+    }
+}
+
+fn validate_mime(input: &mut Input<'_>) -> Result<(), Error> {
+    match crate::input::header::parse::<ContentType>(input)? {
+        Some(mime) if *mime == mime::APPLICATION_WWW_FORM_URLENCODED => Ok(()),
+        Some(..) => Err(unsupported_media_type(
+            ExtractBodyError::UnexpectedContentType {
+                expected: "application/x-www-form-urlencoded",
+            },
+        )),
+        None => Err(unsupported_media_type(ExtractBodyError::MissingContentType)),
+    }
+}
+
+fn payload_too_large() -> Error {
+    crate::error::custom(
+        StatusCode::PAYLOAD_TOO_LARGE,
+        "the length of request body exceeds the limit",
+    )
+}
+
+fn invalid_content(cause: impl Into<failure::Error>) -> Error {
+    crate::error::bad_request(ExtractBodyError::InvalidContent {
+        cause: cause.into(),
+    })
+}
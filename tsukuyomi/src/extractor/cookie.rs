@@ -0,0 +1,137 @@
+//! Extractors for accessing Cookie values.
+
+use {
+    super::Extractor,
+    crate::{error::Error, future::TryFuture},
+    std::{fmt, str::FromStr},
+};
+
+/// Creates an `Extractor` that parses the value of specified Cookie entry into `T`.
+///
+/// If the entry is missing or its value is invalid, the extraction fails
+/// with `400 Bad Request`.
+pub fn typed<T>(
+    name: impl Into<String>,
+) -> impl Extractor<
+    Output = (T,), //
+    Error = Error,
+    Extract = impl TryFuture<Ok = (T,), Error = Error> + Send + 'static,
+>
+where
+    T: FromStr,
+    T::Err: fmt::Debug + fmt::Display + Send + 'static,
+{
+    let name = name.into();
+    super::ready(move |input| {
+        let cookie = input
+            .cookies
+            .jar()?
+            .get(&name)
+            .ok_or_else(|| missing_cookie(&name))?;
+        parse_value(&name, cookie.value()).map(|value| (value,))
+    })
+}
+
+/// Creates an `Extractor` that parses the value of specified Cookie entry into `T`, if exists.
+///
+/// Unlike `typed`, the missing entry is not treated as an error.
+pub fn optional<T>(
+    name: impl Into<String>,
+) -> impl Extractor<
+    Output = (Option<T>,), //
+    Error = Error,
+    Extract = impl TryFuture<Ok = (Option<T>,), Error = Error> + Send + 'static,
+>
+where
+    T: FromStr,
+    T::Err: fmt::Debug + fmt::Display + Send + 'static,
+{
+    let name = name.into();
+    super::ready(move |input| match input.cookies.jar()?.get(&name) {
+        Some(cookie) => parse_value(&name, cookie.value()).map(|value| (Some(value),)),
+        None => Ok((None,)),
+    })
+}
+
+fn parse_value<T>(name: &str, value: &str) -> Result<T, Error>
+where
+    T: FromStr,
+    T::Err: fmt::Debug + fmt::Display + Send + 'static,
+{
+    value
+        .parse()
+        .map_err(|err| crate::error::bad_request(format!("invalid cookie: {}: {}", name, err)))
+}
+
+fn missing_cookie(name: &str) -> Error {
+    crate::error::bad_request(format!("missing cookie: {}", name))
+}
+
+#[cfg(feature = "secure")]
+mod secure {
+    use {
+        super::{missing_cookie, parse_value},
+        crate::{error::Error, extractor::Extractor, future::TryFuture},
+        ::cookie::Key,
+        std::{fmt, str::FromStr},
+    };
+
+    /// Creates an `Extractor` that parses the value of specified signed Cookie entry into `T`.
+    ///
+    /// The `Key` used for verifying the signature is taken from the shared state
+    /// registered in the scope. If the entry is missing or its signature is invalid,
+    /// the extraction fails with `400 Bad Request`.
+    pub fn signed<T>(
+        name: impl Into<String>,
+    ) -> impl Extractor<
+        Output = (T,), //
+        Error = Error,
+        Extract = impl TryFuture<Ok = (T,), Error = Error> + Send + 'static,
+    >
+    where
+        T: FromStr,
+        T::Err: fmt::Debug + fmt::Display + Send + 'static,
+    {
+        let name = name.into();
+        crate::extractor::ready(move |input| {
+            let key = input.states.try_get::<Key>()?;
+            let cookie = input
+                .cookies
+                .signed_jar(key)?
+                .get(&name)
+                .ok_or_else(|| missing_cookie(&name))?;
+            parse_value(&name, cookie.value()).map(|value| (value,))
+        })
+    }
+
+    /// Creates an `Extractor` that parses the value of specified private Cookie entry into `T`.
+    ///
+    /// The `Key` used for decrypting the value is taken from the shared state
+    /// registered in the scope. If the entry is missing or cannot be decrypted,
+    /// the extraction fails with `400 Bad Request`.
+    pub fn private<T>(
+        name: impl Into<String>,
+    ) -> impl Extractor<
+        Output = (T,), //
+        Error = Error,
+        Extract = impl TryFuture<Ok = (T,), Error = Error> + Send + 'static,
+    >
+    where
+        T: FromStr,
+        T::Err: fmt::Debug + fmt::Display + Send + 'static,
+    {
+        let name = name.into();
+        crate::extractor::ready(move |input| {
+            let key = input.states.try_get::<Key>()?;
+            let cookie = input
+                .cookies
+                .private_jar(key)?
+                .get(&name)
+                .ok_or_else(|| missing_cookie(&name))?;
+            parse_value(&name, cookie.value()).map(|value| (value,))
+        })
+    }
+}
+
+#[cfg(feature = "secure")]
+pub use self::secure::{private, signed};
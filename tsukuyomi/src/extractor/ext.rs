@@ -5,16 +5,20 @@ use {
     crate::{
         error::Error,
         generic::{Combine, Func},
+        output::IntoResponse,
         util::Chain, //
     },
 };
 
 pub use self::{
-    fallible::Fallible, //
+    and_then::AndThen, //
+    fallible::Fallible,
     map::Map,
     map_err::MapErr,
+    named::Named,
     optional::Optional,
     or::Or,
+    recover::Recover,
 };
 
 /// A set of extension methods for composing/formatting `Extractor`s.
@@ -39,6 +43,13 @@ pub trait ExtractorExt: Extractor + Sized {
         }
     }
 
+    /// Creates an `Extractor` that runs both extractors and combines their outputs.
+    ///
+    /// If the feature `debug-extractor` is enabled, the error from the chained
+    /// extractors is annotated with the position of failed one, such as
+    /// `extractor #2 failed: ...`. The position of nested chain is represented
+    /// as a dot-separated sequence (e.g. `#1.2`), and the name given by `named`
+    /// is appended to it (e.g. `extractor #2 (query) failed: ...`).
     fn and<E>(self, other: E) -> Chain<Self, E>
     where
         Self: Sized,
@@ -65,6 +76,15 @@ pub trait ExtractorExt: Extractor + Sized {
         Map { extractor: self, f }
     }
 
+    /// Creates an `Extractor` that applies the fallible function to the output of `self`.
+    fn and_then<F, T, E>(self, f: F) -> AndThen<Self, F>
+    where
+        F: Func<Self::Output, Out = Result<T, E>> + Clone,
+        E: Into<Error>,
+    {
+        AndThen { extractor: self, f }
+    }
+
     fn map_err<F, U>(self, f: F) -> MapErr<Self, F>
     where
         F: Fn(Self::Error) -> U + Clone,
@@ -72,6 +92,32 @@ pub trait ExtractorExt: Extractor + Sized {
     {
         MapErr { extractor: self, f }
     }
+
+    /// Creates an `Extractor` that attaches the specified name to the error of `self`.
+    ///
+    /// The error message is prefixed by the name, such as `extractor (query) failed: ...`,
+    /// which makes it easy to find out which extractor failed in a chain of extractors.
+    /// The response of the error is the same as the original one.
+    fn named(self, name: &'static str) -> Named<Self> {
+        Named {
+            extractor: self,
+            name,
+        }
+    }
+
+    /// Creates an `Extractor` that replaces the error of `self` with a custom response.
+    ///
+    /// When the extraction fails, the error is passed to `f` and the returned value is
+    /// converted into the response that the route replies with immediately. The response
+    /// is wrapped in `error::Rejection` so that it bypasses the rendering of errors
+    /// configured in the application.
+    fn recover<F, R>(self, f: F) -> Recover<Self, F>
+    where
+        F: Fn(Self::Error) -> R + Clone,
+        R: IntoResponse,
+    {
+        Recover { extractor: self, f }
+    }
 }
 
 impl<E: Extractor> ExtractorExt for E {}
@@ -114,12 +160,29 @@ mod chain {
 
     impl<L: TryFuture, R: TryFuture> ChainFuture<L, R> {
         fn poll_ready(&mut self, input: &mut Input<'_>) -> Poll<(), Error> {
-            futures01::try_ready!(self.left.poll_ready(input).map_err(Into::into));
-            futures01::try_ready!(self.right.poll_ready(input).map_err(Into::into));
+            futures01::try_ready!(self
+                .left
+                .poll_ready(input)
+                .map_err(|err| annotate(err.into(), 1)));
+            futures01::try_ready!(self
+                .right
+                .poll_ready(input)
+                .map_err(|err| annotate(err.into(), 2)));
             Ok(Async::Ready(()))
         }
     }
 
+    #[cfg(not(feature = "debug-extractor"))]
+    #[inline]
+    fn annotate(err: Error, _: usize) -> Error {
+        err
+    }
+
+    #[cfg(feature = "debug-extractor")]
+    fn annotate(err: Error, position: usize) -> Error {
+        super::annotated::annotate(err, |err| err.position.insert(0, position))
+    }
+
     impl<L: TryFuture, R: TryFuture> TryFuture for ChainFuture<L, R>
     where
         L::Ok: Combine<R::Ok>,
@@ -379,6 +442,65 @@ mod map {
     }
 }
 
+mod and_then {
+    use crate::{
+        error::Error,
+        extractor::Extractor,
+        future::{Poll, TryFuture},
+        generic::{Func, Tuple},
+        input::Input,
+    };
+
+    #[derive(Debug)]
+    pub struct AndThen<E, F> {
+        pub(super) extractor: E,
+        pub(super) f: F,
+    }
+
+    impl<E, F, T, U> Extractor for AndThen<E, F>
+    where
+        E: Extractor,
+        F: Func<E::Output, Out = Result<T, U>> + Clone + Send + 'static,
+        U: Into<Error>,
+    {
+        type Output = (T,);
+        type Error = Error;
+        type Extract = AndThenFuture<E::Extract, F>;
+
+        fn extract(&self) -> Self::Extract {
+            AndThenFuture {
+                future: self.extractor.extract(),
+                f: self.f.clone(),
+            }
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    pub struct AndThenFuture<Fut, F> {
+        future: Fut,
+        f: F,
+    }
+
+    impl<Fut, F, T, U> TryFuture for AndThenFuture<Fut, F>
+    where
+        Fut: TryFuture,
+        Fut::Ok: Tuple,
+        F: Func<Fut::Ok, Out = Result<T, U>>,
+        U: Into<Error>,
+    {
+        type Ok = (T,);
+        type Error = Error;
+
+        fn poll_ready(&mut self, input: &mut Input<'_>) -> Poll<Self::Ok, Self::Error> {
+            let args = futures01::try_ready!(self.future.poll_ready(input).map_err(Into::into));
+            self.f
+                .call(args)
+                .map(|out| (out,).into())
+                .map_err(Into::into)
+        }
+    }
+}
+
 mod map_err {
     use crate::{
         error::Error,
@@ -436,3 +558,197 @@ mod map_err {
         }
     }
 }
+
+mod recover {
+    use crate::{
+        error::{Error, Rejection},
+        extractor::Extractor,
+        future::{Poll, TryFuture},
+        generic::Tuple,
+        input::Input,
+        output::IntoResponse,
+    };
+
+    #[derive(Debug)]
+    pub struct Recover<E, F> {
+        pub(super) extractor: E,
+        pub(super) f: F,
+    }
+
+    impl<E, F, R> Extractor for Recover<E, F>
+    where
+        E: Extractor,
+        F: Fn(E::Error) -> R + Clone,
+        R: IntoResponse,
+    {
+        type Output = E::Output;
+        type Error = Error;
+        type Extract = RecoverFuture<E::Extract, F>;
+
+        fn extract(&self) -> Self::Extract {
+            RecoverFuture {
+                future: self.extractor.extract(),
+                f: self.f.clone(),
+            }
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    pub struct RecoverFuture<Fut, F> {
+        future: Fut,
+        f: F,
+    }
+
+    impl<Fut, F, R> TryFuture for RecoverFuture<Fut, F>
+    where
+        Fut: TryFuture,
+        Fut::Ok: Tuple,
+        F: Fn(Fut::Error) -> R,
+        R: IntoResponse,
+    {
+        type Ok = Fut::Ok;
+        type Error = Error;
+
+        #[inline]
+        fn poll_ready(&mut self, input: &mut Input<'_>) -> Poll<Self::Ok, Self::Error> {
+            self.future.poll_ready(input).map_err(|err| {
+                match (self.f)(err).into_response(input.request) {
+                    Ok(response) => Rejection::new(response).into(),
+                    Err(err) => err.into(),
+                }
+            })
+        }
+    }
+}
+
+mod named {
+    use crate::{
+        error::Error,
+        extractor::Extractor,
+        future::{Poll, TryFuture},
+        generic::Tuple,
+        input::Input,
+    };
+
+    #[derive(Debug)]
+    pub struct Named<E> {
+        pub(super) extractor: E,
+        pub(super) name: &'static str,
+    }
+
+    impl<E> Extractor for Named<E>
+    where
+        E: Extractor,
+    {
+        type Output = E::Output;
+        type Error = Error;
+        type Extract = NamedFuture<E::Extract>;
+
+        fn extract(&self) -> Self::Extract {
+            NamedFuture {
+                future: self.extractor.extract(),
+                name: self.name,
+            }
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    pub struct NamedFuture<Fut> {
+        future: Fut,
+        name: &'static str,
+    }
+
+    impl<Fut> TryFuture for NamedFuture<Fut>
+    where
+        Fut: TryFuture,
+        Fut::Ok: Tuple,
+    {
+        type Ok = Fut::Ok;
+        type Error = Error;
+
+        #[inline]
+        fn poll_ready(&mut self, input: &mut Input<'_>) -> Poll<Self::Ok, Self::Error> {
+            let name = self.name;
+            self.future.poll_ready(input).map_err(|err| {
+                super::annotated::annotate(err.into(), |err| {
+                    err.name.get_or_insert(name);
+                })
+            })
+        }
+    }
+}
+
+mod annotated {
+    use {
+        crate::{
+            error::{Error, Rejection},
+            output::ResponseBody,
+        },
+        http::{HeaderMap, Request, Response},
+        std::fmt,
+    };
+
+    /// Applies `f` to the annotation of the specified error.
+    ///
+    /// `Rejection` is returned as it is, so that the response created by `recover`
+    /// is not hidden from the application.
+    pub(super) fn annotate(err: Error, f: impl FnOnce(&mut AnnotatedError)) -> Error {
+        if err.is::<Rejection>() {
+            return err;
+        }
+        let mut err = AnnotatedError::wrap(err);
+        f(&mut err);
+        err.into()
+    }
+
+    /// The error type that holds the position and the name of the failed extractor.
+    #[derive(Debug)]
+    pub(super) struct AnnotatedError {
+        pub(super) position: Vec<usize>,
+        pub(super) name: Option<&'static str>,
+        cause: Error,
+    }
+
+    impl AnnotatedError {
+        fn wrap(err: Error) -> Self {
+            match err.downcast::<Self>() {
+                Ok(err) => err,
+                Err(cause) => AnnotatedError {
+                    position: vec![],
+                    name: None,
+                    cause,
+                },
+            }
+        }
+    }
+
+    impl fmt::Display for AnnotatedError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("extractor")?;
+            for (i, position) in self.position.iter().enumerate() {
+                f.write_str(if i == 0 { " #" } else { "." })?;
+                write!(f, "{}", position)?;
+            }
+            if let Some(name) = self.name {
+                write!(f, " ({})", name)?;
+            }
+            write!(f, " failed: {}", self.cause)
+        }
+    }
+
+    impl crate::error::HttpError for AnnotatedError {
+        type Body = ResponseBody;
+
+        fn into_response(self, request: &Request<()>) -> Response<Self::Body> {
+            self.cause.into_response(request)
+        }
+
+        fn headers(&self) -> Option<HeaderMap> {
+            self.cause.headers()
+        }
+
+        fn problem_details(&self, members: &mut serde_json::Map<String, serde_json::Value>) {
+            self.cause.problem_details(members)
+        }
+    }
+}
@@ -4,6 +4,7 @@ use {
     super::Extractor,
     crate::{error::Error, future::TryFuture, input::header::HeaderField, util::Never},
     http::header::{HeaderMap, HeaderName, HeaderValue},
+    std::{fmt, str::FromStr},
 };
 
 /// Creates an `Extractor` that parses a header field and returns its result.
@@ -24,6 +25,65 @@ where
     })
 }
 
+/// Creates an `Extractor` that parses the value of specified header field into `T`.
+///
+/// If the header field is missing or its value is invalid, the extraction fails
+/// with `400 Bad Request`.
+pub fn exact<T>(
+    name: HeaderName,
+) -> impl Extractor<
+    Output = (T,), //
+    Error = Error,
+    Extract = impl TryFuture<Ok = (T,), Error = Error> + Send + 'static,
+>
+where
+    T: FromStr,
+    T::Err: fmt::Debug + fmt::Display + Send + 'static,
+{
+    super::ready(move |input| {
+        let h =
+            input.request.headers().get(&name).ok_or_else(|| {
+                crate::error::bad_request(format!("missing header field: {}", name))
+            })?;
+        parse_value(&name, h).map(|value| (value,))
+    })
+}
+
+/// Creates an `Extractor` that parses the value of specified header field into `T`, if exists.
+///
+/// Unlike `exact`, the missing header field is not treated as an error.
+pub fn optional<T>(
+    name: HeaderName,
+) -> impl Extractor<
+    Output = (Option<T>,), //
+    Error = Error,
+    Extract = impl TryFuture<Ok = (Option<T>,), Error = Error> + Send + 'static,
+>
+where
+    T: FromStr,
+    T::Err: fmt::Debug + fmt::Display + Send + 'static,
+{
+    super::ready(move |input| match input.request.headers().get(&name) {
+        Some(h) => parse_value(&name, h).map(|value| (Some(value),)),
+        None => Ok((None,)),
+    })
+}
+
+fn parse_value<T>(name: &HeaderName, h: &HeaderValue) -> Result<T, Error>
+where
+    T: FromStr,
+    T::Err: fmt::Debug + fmt::Display + Send + 'static,
+{
+    h.to_str()
+        .map_err(|_| {
+            crate::error::bad_request(format!("the header field is not a valid string: {}", name))
+        })?
+        .parse()
+        .map_err(|err| {
+            crate::error::bad_request(format!("invalid header field: {}: {}", name, err))
+        })
+}
+
 /// Creates an `Extractor` that checks if a header field equals to the specified value.
 pub fn equals<T>(
     name: HeaderName,
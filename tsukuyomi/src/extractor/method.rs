@@ -49,13 +49,16 @@ define_http_method_extractors! {
     trace => TRACE;
 }
 
+/// Creates an `Extractor` that accepts `GET` and the methods falling back to `GET`
+/// (`HEAD` by default, see `MethodSemantics::fallback`).
 pub fn get_or_head() -> impl Extractor<
     Output = (),
     Error = StatusCode,
     Extract = impl TryFuture<Ok = (), Error = StatusCode> + Send + 'static,
 > {
     super::ready(move |input| {
-        if input.request.method() == Method::GET || input.request.method() == Method::HEAD {
+        let method = input.request.method();
+        if method == Method::GET || input.method_semantics.fallback(method) == Some(Method::GET) {
             Ok(())
         } else {
             Err(StatusCode::METHOD_NOT_ALLOWED)
@@ -0,0 +1,82 @@
+//! Extractors for acquiring resources from a pool registered as a shared state.
+
+use {
+    super::Extractor,
+    crate::{
+        error::Error,
+        future::{Async, Poll, TryFuture},
+        input::Input,
+    },
+    http::StatusCode,
+    std::{marker::PhantomData, time::Duration},
+    tokio_threadpool::blocking as poll_blocking,
+};
+
+/// A trait representing a pool of resources, such as database connections.
+///
+/// For example, the connection pool provided by `r2d2` can implement this trait
+/// by delegating to `r2d2::Pool::get_timeout` and treating its error as the timeout.
+pub trait Pool: Send + Sync + 'static {
+    /// The type of resource acquired from this pool.
+    type Resource;
+
+    /// Acquires a resource from this pool, waiting at most `timeout`.
+    ///
+    /// This method may block the current thread. It returns `Ok(None)` if no
+    /// resource becomes available within the timeout.
+    fn get_timeout(&self, timeout: Duration) -> crate::Result<Option<Self::Resource>>;
+}
+
+/// Creates an `Extractor` that acquires a resource from the pool of type `P`
+/// registered as a shared state.
+///
+/// The acquisition is run as a blocking operation on the worker thread of the
+/// threadpool, in the same way as the file I/O in `fs`. If no resource becomes
+/// available within `timeout`, the extraction fails with `503 Service Unavailable`
+/// instead of waiting indefinitely. If the pool is not registered in the scope,
+/// it fails with `500 Internal Server Error`.
+pub fn acquire<P>(
+    timeout: Duration,
+) -> impl Extractor<
+    Output = (P::Resource,), //
+    Error = Error,
+    Extract = Acquire<P>,
+>
+where
+    P: Pool,
+{
+    super::extract(move || Acquire {
+        timeout,
+        _marker: PhantomData,
+    })
+}
+
+#[doc(hidden)]
+#[allow(missing_debug_implementations)]
+pub struct Acquire<P> {
+    timeout: Duration,
+    _marker: PhantomData<fn() -> P>,
+}
+
+impl<P> TryFuture for Acquire<P>
+where
+    P: Pool,
+{
+    type Ok = (P::Resource,);
+    type Error = Error;
+
+    fn poll_ready(&mut self, input: &mut Input<'_>) -> Poll<Self::Ok, Self::Error> {
+        let pool = input.states.try_get::<P>()?;
+        let timeout = self.timeout;
+        match poll_blocking(|| pool.get_timeout(timeout)) {
+            Ok(Async::Ready(Ok(Some(resource)))) => Ok(Async::Ready((resource,))),
+            Ok(Async::Ready(Ok(None))) => Err(crate::error::custom(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "timed out while acquiring a resource from the pool",
+            )),
+            Ok(Async::Ready(Err(err))) => Err(err),
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Err(err) => Err(crate::error::internal_server_error(err)),
+        }
+    }
+}
@@ -0,0 +1,478 @@
+//! Extractors for parsing the query string.
+
+use {
+    super::Extractor,
+    crate::{
+        error::{Error, HttpError},
+        future::TryFuture,
+    },
+    http::{Request, Response, StatusCode},
+    serde::de::{DeserializeOwned, Deserializer, Visitor},
+    url::form_urlencoded,
+};
+
+/// The error type returned from the extractors in this module.
+///
+/// The error is rendered as `400 Bad Request`, and the callers can inspect
+/// its value by using `Error::downcast_ref::<ExtractQueryError>()`.
+#[derive(Debug, failure::Fail)]
+pub enum ExtractQueryError {
+    /// The request URI does not have the query string.
+    #[fail(display = "missing query")]
+    MissingQuery,
+
+    /// The query string contains a key not corresponding to any field.
+    #[fail(display = "unknown field in the query: `{}`", name)]
+    UnknownField { name: String },
+
+    /// The query string could not be deserialized.
+    #[fail(display = "the query is invalid: {}", cause)]
+    InvalidQuery { cause: failure::Error },
+}
+
+impl HttpError for ExtractQueryError {
+    type Body = String;
+
+    fn into_response(self, _: &Request<()>) -> Response<Self::Body> {
+        Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(self.to_string())
+            .expect("should be a valid response")
+    }
+}
+
+fn missing_query() -> Error {
+    ExtractQueryError::MissingQuery.into()
+}
+
+/// Creates an `Extractor` that parses the query string to `T`, rejecting the unknown fields.
+///
+/// Unlike `extractor::query()`, the extraction fails with `400 Bad Request` if the query string
+/// contains a key which does not correspond to any field of `T`. The check is only performed
+/// when `T` is deserialized as a struct.
+pub fn strict<T>() -> impl Extractor<
+    Output = (T,), //
+    Error = Error,
+    Extract = impl TryFuture<Ok = (T,), Error = Error> + Send + 'static,
+>
+where
+    T: DeserializeOwned,
+{
+    super::ready(|input| {
+        let query_str = input.request.uri().query().ok_or_else(missing_query)?;
+        from_str_strict(query_str)
+            .map(|x| (x,))
+            .map_err(Error::from)
+    })
+}
+
+/// Creates an `Extractor` that parses the query string to `T`, with the support for
+/// repeated and bracketed keys.
+///
+/// The supported syntax is as follows:
+///
+/// * `key=value` - a single value. The empty value (`key=`) is treated as an empty string.
+/// * `key=a&key=b` or `key[]=a&key[]=b` - a sequence of values.
+/// * `key[sub]=value` - a nested map. The brackets can be nested (e.g. `a[b][c]=value`).
+///
+/// A single value can also be deserialized into a sequence with one element.
+///
+/// ```
+/// # use tsukuyomi::extractor::query;
+/// #[derive(Debug, serde::Deserialize)]
+/// struct Filter {
+///     name: String,
+/// }
+///
+/// #[derive(Debug, serde::Deserialize)]
+/// struct Params {
+///     tag: Vec<String>,
+///     filter: Filter,
+/// }
+///
+/// // e.g. "?tag=a&tag=b&filter[name]=x"
+/// # drop(query::multi::<Params>());
+/// ```
+pub fn multi<T>() -> impl Extractor<
+    Output = (T,), //
+    Error = Error,
+    Extract = impl TryFuture<Ok = (T,), Error = Error> + Send + 'static,
+>
+where
+    T: DeserializeOwned,
+{
+    super::ready(|input| {
+        let query_str = input.request.uri().query().ok_or_else(missing_query)?;
+        self::multi::from_str(query_str)
+            .map(|x| (x,))
+            .map_err(Error::from)
+    })
+}
+
+fn from_str_strict<T>(query_str: &str) -> Result<T, ExtractQueryError>
+where
+    T: DeserializeOwned,
+{
+    let mut fields = None;
+    let value = T::deserialize(CaptureFields {
+        inner: serde_urlencoded::Deserializer::new(form_urlencoded::parse(query_str.as_bytes())),
+        fields: &mut fields,
+    })
+    .map_err(|cause| ExtractQueryError::InvalidQuery {
+        cause: cause.into(),
+    })?;
+
+    if let Some(fields) = fields {
+        for (name, _) in form_urlencoded::parse(query_str.as_bytes()) {
+            if !fields.contains(&&*name) {
+                return Err(ExtractQueryError::UnknownField {
+                    name: name.into_owned(),
+                });
+            }
+        }
+    }
+
+    Ok(value)
+}
+
+/// A `Deserializer` that records the field names of the deserialized struct.
+struct CaptureFields<'a, 'de> {
+    inner: serde_urlencoded::Deserializer<'de>,
+    fields: &'a mut Option<&'static [&'static str]>,
+}
+
+macro_rules! forward_to_inner {
+    ($($method:ident),*) => {$(
+        #[inline]
+        fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            self.inner.$method(visitor)
+        }
+    )*};
+}
+
+impl<'a, 'de> Deserializer<'de> for CaptureFields<'a, 'de> {
+    type Error = serde_urlencoded::de::Error;
+
+    forward_to_inner!(
+        deserialize_any,
+        deserialize_bool,
+        deserialize_i8,
+        deserialize_i16,
+        deserialize_i32,
+        deserialize_i64,
+        deserialize_u8,
+        deserialize_u16,
+        deserialize_u32,
+        deserialize_u64,
+        deserialize_f32,
+        deserialize_f64,
+        deserialize_char,
+        deserialize_str,
+        deserialize_string,
+        deserialize_bytes,
+        deserialize_byte_buf,
+        deserialize_option,
+        deserialize_unit,
+        deserialize_seq,
+        deserialize_map,
+        deserialize_identifier,
+        deserialize_ignored_any
+    );
+
+    fn deserialize_unit_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.inner.deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.inner.deserialize_newtype_struct(name, visitor)
+    }
+
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.inner.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.inner.deserialize_tuple_struct(name, len, visitor)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        *self.fields = Some(fields);
+        self.inner.deserialize_struct(name, fields, visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.inner.deserialize_enum(name, variants, visitor)
+    }
+}
+
+mod multi {
+    use {
+        super::ExtractQueryError,
+        indexmap::{map::Entry, IndexMap},
+        serde::de::{
+            self,
+            value::{Error, MapDeserializer, SeqDeserializer},
+            DeserializeOwned, Deserializer, IntoDeserializer, Visitor,
+        },
+        std::mem,
+        url::form_urlencoded,
+    };
+
+    pub(super) fn from_str<T>(query_str: &str) -> Result<T, ExtractQueryError>
+    where
+        T: DeserializeOwned,
+    {
+        let invalid_query = |cause: failure::Error| ExtractQueryError::InvalidQuery { cause };
+
+        let mut root = IndexMap::new();
+        for (key, value) in form_urlencoded::parse(query_str.as_bytes()) {
+            let (name, segments) = parse_key(&*key).map_err(invalid_query)?;
+            insert(&mut root, name, &segments, value.into_owned()).map_err(invalid_query)?;
+        }
+
+        T::deserialize(Node::Map(root)).map_err(|cause| invalid_query(cause.into()))
+    }
+
+    /// Splits a key such as `a[b][c]` into the name and the bracketed segments.
+    fn parse_key(key: &str) -> Result<(String, Vec<String>), failure::Error> {
+        let (name, mut rest) = match key.find('[') {
+            Some(pos) => (&key[..pos], &key[pos..]),
+            None => (key, ""),
+        };
+        if name.is_empty() {
+            failure::bail!("empty key name");
+        }
+
+        let mut segments = vec![];
+        while !rest.is_empty() {
+            if !rest.starts_with('[') {
+                failure::bail!("invalid key: `{}`", key);
+            }
+            let end = rest
+                .find(']')
+                .ok_or_else(|| failure::format_err!("unclosed bracket in the key: `{}`", key))?;
+            segments.push(rest[1..end].to_owned());
+            rest = &rest[end + 1..];
+        }
+
+        Ok((name.to_owned(), segments))
+    }
+
+    fn insert(
+        map: &mut IndexMap<String, Node>,
+        name: String,
+        segments: &[String],
+        value: String,
+    ) -> Result<(), failure::Error> {
+        match segments.split_first() {
+            None => match map.entry(name) {
+                Entry::Vacant(entry) => {
+                    entry.insert(Node::Value(value));
+                }
+                Entry::Occupied(mut entry) => entry.get_mut().push(value)?,
+            },
+            Some((segment, rest)) if segment.is_empty() => {
+                if !rest.is_empty() {
+                    failure::bail!("nested sequences are not supported");
+                }
+                map.entry(name)
+                    .or_insert_with(|| Node::Seq(vec![]))
+                    .push(value)?;
+            }
+            Some((segment, rest)) => {
+                match map
+                    .entry(name)
+                    .or_insert_with(|| Node::Map(IndexMap::new()))
+                {
+                    Node::Map(ref mut map) => insert(map, segment.clone(), rest, value)?,
+                    _ => failure::bail!("conflicted the map and the value in the query"),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[derive(Debug)]
+    enum Node {
+        Value(String),
+        Seq(Vec<Node>),
+        Map(IndexMap<String, Node>),
+    }
+
+    impl Node {
+        fn push(&mut self, value: String) -> Result<(), failure::Error> {
+            match self {
+                Node::Value(ref mut old) => {
+                    let old = mem::replace(old, String::new());
+                    *self = Node::Seq(vec![Node::Value(old), Node::Value(value)]);
+                }
+                Node::Seq(ref mut values) => values.push(Node::Value(value)),
+                Node::Map(..) => failure::bail!("conflicted the map and the value in the query"),
+            }
+            Ok(())
+        }
+
+        fn into_value(self) -> Result<String, Error> {
+            match self {
+                Node::Value(value) => Ok(value),
+                Node::Seq(..) => Err(de::Error::custom("expected a value, found a sequence")),
+                Node::Map(..) => Err(de::Error::custom("expected a value, found a map")),
+            }
+        }
+    }
+
+    impl<'de> IntoDeserializer<'de, Error> for Node {
+        type Deserializer = Self;
+
+        fn into_deserializer(self) -> Self::Deserializer {
+            self
+        }
+    }
+
+    macro_rules! deserialize_parsed {
+        ($($method:ident => $visit:ident,)*) => {$(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: Visitor<'de>,
+            {
+                let value = self.into_value()?;
+                visitor.$visit(value.parse().map_err(de::Error::custom)?)
+            }
+        )*};
+    }
+
+    impl<'de> Deserializer<'de> for Node {
+        type Error = Error;
+
+        fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            match self {
+                Node::Value(value) => visitor.visit_string(value),
+                Node::Seq(values) => visitor.visit_seq(SeqDeserializer::new(values.into_iter())),
+                Node::Map(map) => visitor.visit_map(MapDeserializer::new(map.into_iter())),
+            }
+        }
+
+        deserialize_parsed! {
+            deserialize_bool => visit_bool,
+            deserialize_i8 => visit_i8,
+            deserialize_i16 => visit_i16,
+            deserialize_i32 => visit_i32,
+            deserialize_i64 => visit_i64,
+            deserialize_u8 => visit_u8,
+            deserialize_u16 => visit_u16,
+            deserialize_u32 => visit_u32,
+            deserialize_u64 => visit_u64,
+            deserialize_f32 => visit_f32,
+            deserialize_f64 => visit_f64,
+            deserialize_char => visit_char,
+        }
+
+        fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            visitor.visit_string(self.into_value()?)
+        }
+
+        fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            visitor.visit_some(self)
+        }
+
+        fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            visitor.visit_unit()
+        }
+
+        fn deserialize_newtype_struct<V>(
+            self,
+            _name: &'static str,
+            visitor: V,
+        ) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            visitor.visit_newtype_struct(self)
+        }
+
+        fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            match self {
+                Node::Seq(values) => visitor.visit_seq(SeqDeserializer::new(values.into_iter())),
+                value @ Node::Value(..) => {
+                    visitor.visit_seq(SeqDeserializer::new(Some(value).into_iter()))
+                }
+                Node::Map(..) => Err(de::Error::custom("expected a sequence, found a map")),
+            }
+        }
+
+        fn deserialize_enum<V>(
+            self,
+            _name: &'static str,
+            _variants: &'static [&'static str],
+            visitor: V,
+        ) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            visitor.visit_enum(self.into_value()?.into_deserializer())
+        }
+
+        serde::forward_to_deserialize_any! {
+            str bytes byte_buf unit_struct tuple tuple_struct map struct identifier ignored_any
+        }
+    }
+}
@@ -16,18 +16,21 @@ use {
         header::{self, HeaderMap},
         Request, Response, StatusCode,
     },
+    lazy_static::lazy_static,
     log::trace,
     mime::Mime,
     std::{
         borrow::Cow,
-        cmp, fmt,
+        cmp,
+        collections::HashMap,
+        fmt::{self, Write as _Write},
         fs::{File, Metadata},
-        io::{self, Read as _Read},
+        io::{self, Read as _Read, Seek, SeekFrom},
         mem,
         ops::Deref,
         path::{Path, PathBuf},
         str::FromStr,
-        sync::Arc,
+        sync::{Arc, Mutex},
         time::Duration,
     },
     time::Timespec,
@@ -43,6 +46,62 @@ fn parse_http_date(s: &str) -> Result<Timespec, time::ParseError> {
         .map(|tm| tm.to_timespec())
 }
 
+#[allow(clippy::cast_sign_loss)]
+fn parse_file_time(s: &str) -> Result<FileTime, time::ParseError> {
+    parse_http_date(s).map(|timespec| FileTime::from_unix_time(timespec.sec, timespec.nsec as u32))
+}
+
+/// A byte range requested by the header field `Range`.
+#[derive(Debug, PartialEq)]
+enum ByteRange {
+    Satisfiable { start: u64, end: u64 },
+    Unsatisfiable,
+}
+
+/// Parses the value of `Range` for the representation with the specified length.
+///
+/// Only a single byte range is supported. If the value is malformed or contains
+/// multiple ranges, this function returns `None` and the header field is ignored.
+fn parse_range(value: &str, len: u64) -> Option<ByteRange> {
+    let value = value.trim();
+    if !value.starts_with("bytes=") {
+        return None;
+    }
+    let spec = &value["bytes=".len()..];
+    if spec.contains(',') {
+        return None;
+    }
+
+    let mut parts = spec.splitn(2, '-');
+    let first = parts.next()?.trim();
+    let last = parts.next()?.trim();
+
+    let (start, end) = if first.is_empty() {
+        let suffix: u64 = last.parse().ok()?;
+        if suffix == 0 {
+            return Some(ByteRange::Unsatisfiable);
+        }
+        (len.saturating_sub(suffix), len.saturating_sub(1))
+    } else {
+        let start: u64 = first.parse().ok()?;
+        let end = if last.is_empty() {
+            len.saturating_sub(1)
+        } else {
+            let end: u64 = last.parse().ok()?;
+            if end < start {
+                return None;
+            }
+            cmp::min(end, len.saturating_sub(1))
+        };
+        (start, end)
+    };
+
+    if start >= len {
+        return Some(ByteRange::Unsatisfiable);
+    }
+    Some(ByteRange::Satisfiable { start, end })
+}
+
 #[derive(Debug)]
 struct ETag {
     weak: bool,
@@ -63,6 +122,59 @@ impl ETag {
         }
     }
 
+    /// Creates a strong `ETag` from the hash of file contents.
+    ///
+    /// The hash is computed by 64-bit FNV-1a, which is stable across builds and processes.
+    /// The computed hashes are cached by the path, the modification time and the size
+    /// of the file, so the contents are not read again unless one of them changes.
+    /// Note that the hash function is not cryptographic.
+    fn from_contents(path: &Path, file: &mut File, metadata: &Metadata) -> io::Result<Self> {
+        const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+        lazy_static! {
+            static ref CONTENT_HASHES: Mutex<HashMap<PathBuf, (FileTime, u64, String)>> =
+                Mutex::new(HashMap::new());
+        }
+
+        let last_modified = FileTime::from_last_modification_time(&metadata);
+        if let Some((mtime, len, tag)) = CONTENT_HASHES
+            .lock()
+            .expect("the lock is poisoned")
+            .get(path)
+        {
+            if *mtime == last_modified && *len == metadata.len() {
+                return Ok(Self {
+                    weak: false,
+                    tag: tag.clone(),
+                });
+            }
+        }
+
+        let mut hash = FNV_OFFSET_BASIS;
+        let mut buf = [0; 8192];
+        loop {
+            match file.read(&mut buf)? {
+                0 => break,
+                n => {
+                    for &b in &buf[..n] {
+                        hash ^= u64::from(b);
+                        hash = hash.wrapping_mul(FNV_PRIME);
+                    }
+                }
+            }
+        }
+        file.seek(SeekFrom::Start(0))?;
+        let tag = format!("{:x}-{:016x}", metadata.len(), hash);
+
+        CONTENT_HASHES.lock().expect("the lock is poisoned").insert(
+            path.to_owned(),
+            (last_modified, metadata.len(), tag.clone()),
+        );
+
+        Ok(Self { weak: false, tag })
+    }
+
     fn parse_inner(weak: bool, s: &str) -> Result<Self, failure::Error> {
         if s.len() < 2 {
             failure::bail!("");
@@ -82,9 +194,31 @@ impl ETag {
         })
     }
 
-    fn eq(&self, other: &Self) -> bool {
-        self.tag == other.tag && (self.weak || !other.weak)
+    /// The weak comparison described in RFC 7232, section 2.3.2.
+    fn weak_eq(&self, other: &Self) -> bool {
+        self.tag == other.tag
+    }
+
+    /// The strong comparison described in RFC 7232, section 2.3.2.
+    fn strong_eq(&self, other: &Self) -> bool {
+        !self.weak && !other.weak && self.tag == other.tag
+    }
+}
+
+/// Returns whether the value of `If-None-Match` matches the specified entity tag.
+///
+/// The value is a comma-separated list of entity tags or `*`, and the entity tags
+/// are compared using the weak comparison.
+fn match_if_none_match(value: &str, etag: Option<&ETag>) -> Result<bool, failure::Error> {
+    if value.trim() == "*" {
+        return Ok(true);
     }
+    let mut matched = false;
+    for tag in value.split(',') {
+        let tag: ETag = tag.trim().parse()?;
+        matched |= etag.map_or(false, |etag| etag.weak_eq(&tag));
+    }
+    Ok(matched)
 }
 
 impl FromStr for ETag {
@@ -124,11 +258,170 @@ pub struct OpenConfig {
     /// If this field is set, the generated HTTP response will include a "Cache-Control" header
     /// that includes the parameter max-age.
     pub max_age: Option<Duration>,
+
+    /// The value of "Content-Disposition" header field.
+    ///
+    /// If this field is set, the generated HTTP response will include the header field
+    /// which tells the browser how to handle the content.
+    pub content_disposition: Option<Disposition>,
+
+    /// The value of "Content-Type" header field.
+    ///
+    /// If `None`, it will be guessed from the extension of the file path
+    /// (and falls back to `application/octet-stream` if unknown).
+    pub content_type: Option<Mime>,
+
+    /// The kind of "ETag" header field.
+    ///
+    /// The default value is `EtagMode::WeakMeta`.
+    pub etag: EtagMode,
+
+    /// Whether to treat the resource as immutable.
+    ///
+    /// If this field is set to `true`, the value of "Cache-Control" header field
+    /// will be `public, max-age=31536000, immutable` and `max_age` is ignored.
+    /// This is suitable for the fingerprinted assets, whose content never changes.
+    pub immutable: bool,
+
+    /// Whether to omit the validators ("Last-Modified" and "ETag") from the response.
+    ///
+    /// This field is only effective when `immutable` is set, since the validators are
+    /// redundant for the immutable resources.
+    pub omit_validators: bool,
+}
+
+/// The kind of "ETag" used in `NamedFile`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EtagMode {
+    /// A weak entity tag computed from the size and the modification time of the file.
+    WeakMeta,
+
+    /// A strong entity tag computed from the hash of the file contents.
+    ///
+    /// The file is read entirely when the hash is not cached, that is, for the first
+    /// time or after the file is modified. Since the cache is invalidated by the
+    /// modification time and the size of the file, the entity tag is not updated if
+    /// the contents are replaced while both of them are preserved (e.g. by `touch -r`).
+    StrongHash,
+
+    /// The "ETag" header field is not used.
+    None,
+}
+
+impl Default for EtagMode {
+    fn default() -> Self {
+        EtagMode::WeakMeta
+    }
+}
+
+/// The policy of "Cache-Control" used in `Staticfiles`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CacheControl {
+    /// `public`, with the parameter `max-age` if specified.
+    Public { max_age: Option<Duration> },
+
+    /// `public, max-age=31536000, immutable`.
+    ///
+    /// If `validators` is `false`, "Last-Modified" and "ETag" are omitted from the response.
+    Immutable { validators: bool },
+}
+
+impl CacheControl {
+    fn apply(self, config: &mut OpenConfig) {
+        match self {
+            CacheControl::Public { max_age } => {
+                config.max_age = max_age;
+                config.immutable = false;
+                config.omit_validators = false;
+            }
+            CacheControl::Immutable { validators } => {
+                config.immutable = true;
+                config.omit_validators = !validators;
+            }
+        }
+    }
+}
+
+/// The kind of "Content-Disposition" used in `NamedFile`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Disposition {
+    /// The content is displayed inline in the browser.
+    Inline,
+
+    /// The content is downloaded and saved locally.
+    ///
+    /// If `filename` is `None`, the name of opened file is used instead.
+    Attachment { filename: Option<String> },
+}
+
+impl Disposition {
+    fn to_header_value(&self, default_filename: Option<&str>) -> String {
+        match self {
+            Disposition::Inline => "inline".into(),
+            Disposition::Attachment { filename } => {
+                match filename.as_ref().map(String::as_str).or(default_filename) {
+                    Some(filename) => format!("attachment; {}", encode_filename(filename)),
+                    None => "attachment".into(),
+                }
+            }
+        }
+    }
+}
+
+/// Encodes the filename parameter in "Content-Disposition".
+///
+/// The filename containing non-ASCII or special characters is encoded
+/// using the `filename*` parameter described in RFC 6266, along with
+/// an ASCII fallback for old clients.
+fn encode_filename(filename: &str) -> String {
+    fn is_plain(c: char) -> bool {
+        (c.is_ascii_graphic() || c == ' ') && c != '"' && c != '\\'
+    }
+
+    if filename.chars().all(is_plain) {
+        return format!("filename=\"{}\"", filename);
+    }
+
+    let fallback: String = filename
+        .chars()
+        .map(|c| if is_plain(c) { c } else { '_' })
+        .collect();
+
+    let mut encoded = String::with_capacity(filename.len());
+    for &b in filename.as_bytes() {
+        match b {
+            b'a'..=b'z'
+            | b'A'..=b'Z'
+            | b'0'..=b'9'
+            | b'!'
+            | b'#'
+            | b'$'
+            | b'&'
+            | b'+'
+            | b'-'
+            | b'.'
+            | b'^'
+            | b'_'
+            | b'`'
+            | b'|'
+            | b'~' => encoded.push(b as char),
+            b => {
+                let _ = write!(encoded, "%{:02X}", b);
+            }
+        }
+    }
+
+    format!("filename=\"{}\"; filename*=UTF-8''{}", fallback, encoded)
 }
 
 // ==== NamedFile ====
 
 /// An instance of `Responder` for responding a file.
+///
+/// The content of file is read chunk by chunk while the response body is being sent.
+/// If the client disconnects in the middle of the download, the response body is
+/// dropped and the file is closed without scheduling the reads of subsequent chunks.
+/// Note that the read operation which has already started cannot be interrupted.
 #[derive(Debug, Clone)]
 pub struct NamedFile<P> {
     path: P,
@@ -151,6 +444,14 @@ where
             config: Some(config),
         }
     }
+
+    /// Sets the value of "Content-Disposition" used in the response.
+    pub fn set_content_disposition(mut self, disposition: Disposition) -> Self {
+        self.config
+            .get_or_insert_with(Default::default)
+            .content_disposition = Some(disposition);
+        self
+    }
 }
 
 impl<P> Responder for NamedFile<P>
@@ -185,25 +486,54 @@ where
     type Error = crate::Error;
 
     fn poll_ready(&mut self, input: &mut Input<'_>) -> Poll<Self::Ok, Self::Error> {
-        let (file, meta) = futures01::try_ready!(blocking_io(|| {
-            let file = File::open(&self.path)?;
+        let etag_mode = self
+            .config
+            .as_ref()
+            .map_or_else(EtagMode::default, |config| config.etag);
+        let (file, meta, etag) = futures01::try_ready!(blocking_io(|| {
+            let mut file = File::open(&self.path)?;
             let meta = file.metadata()?;
-            Ok((file, meta))
+            let etag = match etag_mode {
+                EtagMode::WeakMeta => Some(ETag::from_metadata(&meta)),
+                EtagMode::StrongHash => {
+                    Some(ETag::from_contents(self.path.as_ref(), &mut file, &meta)?)
+                }
+                EtagMode::None => None,
+            };
+            Ok((file, meta, etag))
         }));
 
-        let config = self.config.take().unwrap_or_default();
+        let mut config = self.config.take().unwrap_or_default();
 
         let last_modified = FileTime::from_last_modification_time(&meta);
-        let etag = ETag::from_metadata(&meta);
-
-        let content_type = mime_guess::guess_mime_type(&self.path);
+        let now = FileTime::from_system_time(input.clock().now());
+        let cacheable = input
+            .method_semantics()
+            .is_cacheable(input.request.method());
+
+        let content_type = config
+            .content_type
+            .take()
+            .unwrap_or_else(|| mime_guess::guess_mime_type(&self.path));
+
+        let content_disposition = config.content_disposition.as_ref().map(|disposition| {
+            let filename = self
+                .path
+                .as_ref()
+                .file_name()
+                .and_then(|name| name.to_str());
+            disposition.to_header_value(filename)
+        });
 
         let response = NamedFileResponse {
             file,
             meta,
             content_type,
+            content_disposition,
             last_modified,
+            now,
             etag,
+            cacheable,
             config,
         }
         .into_response(input.request)?;
@@ -217,28 +547,29 @@ struct NamedFileResponse {
     file: File,
     meta: Metadata,
     content_type: Mime,
-    etag: ETag,
+    content_disposition: Option<String>,
+    etag: Option<ETag>,
     last_modified: FileTime,
+    now: FileTime,
+    // Whether the response to the request method is cacheable, according to `MethodSemantics`.
+    // The conditional requests and the caching headers are only applied if it is `true`.
+    cacheable: bool,
     config: OpenConfig,
 }
 
 impl NamedFileResponse {
-    #[allow(clippy::cast_sign_loss)]
     fn is_modified(&self, headers: &HeaderMap) -> Result<bool, Error> {
         if let Some(h) = headers.get(header::IF_NONE_MATCH) {
             trace!("NamedFile::is_modified(): validate If-None-Match");
 
-            let etag: ETag = h
-                .to_str()
-                .map_err(crate::error::bad_request)?
-                .parse()
+            let value = h.to_str().map_err(crate::error::bad_request)?;
+            let modified = !match_if_none_match(value, self.etag.as_ref())
                 .map_err(crate::error::bad_request)?;
-            let modified = !etag.eq(&self.etag);
 
             trace!(
-                "--> self.etag={:?}, etag={:?}, modified={}",
+                "--> self.etag={:?}, if_none_match={:?}, modified={}",
                 self.etag,
-                etag,
+                value,
                 modified
             );
             return Ok(modified);
@@ -247,12 +578,11 @@ impl NamedFileResponse {
         if let Some(h) = headers.get(header::IF_MODIFIED_SINCE) {
             trace!("NamedFile::is_modified(): validate If-Modified-Since");
 
-            let if_modified_since = {
-                let timespec = parse_http_date(h.to_str().map_err(crate::error::bad_request)?)
-                    .map_err(crate::error::bad_request)?;
-                FileTime::from_unix_time(timespec.sec, timespec.nsec as u32)
-            };
-            let modified = self.last_modified > if_modified_since;
+            let if_modified_since = parse_file_time(h.to_str().map_err(crate::error::bad_request)?)
+                .map_err(crate::error::bad_request)?;
+            // The date later than the current time is invalid and hence ignored
+            // (RFC 7232, Section 3.3).
+            let modified = if_modified_since > self.now || self.last_modified > if_modified_since;
 
             trace!(
                 "--> if_modified_sicne={:?}, modified={}",
@@ -265,7 +595,40 @@ impl NamedFileResponse {
         Ok(true)
     }
 
+    /// Returns whether the value of `If-Range` matches the current representation.
+    ///
+    /// The entity tag is compared using the strong comparison, and the date is compared
+    /// with the modification time of the file at the precision of seconds.
+    fn if_range_matches(&self, headers: &HeaderMap) -> bool {
+        let value = match headers.get(header::IF_RANGE).map(|h| h.to_str()) {
+            Some(Ok(value)) => value.trim(),
+            Some(Err(..)) => return false,
+            None => return true,
+        };
+
+        let matched = if value.starts_with('"') || value.starts_with("W/") {
+            match (value.parse::<ETag>(), &self.etag) {
+                (Ok(if_range), Some(etag)) => etag.strong_eq(&if_range),
+                _ => false,
+            }
+        } else {
+            parse_file_time(value).ok().map_or(false, |if_range| {
+                if_range.seconds() == self.last_modified.seconds()
+            })
+        };
+
+        trace!(
+            "NamedFile::if_range_matches(): if_range={:?}, matched={}",
+            value,
+            matched
+        );
+        matched
+    }
+
     fn cache_control(&self) -> Cow<'static, str> {
+        if self.config.immutable {
+            return "public, max-age=31536000, immutable".into();
+        }
         match self.config.max_age {
             Some(ref max_age) => format!("public, max-age={}", max_age.as_secs()).into(),
             None => "public".into(),
@@ -274,11 +637,12 @@ impl NamedFileResponse {
 
     #[allow(clippy::cast_possible_wrap)]
     fn last_modified(&self) -> Result<String, time::ParseError> {
-        let tm = time::at(Timespec::new(
+        // IMF-fixdate, described in RFC 7231, section 7.1.1.1.
+        let tm = time::at_utc(Timespec::new(
             self.last_modified.seconds(),
             self.last_modified.nanoseconds() as i32,
         ));
-        time::strftime("%c", &tm)
+        time::strftime("%a, %d %b %Y %T GMT", &tm)
     }
 }
 
@@ -289,28 +653,68 @@ impl IntoResponse for NamedFileResponse {
     fn into_response(self, request: &Request<()>) -> Result<Response<Self::Body>, Self::Error> {
         trace!("NamedFile::respond_to");
 
-        if !self.is_modified(request.headers())? {
+        if self.cacheable && !self.is_modified(request.headers())? {
             return Ok(Response::builder()
                 .status(StatusCode::NOT_MODIFIED)
                 .body(ResponseBody::empty())
-                .unwrap());
+                .expect("should be a valid response"));
         }
 
         // FIXME: optimize
 
+        let len = self.meta.len();
+        let range = match request.headers().get(header::RANGE) {
+            Some(range) if self.if_range_matches(request.headers()) => range
+                .to_str()
+                .ok()
+                .and_then(|range| parse_range(range, len)),
+            _ => None,
+        };
+
+        let mut response = Response::builder();
+        let (offset, count) = match range {
+            Some(ByteRange::Satisfiable { start, end }) => {
+                response.status(StatusCode::PARTIAL_CONTENT).header(
+                    header::CONTENT_RANGE,
+                    &*format!("bytes {}-{}/{}", start, end, len),
+                );
+                (start, end - start + 1)
+            }
+            Some(ByteRange::Unsatisfiable) => {
+                return Ok(Response::builder()
+                    .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                    .header(header::CONTENT_RANGE, &*format!("bytes */{}", len))
+                    .body(ResponseBody::empty())
+                    .expect("should be a valid response"));
+            }
+            None => (0, len),
+        };
+
         let cache_control = self.cache_control();
         let last_modified = self
             .last_modified()
             .map_err(crate::error::internal_server_error)?;
-        let stream = ReadStream::new(self.file, self.meta, self.config.chunk_size);
+        let stream = ReadStream::new(self.file, self.meta, self.config.chunk_size, offset, count);
 
-        Ok(Response::builder()
+        response
             .header(header::CONTENT_TYPE, self.content_type.as_ref())
-            .header(header::CACHE_CONTROL, &*cache_control)
-            .header(header::LAST_MODIFIED, &*last_modified)
-            .header(header::ETAG, &*self.etag.to_string())
+            .header(header::ACCEPT_RANGES, "bytes");
+        if self.cacheable {
+            response.header(header::CACHE_CONTROL, &*cache_control);
+        }
+        if self.cacheable && !(self.config.immutable && self.config.omit_validators) {
+            response.header(header::LAST_MODIFIED, &*last_modified);
+            if let Some(ref etag) = self.etag {
+                response.header(header::ETAG, &*etag.to_string());
+            }
+        }
+        if let Some(ref content_disposition) = self.content_disposition {
+            response.header(header::CONTENT_DISPOSITION, &**content_disposition);
+        }
+
+        Ok(response
             .body(ResponseBody::wrap_stream(stream))
-            .unwrap())
+            .expect("should be a valid response"))
     }
 }
 
@@ -321,16 +725,27 @@ struct ReadStream(State);
 
 #[derive(Debug)]
 enum State {
-    Reading { file: File, buf_size: usize },
+    Reading {
+        file: File,
+        buf_size: usize,
+        offset: u64,
+        remaining: u64,
+    },
     Eof,
     Gone,
 }
 
 impl ReadStream {
-    fn new(file: File, meta: Metadata, buf_size: Option<usize>) -> Self {
+    /// Creates a `ReadStream` that reads `len` bytes from the position `offset` of the file.
+    fn new(file: File, meta: Metadata, buf_size: Option<usize>, offset: u64, len: u64) -> Self {
         let buf_size = finalize_block_size(buf_size, &meta);
         drop(meta);
-        ReadStream(State::Reading { file, buf_size })
+        ReadStream(State::Reading {
+            file,
+            buf_size,
+            offset,
+            remaining: len,
+        })
     }
 }
 
@@ -338,30 +753,46 @@ impl Stream for ReadStream {
     type Item = Bytes;
     type Error = io::Error;
 
+    #[allow(clippy::cast_possible_truncation)]
     fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
         loop {
             match self.0 {
                 State::Reading {
                     ref mut file,
                     buf_size,
-                    ..
+                    ref mut offset,
+                    ref mut remaining,
                 } => {
                     trace!("ReadStream::poll(): polling on the mode State::Reading");
 
-                    let buf = futures01::try_ready!(blocking_io(|| {
+                    let polled = blocking_io(|| {
+                        if *offset > 0 {
+                            file.seek(SeekFrom::Start(*offset))?;
+                            *offset = 0;
+                        }
                         let mut buf = BytesMut::with_capacity(buf_size);
                         if !buf.has_remaining_mut() {
                             buf.reserve(buf_size);
                         }
                         unsafe {
-                            let n = file.read(buf.bytes_mut())?;
+                            let bytes = buf.bytes_mut();
+                            let len = cmp::min(bytes.len() as u64, *remaining) as usize;
+                            let n = file.read(&mut bytes[..len])?;
                             buf.advance_mut(n);
                         }
+                        *remaining -= buf.len() as u64;
                         Ok(buf)
-                    }));
-
-                    if !buf.is_empty() {
-                        return Ok(Async::Ready(Some(buf.freeze())));
+                    });
+
+                    match polled {
+                        Ok(Async::Ready(ref buf)) if buf.is_empty() => {}
+                        Ok(Async::Ready(buf)) => return Ok(Async::Ready(Some(buf.freeze()))),
+                        Ok(Async::NotReady) => return Ok(Async::NotReady),
+                        Err(err) => {
+                            // close the file immediately, since the stream is never resumed.
+                            self.0 = State::Eof;
+                            return Err(err);
+                        }
                     }
                 }
                 State::Eof => {
@@ -382,7 +813,13 @@ impl Stream for ReadStream {
 #[allow(dead_code)]
 const DEFAULT_BUF_SIZE: u64 = 8192;
 
-fn blocking_io<T>(f: impl FnOnce() -> io::Result<T>) -> Poll<T, io::Error> {
+/// Runs the specified blocking operation on the current worker thread of the threadpool.
+///
+/// The operation is executed synchronously within the call of this function, and is never
+/// spawned as a detached task. Therefore, once the future or stream calling this function
+/// is dropped (e.g. the client has gone away), no further blocking operation is scheduled.
+/// Note that the system call which has already started cannot be interrupted.
+pub(crate) fn blocking_io<T>(f: impl FnOnce() -> io::Result<T>) -> Poll<T, io::Error> {
     match poll_blocking(f) {
         Ok(Async::Ready(ready)) => ready.map(Async::Ready),
         Ok(Async::NotReady) => Ok(Async::NotReady),
@@ -444,6 +881,7 @@ struct ServeFileInner {
     path: ArcPath,
     config: Option<OpenConfig>,
     extract_path: bool,
+    index_file: Option<String>,
 }
 
 mod impl_handler_for_serve_file {
@@ -456,8 +894,36 @@ mod impl_handler_for_serve_file {
             input::Input,
         },
         futures01::{Async, Poll},
+        std::{
+            io,
+            path::{Path, PathBuf},
+        },
+        url::percent_encoding::percent_decode,
     };
 
+    /// Resolves the value of catch-all parameter into a path under the root directory.
+    ///
+    /// The joined path is canonicalized so that neither `..` segments (including the
+    /// percent-encoded ones) nor symbolic links can escape from the root directory.
+    fn resolve_path(root: &Path, path: &str) -> Result<PathBuf, Error> {
+        let decoded = percent_decode(path.as_bytes())
+            .decode_utf8()
+            .map_err(crate::error::bad_request)?;
+        let path = root
+            .join(&*decoded)
+            .canonicalize()
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::NotFound => crate::error::not_found(err),
+                _ => crate::error::internal_server_error(err),
+            })?;
+        if !path.starts_with(root) {
+            return Err(crate::error::forbidden(
+                "the requested path is outside of the root directory",
+            ));
+        }
+        Ok(path)
+    }
+
     impl Handler for ServeFile {
         type Output = NamedFile<ArcPath>;
         type Error = Error;
@@ -483,7 +949,17 @@ mod impl_handler_for_serve_file {
                     .as_ref()
                     .and_then(|params| params.catch_all())
                     .ok_or_else(|| crate::error::internal_server_error("missing params"))?;
-                self.inner.path.join(path).into()
+                let mut path = resolve_path(&self.inner.path, path)?;
+                if path.is_dir() {
+                    match self.inner.index_file {
+                        Some(ref index_file) => path.push(index_file),
+                        None => return Err(crate::error::not_found("no index file is configured")),
+                    }
+                    if !path.is_file() {
+                        return Err(crate::error::not_found("missing index file"));
+                    }
+                }
+                path.into()
             } else {
                 self.inner.path.clone()
             };
@@ -501,6 +977,8 @@ mod impl_handler_for_serve_file {
 pub struct Staticfiles<P> {
     root_dir: P,
     config: Option<OpenConfig>,
+    index_file: Option<String>,
+    catch_all: bool,
 }
 
 impl<P> Staticfiles<P>
@@ -512,6 +990,8 @@ where
         Self {
             root_dir,
             config: None,
+            index_file: None,
+            catch_all: false,
         }
     }
 
@@ -522,6 +1002,39 @@ where
             ..self
         }
     }
+
+    /// Sets the policy of "Cache-Control" used in handlers.
+    ///
+    /// This method overwrites the corresponding fields of `OpenConfig`, and hence should be
+    /// called after `open_config`.
+    pub fn cache_control(mut self, cache_control: CacheControl) -> Self {
+        cache_control.apply(self.config.get_or_insert_with(Default::default));
+        self
+    }
+
+    /// Sets the name of file served when the request path points to a directory (e.g. `index.html`).
+    ///
+    /// If this value is not set or the file does not exist in the directory,
+    /// such requests are rejected with `404 Not Found`.
+    pub fn index_file(self, name: impl Into<String>) -> Self {
+        Self {
+            index_file: Some(name.into()),
+            ..self
+        }
+    }
+
+    /// Registers a single catch-all route that serves the entire directory tree,
+    /// instead of the routes for each entry in the root directory.
+    ///
+    /// This mode is suitable for large directory trees, and the files added after
+    /// the configuration are also served. In this mode, `index.html` is used as
+    /// the index file unless `index_file` is specified explicitly.
+    pub fn catch_all(self) -> Self {
+        Self {
+            catch_all: true,
+            ..self
+        }
+    }
 }
 
 impl<P, M, C> crate::config::Config<M, C> for Staticfiles<P>
@@ -534,7 +1047,31 @@ where
     type Error = crate::config::Error;
 
     fn configure(self, scope: &mut crate::app::config::Scope<'_, M, C>) -> crate::app::Result<()> {
-        let Self { root_dir, config } = self;
+        let Self {
+            root_dir,
+            config,
+            index_file,
+            catch_all,
+        } = self;
+
+        if catch_all {
+            let path = root_dir
+                .as_ref()
+                .canonicalize()
+                .map(|path| ArcPath(Arc::new(path)))
+                .map_err(crate::config::Error::custom)?;
+            return scope.route(
+                "/*path",
+                ServeFile {
+                    inner: Arc::new(ServeFileInner {
+                        path,
+                        config,
+                        extract_path: true,
+                        index_file: Some(index_file.unwrap_or_else(|| "index.html".into())),
+                    }),
+                },
+            );
+        }
 
         for entry in std::fs::read_dir(root_dir).map_err(crate::config::Error::custom)? {
             let entry = entry.map_err(crate::config::Error::custom)?;
@@ -561,6 +1098,7 @@ where
                             path,
                             config: config.clone(),
                             extract_path: false,
+                            index_file: None,
                         }),
                     },
                 )?;
@@ -572,6 +1110,7 @@ where
                             path,
                             config: config.clone(),
                             extract_path: true,
+                            index_file: index_file.clone(),
                         }),
                     },
                 )?;
@@ -88,6 +88,141 @@ where
     self::poll_fn(move |input| (f.take().unwrap())(input).map(Into::into))
 }
 
+/// An extension trait that provides the combinators of `TryFuture`.
+///
+/// The combinators are lazy, that is, the provided closures are not called
+/// until the returned future is polled and the preceding future is completed.
+pub trait TryFutureExt: TryFuture + Sized {
+    /// Transforms the successful output of this future using the provided closure.
+    fn map<F, T>(self, f: F) -> Map<Self, F>
+    where
+        F: FnOnce(Self::Ok) -> T,
+    {
+        Map {
+            future: self,
+            f: Some(f),
+        }
+    }
+
+    /// Transforms the error of this future using the provided closure.
+    fn map_err<F, E>(self, f: F) -> MapErr<Self, F>
+    where
+        F: FnOnce(Self::Error) -> E,
+        E: Into<Error>,
+    {
+        MapErr {
+            future: self,
+            f: Some(f),
+        }
+    }
+
+    /// Chains a future created from the successful output of this future.
+    ///
+    /// The error types of both futures are unified into `Error`.
+    fn and_then<F, R>(self, f: F) -> AndThen<Self, R, F>
+    where
+        F: FnOnce(Self::Ok) -> R,
+        R: TryFuture,
+    {
+        AndThen {
+            state: AndThenState::First(self, Some(f)),
+        }
+    }
+}
+
+impl<F: TryFuture> TryFutureExt for F {}
+
+/// The future returned from `TryFutureExt::map`.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled."]
+pub struct Map<Fut, F> {
+    future: Fut,
+    f: Option<F>,
+}
+
+impl<Fut, F, T> TryFuture for Map<Fut, F>
+where
+    Fut: TryFuture,
+    F: FnOnce(Fut::Ok) -> T,
+{
+    type Ok = T;
+    type Error = Fut::Error;
+
+    #[inline]
+    fn poll_ready(&mut self, input: &mut Input<'_>) -> Poll<Self::Ok, Self::Error> {
+        let output = try_ready!(self.future.poll_ready(input));
+        let f = self.f.take().expect("the future has already been polled");
+        Ok(Async::Ready(f(output)))
+    }
+}
+
+/// The future returned from `TryFutureExt::map_err`.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled."]
+pub struct MapErr<Fut, F> {
+    future: Fut,
+    f: Option<F>,
+}
+
+impl<Fut, F, E> TryFuture for MapErr<Fut, F>
+where
+    Fut: TryFuture,
+    F: FnOnce(Fut::Error) -> E,
+    E: Into<Error>,
+{
+    type Ok = Fut::Ok;
+    type Error = E;
+
+    #[inline]
+    fn poll_ready(&mut self, input: &mut Input<'_>) -> Poll<Self::Ok, Self::Error> {
+        match self.future.poll_ready(input) {
+            Ok(polled) => Ok(polled),
+            Err(err) => {
+                let f = self.f.take().expect("the future has already been polled");
+                Err(f(err))
+            }
+        }
+    }
+}
+
+/// The future returned from `TryFutureExt::and_then`.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled."]
+pub struct AndThen<Fut, R, F> {
+    state: AndThenState<Fut, R, F>,
+}
+
+#[derive(Debug)]
+enum AndThenState<Fut, R, F> {
+    First(Fut, Option<F>),
+    Second(R),
+}
+
+impl<Fut, R, F> TryFuture for AndThen<Fut, R, F>
+where
+    Fut: TryFuture,
+    F: FnOnce(Fut::Ok) -> R,
+    R: TryFuture,
+{
+    type Ok = R::Ok;
+    type Error = Error;
+
+    fn poll_ready(&mut self, input: &mut Input<'_>) -> Poll<Self::Ok, Self::Error> {
+        loop {
+            self.state = match self.state {
+                AndThenState::First(ref mut future, ref mut f) => {
+                    let output = try_ready!(future.poll_ready(input).map_err(Into::into));
+                    let f = f.take().expect("the future has already been polled");
+                    AndThenState::Second(f(output))
+                }
+                AndThenState::Second(ref mut future) => {
+                    return future.poll_ready(input).map_err(Into::into);
+                }
+            };
+        }
+    }
+}
+
 /// A wrapper struct that provides the implementation of `TryFuture` for
 /// implementors of futures 0.1 `Future`.
 #[derive(Debug)]
@@ -170,3 +305,104 @@ impl<F: TryFuture> TryFuture for MaybeDone<F> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::input::{
+            clock::SystemClock, localmap::LocalMap, method::MethodSemantics, state::States, Cookies,
+        },
+        http::{Request, StatusCode},
+        std::marker::PhantomData,
+    };
+
+    fn with_input<R>(f: impl FnOnce(&mut Input<'_>) -> R) -> R {
+        let request = Request::new(());
+        let mut jar = None;
+        let mut cookies = Cookies::new(&mut jar, &request);
+        let mut locals = LocalMap::default();
+        let mut response_headers = None;
+        let states = States::default();
+        let method_semantics = MethodSemantics::default();
+        f(&mut Input {
+            request: &request,
+            params: &None,
+            cookies: &mut cookies,
+            locals: &mut locals,
+            response_headers: &mut response_headers,
+            states: &states,
+            scope_prefix: None,
+            route_pattern: None,
+            method_semantics: &method_semantics,
+            clock: &SystemClock,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Creates a future that is not ready at the first poll.
+    fn delayed<T, E>(result: Result<T, E>) -> impl TryFuture<Ok = T, Error = E>
+    where
+        E: Into<Error>,
+    {
+        let mut result = Some(result);
+        let mut polled = false;
+        poll_fn(move |_| {
+            if !polled {
+                polled = true;
+                return Ok(Async::NotReady);
+            }
+            result
+                .take()
+                .expect("the future has already been polled")
+                .map(Async::Ready)
+        })
+    }
+
+    #[test]
+    fn map() {
+        with_input(|input| {
+            let mut future = delayed(Ok::<_, Error>(21)).map(|x| x * 2);
+            assert!(future.poll_ready(input).unwrap().is_not_ready());
+            assert_eq!(future.poll_ready(input).unwrap(), Async::Ready(42));
+        })
+    }
+
+    #[test]
+    fn map_err() {
+        with_input(|input| {
+            let mut future =
+                delayed(Err::<(), _>(StatusCode::BAD_REQUEST)).map_err(|_| StatusCode::NOT_FOUND);
+            assert!(future.poll_ready(input).unwrap().is_not_ready());
+            assert_eq!(future.poll_ready(input), Err(StatusCode::NOT_FOUND));
+        })
+    }
+
+    #[test]
+    fn and_then() {
+        with_input(|input| {
+            let mut future =
+                delayed(Ok::<_, Error>(21)).and_then(|x| delayed(Ok::<_, Error>(x * 2)));
+            assert!(future.poll_ready(input).unwrap().is_not_ready());
+            assert!(future.poll_ready(input).unwrap().is_not_ready());
+            assert_eq!(future.poll_ready(input).unwrap(), Async::Ready(42));
+        })
+    }
+
+    #[test]
+    fn and_then_error() {
+        with_input(|input| {
+            let mut future = delayed(Err::<u32, _>(StatusCode::BAD_REQUEST)).and_then(
+                |_| -> Compat01<futures01::future::FutureResult<u32, Error>> {
+                    unreachable!("the closure should not be called")
+                },
+            );
+            assert!(future.poll_ready(input).unwrap().is_not_ready());
+            let err = future.poll_ready(input).unwrap_err();
+            assert_eq!(
+                err.downcast_ref::<StatusCode>(),
+                Some(&StatusCode::BAD_REQUEST)
+            );
+        })
+    }
+}
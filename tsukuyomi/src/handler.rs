@@ -100,6 +100,31 @@ where
     }
 }
 
+macro_rules! impl_try_from_for_arrays {
+    ($($N:expr),*) => {$(
+        impl<M> TryFrom<[M; $N]> for AllowedMethods
+        where
+            M: Clone,
+            Method: HttpTryFrom<M>,
+        {
+            type Error = http::Error;
+
+            #[inline]
+            fn try_from(methods: [M; $N]) -> std::result::Result<Self, Self::Error> {
+                let methods: Vec<_> = methods
+                    .iter()
+                    .cloned()
+                    .map(Method::try_from)
+                    .collect::<std::result::Result<_, _>>()
+                    .map_err(Into::into)?;
+                Ok(AllowedMethods::from_iter(methods))
+            }
+        }
+    )*};
+}
+
+impl_try_from_for_arrays!(1, 2, 3, 4, 5, 6, 7, 8, 9);
+
 impl<'a> TryFrom<&'a str> for AllowedMethods {
     type Error = failure::Error;
 
@@ -1,15 +1,29 @@
 //! Components for accessing the incoming request data.
 
 pub mod body;
+pub mod clock;
 pub mod header;
 pub mod localmap;
+pub mod method;
 pub mod param;
+pub mod state;
 
 use {
-    self::{localmap::LocalMap, param::Params},
+    self::{
+        clock::Clock,
+        localmap::{local_key, LocalData, LocalMap},
+        method::MethodSemantics,
+        param::Params,
+        state::States,
+    },
     cookie::{Cookie, CookieJar},
     http::{header::HeaderMap, Request},
-    std::{marker::PhantomData, rc::Rc},
+    std::{
+        borrow::Cow,
+        marker::PhantomData,
+        net::{IpAddr, SocketAddr},
+        rc::Rc,
+    },
 };
 
 /// A proxy object for accessing the incoming HTTP request data.
@@ -30,9 +44,263 @@ pub struct Input<'task> {
     /// A map of header fields that will be inserted at reply to the client.
     pub response_headers: &'task mut Option<HeaderMap>,
 
+    /// A set of shared states visible from the scope that the matched handler belongs to.
+    pub states: &'task States,
+
+    pub(crate) scope_prefix: Option<&'task str>,
+
+    pub(crate) route_pattern: Option<&'task str>,
+
+    pub(crate) method_semantics: &'task MethodSemantics,
+
+    pub(crate) clock: &'task dyn Clock,
+
     pub(crate) _marker: PhantomData<Rc<()>>,
 }
 
+impl<'task> Input<'task> {
+    /// Returns the remaining part of the request path after stripping the prefix of
+    /// the scope that the matched handler belongs to.
+    ///
+    /// This is useful for handlers that delegate the request to another service
+    /// (e.g. proxies), mounted as the default handler of a scope:
+    ///
+    /// ```ignore
+    /// mount("/api").with(path!("*").to(endpoint::any().call(...)))
+    /// ```
+    ///
+    /// In the above case, the request to `/api/users/42` yields `/users/42`.
+    /// The parameter segments in the prefix (e.g. `/tenants/:tenant`) are stripped as well.
+    /// The return value is `None` if the request path does not start with the prefix.
+    pub fn remaining_path(&self) -> Option<&'task str> {
+        let path = self.request.uri().path();
+        let prefix = self.scope_prefix.unwrap_or("/").trim_end_matches('/');
+        let len = crate::uri::match_prefix(prefix, path)?;
+        match &path[len..] {
+            "" => Some("/"),
+            remaining if remaining.starts_with('/') => Some(remaining),
+            _ => None,
+        }
+    }
+
+    /// Returns the pattern of the matched route, e.g. `/users/:id`.
+    ///
+    /// The returned value contains the prefixes of the scopes that the route belongs to,
+    /// and is suitable for grouping the requests in logging or metrics, rather than
+    /// the concrete request path. The return value is `None` if the request is
+    /// handled by a default handler (i.e. no route is matched).
+    pub fn matched_route_pattern(&self) -> Option<&'task str> {
+        self.route_pattern
+    }
+
+    /// Returns the semantics of request methods configured in the application.
+    ///
+    /// The features depending on whether the request method is safe, idempotent or
+    /// cacheable should consult this value rather than checking the method directly.
+    pub fn method_semantics(&self) -> &'task MethodSemantics {
+        self.method_semantics
+    }
+
+    /// Returns the clock configured in the application.
+    ///
+    /// By default, the clock returns the time of the system.
+    pub fn clock(&self) -> &'task dyn Clock {
+        self.clock
+    }
+
+    /// Returns `true` if the request is received over a secure connection.
+    ///
+    /// The request is considered to be secure if one of the following conditions is satisfied:
+    ///
+    /// * The scheme of request URI is `https`.
+    /// * A value of [`Secure`] is stored in the extensions of request. `tsukuyomi-server`
+    ///   inserts it if the connection is established by a TLS acceptor.
+    /// * A value of [`Secure`] is stored in the local map. The modifier
+    ///   [`trust_forwarded_proto`] stores it if a trusted proxy forwarded the request
+    ///   with `Forwarded: proto=https` or `X-Forwarded-Proto: https`.
+    ///
+    /// [`Secure`]: ./struct.Secure.html
+    /// [`trust_forwarded_proto`]: ../modifiers/fn.trust_forwarded_proto.html
+    pub fn is_secure(&self) -> bool {
+        self.request.uri().scheme_part() == Some(&http::uri::Scheme::HTTPS)
+            || self.request.extensions().get::<Secure>().is_some()
+            || self.locals.contains_key(&Secure::KEY)
+    }
+
+    /// Returns the value of the query parameter with the specified name, if exists.
+    ///
+    /// The query string is parsed as `application/x-www-form-urlencoded`, that is,
+    /// the names and values are percent-decoded and `+` is decoded as a space.
+    /// If the parameter occurs more than once, the first value is returned.
+    /// The parameter without `=` (e.g. `?foo`) has the empty value.
+    pub fn query_param(&self, name: &str) -> Option<Cow<'task, str>> {
+        self.query_params()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Returns an iterator of the decoded pairs of names and values in the query string.
+    ///
+    /// The pairs are yielded in the order of appearance, including the repeated names.
+    /// See [`query_param`] for how the names and values are decoded.
+    ///
+    /// [`query_param`]: #method.query_param
+    pub fn query_params(&self) -> impl Iterator<Item = (Cow<'task, str>, Cow<'task, str>)> {
+        let query = self.request.uri().query().unwrap_or("");
+        url::form_urlencoded::parse(query.as_bytes())
+    }
+
+    /// Returns the extensions of the request.
+    ///
+    /// The extensions contain the values inserted by the components outside of
+    /// the application, such as the server or the `ModifyService` wrapping the
+    /// `AppService` (e.g. the information of TLS peer certificates). Note that the
+    /// extensions cannot be modified within the application; the request-local values
+    /// shared among the handlers and modifiers should be stored in `locals` instead.
+    pub fn extensions(&self) -> &'task http::Extensions {
+        self.request.extensions()
+    }
+
+    /// Returns the address of the peer that sent the request, if available.
+    ///
+    /// The value is taken from the `SocketAddr` stored in the extensions of request,
+    /// which is expected to be inserted by the server (`tsukuyomi-server` inserts it
+    /// for the TCP connections). Note that the returned address is the direct peer of
+    /// the connection, which is not necessarily the origin client, e.g. when the
+    /// application is running behind a reverse proxy. See also [`forwarded_for`].
+    ///
+    /// [`forwarded_for`]: #method.forwarded_for
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.request.extensions().get::<SocketAddr>().cloned()
+    }
+
+    /// Returns the list of addresses in the header field `X-Forwarded-For`,
+    /// in the order from the origin client to the nearest proxy.
+    ///
+    /// The entries that are not valid IP addresses (e.g. `unknown`) are skipped.
+    /// Since the header field can be set arbitrarily by the client, the returned addresses
+    /// should be trusted only if the request is forwarded by a trusted proxy.
+    pub fn forwarded_for(&self) -> Vec<IpAddr> {
+        self.request
+            .headers()
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|addr| addr.trim().parse().ok())
+            .collect()
+    }
+
+    /// Returns the identifier of the current request, if assigned.
+    ///
+    /// The identifier is assigned by the modifier [`request_id`], which takes it from
+    /// the request header (`X-Request-Id` by default) or generates a new one.
+    ///
+    /// [`request_id`]: ../modifiers/fn.request_id.html
+    pub fn request_id(&self) -> Option<&str> {
+        RequestId::get(self.locals).map(|id| id.0.as_str())
+    }
+
+    /// Returns the scheme of the current request seen from the client, `"https"` or `"http"`.
+    ///
+    /// The scheme is determined by [`is_secure`](#method.is_secure), and hence the value
+    /// forwarded by the proxy is respected only if trusted by the modifier
+    /// [`trust_forwarded_proto`](../modifiers/fn.trust_forwarded_proto.html).
+    pub fn scheme(&self) -> &'static str {
+        if self.is_secure() {
+            "https"
+        } else {
+            "http"
+        }
+    }
+
+    /// Returns the host name (and the port) of the current request seen from the client.
+    ///
+    /// The host is taken from the value forwarded by the proxy (`Forwarded` or
+    /// `X-Forwarded-Host`) if trusted by the modifier [`trust_forwarded_host`],
+    /// the authority of request URI, or the header field `Host`, in this order.
+    /// If none of them is available, or the value of `Host` is not a valid string,
+    /// this method returns `None`.
+    ///
+    /// [`trust_forwarded_host`]: ../modifiers/fn.trust_forwarded_host.html
+    pub fn host(&self) -> Option<&str> {
+        if let Some(host) = self.locals.get(&ForwardedHost::KEY) {
+            return Some(host.0.as_str());
+        }
+        match self.request.uri().authority_part() {
+            Some(authority) => Some(authority.as_str()),
+            None => self
+                .request
+                .headers()
+                .get(http::header::HOST)
+                .and_then(|host| host.to_str().ok()),
+        }
+    }
+
+    /// Creates an absolute URL from the specified path, using the scheme and host
+    /// of the current request.
+    ///
+    /// The scheme and host are determined by [`scheme`](#method.scheme) and
+    /// [`host`](#method.host), respectively.
+    /// The characters that are not allowed in URLs are percent-encoded.
+    pub fn absolute_url(&self, path: &str) -> crate::error::Result<String> {
+        let scheme = self.scheme();
+        let host = self
+            .host()
+            .ok_or_else(|| crate::error::bad_request("missing or invalid Host"))?;
+        let base = url::Url::parse(&format!("{}://{}/", scheme, host))
+            .map_err(|_| crate::error::bad_request("invalid host name"))?;
+        let url = base
+            .join(path)
+            .ok()
+            // reject the path that changes the origin, e.g. `//example.com/`
+            .filter(|url| url.origin() == base.origin())
+            .ok_or_else(|| crate::error::internal_server_error("invalid path"))?;
+        Ok(url.into_string())
+    }
+
+    /// Appends a `Link` header field with `rel=preload` to the response.
+    ///
+    /// See also [`output::preload`](../output/fn.preload.html).
+    pub fn preload(&mut self, uri: &str, as_: &str) -> crate::error::Result<()> {
+        crate::output::preload(
+            self.response_headers.get_or_insert_with(Default::default),
+            uri,
+            as_,
+        )
+    }
+}
+
+/// See also [`Input::is_secure`](./struct.Input.html#method.is_secure).
+pub use tsukuyomi_service::Secure;
+
+impl LocalData for Secure {
+    local_key! {
+        /// The local key to mark the current request as secure.
+        const KEY: Self;
+    }
+}
+
+/// The host name forwarded by a trusted proxy.
+#[derive(Debug)]
+pub(crate) struct ForwardedHost(pub(crate) String);
+
+impl LocalData for ForwardedHost {
+    local_key! {
+        const KEY: Self;
+    }
+}
+
+/// The identifier of the current request assigned by `modifiers::request_id`.
+#[derive(Debug)]
+pub(crate) struct RequestId(pub(crate) String);
+
+impl LocalData for RequestId {
+    local_key! {
+        const KEY: Self;
+    }
+}
+
 /// A proxy object for accessing Cookie values.
 #[derive(Debug)]
 pub struct Cookies<'task> {
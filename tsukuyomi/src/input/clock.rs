@@ -0,0 +1,52 @@
+//! The source of the current time.
+
+use std::{fmt, time::SystemTime};
+
+/// A trait representing the source of the current time.
+///
+/// The features depending on the current time (e.g. the validation of `Last-Modified`)
+/// should obtain it through `Input::clock` rather than `SystemTime::now`, so that
+/// their behavior can be reproduced in the test cases by replacing the clock.
+pub trait Clock: Send + Sync + 'static {
+    /// Returns the current time.
+    fn now(&self) -> SystemTime;
+}
+
+impl fmt::Debug for dyn Clock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Clock").finish()
+    }
+}
+
+/// A `Clock` that returns the time of the system.
+///
+/// This is the default clock used in the application.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    #[inline]
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A `Clock` that always returns the specified time.
+///
+/// This is mainly used in the test cases. If a value of this type is stored in
+/// the extensions of request, it is used in place of the clock configured in the
+/// application. The test server can insert it into every request, as follows:
+///
+/// ```ignore
+/// let mut server = tsukuyomi_server::test::server(app)?
+///     .extension(FixedClock(UNIX_EPOCH + Duration::from_secs(1_000_000_000)));
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct FixedClock(pub SystemTime);
+
+impl Clock for FixedClock {
+    #[inline]
+    fn now(&self) -> SystemTime {
+        self.0
+    }
+}
@@ -0,0 +1,134 @@
+//! The semantics of request methods.
+
+use {http::Method, std::collections::HashMap};
+
+/// A set of properties of a request method.
+///
+/// See [RFC 7231, Section 4.2](https://tools.ietf.org/html/rfc7231#section-4.2)
+/// for the definition of each property. The framework itself consults only `cacheable`,
+/// and the other properties are provided for the application code (e.g. a proxy
+/// that retries the idempotent requests) through `MethodSemantics::get`.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Semantics {
+    /// Whether the method is essentially read-only.
+    pub safe: bool,
+
+    /// Whether the multiple identical requests have the same effect as a single request.
+    ///
+    /// The requests with idempotent methods can be retried automatically.
+    pub idempotent: bool,
+
+    /// Whether the responses to the method are allowed to be stored for future reuse.
+    pub cacheable: bool,
+}
+
+impl Semantics {
+    /// Returns the semantics of the method defined in RFC 7231 and RFC 5789.
+    ///
+    /// The responses to `POST` are treated as non-cacheable, since they are cacheable
+    /// only if the explicit freshness information is provided. The unknown methods are
+    /// considered to be neither safe, idempotent nor cacheable.
+    pub fn standard(method: &Method) -> Self {
+        match *method {
+            Method::GET | Method::HEAD => Semantics {
+                safe: true,
+                idempotent: true,
+                cacheable: true,
+            },
+            Method::OPTIONS | Method::TRACE => Semantics {
+                safe: true,
+                idempotent: true,
+                cacheable: false,
+            },
+            Method::PUT | Method::DELETE => Semantics {
+                safe: false,
+                idempotent: true,
+                cacheable: false,
+            },
+            _ => Semantics::default(),
+        }
+    }
+}
+
+/// A mapping from request methods to their semantics.
+///
+/// This value is shared among the features that depend on the semantics of request
+/// methods (e.g. retrying requests, synthesizing the responses to `HEAD` and caching).
+/// The methods that are not registered explicitly are resolved by `Semantics::standard`.
+///
+/// It also holds the fallback of methods, which is used when no endpoint accepts the
+/// request method explicitly. By default, `HEAD` falls back to `GET`.
+#[derive(Debug, Clone, Default)]
+pub struct MethodSemantics {
+    overrides: HashMap<Method, Semantics>,
+    fallbacks: HashMap<Method, Option<Method>>,
+}
+
+impl MethodSemantics {
+    /// Creates a new `MethodSemantics` initialized with the standard semantics.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Overrides the semantics of the specified method.
+    ///
+    /// This is mainly used to declare the semantics of extension methods,
+    /// e.g. `PROPFIND` in WebDAV.
+    pub fn set(mut self, method: Method, semantics: Semantics) -> Self {
+        self.overrides.insert(method, semantics);
+        self
+    }
+
+    /// Overrides the fallback of the specified method.
+    ///
+    /// If `None` is given, the method never falls back to the other one.
+    pub fn set_fallback(mut self, method: Method, fallback: Option<Method>) -> Self {
+        self.fallbacks.insert(method, fallback);
+        self
+    }
+
+    /// Returns the method that the endpoints accepting it handle the specified method,
+    /// if the method is not accepted explicitly.
+    pub fn fallback(&self, method: &Method) -> Option<Method> {
+        match self.fallbacks.get(method) {
+            Some(fallback) => fallback.clone(),
+            None if *method == Method::HEAD => Some(Method::GET),
+            None => None,
+        }
+    }
+
+    /// Returns `true` if the responses to the specified method must not have
+    /// the message body, i.e. the method is `HEAD`.
+    pub fn omits_response_body(&self, method: &Method) -> bool {
+        *method == Method::HEAD
+    }
+
+    /// Returns an iterator of the methods falling back to the other one, paired with
+    /// their fallbacks.
+    pub(crate) fn fallbacks(&self) -> impl Iterator<Item = (Method, Method)> + '_ {
+        let standard = Some((Method::HEAD, Method::GET))
+            .into_iter()
+            .filter(move |(method, _)| !self.fallbacks.contains_key(method));
+        let overrides = self
+            .fallbacks
+            .iter()
+            .filter_map(|(method, fallback)| Some((method.clone(), fallback.clone()?)));
+        standard.chain(overrides)
+    }
+
+    /// Returns the semantics of the specified method.
+    pub fn get(&self, method: &Method) -> Semantics {
+        self.overrides
+            .get(method)
+            .cloned()
+            .unwrap_or_else(|| Semantics::standard(method))
+    }
+
+    /// Returns `true` if the responses to the specified method are cacheable.
+    ///
+    /// `NamedFile` evaluates the conditional requests and appends the caching headers
+    /// (e.g. `Cache-Control` and `ETag`) only if this method returns `true`.
+    pub fn is_cacheable(&self, method: &Method) -> bool {
+        self.get(method).cacheable
+    }
+}
@@ -1,5 +1,5 @@
 use {
-    crate::{app::Captures, uri::CaptureNames},
+    crate::{app::Captures, uri::CaptureNames, util::Never},
     std::borrow::Cow,
     std::ops::Index,
     std::str::Utf8Error,
@@ -34,6 +34,15 @@ impl<'input> Params<'input> {
         self.path.get(s..e)
     }
 
+    /// Returns the percent-decoded bytes of catch-all parameter, if exists.
+    ///
+    /// Unlike the parameters extracted as `String` or `PathBuf`, the decoded
+    /// value is not required to be a valid UTF-8 sequence.
+    pub fn catch_all_bytes(&self) -> Option<Cow<'_, [u8]>> {
+        self.catch_all()
+            .map(|s| percent_decode(s.as_bytes()).into())
+    }
+
     /// Returns the value of parameter whose name is equal to `name`, if exists.
     pub fn name(&self, name: &str) -> Option<&str> {
         match name {
@@ -68,6 +77,15 @@ impl PercentEncoded {
         &*(s as *const str as *const Self)
     }
 
+    /// Returns the raw (percent-encoded) string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn decode_bytes(&self) -> Cow<'_, [u8]> {
+        percent_decode(self.0.as_bytes()).into()
+    }
+
     pub fn decode_utf8(&self) -> Result<Cow<'_, str>, Utf8Error> {
         percent_decode(self.0.as_bytes()).decode_utf8()
     }
@@ -113,6 +131,24 @@ impl_from_percent_encoded!(
     uuid::Uuid
 );
 
+impl FromPercentEncoded for Vec<u8> {
+    type Error = Never;
+
+    #[inline]
+    fn from_percent_encoded(s: &PercentEncoded) -> Result<Self, Self::Error> {
+        Ok(s.decode_bytes().into_owned())
+    }
+}
+
+impl FromPercentEncoded for bytes::Bytes {
+    type Error = Never;
+
+    #[inline]
+    fn from_percent_encoded(s: &PercentEncoded) -> Result<Self, Self::Error> {
+        Ok(Self::from(s.decode_bytes().into_owned()))
+    }
+}
+
 impl FromPercentEncoded for std::path::PathBuf {
     type Error = crate::Error;
 
@@ -0,0 +1,71 @@
+//! Components for accessing the shared states registered in the scopes.
+
+use {
+    crate::error::Error,
+    std::{
+        any::{Any, TypeId},
+        collections::HashMap,
+        fmt,
+        sync::Arc,
+    },
+};
+
+/// A set of shared states associated with a scope.
+///
+/// The states registered in a scope are also visible from its sub-scopes.
+/// If the same type of state is registered in both a scope and its ancestor,
+/// the one registered in the nearest scope is used.
+#[derive(Default, Clone)]
+pub struct States {
+    map: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl fmt::Debug for States {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("States").finish()
+    }
+}
+
+impl States {
+    pub(crate) fn insert<T>(&mut self, state: T)
+    where
+        T: Send + Sync + 'static,
+    {
+        self.map.insert(TypeId::of::<T>(), Arc::new(state));
+    }
+
+    /// Adds the states in the parent scope that are not overridden by this scope.
+    pub(crate) fn inherit(&mut self, parent: &Self) {
+        for (&id, state) in &parent.map {
+            self.map.entry(id).or_insert_with(|| state.clone());
+        }
+    }
+
+    /// Returns `true` if the state of the specified type is registered.
+    pub fn contains<T>(&self) -> bool
+    where
+        T: Send + Sync + 'static,
+    {
+        self.map.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Returns the reference to the state of the specified type, if available.
+    pub fn get<T>(&self) -> Option<&T>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.map.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+
+    /// Returns the reference to the state of the specified type.
+    ///
+    /// If the state is not registered, this method returns an error
+    /// with the status code `500 Internal Server Error`.
+    pub fn try_get<T>(&self) -> Result<&T, Error>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.get()
+            .ok_or_else(|| crate::error::internal_server_error("the state is not registered"))
+    }
+}
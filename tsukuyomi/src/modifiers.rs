@@ -1,6 +1,20 @@
 //! A set of built-in `ModifyHandler`s.
 
-pub use self::{default_options::DefaultOptions, map_output::MapOutput};
+pub use self::{
+    content_length_limit::ContentLengthLimit, //
+    default_options::DefaultOptions,
+    instrumented::{Instrumented, Metrics},
+    logger::{LogRecord, Logger},
+    map_output::MapOutput,
+    problem_json::ProblemJson,
+    rate_limit::RateLimit,
+    request_id::RequestId,
+    security_headers::SecurityHeaders,
+    trust_forwarded::{TrustForwardedHost, TrustForwardedProto},
+};
+
+#[cfg(feature = "tracing")]
+pub use self::trace::Trace;
 
 /// Creates a `ModifyHandler` that overwrites the handling when receiving `OPTIONS`.
 pub fn default_options() -> DefaultOptions {
@@ -186,3 +200,1712 @@ mod map_output {
         }
     }
 }
+
+/// Creates a `ModifyHandler` that trusts the scheme forwarded by the reverse proxy.
+///
+/// The scheme is taken from the parameter `proto` in the first element of `Forwarded`
+/// (RFC 7239), or the first value of `X-Forwarded-Proto` if the former is missing.
+/// If it is `https`, the request is marked as secure and `Input::is_secure` returns `true`.
+/// This modifier should be used only if the application is deployed behind a trusted proxy,
+/// since the header fields can be set arbitrarily by the clients.
+pub fn trust_forwarded_proto() -> TrustForwardedProto {
+    TrustForwardedProto(())
+}
+
+/// Creates a `ModifyHandler` that trusts the host name forwarded by the reverse proxy.
+///
+/// The host name is taken from the parameter `host` in the first element of `Forwarded`,
+/// or the first value of `X-Forwarded-Host` if the former is missing, and is used
+/// by `Input::host` and `Input::absolute_url`. As with `trust_forwarded_proto`,
+/// this modifier should be used only behind a trusted proxy.
+pub fn trust_forwarded_host() -> TrustForwardedHost {
+    TrustForwardedHost(())
+}
+
+mod trust_forwarded {
+    use crate::{
+        future::{Poll, TryFuture},
+        handler::{AllowedMethods, Handler, ModifyHandler},
+        input::{localmap::LocalData, ForwardedHost, Input, Secure},
+    };
+
+    pub trait TrustForwarded: Clone {
+        fn apply(input: &mut Input<'_>);
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct TrustForwardedProto(pub(super) ());
+
+    impl TrustForwarded for TrustForwardedProto {
+        fn apply(input: &mut Input<'_>) {
+            if forwarded_param(input, "proto")
+                .or_else(|| first_value(input, "x-forwarded-proto"))
+                .map_or(false, |proto| proto.eq_ignore_ascii_case("https"))
+            {
+                Secure.insert_into(input.locals);
+            }
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct TrustForwardedHost(pub(super) ());
+
+    impl TrustForwarded for TrustForwardedHost {
+        fn apply(input: &mut Input<'_>) {
+            if let Some(host) = forwarded_param(input, "host")
+                .or_else(|| first_value(input, "x-forwarded-host"))
+                .map(ToOwned::to_owned)
+            {
+                ForwardedHost(host).insert_into(input.locals);
+            }
+        }
+    }
+
+    /// Returns the value of the specified parameter in the first element of `Forwarded`.
+    fn forwarded_param<'a>(input: &'a Input<'_>, name: &str) -> Option<&'a str> {
+        first_value(input, "forwarded")?
+            .split(';')
+            .filter_map(|pair| {
+                let mut parts = pair.splitn(2, '=');
+                Some((parts.next()?.trim(), parts.next()?.trim()))
+            })
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.trim_matches('"'))
+            .filter(|value| !value.is_empty())
+    }
+
+    fn first_value<'a>(input: &'a Input<'_>, name: &str) -> Option<&'a str> {
+        input
+            .request
+            .headers()
+            .get(name)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|s| s.split(',').next())
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+    }
+
+    macro_rules! impl_modify_handler {
+        ($($t:ty),*) => {$(
+            impl<H> ModifyHandler<H> for $t
+            where
+                H: Handler,
+            {
+                type Output = H::Output;
+                type Handler = TrustForwardedHandler<H, Self>; // private
+
+                fn modify(&self, inner: H) -> Self::Handler {
+                    TrustForwardedHandler {
+                        inner,
+                        _marker: std::marker::PhantomData,
+                    }
+                }
+            }
+        )*};
+    }
+
+    impl_modify_handler!(TrustForwardedProto, TrustForwardedHost);
+
+    #[allow(missing_debug_implementations)]
+    pub struct TrustForwardedHandler<H, T> {
+        inner: H,
+        _marker: std::marker::PhantomData<fn() -> T>,
+    }
+
+    impl<H, T> Handler for TrustForwardedHandler<H, T>
+    where
+        H: Handler,
+        T: TrustForwarded,
+    {
+        type Output = H::Output;
+        type Error = H::Error;
+        type Handle = HandleTrustForwarded<H::Handle, T>;
+
+        fn handle(&self) -> Self::Handle {
+            HandleTrustForwarded {
+                inner: self.inner.handle(),
+                applied: false,
+                _marker: std::marker::PhantomData,
+            }
+        }
+
+        fn allowed_methods(&self) -> Option<&AllowedMethods> {
+            self.inner.allowed_methods()
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    pub struct HandleTrustForwarded<H, T> {
+        inner: H,
+        applied: bool,
+        _marker: std::marker::PhantomData<fn() -> T>,
+    }
+
+    impl<H, T> TryFuture for HandleTrustForwarded<H, T>
+    where
+        H: TryFuture,
+        T: TrustForwarded,
+    {
+        type Ok = H::Ok;
+        type Error = H::Error;
+
+        #[inline]
+        fn poll_ready(&mut self, input: &mut Input<'_>) -> Poll<Self::Ok, Self::Error> {
+            if !self.applied {
+                self.applied = true;
+                T::apply(input);
+            }
+            self.inner.poll_ready(input)
+        }
+    }
+}
+
+/// Creates a `ModifyHandler` that records the metrics of the wrapped handler.
+///
+/// The recorded values are shared among the clones of the returned value,
+/// and can be obtained via `Instrumented::metrics`.
+pub fn instrumented() -> Instrumented {
+    Instrumented::default()
+}
+
+mod instrumented {
+    use {
+        crate::{
+            future::{Async, Poll, TryFuture},
+            handler::{AllowedMethods, Handler, ModifyHandler},
+            input::Input,
+        },
+        std::{
+            sync::{
+                atomic::{AtomicUsize, Ordering},
+                Arc, Mutex,
+            },
+            time::{Duration, Instant},
+        },
+    };
+
+    /// A `ModifyHandler` that records the number of calls and the elapsed time
+    /// of the wrapped handler.
+    ///
+    /// Unlike the modifiers applied to the entire scope, the metrics are recorded
+    /// only for the handlers that this value is applied to, e.g.
+    /// `path!("/").to(endpoint).modify(instrumented.clone())`.
+    #[derive(Debug, Clone, Default)]
+    pub struct Instrumented {
+        metrics: Arc<Metrics>,
+    }
+
+    impl Instrumented {
+        /// Returns a reference to the metrics recorded by this modifier.
+        pub fn metrics(&self) -> &Arc<Metrics> {
+            &self.metrics
+        }
+    }
+
+    impl<H> ModifyHandler<H> for Instrumented
+    where
+        H: Handler,
+    {
+        type Output = H::Output;
+        type Handler = InstrumentedHandler<H>; // private
+
+        fn modify(&self, inner: H) -> Self::Handler {
+            InstrumentedHandler {
+                inner,
+                metrics: self.metrics.clone(),
+            }
+        }
+    }
+
+    /// The metrics recorded by `Instrumented`.
+    ///
+    /// The elapsed time is measured from the first poll of the handler to its completion.
+    /// The calls cancelled before the completion are not recorded.
+    #[derive(Debug, Default)]
+    pub struct Metrics {
+        in_flight: AtomicUsize,
+        completed: AtomicUsize,
+        failed: AtomicUsize,
+        elapsed: Mutex<Elapsed>,
+    }
+
+    #[derive(Debug, Default)]
+    struct Elapsed {
+        total: Duration,
+        max: Duration,
+    }
+
+    impl Metrics {
+        /// Returns the number of calls currently being processed.
+        pub fn in_flight(&self) -> usize {
+            self.in_flight.load(Ordering::SeqCst)
+        }
+
+        /// Returns the number of completed calls, including the failed ones.
+        pub fn completed(&self) -> usize {
+            self.completed.load(Ordering::SeqCst)
+        }
+
+        /// Returns the number of calls completed with an error.
+        pub fn failed(&self) -> usize {
+            self.failed.load(Ordering::SeqCst)
+        }
+
+        /// Returns the total elapsed time of the completed calls.
+        pub fn total_time(&self) -> Duration {
+            self.elapsed.lock().expect("the lock is poisoned").total
+        }
+
+        /// Returns the longest elapsed time of the completed calls.
+        pub fn max_time(&self) -> Duration {
+            self.elapsed.lock().expect("the lock is poisoned").max
+        }
+
+        /// Returns the average elapsed time of the completed calls, if any.
+        pub fn mean_time(&self) -> Option<Duration> {
+            let completed = self.completed() as u32;
+            if completed > 0 {
+                Some(self.total_time() / completed)
+            } else {
+                None
+            }
+        }
+
+        fn record(&self, elapsed: Duration, is_err: bool) {
+            {
+                let mut e = self.elapsed.lock().expect("the lock is poisoned");
+                e.total += elapsed;
+                if elapsed > e.max {
+                    e.max = elapsed;
+                }
+            }
+            if is_err {
+                self.failed.fetch_add(1, Ordering::SeqCst);
+            }
+            self.completed.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    pub struct InstrumentedHandler<H> {
+        inner: H,
+        metrics: Arc<Metrics>,
+    }
+
+    impl<H> Handler for InstrumentedHandler<H>
+    where
+        H: Handler,
+    {
+        type Output = H::Output;
+        type Error = H::Error;
+        type Handle = HandleInstrumented<H::Handle>;
+
+        fn handle(&self) -> Self::Handle {
+            HandleInstrumented {
+                inner: self.inner.handle(),
+                metrics: self.metrics.clone(),
+                started: None,
+            }
+        }
+
+        fn allowed_methods(&self) -> Option<&AllowedMethods> {
+            self.inner.allowed_methods()
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    pub struct HandleInstrumented<H> {
+        inner: H,
+        metrics: Arc<Metrics>,
+        started: Option<Instant>,
+    }
+
+    impl<H> TryFuture for HandleInstrumented<H>
+    where
+        H: TryFuture,
+    {
+        type Ok = H::Ok;
+        type Error = H::Error;
+
+        #[inline]
+        fn poll_ready(&mut self, input: &mut Input<'_>) -> Poll<Self::Ok, Self::Error> {
+            if self.started.is_none() {
+                self.started = Some(Instant::now());
+                self.metrics.in_flight.fetch_add(1, Ordering::SeqCst);
+            }
+
+            let polled = self.inner.poll_ready(input);
+            let is_err = match polled {
+                Ok(Async::NotReady) => return polled,
+                Ok(Async::Ready(..)) => false,
+                Err(..) => true,
+            };
+            if let Some(started) = self.started.take() {
+                self.metrics.in_flight.fetch_sub(1, Ordering::SeqCst);
+                self.metrics.record(started.elapsed(), is_err);
+            }
+            polled
+        }
+    }
+
+    impl<H> Drop for HandleInstrumented<H> {
+        fn drop(&mut self) {
+            if self.started.take().is_some() {
+                self.metrics.in_flight.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+    }
+}
+
+/// Creates a `ModifyHandler` that logs the requests processed by the wrapped handler.
+///
+/// The log records are emitted via the `log` crate at the level `Info` by default.
+pub fn logger() -> Logger {
+    Logger::default()
+}
+
+mod logger {
+    use {
+        crate::{
+            error::Error,
+            future::{Async, Poll, TryFuture},
+            handler::{AllowedMethods, Handler, ModifyHandler},
+            input::{
+                localmap::{local_key, LocalData},
+                Input,
+            },
+            output::{IntoResponse, ResponseBody},
+            util::Never,
+        },
+        http::{Method, Response, StatusCode},
+        std::{
+            fmt,
+            sync::Arc,
+            time::{Duration, Instant},
+        },
+    };
+
+    /// A `ModifyHandler` that logs the method, path, status and the elapsed time
+    /// of each request.
+    ///
+    /// The output of the wrapped handler is converted into an HTTP response in order
+    /// to obtain its status code. The errors are also converted into responses at
+    /// this point, and hence the modifiers applied outside of this one never observe
+    /// the errors from the handler.
+    #[derive(Clone)]
+    pub struct Logger {
+        level: log::Level,
+        format: Arc<dyn Fn(&LogRecord<'_>) -> String + Send + Sync + 'static>,
+    }
+
+    impl fmt::Debug for Logger {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("Logger")
+                .field("level", &self.level)
+                .finish()
+        }
+    }
+
+    impl Default for Logger {
+        fn default() -> Self {
+            Self {
+                level: log::Level::Info,
+                format: Arc::new(|record| {
+                    let mut message = format!(
+                        "{} {} {} ({:?})",
+                        record.method(),
+                        record.path(),
+                        record.status().as_u16(),
+                        record.elapsed()
+                    );
+                    if let Some(request_id) = record.request_id() {
+                        message += &format!(" [{}]", request_id);
+                    }
+                    message
+                }),
+            }
+        }
+    }
+
+    impl Logger {
+        /// Sets the level of log records emitted by this modifier.
+        pub fn level(self, level: log::Level) -> Self {
+            Self { level, ..self }
+        }
+
+        /// Sets the function that formats the log messages.
+        ///
+        /// The function is not called if the level of this modifier is disabled.
+        pub fn format<F>(self, f: F) -> Self
+        where
+            F: Fn(&LogRecord<'_>) -> String + Send + Sync + 'static,
+        {
+            Self {
+                format: Arc::new(f),
+                ..self
+            }
+        }
+    }
+
+    /// The information about a processed request passed to the format function of `Logger`.
+    #[derive(Debug)]
+    pub struct LogRecord<'a> {
+        method: &'a Method,
+        path: &'a str,
+        status: StatusCode,
+        elapsed: Duration,
+        request_id: Option<&'a str>,
+    }
+
+    impl<'a> LogRecord<'a> {
+        /// Returns the method of the request.
+        pub fn method(&self) -> &Method {
+            self.method
+        }
+
+        /// Returns the path of the request URI.
+        pub fn path(&self) -> &str {
+            self.path
+        }
+
+        /// Returns the status code of the response.
+        pub fn status(&self) -> StatusCode {
+            self.status
+        }
+
+        /// Returns the elapsed time from the start of handling to the completion.
+        pub fn elapsed(&self) -> Duration {
+            self.elapsed
+        }
+
+        /// Returns the identifier of the request assigned by `request_id`, if exists.
+        pub fn request_id(&self) -> Option<&str> {
+            self.request_id
+        }
+    }
+
+    /// The time when the handling of the current request has started.
+    ///
+    /// When multiple `Logger`s are applied, the earliest one is shared among them.
+    #[derive(Debug)]
+    struct StartedAt(Instant);
+
+    impl LocalData for StartedAt {
+        local_key! {
+            const KEY: Self;
+        }
+    }
+
+    impl<H> ModifyHandler<H> for Logger
+    where
+        H: Handler,
+        H::Output: IntoResponse,
+    {
+        type Output = Response<ResponseBody>;
+        type Handler = LoggerHandler<H>; // private
+
+        fn modify(&self, inner: H) -> Self::Handler {
+            LoggerHandler {
+                inner,
+                logger: self.clone(),
+            }
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    pub struct LoggerHandler<H> {
+        inner: H,
+        logger: Logger,
+    }
+
+    impl<H> Handler for LoggerHandler<H>
+    where
+        H: Handler,
+        H::Output: IntoResponse,
+    {
+        type Output = Response<ResponseBody>;
+        type Error = Never;
+        type Handle = HandleLogger<H::Handle>;
+
+        fn handle(&self) -> Self::Handle {
+            HandleLogger {
+                inner: self.inner.handle(),
+                logger: self.logger.clone(),
+                started: false,
+            }
+        }
+
+        fn allowed_methods(&self) -> Option<&AllowedMethods> {
+            self.inner.allowed_methods()
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    pub struct HandleLogger<H> {
+        inner: H,
+        logger: Logger,
+        started: bool,
+    }
+
+    impl<H> TryFuture for HandleLogger<H>
+    where
+        H: TryFuture,
+        H::Ok: IntoResponse,
+        H::Error: Into<Error>,
+    {
+        type Ok = Response<ResponseBody>;
+        type Error = Never;
+
+        fn poll_ready(&mut self, input: &mut Input<'_>) -> Poll<Self::Ok, Self::Error> {
+            if !self.started {
+                self.started = true;
+                StartedAt::entry(input.locals).or_insert_with(|| StartedAt(Instant::now()));
+            }
+
+            let response = match self.inner.poll_ready(input) {
+                Ok(Async::NotReady) => return Ok(Async::NotReady),
+                Ok(Async::Ready(output)) => output
+                    .into_response(input.request)
+                    .map(|response| response.map(Into::into))
+                    .unwrap_or_else(|err| Into::<Error>::into(err).into_response(input.request)),
+                Err(err) => Into::<Error>::into(err).into_response(input.request),
+            };
+
+            if log::log_enabled!(self.logger.level) {
+                let elapsed = StartedAt::get(input.locals)
+                    .map(|started| started.0.elapsed())
+                    .unwrap_or_default();
+                let record = LogRecord {
+                    method: input.request.method(),
+                    path: input.request.uri().path(),
+                    status: response.status(),
+                    elapsed,
+                    request_id: input.request_id(),
+                };
+                log::log!(self.logger.level, "{}", (self.logger.format)(&record));
+            }
+
+            Ok(Async::Ready(response))
+        }
+    }
+}
+
+/// Creates a `ModifyHandler` that limits the length of request bodies.
+///
+/// The requests whose `Content-Length` exceeds the limit are rejected with
+/// `413 Payload Too Large` before calling the wrapped handler, or with
+/// `417 Expectation Failed` if the client is waiting for `100 Continue`
+/// before sending the body. If the header is missing (e.g. chunked requests),
+/// the request body is replaced with the one that fails when the received length
+/// exceeds the limit, and the error from the handler is replaced with
+/// `413 Payload Too Large`.
+pub fn content_length_limit(limit: u64) -> ContentLengthLimit {
+    ContentLengthLimit { limit }
+}
+
+mod content_length_limit {
+    use {
+        crate::{
+            error::Error,
+            future::{Async, Poll, TryFuture},
+            handler::{AllowedMethods, Handler, ModifyHandler},
+            input::{body::RequestBody, localmap::LocalData, Input},
+        },
+        futures01::Stream,
+        http::{
+            header::{CONTENT_LENGTH, EXPECT},
+            StatusCode,
+        },
+        hyper::body::{Body, Payload},
+        std::sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+    };
+
+    #[derive(Debug, Clone)]
+    pub struct ContentLengthLimit {
+        pub(super) limit: u64,
+    }
+
+    impl<H> ModifyHandler<H> for ContentLengthLimit
+    where
+        H: Handler,
+    {
+        type Output = H::Output;
+        type Handler = ContentLengthLimitHandler<H>; // private
+
+        fn modify(&self, inner: H) -> Self::Handler {
+            ContentLengthLimitHandler {
+                inner,
+                limit: self.limit,
+            }
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    pub struct ContentLengthLimitHandler<H> {
+        inner: H,
+        limit: u64,
+    }
+
+    impl<H> Handler for ContentLengthLimitHandler<H>
+    where
+        H: Handler,
+    {
+        type Output = H::Output;
+        type Error = Error;
+        type Handle = HandleContentLengthLimit<H::Handle>;
+
+        fn handle(&self) -> Self::Handle {
+            HandleContentLengthLimit {
+                inner: self.inner.handle(),
+                limit: self.limit,
+                checked: false,
+                exceeded: Arc::new(AtomicBool::new(false)),
+            }
+        }
+
+        fn allowed_methods(&self) -> Option<&AllowedMethods> {
+            self.inner.allowed_methods()
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    pub struct HandleContentLengthLimit<H> {
+        inner: H,
+        limit: u64,
+        checked: bool,
+        exceeded: Arc<AtomicBool>,
+    }
+
+    impl<H> TryFuture for HandleContentLengthLimit<H>
+    where
+        H: TryFuture,
+        H::Error: Into<Error>,
+    {
+        type Ok = H::Ok;
+        type Error = Error;
+
+        fn poll_ready(&mut self, input: &mut Input<'_>) -> Poll<Self::Ok, Self::Error> {
+            if !self.checked {
+                self.checked = true;
+
+                let content_length = input
+                    .request
+                    .headers()
+                    .get(CONTENT_LENGTH)
+                    .and_then(|h| h.to_str().ok())
+                    .and_then(|s| s.parse::<u64>().ok());
+                match content_length {
+                    Some(len) if len > self.limit => {
+                        // the client waiting for `100 Continue` has not sent the body yet.
+                        let expects_continue =
+                            input.request.headers().get(EXPECT).map_or(false, |h| {
+                                h.as_bytes().eq_ignore_ascii_case(b"100-continue")
+                            });
+                        if expects_continue {
+                            return Err(crate::error::custom(
+                                StatusCode::EXPECTATION_FAILED,
+                                "the length of request body exceeds the limit",
+                            ));
+                        }
+                        return Err(payload_too_large());
+                    }
+                    Some(..) => {}
+                    None => {
+                        if let Some(body) = RequestBody::take_from(input.locals) {
+                            let limited = LimitedBody {
+                                body,
+                                remaining: self.limit,
+                                exceeded: self.exceeded.clone(),
+                            };
+                            RequestBody::from(Body::wrap_stream(limited)).insert_into(input.locals);
+                        }
+                    }
+                }
+            }
+
+            let exceeded = &self.exceeded;
+            self.inner.poll_ready(input).map_err(|err| {
+                if exceeded.load(Ordering::SeqCst) {
+                    payload_too_large()
+                } else {
+                    err.into()
+                }
+            })
+        }
+    }
+
+    fn payload_too_large() -> Error {
+        crate::error::custom(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "the length of request body exceeds the limit",
+        )
+    }
+
+    /// A stream of request body that fails when the received length exceeds the limit.
+    struct LimitedBody {
+        body: RequestBody,
+        remaining: u64,
+        exceeded: Arc<AtomicBool>,
+    }
+
+    impl Stream for LimitedBody {
+        type Item = hyper::Chunk;
+        type Error = Box<dyn std::error::Error + Send + Sync>;
+
+        fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+            match futures01::try_ready!(self.body.poll_data()) {
+                Some(chunk) => {
+                    let len = chunk.len() as u64;
+                    if len > self.remaining {
+                        self.exceeded.store(true, Ordering::SeqCst);
+                        return Err("the length of request body exceeds the limit".into());
+                    }
+                    self.remaining -= len;
+                    Ok(Async::Ready(Some(chunk)))
+                }
+                None => Ok(Async::Ready(None)),
+            }
+        }
+    }
+}
+
+/// Creates a `ModifyHandler` that adds the header fields related to security
+/// to the responses.
+///
+/// See [`SecurityHeaders`](./struct.SecurityHeaders.html) for the default values.
+pub fn security_headers() -> SecurityHeaders {
+    SecurityHeaders::default()
+}
+
+mod security_headers {
+    use {
+        crate::{
+            error::Error,
+            future::{Async, Poll, TryFuture},
+            handler::{AllowedMethods, Handler, ModifyHandler},
+            input::Input,
+            output::{IntoResponse, ResponseBody},
+        },
+        http::{
+            header::{
+                HeaderName, HeaderValue, CONTENT_SECURITY_POLICY, REFERRER_POLICY,
+                STRICT_TRANSPORT_SECURITY, X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS,
+            },
+            HttpTryFrom, Response,
+        },
+        std::{sync::Arc, time::Duration},
+    };
+
+    /// A `ModifyHandler` that adds the header fields related to security to the responses.
+    ///
+    /// The following header fields are added by default:
+    ///
+    /// * `X-Content-Type-Options: nosniff`
+    /// * `X-Frame-Options: SAMEORIGIN`
+    /// * `Referrer-Policy: strict-origin-when-cross-origin`
+    ///
+    /// `Strict-Transport-Security` and `Content-Security-Policy` are added only if
+    /// they are configured explicitly. The header fields already set by the handler
+    /// are left unchanged. The errors from the handler are passed through as they are.
+    #[derive(Debug, Clone)]
+    pub struct SecurityHeaders {
+        content_type_options: bool,
+        frame_options: Option<HeaderValue>,
+        referrer_policy: Option<HeaderValue>,
+        strict_transport_security: Option<HeaderValue>,
+        content_security_policy: Option<HeaderValue>,
+    }
+
+    impl Default for SecurityHeaders {
+        fn default() -> Self {
+            Self {
+                content_type_options: true,
+                frame_options: Some(HeaderValue::from_static("SAMEORIGIN")),
+                referrer_policy: Some(HeaderValue::from_static("strict-origin-when-cross-origin")),
+                strict_transport_security: None,
+                content_security_policy: None,
+            }
+        }
+    }
+
+    impl SecurityHeaders {
+        /// Sets whether to add `X-Content-Type-Options: nosniff`.
+        pub fn content_type_options(self, enabled: bool) -> Self {
+            Self {
+                content_type_options: enabled,
+                ..self
+            }
+        }
+
+        /// Sets the value of `X-Frame-Options`.
+        pub fn frame_options<T>(self, value: T) -> http::Result<Self>
+        where
+            HeaderValue: HttpTryFrom<T>,
+        {
+            Ok(Self {
+                frame_options: Some(HeaderValue::try_from(value).map_err(Into::into)?),
+                ..self
+            })
+        }
+
+        /// Disables the header field `X-Frame-Options`.
+        pub fn disable_frame_options(self) -> Self {
+            Self {
+                frame_options: None,
+                ..self
+            }
+        }
+
+        /// Sets the value of `Referrer-Policy`.
+        pub fn referrer_policy<T>(self, value: T) -> http::Result<Self>
+        where
+            HeaderValue: HttpTryFrom<T>,
+        {
+            Ok(Self {
+                referrer_policy: Some(HeaderValue::try_from(value).map_err(Into::into)?),
+                ..self
+            })
+        }
+
+        /// Disables the header field `Referrer-Policy`.
+        pub fn disable_referrer_policy(self) -> Self {
+            Self {
+                referrer_policy: None,
+                ..self
+            }
+        }
+
+        /// Enables the header field `Strict-Transport-Security` with the specified parameters.
+        pub fn strict_transport_security(
+            self,
+            max_age: Duration,
+            include_subdomains: bool,
+        ) -> Self {
+            let value = if include_subdomains {
+                format!("max-age={}; includeSubDomains", max_age.as_secs())
+            } else {
+                format!("max-age={}", max_age.as_secs())
+            };
+            Self {
+                strict_transport_security: Some(
+                    HeaderValue::from_str(&value).expect("should be a valid header value"),
+                ),
+                ..self
+            }
+        }
+
+        /// Enables the header field `Content-Security-Policy` with the specified value.
+        pub fn content_security_policy<T>(self, value: T) -> http::Result<Self>
+        where
+            HeaderValue: HttpTryFrom<T>,
+        {
+            Ok(Self {
+                content_security_policy: Some(HeaderValue::try_from(value).map_err(Into::into)?),
+                ..self
+            })
+        }
+
+        fn headers(&self) -> Vec<(HeaderName, HeaderValue)> {
+            let mut headers = vec![];
+            if self.content_type_options {
+                headers.push((X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff")));
+            }
+            let optional_headers = vec![
+                (X_FRAME_OPTIONS, &self.frame_options),
+                (REFERRER_POLICY, &self.referrer_policy),
+                (STRICT_TRANSPORT_SECURITY, &self.strict_transport_security),
+                (CONTENT_SECURITY_POLICY, &self.content_security_policy),
+            ];
+            for (name, value) in optional_headers {
+                if let Some(value) = value {
+                    headers.push((name, value.clone()));
+                }
+            }
+            headers
+        }
+    }
+
+    impl<H> ModifyHandler<H> for SecurityHeaders
+    where
+        H: Handler,
+        H::Output: IntoResponse,
+    {
+        type Output = Response<ResponseBody>;
+        type Handler = SecurityHeadersHandler<H>; // private
+
+        fn modify(&self, inner: H) -> Self::Handler {
+            SecurityHeadersHandler {
+                inner,
+                headers: Arc::new(self.headers()),
+            }
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    pub struct SecurityHeadersHandler<H> {
+        inner: H,
+        headers: Arc<Vec<(HeaderName, HeaderValue)>>,
+    }
+
+    impl<H> Handler for SecurityHeadersHandler<H>
+    where
+        H: Handler,
+        H::Output: IntoResponse,
+    {
+        type Output = Response<ResponseBody>;
+        type Error = Error;
+        type Handle = HandleSecurityHeaders<H::Handle>;
+
+        fn handle(&self) -> Self::Handle {
+            HandleSecurityHeaders {
+                inner: self.inner.handle(),
+                headers: self.headers.clone(),
+            }
+        }
+
+        fn allowed_methods(&self) -> Option<&AllowedMethods> {
+            self.inner.allowed_methods()
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    pub struct HandleSecurityHeaders<H> {
+        inner: H,
+        headers: Arc<Vec<(HeaderName, HeaderValue)>>,
+    }
+
+    impl<H> TryFuture for HandleSecurityHeaders<H>
+    where
+        H: TryFuture,
+        H::Ok: IntoResponse,
+        H::Error: Into<Error>,
+    {
+        type Ok = Response<ResponseBody>;
+        type Error = Error;
+
+        fn poll_ready(&mut self, input: &mut Input<'_>) -> Poll<Self::Ok, Self::Error> {
+            let output =
+                futures01::try_ready!(self.inner.poll_ready(input).map_err(Into::<Error>::into));
+            let mut response = output
+                .into_response(input.request)
+                .map(|response| response.map(Into::into))
+                .map_err(Into::<Error>::into)?;
+            for (name, value) in self.headers.iter() {
+                if !response.headers().contains_key(name) {
+                    response.headers_mut().insert(name.clone(), value.clone());
+                }
+            }
+            Ok(Async::Ready(response))
+        }
+    }
+}
+
+/// Creates a `ModifyHandler` that renders the errors from the wrapped handler
+/// as the problem details defined in RFC 7807.
+///
+/// The error is rendered as an `application/problem+json` response which has the members
+/// `status`, `title` and `detail`. The status code and the header fields are taken from the
+/// response created by the error, and the additional members can be provided by
+/// `HttpError::problem_details`.
+pub fn problem_json() -> ProblemJson {
+    ProblemJson(())
+}
+
+mod problem_json {
+    use {
+        crate::{
+            error::{Error, HttpError, Rejection},
+            future::{Poll, TryFuture},
+            handler::{AllowedMethods, Handler, ModifyHandler},
+            input::Input,
+        },
+        http::{
+            header::{HeaderValue, CONTENT_LENGTH, CONTENT_TYPE},
+            Request, Response,
+        },
+        serde_json::Value,
+        std::fmt,
+    };
+
+    #[derive(Debug, Clone)]
+    pub struct ProblemJson(pub(super) ());
+
+    impl<H> ModifyHandler<H> for ProblemJson
+    where
+        H: Handler,
+    {
+        type Output = H::Output;
+        type Handler = ProblemJsonHandler<H>; // private
+
+        fn modify(&self, inner: H) -> Self::Handler {
+            ProblemJsonHandler { inner }
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    pub struct ProblemJsonHandler<H> {
+        inner: H,
+    }
+
+    impl<H> Handler for ProblemJsonHandler<H>
+    where
+        H: Handler,
+    {
+        type Output = H::Output;
+        type Error = Error;
+        type Handle = HandleProblemJson<H::Handle>;
+
+        fn handle(&self) -> Self::Handle {
+            HandleProblemJson {
+                inner: self.inner.handle(),
+            }
+        }
+
+        fn allowed_methods(&self) -> Option<&AllowedMethods> {
+            self.inner.allowed_methods()
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    pub struct HandleProblemJson<H> {
+        inner: H,
+    }
+
+    impl<H> TryFuture for HandleProblemJson<H>
+    where
+        H: TryFuture,
+        H::Error: Into<Error>,
+    {
+        type Ok = H::Ok;
+        type Error = Error;
+
+        fn poll_ready(&mut self, input: &mut Input<'_>) -> Poll<Self::Ok, Self::Error> {
+            self.inner.poll_ready(input).map_err(|err| {
+                let err: Error = err.into();
+                if err.is::<Problem>() || err.is::<Rejection>() {
+                    // has already been rendered by another `ProblemJson`, or
+                    // must be returned to the client as it is.
+                    return err;
+                }
+                Problem::new(err, input.request).into()
+            })
+        }
+    }
+
+    /// An error value rendered as a problem details object.
+    #[derive(Debug)]
+    struct Problem {
+        response: Response<String>,
+    }
+
+    impl Problem {
+        fn new(err: Error, request: &Request<()>) -> Self {
+            let mut members = serde_json::Map::new();
+            err.problem_details(&mut members);
+            let detail = err.to_string();
+
+            let mut response = err.into_response(request);
+            let status = response.status();
+            members.insert("status".into(), Value::from(status.as_u16()));
+            members
+                .entry("title")
+                .or_insert_with(|| Value::from(status.canonical_reason().unwrap_or("Unknown")));
+            members
+                .entry("detail")
+                .or_insert_with(|| Value::from(detail));
+
+            let body = Value::Object(members).to_string();
+            response.headers_mut().remove(CONTENT_LENGTH);
+            response.headers_mut().insert(
+                CONTENT_TYPE,
+                HeaderValue::from_static("application/problem+json"),
+            );
+            Self {
+                response: response.map(|_| body),
+            }
+        }
+    }
+
+    impl fmt::Display for Problem {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(self.response.body())
+        }
+    }
+
+    impl HttpError for Problem {
+        type Body = String;
+
+        fn into_response(self, _: &Request<()>) -> Response<Self::Body> {
+            self.response
+        }
+    }
+}
+
+/// Creates a `ModifyHandler` that limits the rate of requests for each key
+/// using the token bucket algorithm.
+///
+/// Each key is allowed to send `requests` requests per `period`, and the burst size
+/// defaults to `requests`. By default, the key is the IP address of the remote peer
+/// returned from `Input::remote_addr`; the other key can be extracted by `RateLimit::key`.
+/// The requests whose key cannot be extracted are not limited.
+///
+/// When the bucket of a key is exhausted, the request is rejected with
+/// `429 Too Many Requests` and the header field `Retry-After` before calling
+/// the wrapped handler. The buckets are shared among all handlers wrapped by
+/// the same modifier, and the idle ones are cleaned up periodically.
+///
+/// # Panics
+///
+/// This function panics if `requests` or `period` is zero.
+pub fn rate_limit(requests: u32, period: std::time::Duration) -> RateLimit {
+    RateLimit::new(requests, period)
+}
+
+mod rate_limit {
+    use {
+        crate::{
+            error::{Error, HttpError},
+            future::{Poll, TryFuture},
+            handler::{AllowedMethods, Handler, ModifyHandler},
+            input::Input,
+        },
+        http::{
+            header::{HeaderMap, HeaderValue, RETRY_AFTER},
+            Request, Response, StatusCode,
+        },
+        std::{
+            collections::{hash_map::DefaultHasher, HashMap},
+            fmt,
+            hash::{Hash, Hasher},
+            sync::{Arc, Mutex},
+            time::{Duration, SystemTime, UNIX_EPOCH},
+        },
+    };
+
+    const NUM_SHARDS: usize = 16;
+
+    type KeyFn = dyn Fn(&Input<'_>) -> Option<String> + Send + Sync + 'static;
+
+    #[derive(Clone)]
+    pub struct RateLimit {
+        rate: f64,
+        burst: u32,
+        key_fn: Arc<KeyFn>,
+        buckets: Arc<Vec<Mutex<Shard>>>,
+    }
+
+    #[cfg_attr(tarpaulin, skip)]
+    impl fmt::Debug for RateLimit {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("RateLimit")
+                .field("rate", &self.rate)
+                .field("burst", &self.burst)
+                .finish()
+        }
+    }
+
+    impl RateLimit {
+        pub(super) fn new(requests: u32, period: Duration) -> Self {
+            assert!(
+                requests > 0,
+                "the number of requests must be greater than zero"
+            );
+            let period = period.as_secs() as f64 + f64::from(period.subsec_nanos()) * 1e-9;
+            assert!(period > 0.0, "the period must be greater than zero");
+            Self {
+                rate: f64::from(requests) / period,
+                burst: requests,
+                key_fn: Arc::new(|input: &Input<'_>| {
+                    input.remote_addr().map(|addr| addr.ip().to_string())
+                }),
+                buckets: Arc::new(
+                    (0..NUM_SHARDS)
+                        .map(|_| Mutex::new(Shard::default()))
+                        .collect(),
+                ),
+            }
+        }
+
+        /// Sets the maximum number of requests accepted at once for each key.
+        ///
+        /// The default value is the number of requests per period.
+        ///
+        /// # Panics
+        ///
+        /// This method panics if `burst` is zero.
+        pub fn burst(self, burst: u32) -> Self {
+            assert!(burst > 0, "the burst size must be greater than zero");
+            Self { burst, ..self }
+        }
+
+        /// Sets the function that extracts the key from the incoming request.
+        ///
+        /// If the function returns `None`, the request is not limited.
+        pub fn key<F>(self, key_fn: F) -> Self
+        where
+            F: Fn(&Input<'_>) -> Option<String> + Send + Sync + 'static,
+        {
+            Self {
+                key_fn: Arc::new(key_fn),
+                ..self
+            }
+        }
+
+        /// Consumes a token of the bucket associated with the key, and returns
+        /// the number of seconds until the next token is available if exhausted.
+        fn acquire(&self, key: String, now: SystemTime) -> Option<u64> {
+            let mut hasher = DefaultHasher::new();
+            key.hash(&mut hasher);
+            let index = (hasher.finish() % NUM_SHARDS as u64) as usize;
+            let mut shard = self.buckets[index].lock().expect("the lock is poisoned");
+            let rate = self.rate;
+            let burst = f64::from(self.burst);
+
+            // remove the idle buckets, which are refilled and equivalent to the new ones.
+            if elapsed_secs(shard.last_cleanup, now) >= burst / rate {
+                shard
+                    .buckets
+                    .retain(|_, bucket| bucket.tokens(now, rate, burst) < burst);
+                shard.last_cleanup = now;
+            }
+
+            let bucket = shard.buckets.entry(key).or_insert_with(|| Bucket {
+                tokens: burst,
+                updated: now,
+            });
+            bucket.tokens = bucket.tokens(now, rate, burst);
+            bucket.updated = now;
+            if bucket.tokens >= 1.0 {
+                bucket.tokens -= 1.0;
+                None
+            } else {
+                let wait = (1.0 - bucket.tokens) / rate;
+                Some(wait.ceil().max(1.0) as u64)
+            }
+        }
+    }
+
+    struct Shard {
+        buckets: HashMap<String, Bucket>,
+        last_cleanup: SystemTime,
+    }
+
+    impl Default for Shard {
+        fn default() -> Self {
+            Self {
+                buckets: HashMap::new(),
+                last_cleanup: UNIX_EPOCH,
+            }
+        }
+    }
+
+    struct Bucket {
+        tokens: f64,
+        updated: SystemTime,
+    }
+
+    impl Bucket {
+        /// Returns the number of tokens refilled until the specified time.
+        fn tokens(&self, now: SystemTime, rate: f64, burst: f64) -> f64 {
+            (self.tokens + elapsed_secs(self.updated, now) * rate).min(burst)
+        }
+    }
+
+    fn elapsed_secs(since: SystemTime, now: SystemTime) -> f64 {
+        now.duration_since(since)
+            .map(|d| d.as_secs() as f64 + f64::from(d.subsec_nanos()) * 1e-9)
+            .unwrap_or(0.0)
+    }
+
+    impl<H> ModifyHandler<H> for RateLimit
+    where
+        H: Handler,
+    {
+        type Output = H::Output;
+        type Handler = RateLimitHandler<H>; // private
+
+        fn modify(&self, inner: H) -> Self::Handler {
+            RateLimitHandler {
+                inner,
+                rate_limit: self.clone(),
+            }
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    pub struct RateLimitHandler<H> {
+        inner: H,
+        rate_limit: RateLimit,
+    }
+
+    impl<H> Handler for RateLimitHandler<H>
+    where
+        H: Handler,
+    {
+        type Output = H::Output;
+        type Error = Error;
+        type Handle = HandleRateLimit<H::Handle>;
+
+        fn handle(&self) -> Self::Handle {
+            HandleRateLimit {
+                inner: self.inner.handle(),
+                rate_limit: Some(self.rate_limit.clone()),
+            }
+        }
+
+        fn allowed_methods(&self) -> Option<&AllowedMethods> {
+            self.inner.allowed_methods()
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    pub struct HandleRateLimit<H> {
+        inner: H,
+        rate_limit: Option<RateLimit>,
+    }
+
+    impl<H> TryFuture for HandleRateLimit<H>
+    where
+        H: TryFuture,
+        H::Error: Into<Error>,
+    {
+        type Ok = H::Ok;
+        type Error = Error;
+
+        fn poll_ready(&mut self, input: &mut Input<'_>) -> Poll<Self::Ok, Self::Error> {
+            if let Some(rate_limit) = self.rate_limit.take() {
+                if let Some(key) = (rate_limit.key_fn)(input) {
+                    if let Some(retry_after) = rate_limit.acquire(key, input.clock().now()) {
+                        return Err(TooManyRequests { retry_after }.into());
+                    }
+                }
+            }
+            self.inner.poll_ready(input).map_err(Into::into)
+        }
+    }
+
+    #[derive(Debug)]
+    struct TooManyRequests {
+        retry_after: u64,
+    }
+
+    impl fmt::Display for TooManyRequests {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("too many requests")
+        }
+    }
+
+    impl HttpError for TooManyRequests {
+        type Body = String;
+
+        fn into_response(self, _: &Request<()>) -> Response<Self::Body> {
+            Response::builder()
+                .status(StatusCode::TOO_MANY_REQUESTS)
+                .body(self.to_string())
+                .expect("should be a valid response")
+        }
+
+        fn headers(&self) -> Option<HeaderMap> {
+            let mut headers = HeaderMap::new();
+            headers.insert(RETRY_AFTER, HeaderValue::from(self.retry_after));
+            Some(headers)
+        }
+    }
+}
+
+/// Creates a `ModifyHandler` that assigns an identifier to each request.
+///
+/// The identifier is taken from the request header `X-Request-Id` if exists,
+/// or generated as a random UUID otherwise. The assigned identifier can be obtained
+/// via `Input::request_id` (and `LogRecord::request_id` in `logger`), and is echoed
+/// on the response with the same header name.
+pub fn request_id() -> RequestId {
+    RequestId::default()
+}
+
+mod request_id {
+    use {
+        crate::{
+            future::{Poll, TryFuture},
+            handler::{AllowedMethods, Handler, ModifyHandler},
+            input::{localmap::LocalData, Input, RequestId as CurrentRequestId},
+        },
+        http::header::{HeaderName, HeaderValue},
+        std::{fmt, sync::Arc},
+    };
+
+    /// The maximum length of the identifiers accepted from the request header.
+    const MAX_LEN: usize = 128;
+
+    #[derive(Clone)]
+    pub struct RequestId {
+        header_name: HeaderName,
+        generator: Arc<dyn Fn() -> String + Send + Sync + 'static>,
+    }
+
+    impl fmt::Debug for RequestId {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("RequestId")
+                .field("header_name", &self.header_name)
+                .finish()
+        }
+    }
+
+    impl Default for RequestId {
+        fn default() -> Self {
+            Self {
+                header_name: HeaderName::from_static("x-request-id"),
+                generator: Arc::new(|| uuid::Uuid::new_v4().to_string()),
+            }
+        }
+    }
+
+    impl RequestId {
+        /// Sets the name of header field used for receiving and echoing the identifier.
+        ///
+        /// The default value is `X-Request-Id`.
+        pub fn header_name(self, header_name: HeaderName) -> Self {
+            Self {
+                header_name,
+                ..self
+            }
+        }
+
+        /// Sets the function that generates the identifier when the request does not have one.
+        ///
+        /// The generated value must be a valid header value.
+        pub fn generator<F>(self, f: F) -> Self
+        where
+            F: Fn() -> String + Send + Sync + 'static,
+        {
+            Self {
+                generator: Arc::new(f),
+                ..self
+            }
+        }
+
+        fn apply(&self, input: &mut Input<'_>) {
+            if CurrentRequestId::contains(input.locals) {
+                return;
+            }
+
+            let received = input
+                .request
+                .headers()
+                .get(&self.header_name)
+                .filter(|value| !value.is_empty() && value.len() <= MAX_LEN)
+                .and_then(|value| value.to_str().ok())
+                .map(ToOwned::to_owned);
+            let request_id = received.unwrap_or_else(|| (self.generator)());
+
+            match HeaderValue::from_shared(request_id.clone().into()) {
+                Ok(value) => {
+                    input
+                        .response_headers
+                        .get_or_insert_with(Default::default)
+                        .insert(self.header_name.clone(), value);
+                }
+                Err(..) => log::warn!(
+                    "the request id is not a valid header value: {:?}",
+                    request_id
+                ),
+            }
+            CurrentRequestId(request_id).insert_into(input.locals);
+        }
+    }
+
+    impl<H> ModifyHandler<H> for RequestId
+    where
+        H: Handler,
+    {
+        type Output = H::Output;
+        type Handler = RequestIdHandler<H>; // private
+
+        fn modify(&self, inner: H) -> Self::Handler {
+            RequestIdHandler {
+                inner,
+                request_id: self.clone(),
+            }
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    pub struct RequestIdHandler<H> {
+        inner: H,
+        request_id: RequestId,
+    }
+
+    impl<H> Handler for RequestIdHandler<H>
+    where
+        H: Handler,
+    {
+        type Output = H::Output;
+        type Error = H::Error;
+        type Handle = HandleRequestId<H::Handle>;
+
+        fn handle(&self) -> Self::Handle {
+            HandleRequestId {
+                inner: self.inner.handle(),
+                request_id: Some(self.request_id.clone()),
+            }
+        }
+
+        fn allowed_methods(&self) -> Option<&AllowedMethods> {
+            self.inner.allowed_methods()
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    pub struct HandleRequestId<H> {
+        inner: H,
+        request_id: Option<RequestId>,
+    }
+
+    impl<H> TryFuture for HandleRequestId<H>
+    where
+        H: TryFuture,
+    {
+        type Ok = H::Ok;
+        type Error = H::Error;
+
+        #[inline]
+        fn poll_ready(&mut self, input: &mut Input<'_>) -> Poll<Self::Ok, Self::Error> {
+            if let Some(request_id) = self.request_id.take() {
+                request_id.apply(input);
+            }
+            self.inner.poll_ready(input)
+        }
+    }
+}
+
+/// Creates a `ModifyHandler` that instruments the handling of each request with
+/// a `tracing` span.
+///
+/// The span named `request` is opened at the first poll of the handler, with the fields
+/// `method`, `path` and `route` (the matched route pattern). When the handler completes,
+/// the fields `status` and `latency` are recorded and an event is emitted within the span.
+///
+/// Since the handler is a `TryFuture` which may be polled several times until it completes,
+/// the span is entered at the beginning of each `poll_ready` and exited when it returns.
+/// Therefore, the events emitted while polling the handler are attributed to the span,
+/// and the ones from the other requests processed on the same thread in the meantime are not.
+///
+/// As with `logger`, the output of the wrapped handler and its errors are converted into
+/// an HTTP response in order to obtain the status code.
+///
+/// This function requires the feature `tracing`, which is not included in `full`,
+/// since the crate `tracing` does not support the minimum Rust version of this crate (1.31).
+#[cfg(feature = "tracing")]
+pub fn trace() -> Trace {
+    Trace(())
+}
+
+#[cfg(feature = "tracing")]
+mod trace {
+    use {
+        crate::{
+            error::Error,
+            future::{Async, Poll, TryFuture},
+            handler::{AllowedMethods, Handler, ModifyHandler},
+            input::Input,
+            output::{IntoResponse, ResponseBody},
+            util::Never,
+        },
+        http::Response,
+        std::time::Instant,
+        tracing::{field, Span},
+    };
+
+    #[derive(Debug, Clone)]
+    pub struct Trace(pub(super) ());
+
+    impl<H> ModifyHandler<H> for Trace
+    where
+        H: Handler,
+        H::Output: IntoResponse,
+    {
+        type Output = Response<ResponseBody>;
+        type Handler = TraceHandler<H>; // private
+
+        fn modify(&self, inner: H) -> Self::Handler {
+            TraceHandler { inner }
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    pub struct TraceHandler<H> {
+        inner: H,
+    }
+
+    impl<H> Handler for TraceHandler<H>
+    where
+        H: Handler,
+        H::Output: IntoResponse,
+    {
+        type Output = Response<ResponseBody>;
+        type Error = Never;
+        type Handle = HandleTrace<H::Handle>;
+
+        fn handle(&self) -> Self::Handle {
+            HandleTrace {
+                inner: self.inner.handle(),
+                span: None,
+            }
+        }
+
+        fn allowed_methods(&self) -> Option<&AllowedMethods> {
+            self.inner.allowed_methods()
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    pub struct HandleTrace<H> {
+        inner: H,
+        span: Option<(Span, Instant)>,
+    }
+
+    impl<H> TryFuture for HandleTrace<H>
+    where
+        H: TryFuture,
+        H::Ok: IntoResponse,
+        H::Error: Into<Error>,
+    {
+        type Ok = Response<ResponseBody>;
+        type Error = Never;
+
+        fn poll_ready(&mut self, input: &mut Input<'_>) -> Poll<Self::Ok, Self::Error> {
+            let (span, started) = self.span.get_or_insert_with(|| {
+                let span = tracing::info_span!(
+                    "request",
+                    method = %input.request.method(),
+                    path = %input.request.uri().path(),
+                    route = %input.matched_route_pattern().unwrap_or(""),
+                    status = field::Empty,
+                    latency = field::Empty,
+                );
+                (span, Instant::now())
+            });
+            let _enter = span.enter();
+
+            let response = match self.inner.poll_ready(input) {
+                Ok(Async::NotReady) => return Ok(Async::NotReady),
+                Ok(Async::Ready(output)) => output
+                    .into_response(input.request)
+                    .map(|response| response.map(Into::into))
+                    .unwrap_or_else(|err| Into::<Error>::into(err).into_response(input.request)),
+                Err(err) => Into::<Error>::into(err).into_response(input.request),
+            };
+
+            let status = response.status().as_u16();
+            let latency = started.elapsed();
+            span.record("status", &status);
+            span.record("latency", &field::debug(latency));
+            tracing::info!(status = status, latency = ?latency, "request completed");
+
+            Ok(Async::Ready(response))
+        }
+    }
+}
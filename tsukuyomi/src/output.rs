@@ -1,14 +1,19 @@
 //! Components for constructing HTTP responses.
 
+mod compress;
 pub mod redirect;
+pub mod sse;
 
-pub use tsukuyomi_macros::IntoResponse;
+pub use {self::compress::Compressed, tsukuyomi_macros::IntoResponse};
 
 use {
     crate::{error::Error, input::body::RequestBody, util::Never},
     bytes::{Buf, Bytes, IntoBuf},
     futures01::{Poll, Stream},
-    http::{header::HeaderMap, Request, Response, StatusCode},
+    http::{
+        header::{self, HeaderMap, HeaderValue},
+        HttpTryFrom, Request, Response, StatusCode,
+    },
     hyper::body::{Body, Payload},
     serde::Serialize,
 };
@@ -37,6 +42,12 @@ impl ResponseBody {
     }
 
     /// Wraps a `Stream` into a `ResponseBody`.
+    ///
+    /// If the stream returns an error, the error is logged and the response is aborted
+    /// rather than being terminated as a complete message. That is, the connection is
+    /// closed without sending the remaining part of the message in HTTP/1.x (e.g. the last
+    /// chunk of the chunked transfer encoding), and the stream is reset in HTTP/2.
+    /// The client therefore observes an incomplete response instead of a short body.
     pub fn wrap_stream<S>(stream: S) -> Self
     where
         S: Stream + Send + 'static,
@@ -44,7 +55,13 @@ impl ResponseBody {
         S::Item: IntoBuf,
     {
         ResponseBody(Body::wrap_stream(
-            stream.map(|chunk| chunk.into_buf().collect::<Bytes>()),
+            stream
+                .map(|chunk| chunk.into_buf().collect::<Bytes>())
+                .map_err(|err| {
+                    let err: Box<dyn std::error::Error + Send + Sync + 'static> = err.into();
+                    log::error!("aborting the response body due to a stream error: {}", err);
+                    err
+                }),
         ))
     }
 }
@@ -187,6 +204,38 @@ mod impl_into_response_for_either {
     }
 }
 
+/// A wrapper of `IntoResponse` that overrides the status code of the response.
+///
+/// The header fields and the body created by the inner value are preserved.
+#[derive(Debug)]
+pub struct WithStatus<T>(pub StatusCode, pub T);
+
+impl<T> IntoResponse for WithStatus<T>
+where
+    T: IntoResponse,
+{
+    type Body = T::Body;
+    type Error = T::Error;
+
+    fn into_response(self, request: &Request<()>) -> Result<Response<Self::Body>, Self::Error> {
+        let WithStatus(status, inner) = self;
+        inner.into_response(request).map(|mut response| {
+            *response.status_mut() = status;
+            response
+        })
+    }
+}
+
+/// An extension trait that provides the combinators for `IntoResponse`.
+pub trait IntoResponseExt: IntoResponse + Sized {
+    /// Wraps itself to override the status code of the response.
+    fn with_status(self, status: StatusCode) -> WithStatus<Self> {
+        WithStatus(status, self)
+    }
+}
+
+impl<T: IntoResponse> IntoResponseExt for T {}
+
 impl<T> IntoResponse for Response<T>
 where
     T: Into<ResponseBody>,
@@ -264,13 +313,52 @@ where
 }
 
 /// Creates a JSON responder from the specified data.
-#[allow(deprecated)]
+///
+/// The status code of the response can be changed by `Json::status`.
 #[inline]
-pub fn json<T>(data: T) -> impl IntoResponse<Body = Vec<u8>, Error = Error>
+pub fn json<T>(data: T) -> Json<T>
 where
     T: Serialize,
 {
-    self::into_response(move |request| self::into_response::json(data, request))
+    Json {
+        data,
+        status: StatusCode::OK,
+    }
+}
+
+/// A responder that serializes the inner data into a JSON response.
+///
+/// The values of this type are created by [`json`](./fn.json.html).
+#[derive(Debug)]
+pub struct Json<T> {
+    data: T,
+    status: StatusCode,
+}
+
+impl<T> Json<T> {
+    /// Sets the status code of the response.
+    pub fn status(self, status: StatusCode) -> Self {
+        Self { status, ..self }
+    }
+}
+
+impl<T> IntoResponse for Json<T>
+where
+    T: Serialize,
+{
+    type Body = Vec<u8>;
+    type Error = Error;
+
+    fn into_response(self, _: &Request<()>) -> Result<Response<Self::Body>, Self::Error> {
+        let status = self.status;
+        serde_json::to_vec(&self.data)
+            .map(|body| {
+                let mut response = self::make_response(body, "application/json");
+                *response.status_mut() = status;
+                response
+            })
+            .map_err(crate::error::internal_server_error)
+    }
 }
 
 /// Creates a JSON responder with pretty output from the specified data.
@@ -293,6 +381,92 @@ where
     self::into_response(move |request| self::into_response::html(body, request))
 }
 
+/// Creates a responder that returns an empty response with the status code `204 No Content`.
+///
+/// It is equivalent to returning `()` from the handler.
+#[inline]
+pub fn no_content() -> impl IntoResponse<Body = (), Error = Never> {
+    self::into_response(|_| {
+        let mut response = Response::new(());
+        *response.status_mut() = StatusCode::NO_CONTENT;
+        Ok(response)
+    })
+}
+
+/// Creates a responder that sends the items of the specified stream as the response body.
+///
+/// The items are forwarded to the client as they are produced, without buffering the
+/// entire stream. Since the length of the body is unknown, the response is sent with
+/// the chunked transfer encoding (or without `Content-Length` in HTTP/2).
+///
+/// If the stream returns an error in the middle, the response is aborted as described
+/// in [`ResponseBody::wrap_stream`](./struct.ResponseBody.html#method.wrap_stream).
+#[inline]
+pub fn stream<S>(stream: S) -> Streaming<S>
+where
+    S: Stream + Send + 'static,
+    S::Error: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+    S::Item: IntoBuf,
+{
+    Streaming {
+        stream,
+        content_type: None,
+    }
+}
+
+/// A responder that sends the items of a `Stream` as the response body.
+///
+/// The values of this type are created by [`stream`](./fn.stream.html).
+#[derive(Debug)]
+pub struct Streaming<S> {
+    stream: S,
+    content_type: Option<HeaderValue>,
+}
+
+impl<S> Streaming<S> {
+    /// Sets the value of `Content-Type` of the response.
+    pub fn content_type<T>(self, content_type: T) -> http::Result<Self>
+    where
+        HeaderValue: HttpTryFrom<T>,
+    {
+        Ok(Self {
+            content_type: Some(HeaderValue::try_from(content_type).map_err(Into::into)?),
+            ..self
+        })
+    }
+}
+
+impl<S> IntoResponse for Streaming<S>
+where
+    S: Stream + Send + 'static,
+    S::Error: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+    S::Item: IntoBuf,
+{
+    type Body = ResponseBody;
+    type Error = Never;
+
+    fn into_response(self, _: &Request<()>) -> Result<Response<Self::Body>, Self::Error> {
+        let mut response = Response::new(ResponseBody::wrap_stream(self.stream));
+        if let Some(content_type) = self.content_type {
+            response
+                .headers_mut()
+                .insert(header::CONTENT_TYPE, content_type);
+        }
+        Ok(response)
+    }
+}
+
+/// Appends a `Link` header field which hints the client to preload the specified resource.
+///
+/// The entry is formatted as `<{uri}>; rel=preload; as={as_}` and merged into the existing
+/// `Link` header field if exists.
+pub fn preload(headers: &mut HeaderMap, uri: &str, as_: &str) -> crate::error::Result<()> {
+    let value = HeaderValue::from_shared(format!("<{}>; rel=preload; as={}", uri, as_).into())
+        .map_err(crate::error::internal_server_error)?;
+    crate::util::merge_header_value(headers, header::LINK, value);
+    Ok(())
+}
+
 /// Create an instance of `Response<T>` with the provided body and content type.
 fn make_response<T>(body: T, content_type: &'static str) -> Response<T> {
     let mut response = Response::new(body);
@@ -307,7 +481,10 @@ pub mod preset {
     use {
         super::ResponseBody,
         crate::{error::Error, util::Never},
-        http::{Request, Response},
+        http::{
+            header::{self, HeaderMap},
+            Request, Response, StatusCode,
+        },
         serde::Serialize,
     };
 
@@ -353,6 +530,105 @@ pub mod preset {
         }
     }
 
+    /// A preset that serializes the value in MessagePack format.
+    ///
+    /// This preset is available only if the feature `rmp-serde` is enabled.
+    #[cfg(feature = "rmp-serde")]
+    #[allow(missing_debug_implementations)]
+    pub struct MsgPack(());
+
+    #[cfg(feature = "rmp-serde")]
+    impl<T> Preset<T> for MsgPack
+    where
+        T: Serialize,
+    {
+        type Body = Vec<u8>;
+        type Error = Error;
+
+        fn into_response(data: T, _: &Request<()>) -> Result<Response<Self::Body>, Self::Error> {
+            rmp_serde::to_vec(&data)
+                .map(|body| super::make_response(body, "application/msgpack"))
+                .map_err(crate::error::internal_server_error)
+        }
+    }
+
+    /// A preset that chooses the serialization format based on the header field `Accept`.
+    ///
+    /// The value is serialized in JSON, or in MessagePack if the feature `rmp-serde`
+    /// is enabled and the client prefers it. JSON is chosen if the request does not
+    /// have `Accept`. If none of the formats are acceptable, it returns an error
+    /// with the status code `406 Not Acceptable`.
+    #[allow(missing_debug_implementations)]
+    pub struct Negotiated(());
+
+    #[cfg(not(feature = "rmp-serde"))]
+    const NEGOTIATED_CANDIDATES: &[&str] = &["application/json"];
+
+    #[cfg(feature = "rmp-serde")]
+    const NEGOTIATED_CANDIDATES: &[&str] = &["application/json", "application/msgpack"];
+
+    impl<T> Preset<T> for Negotiated
+    where
+        T: Serialize,
+    {
+        type Body = Vec<u8>;
+        type Error = Error;
+
+        fn into_response(
+            data: T,
+            request: &Request<()>,
+        ) -> Result<Response<Self::Body>, Self::Error> {
+            match negotiate(request.headers(), NEGOTIATED_CANDIDATES) {
+                #[cfg(feature = "rmp-serde")]
+                Some("application/msgpack") => MsgPack::into_response(data, request),
+                Some(..) => Json::into_response(data, request),
+                None => Err(crate::error::custom(
+                    StatusCode::NOT_ACCEPTABLE,
+                    "no acceptable media type",
+                )),
+            }
+        }
+    }
+
+    /// Chooses the most preferred media type from the candidates.
+    ///
+    /// The first candidate is chosen if the request does not have `Accept`.
+    fn negotiate<'a>(headers: &HeaderMap, candidates: &[&'a str]) -> Option<&'a str> {
+        let ranges: Vec<(&str, f32)> =
+            crate::util::parse_qvalue_list(headers, header::ACCEPT).collect();
+        if ranges.is_empty() {
+            return candidates.first().cloned();
+        }
+
+        let mut chosen: Option<(&str, f32)> = None;
+        for &candidate in candidates {
+            let ty = candidate.split('/').next().unwrap_or(candidate);
+            // the quality value of the most specific media range matching the candidate.
+            let qvalue = ranges
+                .iter()
+                .filter_map(|&(range, qvalue)| {
+                    let specificity = if range.eq_ignore_ascii_case(candidate) {
+                        2
+                    } else if range.ends_with("/*")
+                        && range[..range.len() - 2].eq_ignore_ascii_case(ty)
+                    {
+                        1
+                    } else if range == "*/*" {
+                        0
+                    } else {
+                        return None;
+                    };
+                    Some((specificity, qvalue))
+                })
+                .max_by_key(|&(specificity, _)| specificity)
+                .map_or(0.0, |(_, qvalue)| qvalue);
+            if qvalue > 0.0 && chosen.map_or(true, |(_, chosen_qvalue)| qvalue > chosen_qvalue) {
+                chosen = Some((candidate, qvalue));
+            }
+        }
+        chosen.map(|(candidate, _)| candidate)
+    }
+
     #[allow(missing_debug_implementations)]
     pub struct Html(());
 
@@ -0,0 +1,181 @@
+//! Components for compressing the response body.
+
+use {
+    super::{IntoResponse, ResponseBody},
+    crate::{
+        error::Error,
+        future::{Poll, TryFuture},
+        input::Input,
+        responder::Responder,
+    },
+    bytes::Bytes,
+    flate2::{write::GzEncoder, Compression},
+    futures01::{Async, Stream},
+    http::{
+        header::{self, HeaderMap, HeaderValue},
+        Request, Response, StatusCode,
+    },
+    hyper::body::Payload,
+    std::{
+        io::{self, Write},
+        mem,
+    },
+};
+
+/// A `Responder` that compresses the response body with gzip if the client accepts it.
+///
+/// The compression is skipped if the request does not contain `gzip` in `Accept-Encoding`,
+/// or if the response has already been encoded.
+///
+/// # Range requests
+///
+/// The byte ranges of a response are always computed on the uncompressed representation,
+/// so the partial content (that is, a response with the status `206 Partial Content` or
+/// the header `Content-Range`) is passed through without compression.
+/// On the other hand, the header `Accept-Ranges` is removed from the response when its body
+/// is compressed, since the offsets in the following range requests would not match the
+/// encoded bytes sent to the client.
+#[derive(Debug, Clone)]
+pub struct Compressed<R> {
+    responder: R,
+}
+
+impl<R> Compressed<R>
+where
+    R: Responder,
+{
+    /// Wraps the specified `Responder` to compress its response body.
+    pub fn new(responder: R) -> Self {
+        Self { responder }
+    }
+}
+
+impl<R> Responder for Compressed<R>
+where
+    R: Responder,
+{
+    type Response = Response<ResponseBody>;
+    type Error = Error;
+    type Respond = CompressedRespond<R::Respond>;
+
+    fn respond(self) -> Self::Respond {
+        CompressedRespond {
+            respond: self.responder.respond(),
+        }
+    }
+}
+
+#[allow(missing_debug_implementations)]
+pub struct CompressedRespond<R> {
+    respond: R,
+}
+
+impl<R> TryFuture for CompressedRespond<R>
+where
+    R: TryFuture,
+    R::Ok: IntoResponse,
+{
+    type Ok = Response<ResponseBody>;
+    type Error = Error;
+
+    fn poll_ready(&mut self, input: &mut Input<'_>) -> Poll<Self::Ok, Self::Error> {
+        let output = futures01::try_ready!(self.respond.poll_ready(input).map_err(Into::into));
+        let response = output
+            .into_response(input.request)
+            .map_err(Into::into)?
+            .map(Into::into);
+        Ok(Async::Ready(gzip(response, input.request)))
+    }
+}
+
+/// Returns `true` if the header field `Accept-Encoding` in the request allows gzip.
+///
+/// The quality value of `gzip` takes precedence over the one of `*`.
+pub(crate) fn accepts_gzip(headers: &HeaderMap) -> bool {
+    let mut gzip = None;
+    let mut any = None;
+    for (coding, qvalue) in crate::util::parse_qvalue_list(headers, header::ACCEPT_ENCODING) {
+        if coding.eq_ignore_ascii_case("gzip") {
+            gzip = Some(qvalue);
+        } else if coding == "*" {
+            any = Some(qvalue);
+        }
+    }
+    gzip.or(any).map_or(false, |qvalue| qvalue > 0.0)
+}
+
+/// Compresses the response body with gzip, if possible.
+pub(crate) fn gzip(
+    mut response: Response<ResponseBody>,
+    request: &Request<()>,
+) -> Response<ResponseBody> {
+    crate::util::merge_header_value(
+        response.headers_mut(),
+        header::VARY,
+        HeaderValue::from_static("accept-encoding"),
+    );
+
+    if !accepts_gzip(request.headers())
+        || response.headers().contains_key(header::CONTENT_ENCODING)
+        || response.status() == StatusCode::NO_CONTENT
+        || response.status() == StatusCode::NOT_MODIFIED
+        || response.status() == StatusCode::PARTIAL_CONTENT
+        || response.headers().contains_key(header::CONTENT_RANGE)
+    {
+        return response;
+    }
+
+    response.headers_mut().remove(header::CONTENT_LENGTH);
+    response.headers_mut().remove(header::ACCEPT_RANGES);
+    response
+        .headers_mut()
+        .insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+
+    response.map(|body| {
+        ResponseBody::wrap_stream(GzipStream {
+            body,
+            encoder: Some(GzEncoder::new(Vec::new(), Compression::default())),
+        })
+    })
+}
+
+/// A `Stream` that compresses the chunks of the inner body incrementally.
+struct GzipStream {
+    body: ResponseBody,
+    encoder: Option<GzEncoder<Vec<u8>>>,
+}
+
+impl Stream for GzipStream {
+    type Item = Bytes;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        loop {
+            let encoder = match self.encoder {
+                Some(ref mut encoder) => encoder,
+                None => return Ok(Async::Ready(None)),
+            };
+
+            let polled = self
+                .body
+                .poll_data()
+                .map_err(|err| io::Error::new(io::ErrorKind::Other, err));
+            match futures01::try_ready!(polled) {
+                Some(chunk) => {
+                    encoder.write_all(&*chunk)?;
+                    let compressed = mem::replace(encoder.get_mut(), Vec::new());
+                    if !compressed.is_empty() {
+                        return Ok(Async::Ready(Some(compressed.into())));
+                    }
+                }
+                None => {
+                    let encoder = self
+                        .encoder
+                        .take()
+                        .expect("the encoder should be available");
+                    return Ok(Async::Ready(Some(encoder.finish()?.into())));
+                }
+            }
+        }
+    }
+}
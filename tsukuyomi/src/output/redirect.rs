@@ -11,15 +11,70 @@ pub struct Redirect {
 }
 
 impl Redirect {
+    /// Creates a `Redirect` with the specified status code and location.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, this function panics if the status code is not a redirection (`3xx`).
+    /// Use `try_new` when the status code is not known statically.
     pub fn new<T>(status: StatusCode, location: T) -> Self
     where
         T: Into<Cow<'static, str>>,
     {
+        debug_assert!(
+            status.is_redirection(),
+            "the status code must be a redirection: {}",
+            status
+        );
         Self {
             status,
             location: location.into(),
         }
     }
+
+    /// Creates a `Redirect` with the specified status code and location.
+    ///
+    /// It returns an error if the status code is not a redirection (`3xx`).
+    pub fn try_new<T>(status: StatusCode, location: T) -> crate::error::Result<Self>
+    where
+        T: Into<Cow<'static, str>>,
+    {
+        if !status.is_redirection() {
+            return Err(crate::error::internal_server_error(format!(
+                "the status code is not a redirection: {}",
+                status
+            )));
+        }
+        Ok(Self {
+            status,
+            location: location.into(),
+        })
+    }
+}
+
+impl Redirect {
+    /// Appends the specified query string to the location.
+    ///
+    /// If the location already has a query string, the specified one is
+    /// concatenated with `&`.
+    pub fn append_query(self, query: &str) -> Self {
+        let query = query.trim_left_matches('?');
+        if query.is_empty() {
+            return self;
+        }
+        let separator = if self.location.contains('?') {
+            '&'
+        } else {
+            '?'
+        };
+        let mut location = self.location.into_owned();
+        location.push(separator);
+        location.push_str(query);
+        Self {
+            location: location.into(),
+            ..self
+        }
+    }
 }
 
 impl IntoResponse for Redirect {
@@ -56,3 +111,18 @@ define_funcs! {
     permanent_redirect => PERMANENT_REDIRECT,
     to => MOVED_PERMANENTLY,
 }
+
+/// Creates a `Redirect` to the path built from the specified route pattern and parameters.
+///
+/// The parameter values are percent-encoded and assigned to the parameters in the pattern
+/// in order. It returns an error if the pattern is invalid or the number of values is
+/// mismatched. The status code is the same as `to`.
+pub fn to_route<T>(pattern: &str, params: &[T]) -> crate::error::Result<Redirect>
+where
+    T: AsRef<str>,
+{
+    let location = crate::uri::Uri::parse(pattern)
+        .and_then(|uri| uri.build_path(params))
+        .map_err(crate::error::internal_server_error)?;
+    Ok(self::to(location))
+}
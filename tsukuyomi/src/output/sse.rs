@@ -0,0 +1,195 @@
+//! Components for sending Server-Sent Events.
+//!
+//! See [the specification](https://html.spec.whatwg.org/multipage/server-sent-events.html)
+//! for details.
+
+use {
+    super::{IntoResponse, ResponseBody},
+    crate::util::Never,
+    bytes::Bytes,
+    futures01::{Async, Future, Poll, Stream},
+    http::{header, Request, Response},
+    std::{
+        error::Error as StdError,
+        fmt::Write,
+        time::{Duration, Instant},
+    },
+    tokio_timer::Delay,
+};
+
+/// An event sent to the client.
+#[derive(Debug, Clone, Default)]
+pub struct Event {
+    data: String,
+    event: Option<String>,
+    id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl Event {
+    /// Creates an `Event` with the specified data.
+    ///
+    /// The data containing line breaks is sent as multiple `data` fields.
+    pub fn data<T>(data: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            data: data.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Sets the name of this event.
+    pub fn event<T>(self, event: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            event: Some(event.into()),
+            ..self
+        }
+    }
+
+    /// Sets the ID of this event.
+    pub fn id<T>(self, id: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            id: Some(id.into()),
+            ..self
+        }
+    }
+
+    /// Sets the reconnection time used by the client.
+    pub fn retry(self, retry: Duration) -> Self {
+        Self {
+            retry: Some(retry),
+            ..self
+        }
+    }
+
+    fn to_bytes(&self) -> Bytes {
+        let mut buf = String::new();
+        if let Some(ref event) = self.event {
+            write_field(&mut buf, "event", event);
+        }
+        if let Some(ref id) = self.id {
+            write_field(&mut buf, "id", id);
+        }
+        if let Some(retry) = self.retry {
+            let millis = retry.as_secs() * 1000 + u64::from(retry.subsec_millis());
+            write_field(&mut buf, "retry", &millis.to_string());
+        }
+        if self.data.is_empty() {
+            write_field(&mut buf, "data", "");
+        }
+        for line in self.data.lines() {
+            write_field(&mut buf, "data", line);
+        }
+        buf.push('\n');
+        buf.into()
+    }
+}
+
+fn write_field(buf: &mut String, name: &str, value: &str) {
+    // The line breaks are removed since they cannot be included in a field.
+    let value = value.replace(|c: char| c == '\r' || c == '\n', "");
+    let _ = writeln!(buf, "{}: {}", name, value);
+}
+
+/// A responder that sends the items of a `Stream` as Server-Sent Events.
+#[derive(Debug)]
+pub struct EventStream<S> {
+    stream: S,
+    keep_alive: Option<Duration>,
+}
+
+impl<S> EventStream<S>
+where
+    S: Stream<Item = Event> + Send + 'static,
+    S::Error: Into<Box<dyn StdError + Send + Sync + 'static>>,
+{
+    /// Creates an `EventStream` from the specified stream of events.
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            keep_alive: None,
+        }
+    }
+
+    /// Enables sending the comments periodically when no event is sent
+    /// during the specified interval.
+    ///
+    /// The keep-alive comments prevent the proxies from closing the idle connection.
+    /// This feature requires the timer provided by the Tokio runtime.
+    pub fn keep_alive(self, interval: Duration) -> Self {
+        Self {
+            keep_alive: Some(interval),
+            ..self
+        }
+    }
+}
+
+impl<S> IntoResponse for EventStream<S>
+where
+    S: Stream<Item = Event> + Send + 'static,
+    S::Error: Into<Box<dyn StdError + Send + Sync + 'static>>,
+{
+    type Body = ResponseBody;
+    type Error = Never;
+
+    fn into_response(self, _: &Request<()>) -> Result<Response<Self::Body>, Self::Error> {
+        let body = EventStreamBody {
+            stream: self.stream,
+            keep_alive: self
+                .keep_alive
+                .map(|interval| (Delay::new(Instant::now() + interval), interval)),
+        };
+        Ok(Response::builder()
+            .header(header::CONTENT_TYPE, "text/event-stream")
+            .header(header::CACHE_CONTROL, "no-cache")
+            // disable the response buffering by the reverse proxies such as Nginx.
+            .header("x-accel-buffering", "no")
+            .body(ResponseBody::wrap_stream(body))
+            .expect("should be a valid response"))
+    }
+}
+
+struct EventStreamBody<S> {
+    stream: S,
+    keep_alive: Option<(Delay, Duration)>,
+}
+
+impl<S> Stream for EventStreamBody<S>
+where
+    S: Stream<Item = Event>,
+    S::Error: Into<Box<dyn StdError + Send + Sync + 'static>>,
+{
+    type Item = Bytes;
+    type Error = Box<dyn StdError + Send + Sync + 'static>;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        match self.stream.poll() {
+            Ok(Async::Ready(Some(event))) => {
+                if let Some((ref mut delay, interval)) = self.keep_alive {
+                    delay.reset(Instant::now() + interval);
+                }
+                return Ok(Async::Ready(Some(event.to_bytes())));
+            }
+            Ok(Async::Ready(None)) => return Ok(Async::Ready(None)),
+            Ok(Async::NotReady) => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        if let Some((ref mut delay, interval)) = self.keep_alive {
+            if delay.poll()?.is_ready() {
+                delay.reset(Instant::now() + interval);
+                return Ok(Async::Ready(Some(Bytes::from_static(b": keep-alive\n\n"))));
+            }
+        }
+
+        Ok(Async::NotReady)
+    }
+}
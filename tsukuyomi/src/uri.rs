@@ -7,6 +7,7 @@ use {
         hash::{Hash, Hasher},
         str::FromStr,
     },
+    url::percent_encoding::{utf8_percent_encode, DEFAULT_ENCODE_SET, PATH_SEGMENT_ENCODE_SET},
 };
 
 #[derive(Debug, Clone, PartialEq)]
@@ -189,6 +190,72 @@ impl Uri {
         }
     }
 
+    /// Returns `true` if the specified request path is a prefix of this URI.
+    ///
+    /// The parameter segments in this URI match an arbitrary segment in the path.
+    pub fn starts_with_path(&self, path: &str) -> bool {
+        let mut segments = self.as_str().split('/');
+        let mut path_segments = path.split('/').peekable();
+        while let Some(path_segment) = path_segments.next() {
+            let segment = match segments.next() {
+                Some(segment) => segment,
+                None => return false,
+            };
+            let is_last = path_segments.peek().is_none();
+            let matched = match segment.as_bytes().first() {
+                Some(b'*') => return true,
+                Some(b':') => is_last || !path_segment.is_empty(),
+                _ if is_last => segment.starts_with(path_segment),
+                _ => segment == path_segment,
+            };
+            if !matched {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Builds a path by substituting the parameters in this URI with the specified values.
+    ///
+    /// The values are percent-encoded and assigned to the parameters in order.
+    /// The slashes in the value of a wildcard parameter are preserved.
+    pub fn build_path<T>(&self, values: &[T]) -> Result<String, Error>
+    where
+        T: AsRef<str>,
+    {
+        let num_params = self.capture_names().map_or(0, |names| names.params.len());
+        if values.len() != num_params {
+            failure::bail!(
+                "mismatched number of parameters: expected {}, got {}",
+                num_params,
+                values.len()
+            );
+        }
+
+        let mut values = values.iter().map(AsRef::as_ref);
+        let mut path = String::new();
+        for (i, segment) in self.as_str().split('/').enumerate() {
+            if i > 0 {
+                path.push('/');
+            }
+            match segment.as_bytes().first() {
+                Some(b':') => {
+                    let value = values.next().expect("the length has already checked");
+                    if value.is_empty() {
+                        failure::bail!("empty value for the parameter '{}'", &segment[1..]);
+                    }
+                    path.extend(utf8_percent_encode(value, PATH_SEGMENT_ENCODE_SET));
+                }
+                Some(b'*') => {
+                    let value = values.next().expect("the length has already checked");
+                    path.extend(utf8_percent_encode(value, DEFAULT_ENCODE_SET));
+                }
+                _ => path.push_str(segment),
+            }
+        }
+        Ok(path)
+    }
+
     pub fn join(&self, other: impl AsRef<Self>) -> Result<Self, Error> {
         match self.0.clone() {
             UriKind::Root => Ok(other.as_ref().clone()),
@@ -260,9 +327,42 @@ impl CaptureNames {
         Ok(())
     }
 
+    pub fn num_params(&self) -> usize {
+        self.params.len()
+    }
+
     pub fn position(&self, name: &str) -> Option<usize> {
         Some(self.params.get_full(name)?.0)
     }
+
+    pub fn has_wildcard(&self) -> bool {
+        self.has_wildcard
+    }
+}
+
+/// Returns the length of the leading part of `path` matched by the prefix pattern.
+///
+/// The parameter segments in the pattern (e.g. `:id`) match an arbitrary non-empty segment.
+pub(crate) fn match_prefix(prefix: &str, path: &str) -> Option<usize> {
+    let mut pos = 0;
+    for segment in prefix.split('/').skip(1) {
+        if !path[pos..].starts_with('/') {
+            return None;
+        }
+        pos += 1;
+        let end = path[pos..].find('/').map_or(path.len(), |i| pos + i);
+        let path_segment = &path[pos..end];
+        let matched = if segment.starts_with(':') {
+            !path_segment.is_empty()
+        } else {
+            segment == path_segment
+        };
+        if !matched {
+            return None;
+        }
+        pos = end;
+    }
+    Some(pos)
 }
 
 #[allow(clippy::non_ascii_literal)]
@@ -373,4 +473,47 @@ mod tests {
             Uri::static_("/path/to")
         );
     ];
+
+    #[test]
+    fn starts_with_path() {
+        let uri: Uri = "/tenants/:tenant/users".parse().unwrap();
+        assert!(uri.starts_with_path("/"));
+        assert!(uri.starts_with_path("/ten"));
+        assert!(uri.starts_with_path("/tenants/acme"));
+        assert!(uri.starts_with_path("/tenants/acme/us"));
+        assert!(!uri.starts_with_path("/tenants//users"));
+        assert!(!uri.starts_with_path("/tenants/acme/posts"));
+        assert!(!uri.starts_with_path("/tenants/acme/users/42"));
+    }
+
+    #[test]
+    fn match_prefix_with_params() {
+        assert_eq!(match_prefix("", "/users"), Some(0));
+        assert_eq!(match_prefix("/api", "/api/users"), Some(4));
+        assert_eq!(match_prefix("/api", "/apis"), None);
+        assert_eq!(
+            match_prefix("/tenants/:tenant", "/tenants/acme/users"),
+            Some(13)
+        );
+        assert_eq!(match_prefix("/tenants/:tenant", "/tenants/acme"), Some(13));
+        assert_eq!(match_prefix("/tenants/:tenant", "/tenants/"), None);
+        assert_eq!(match_prefix("/tenants/:tenant", "/users/acme"), None);
+    }
+
+    #[test]
+    fn build_path() {
+        let uri: Uri = "/posts/:id/files/*path".parse().unwrap();
+        assert_eq!(
+            uri.build_path(&["42", "path/to/a file"]).unwrap(),
+            "/posts/42/files/path/to/a%20file"
+        );
+        assert_eq!(
+            uri.build_path(&["a/b", "c"]).unwrap(),
+            "/posts/a%2Fb/files/c"
+        );
+        assert!(uri.build_path(&["42"]).is_err());
+        assert!(uri.build_path(&["", "c"]).is_err());
+
+        assert_eq!(Uri::root().build_path::<&str>(&[]).unwrap(), "/");
+    }
 }
@@ -1,6 +1,9 @@
 //! Miscellaneous components used within the framework.
 
-use std::{error::Error as StdError, fmt};
+use {
+    http::header::{Entry, HeaderMap, HeaderName, HeaderValue},
+    std::{error::Error as StdError, fmt},
+};
 
 /// A helper type which emulates the standard `never_type` (`!`).
 #[allow(clippy::empty_enum)]
@@ -84,3 +87,55 @@ pub enum Either<L, R> {
     Left(L),
     Right(R),
 }
+
+/// Appends a value to the header field with the specified name, merging into the
+/// existing entry as a comma-separated list (e.g. `Vary`, `Link`).
+///
+/// If the header field has multiple values, they are folded into a single one.
+pub fn merge_header_value(headers: &mut HeaderMap, name: HeaderName, value: HeaderValue) {
+    match headers.entry(name).expect("never fails") {
+        Entry::Occupied(mut entry) => {
+            let mut merged = Vec::new();
+            for existing in entry.iter() {
+                merged.extend_from_slice(existing.as_bytes());
+                merged.extend_from_slice(b", ");
+            }
+            merged.extend_from_slice(value.as_bytes());
+            entry.insert(HeaderValue::from_bytes(&merged).expect("should be a valid header value"));
+        }
+        Entry::Vacant(entry) => {
+            entry.insert(value);
+        }
+    }
+}
+
+/// Parses the header fields with the comma-separated list of values weighted by
+/// the quality value (e.g. `Accept`, `Accept-Encoding`), and returns an iterator
+/// of the pairs of values and their quality values.
+///
+/// The quality value defaults to `1.0` if omitted, and the other parameters are ignored.
+pub(crate) fn parse_qvalue_list<'a>(
+    headers: &'a HeaderMap,
+    name: HeaderName,
+) -> impl Iterator<Item = (&'a str, f32)> + 'a {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|h| h.to_str().ok())
+        .flat_map(|h| h.split(','))
+        .filter_map(|item| {
+            let mut params = item.split(';').map(str::trim);
+            let value = params.next().filter(|value| !value.is_empty())?;
+            let qvalue = params
+                .filter_map(|param| {
+                    if param.starts_with("q=") {
+                        param[2..].parse::<f32>().ok()
+                    } else {
+                        None
+                    }
+                })
+                .next()
+                .unwrap_or(1.0);
+            Some((value, qvalue))
+        })
+}
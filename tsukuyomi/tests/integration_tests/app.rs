@@ -1,7 +1,7 @@
 use {
-    http::{header, Request, StatusCode},
+    http::{header, Method, Request, StatusCode},
     tsukuyomi::{
-        config::prelude::*, //
+        config::{self, prelude::*}, //
         extractor,
         App,
     },
@@ -81,6 +81,7 @@ fn cookies() -> tsukuyomi_server::Result<()> {
                         Cookie::build("session", "dummy_session_id")
                             .domain("www.example.com")
                             .expires(expires_in)
+                            .max_age(Duration::days(1))
                             .finish(),
                     );
                     Ok::<_, tsukuyomi::error::Error>(())
@@ -122,6 +123,76 @@ fn cookies() -> tsukuyomi_server::Result<()> {
     Ok(())
 }
 
+#[test]
+fn cookies_expires_with_fixed_clock() -> tsukuyomi_server::Result<()> {
+    use {
+        cookie::Cookie,
+        std::time::{Duration, UNIX_EPOCH},
+        tsukuyomi::input::clock::FixedClock,
+    };
+
+    let app = App::create(chain![
+        path!("/login") //
+            .to(endpoint::any()
+                .extract(extractor::ready(|input| {
+                    input.cookies.jar()?.add(
+                        Cookie::build("session", "dummy_session_id")
+                            .max_age(time::Duration::days(1))
+                            .finish(),
+                    );
+                    Ok::<_, tsukuyomi::error::Error>(())
+                }))
+                .call(|| "Logged in")),
+        path!("/remember") //
+            .to(endpoint::any()
+                .extract(extractor::ready(|input| {
+                    input.cookies.jar()?.add(
+                        Cookie::build("remember", "1")
+                            .max_age(time::Duration::days(1))
+                            .expires(time::at_utc(time::Timespec::new(2_000_000_000, 0)))
+                            .finish(),
+                    );
+                    Ok::<_, tsukuyomi::error::Error>(())
+                }))
+                .call(|| "Remembered")),
+        path!("/logout") //
+            .to(endpoint::any()
+                .extract(extractor::ready(|input| {
+                    input.cookies.jar()?.remove(Cookie::named("session"));
+                    Ok::<_, tsukuyomi::error::Error>(())
+                }))
+                .call(|| "Logged out")),
+    ])?;
+    let now = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    let mut server = tsukuyomi_server::test::server(app)?.extension(FixedClock(now));
+
+    let response = server.perform("/login")?;
+    let cookie = Cookie::parse_encoded(response.header(header::SET_COOKIE)?.to_str()?)?;
+    assert_eq!(
+        cookie.expires().map(|tm| tm.to_timespec().sec),
+        Some(1_000_000_000 + 24 * 60 * 60)
+    );
+
+    // the explicit expiration date is not overwritten.
+    let response = server.perform("/remember")?;
+    let cookie = Cookie::parse_encoded(response.header(header::SET_COOKIE)?.to_str()?)?;
+    assert_eq!(
+        cookie.expires().map(|tm| tm.to_timespec().sec),
+        Some(2_000_000_000)
+    );
+
+    let response = server
+        .perform(Request::get("/logout").header(header::COOKIE, "session=dummy_session_id"))?;
+    let cookie = Cookie::parse_encoded(response.header(header::SET_COOKIE)?.to_str()?)?;
+    assert_eq!(cookie.value(), "");
+    assert_eq!(
+        cookie.expires().map(|tm| tm.to_timespec().sec),
+        Some(1_000_000_000 - 365 * 24 * 60 * 60)
+    );
+
+    Ok(())
+}
+
 #[test]
 fn default_options() -> tsukuyomi_server::Result<()> {
     let app = App::create(
@@ -138,7 +209,34 @@ fn default_options() -> tsukuyomi_server::Result<()> {
     let response = server.perform(Request::options("/path"))?;
     assert_eq!(response.status(), 204);
     assert_eq!(response.header(header::ALLOW)?, "GET, POST, OPTIONS");
-    assert_eq!(response.header(header::CONTENT_LENGTH)?, "0");
+    assert!(!response.headers().contains_key(header::CONTENT_LENGTH));
+
+    Ok(())
+}
+
+#[test]
+fn allowed_methods() -> tsukuyomi_server::Result<()> {
+    let app = App::create(chain![
+        path!("/array") //
+            .to(endpoint::allow_only(["GET", "POST"])?.call(|| "array")),
+        path!("/methods") //
+            .to(endpoint::methods(vec![Method::PUT, Method::DELETE]).call(|| "methods")),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform(Request::post("/array"))?;
+    assert_eq!(response.status(), 200);
+
+    let response = server.perform(Request::put("/array"))?;
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+    let response = server.perform(Request::delete("/methods"))?;
+    assert_eq!(response.status(), 200);
+
+    let response = server.perform(Request::get("/methods"))?;
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+    assert!(endpoint::allow_only(["GET", "P OST"]).is_err());
 
     Ok(())
 }
@@ -210,3 +308,1401 @@ fn scoped_fallback() -> tsukuyomi_server::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn default_handler_per_scope() -> tsukuyomi_server::Result<()> {
+    use tsukuyomi::{future::oneshot, handler::handler};
+
+    let app = App::create(chain![
+        mount("/app").with(chain![
+            config::default_handler(handler(
+                || oneshot(|_| Ok::<_, tsukuyomi::Error>("index.html")),
+                None,
+            )),
+            path!("/login") //
+                .to(endpoint::get().reply("login")),
+            mount("/admin").with(config::default_handler(handler(
+                || oneshot(|_| Ok::<_, tsukuyomi::Error>("admin.html")),
+                None,
+            ))),
+            mount("/settings").with(
+                path!("/profile") //
+                    .to(endpoint::get().reply("profile")),
+            ),
+        ]),
+        mount("/api").with(
+            path!("/users") //
+                .to(endpoint::get().reply("users")),
+        ),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/app/login")?;
+    assert_eq!(response.body().to_utf8()?, "login");
+
+    let response = server.perform("/app/dashboard")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "index.html");
+
+    // the nearest ancestor's default handler is chosen.
+    let response = server.perform("/app/admin/users")?;
+    assert_eq!(response.body().to_utf8()?, "admin.html");
+
+    let response = server.perform("/app/settings/unknown")?;
+    assert_eq!(response.body().to_utf8()?, "index.html");
+
+    let response = server.perform("/api/unknown")?;
+    assert_eq!(response.status(), 404);
+
+    Ok(())
+}
+
+#[test]
+fn param_regex() -> tsukuyomi_server::Result<()> {
+    let app = App::create(chain![
+        path!("/users/:id") //
+            .param_regex("id", "[0-9]+")
+            .to(endpoint::get().call(|id: u32| format!("user {}", id))),
+        mount("/posts").with(chain![
+            path!("*") //
+                .to(endpoint::call(|| "fallback")),
+            path!("/:slug") //
+                .param_regex("slug", "[a-z0-9]+(-[a-z0-9]+)*")
+                .to(endpoint::get().call(|slug: String| format!("post {}", slug))),
+        ]),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/users/123")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "user 123");
+
+    let response = server.perform("/users/abc")?;
+    assert_eq!(response.status(), 404);
+
+    let response = server.perform("/users/12a")?;
+    assert_eq!(response.status(), 404);
+
+    let response = server.perform("/posts/hello-world-2")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "post hello-world-2");
+
+    let response = server.perform("/posts/Hello_World")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "fallback");
+
+    let response = server.perform("/posts/hello-")?;
+    assert_eq!(response.body().to_utf8()?, "fallback");
+
+    Ok(())
+}
+
+#[test]
+fn param_regex_invalid() {
+    assert!(App::create(
+        path!("/users/:id") //
+            .param_regex("name", "[0-9]+")
+            .to(endpoint::call(|_id: u32| "")),
+    )
+    .is_err());
+
+    assert!(App::create(
+        path!("/users/:id") //
+            .param_regex("id", "[0-9")
+            .to(endpoint::call(|_id: u32| "")),
+    )
+    .is_err());
+}
+
+#[test]
+fn param_invalid_value() -> tsukuyomi_server::Result<()> {
+    let app = App::create(chain![
+        path!("/users/:id/posts/:index") //
+            .to(endpoint::get().call(|id: u32, index: u8| format!("{}-{}", id, index))),
+        path!("/files/*path") //
+            .to(endpoint::get().call(|index: usize| format!("{}", index))),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/users/42/posts/3")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "42-3");
+
+    let response = server.perform("/users/abc/posts/3")?;
+    assert_eq!(response.status(), 400);
+    let body = response.body().to_utf8()?;
+    assert!(
+        body.contains("invalid value 'abc' for parameter 'id'"),
+        "unexpected message: {}",
+        body
+    );
+
+    let response = server.perform("/users/42/posts/1000")?;
+    assert_eq!(response.status(), 400);
+    let body = response.body().to_utf8()?;
+    assert!(
+        body.contains("invalid value '1000' for parameter 'index'"),
+        "unexpected message: {}",
+        body
+    );
+
+    let response = server.perform("/files/a%20b")?;
+    assert_eq!(response.status(), 400);
+    let body = response.body().to_utf8()?;
+    assert!(
+        body.contains("invalid value 'a%20b' for parameter 'path'"),
+        "unexpected message: {}",
+        body
+    );
+
+    Ok(())
+}
+
+#[test]
+fn optional_param() -> tsukuyomi_server::Result<()> {
+    let app = App::create(chain![
+        path!("/items/:page?") //
+            .param_regex("page", "[0-9]+")
+            .to(endpoint::get().call(|page: Option<u32>| match page {
+                Some(page) => format!("page {}", page),
+                None => "first page".into(),
+            })),
+        path!("/users/:id/posts/:post?") //
+            .to(endpoint::get().call(|id: u32, post: Option<String>| {
+                format!("{}:{}", id, post.as_ref().map_or("<none>", |s| &**s))
+            })),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/items")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "first page");
+
+    let response = server.perform("/items/3")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "page 3");
+
+    let response = server.perform("/items/abc")?;
+    assert_eq!(response.status(), 404);
+
+    let response = server.perform("/users/42/posts")?;
+    assert_eq!(response.body().to_utf8()?, "42:<none>");
+
+    let response = server.perform("/users/42/posts/hello")?;
+    assert_eq!(response.body().to_utf8()?, "42:hello");
+
+    Ok(())
+}
+
+#[test]
+fn guard() -> tsukuyomi_server::Result<()> {
+    let app = App::create(chain![
+        path!("/items") //
+            .guard(|input| input.request.uri().query() == Some("format=json"))
+            .to(endpoint::get().call(|| "json")),
+        path!("/items") //
+            .guard(|input| input.request.uri().query() == Some("format=xml"))
+            .to(endpoint::get().call(|| "xml")),
+        path!("/items/:id") //
+            .guard(|input| input.request.headers().contains_key("x-admin"))
+            .to(endpoint::get().call(|id: u32| format!("admin {}", id))),
+        path!("/items/:id") //
+            .to(endpoint::get().call(|id: u32| format!("item {}", id))),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/items?format=json")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "json");
+
+    let response = server.perform("/items?format=xml")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "xml");
+
+    let response = server.perform("/items?format=yaml")?;
+    assert_eq!(response.status(), 404);
+
+    let response = server.perform("/items")?;
+    assert_eq!(response.status(), 404);
+
+    let response = server.perform(Request::get("/items/42").header("x-admin", "1"))?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "admin 42");
+
+    let response = server.perform("/items/42")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "item 42");
+
+    Ok(())
+}
+
+#[test]
+fn guard_with_different_methods() -> tsukuyomi_server::Result<()> {
+    let app = App::create(chain![
+        path!("/items") //
+            .guard(|input| input.request.headers().contains_key("x-api-key"))
+            .to(endpoint::get().call(|| "list")),
+        path!("/items") //
+            .guard(|input| input.request.headers().contains_key("x-api-key"))
+            .to(endpoint::post().call(|| "create")),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform(Request::get("/items").header("x-api-key", "secret"))?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "list");
+
+    // the request is not dispatched to the endpoint for GET, even though it passes the guard.
+    let response = server.perform(Request::post("/items").header("x-api-key", "secret"))?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "create");
+
+    let response = server.perform(Request::head("/items").header("x-api-key", "secret"))?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "");
+
+    let response = server.perform(Request::put("/items").header("x-api-key", "secret"))?;
+    assert_eq!(response.status(), 405);
+
+    let response = server.perform(Request::post("/items"))?;
+    assert_eq!(response.status(), 404);
+
+    Ok(())
+}
+
+#[test]
+fn guard_unreachable_route() {
+    assert!(App::create(chain![
+        path!("/items") //
+            .to(endpoint::call(|| "")),
+        path!("/items") //
+            .guard(|_| true)
+            .to(endpoint::call(|| "")),
+    ])
+    .is_err());
+
+    assert!(App::create(
+        path!("*") //
+            .guard(|_| true)
+            .to(endpoint::call(|| "")),
+    )
+    .is_err());
+}
+
+#[test]
+fn redirect_trailing_slash() -> tsukuyomi_server::Result<()> {
+    let app = App::create(chain![
+        config::redirect_trailing_slash(true),
+        path!("/foo") //
+            .to(endpoint::reply("foo")),
+        path!("/bar/") //
+            .to(endpoint::reply("bar")),
+        path!("/items/") //
+            .to(endpoint::reply("items")),
+        path!("/items/:id") //
+            .to(endpoint::call(|id: u32| format!("item {}", id))),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/foo/?a=b")?;
+    assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(response.header(header::LOCATION)?, "/foo?a=b");
+
+    let response = server.perform(Request::post("/bar"))?;
+    assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(response.header(header::LOCATION)?, "/bar/");
+
+    let response = server.perform("/items")?;
+    assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(response.header(header::LOCATION)?, "/items/");
+
+    let response = server.perform("/items/")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "items");
+
+    let response = server.perform("/items/1/")?;
+    assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(response.header(header::LOCATION)?, "/items/1");
+
+    let response = server.perform("/baz/")?;
+    assert_eq!(response.status(), 404);
+
+    Ok(())
+}
+
+#[test]
+fn redirect_trailing_slash_disabled() -> tsukuyomi_server::Result<()> {
+    let app = App::create(
+        path!("/foo") //
+            .to(endpoint::reply("foo")),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/foo/")?;
+    assert_eq!(response.status(), 404);
+
+    Ok(())
+}
+
+#[test]
+fn remaining_path() -> tsukuyomi_server::Result<()> {
+    let remaining_path = || {
+        extractor::ready(|input| {
+            input
+                .remaining_path()
+                .map(|path| (path.to_owned(),))
+                .ok_or_else(|| tsukuyomi::error::internal_server_error("unreachable"))
+        })
+    };
+
+    let app = App::create(chain![
+        mount("/api").with(chain![
+            path!("*") //
+                .to(endpoint::any()
+                    .extract(remaining_path())
+                    .call(|path: String| path)),
+            path!("/users/:id") //
+                .to(endpoint::get()
+                    .extract(remaining_path())
+                    .call(|_id: u32, path: String| path)),
+        ]),
+        path!("/foo") //
+            .to(endpoint::get()
+                .extract(remaining_path())
+                .call(|path: String| path)),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/api/users/42/posts")?;
+    assert_eq!(response.body().to_utf8()?, "/users/42/posts");
+
+    let response = server.perform("/api")?;
+    assert_eq!(response.body().to_utf8()?, "/");
+
+    let response = server.perform("/api/users/42")?;
+    assert_eq!(response.body().to_utf8()?, "/users/42");
+
+    let response = server.perform("/foo")?;
+    assert_eq!(response.body().to_utf8()?, "/foo");
+
+    Ok(())
+}
+
+#[test]
+fn mount_with_params() -> tsukuyomi_server::Result<()> {
+    let param = |name: &'static str| {
+        extractor::ready(move |input| {
+            input
+                .params
+                .as_ref()
+                .and_then(|params| params.name(name))
+                .map(|value| (value.to_owned(),))
+                .ok_or_else(|| tsukuyomi::error::internal_server_error("missing parameter"))
+        })
+    };
+
+    let app = App::create(chain![
+        mount("/tenants/:tenant").with(chain![
+            path!("*") //
+                .to(endpoint::any()
+                    .extract(param("tenant"))
+                    .call(|tenant: String| format!("fallback {}", tenant))),
+            path!("/users/:id") //
+                .to(endpoint::get()
+                    .extract(param("tenant"))
+                    .call(|id: u32, tenant: String| format!("{} user {}", tenant, id))),
+        ]),
+        mount("/orgs/:org").with(
+            mount("/projects/:project").with(
+                path!("/") //
+                    .to(endpoint::get()
+                        .extract(param("org"))
+                        .extract(param("project"))
+                        .call(|org: String, project: String| format!("{}/{}", org, project))),
+            )
+        ),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/tenants/acme/users/42")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "acme user 42");
+
+    let response = server.perform("/tenants/acme")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "fallback acme");
+
+    let response = server.perform("/tenants/acme/users/42/posts")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "fallback acme");
+
+    let response = server.perform("/orgs/tsukuyomi/projects/ganymede")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "tsukuyomi/ganymede");
+
+    let response = server.perform("/orgs/tsukuyomi")?;
+    assert_eq!(response.status(), 404);
+
+    assert!(App::create(mount("/files/*path").with(())).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn routes() -> tsukuyomi_server::Result<()> {
+    let app = App::create(chain![
+        path!("*") //
+            .to(endpoint::call(|| "fallback")),
+        path!("/") //
+            .to(endpoint::get().call(|| "index")),
+        mount("/api").with(chain![
+            path!("/users/:id") //
+                .to(endpoint::allow_only("GET, DELETE")?.call(|_id: u32| "user")),
+            path!("/posts") //
+                .to(endpoint::call(|| "posts")),
+        ]),
+    ])?;
+
+    let routes = app.routes();
+    let uris: Vec<_> = routes.iter().map(|route| route.uri()).collect();
+    assert_eq!(uris, vec!["/", "/api/users/:id", "/api/posts"]);
+
+    let methods = |i: usize| {
+        routes[i]
+            .allowed_methods()
+            .map(|methods| methods.iter().cloned().collect::<Vec<_>>())
+    };
+    assert_eq!(methods(0), Some(vec![Method::GET]));
+    assert_eq!(methods(1), Some(vec![Method::GET, Method::DELETE]));
+    assert_eq!(methods(2), None);
+
+    assert_ne!(routes[0].scope_id(), routes[1].scope_id());
+    assert_eq!(routes[1].scope_id(), routes[2].scope_id());
+
+    Ok(())
+}
+
+#[test]
+fn method_semantics() -> tsukuyomi_server::Result<()> {
+    use tsukuyomi::input::method::{MethodSemantics, Semantics};
+
+    let propfind = Method::from_bytes(b"PROPFIND").unwrap();
+    let app = App::create(chain![
+        config::method_semantics(MethodSemantics::new().set(
+            propfind.clone(),
+            Semantics {
+                safe: true,
+                idempotent: true,
+                cacheable: false,
+            },
+        )),
+        path!("/") //
+            .to(endpoint::any()
+                .extract(extractor::ready(|input| {
+                    let semantics = input.method_semantics().get(input.request.method());
+                    Ok::<_, tsukuyomi::Error>((format!(
+                        "{} {} {}",
+                        semantics.safe, semantics.idempotent, semantics.cacheable
+                    ),))
+                }))
+                .call(|flags: String| flags)),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform(Request::get("/"))?;
+    assert_eq!(response.body().to_utf8()?, "true true true");
+
+    let response = server.perform(Request::post("/"))?;
+    assert_eq!(response.body().to_utf8()?, "false false false");
+
+    let response = server.perform(Request::delete("/"))?;
+    assert_eq!(response.body().to_utf8()?, "false true false");
+
+    let response = server.perform(Request::builder().method(propfind).uri("/"))?;
+    assert_eq!(response.body().to_utf8()?, "true true false");
+
+    Ok(())
+}
+
+#[test]
+fn method_fallback() -> tsukuyomi_server::Result<()> {
+    use tsukuyomi::input::method::MethodSemantics;
+
+    let propfind = Method::from_bytes(b"PROPFIND").unwrap();
+    let app = App::create(chain![
+        config::method_semantics(
+            MethodSemantics::new()
+                .set_fallback(Method::HEAD, None)
+                .set_fallback(propfind.clone(), Some(Method::GET)),
+        ),
+        config::auto_options(true),
+        path!("/") //
+            .to(endpoint::get().reply("get")),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform(Request::head("/"))?;
+    assert_eq!(response.status(), 405);
+
+    let response = server.perform(Request::builder().method(propfind).uri("/"))?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "get");
+
+    let response = server.perform(Request::options("/"))?;
+    assert_eq!(response.header(header::ALLOW)?, "GET, PROPFIND, OPTIONS");
+
+    Ok(())
+}
+
+#[test]
+fn preload_link_headers() -> tsukuyomi_server::Result<()> {
+    let app = App::create(
+        path!("/") //
+            .to(endpoint::get()
+                .extract(extractor::ready(|input| {
+                    input.preload("/app.js", "script")?;
+                    input.preload("/style.css", "style")?;
+                    Ok::<_, tsukuyomi::Error>(())
+                }))
+                .reply("index")),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/")?;
+    assert_eq!(
+        response.header(header::LINK)?,
+        "</app.js>; rel=preload; as=script, </style.css>; rel=preload; as=style"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn is_secure() -> tsukuyomi_server::Result<()> {
+    let is_secure = || extractor::ready(|input| Ok::<_, tsukuyomi::Error>((input.is_secure(),)));
+
+    let app = App::create(chain![
+        path!("/direct") //
+            .to(endpoint::get()
+                .extract(is_secure())
+                .call(|secure: bool| format!("{}", secure))),
+        path!("/proxied") //
+            .to(endpoint::get()
+                .extract(is_secure())
+                .call(|secure: bool| format!("{}", secure)))
+            .modify(tsukuyomi::modifiers::trust_forwarded_proto()),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/direct")?;
+    assert_eq!(response.body().to_utf8()?, "false");
+
+    let response = server.perform(
+        Request::get("/direct").extension(tsukuyomi::input::Secure), //
+    )?;
+    assert_eq!(response.body().to_utf8()?, "true");
+
+    let response = server.perform(
+        Request::get("/direct").header("x-forwarded-proto", "https"), //
+    )?;
+    assert_eq!(response.body().to_utf8()?, "false");
+
+    let response = server.perform(
+        Request::get("/proxied").header("x-forwarded-proto", "https"), //
+    )?;
+    assert_eq!(response.body().to_utf8()?, "true");
+
+    let response = server.perform(
+        Request::get("/proxied").header("x-forwarded-proto", "http"), //
+    )?;
+    assert_eq!(response.body().to_utf8()?, "false");
+
+    Ok(())
+}
+
+#[test]
+fn remote_addr_and_forwarded_for() -> tsukuyomi_server::Result<()> {
+    use std::net::{IpAddr, SocketAddr};
+
+    let app = App::create(
+        path!("/") //
+            .to(endpoint::get()
+                .extract(extractor::ready(|input| {
+                    Ok::<_, tsukuyomi::Error>((input.remote_addr(), input.forwarded_for()))
+                }))
+                .call(
+                    |remote_addr: Option<SocketAddr>, forwarded_for: Vec<IpAddr>| {
+                        let remote_addr = remote_addr.map_or_else(|| "-".into(), |a| a.to_string());
+                        let forwarded_for: Vec<_> =
+                            forwarded_for.iter().map(ToString::to_string).collect();
+                        format!("{} [{}]", remote_addr, forwarded_for.join(", "))
+                    },
+                )),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/")?;
+    assert_eq!(response.body().to_utf8()?, "- []");
+
+    let remote_addr: SocketAddr = ([192, 168, 0, 1], 12345).into();
+    let response = server.perform(
+        Request::get("/")
+            .extension(remote_addr)
+            .header("x-forwarded-for", "203.0.113.1, unknown")
+            .header("x-forwarded-for", "2001:db8::1"),
+    )?;
+    assert_eq!(
+        response.body().to_utf8()?,
+        "192.168.0.1:12345 [203.0.113.1, 2001:db8::1]"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn query_param() -> tsukuyomi_server::Result<()> {
+    let app = App::create(
+        path!("/") //
+            .to(endpoint::get()
+                .extract(extractor::ready(|input| {
+                    let params: Vec<_> = input
+                        .query_params()
+                        .map(|(key, value)| format!("{}={}", key, value))
+                        .collect();
+                    Ok::<_, tsukuyomi::Error>((
+                        input.query_param("name").map(|name| name.into_owned()),
+                        params,
+                    ))
+                }))
+                .call(|name: Option<String>, params: Vec<String>| {
+                    format!(
+                        "{} [{}]",
+                        name.map_or_else(|| "-".into(), |name| format!("{:?}", name)),
+                        params.join(", ")
+                    )
+                })),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/")?;
+    assert_eq!(response.body().to_utf8()?, "- []");
+
+    let response = server.perform("/?foo=bar")?;
+    assert_eq!(response.body().to_utf8()?, "- [foo=bar]");
+
+    let response = server.perform("/?name=")?;
+    assert_eq!(response.body().to_utf8()?, r#""" [name=]"#);
+
+    let response = server.perform("/?name")?;
+    assert_eq!(response.body().to_utf8()?, r#""" [name=]"#);
+
+    let response = server.perform("/?name=John+Doe&name=alice&x=%E3%81%82")?;
+    assert_eq!(
+        response.body().to_utf8()?,
+        r#""John Doe" [name=John Doe, name=alice, x=あ]"#
+    );
+
+    Ok(())
+}
+
+#[test]
+fn absolute_url() -> tsukuyomi_server::Result<()> {
+    let absolute_url = |path: &'static str| {
+        extractor::ready(move |input| input.absolute_url(path).map(|url| (url,)))
+    };
+
+    let app = App::create(chain![
+        path!("/direct") //
+            .to(endpoint::get()
+                .extract(absolute_url("/callback?code=a b"))
+                .call(|url: String| url)),
+        path!("/invalid") //
+            .to(endpoint::get()
+                .extract(absolute_url("//example.org/"))
+                .call(|url: String| url)),
+        path!("/proxied") //
+            .to(endpoint::get()
+                .extract(absolute_url("/sitemap.xml"))
+                .call(|url: String| url))
+            .modify(tsukuyomi::modifiers::trust_forwarded_proto())
+            .modify(tsukuyomi::modifiers::trust_forwarded_host()),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform(
+        Request::get("/direct").header("host", "localhost:4000"), //
+    )?;
+    assert_eq!(
+        response.body().to_utf8()?,
+        "http://localhost:4000/callback?code=a%20b"
+    );
+
+    let response = server.perform(
+        Request::get("/invalid").header("host", "localhost:4000"), //
+    )?;
+    assert_eq!(response.status(), 500);
+
+    let response = server.perform(
+        Request::get("/proxied")
+            .header("host", "localhost:4000")
+            .header("x-forwarded-proto", "https")
+            .header("x-forwarded-host", "example.com, localhost:4000"),
+    )?;
+    assert_eq!(
+        response.body().to_utf8()?,
+        "https://example.com/sitemap.xml"
+    );
+
+    let response = server.perform("/proxied")?;
+    assert_eq!(response.status(), 400);
+
+    Ok(())
+}
+
+#[test]
+fn scheme_and_host() -> tsukuyomi_server::Result<()> {
+    let scheme_and_host = || {
+        extractor::ready(|input| {
+            Ok::<_, tsukuyomi::Error>((format!(
+                "{} {}",
+                input.scheme(),
+                input.host().unwrap_or("<none>")
+            ),))
+        })
+    };
+
+    let app = App::create(chain![
+        path!("/direct") //
+            .to(endpoint::get()
+                .extract(scheme_and_host())
+                .call(|s: String| s)),
+        path!("/proxied") //
+            .to(endpoint::get()
+                .extract(scheme_and_host())
+                .call(|s: String| s))
+            .modify(tsukuyomi::modifiers::trust_forwarded_proto())
+            .modify(tsukuyomi::modifiers::trust_forwarded_host()),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    // the forwarded headers are ignored unless trusted.
+    let response = server.perform(
+        Request::get("/direct")
+            .header("host", "localhost:4000")
+            .header("forwarded", "proto=https;host=example.com")
+            .header("x-forwarded-proto", "https")
+            .header("x-forwarded-host", "example.com"),
+    )?;
+    assert_eq!(response.body().to_utf8()?, "http localhost:4000");
+
+    let response = server.perform("/direct")?;
+    assert_eq!(response.body().to_utf8()?, "http <none>");
+
+    let response = server.perform(
+        Request::get("/proxied")
+            .header("host", "localhost:4000")
+            .header("x-forwarded-proto", "https")
+            .header("x-forwarded-host", "example.com"),
+    )?;
+    assert_eq!(response.body().to_utf8()?, "https example.com");
+
+    // `Forwarded` takes precedence over `X-Forwarded-*`.
+    let response = server.perform(
+        Request::get("/proxied")
+            .header("host", "localhost:4000")
+            .header(
+                "forwarded",
+                r#"for=192.0.2.60;Proto=https;host="example.org:8443", for=198.51.100.17"#,
+            )
+            .header("x-forwarded-proto", "http")
+            .header("x-forwarded-host", "example.com"),
+    )?;
+    assert_eq!(response.body().to_utf8()?, "https example.org:8443");
+
+    let response = server.perform(
+        Request::get("/proxied")
+            .header("host", "localhost:4000")
+            .header("forwarded", "for=192.0.2.60"),
+    )?;
+    assert_eq!(response.body().to_utf8()?, "http localhost:4000");
+
+    Ok(())
+}
+
+#[test]
+fn matched_route_pattern() -> tsukuyomi_server::Result<()> {
+    let pattern = || {
+        extractor::ready(|input| {
+            Ok::<_, tsukuyomi::Error>((input
+                .matched_route_pattern()
+                .unwrap_or("<none>")
+                .to_owned(),))
+        })
+    };
+
+    let app = App::create(chain![
+        path!("/") //
+            .to(endpoint::get().extract(pattern()).call(|p: String| p)),
+        mount("/api").with(chain![
+            path!("*") //
+                .to(endpoint::any().extract(pattern()).call(|p: String| p)),
+            path!("/users/:id") //
+                .to(endpoint::get()
+                    .extract(pattern())
+                    .call(|_id: u32, p: String| p)),
+        ]),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/")?;
+    assert_eq!(response.body().to_utf8()?, "/");
+
+    let response = server.perform("/api/users/42")?;
+    assert_eq!(response.body().to_utf8()?, "/api/users/:id");
+
+    let response = server.perform("/api/posts")?;
+    assert_eq!(response.body().to_utf8()?, "<none>");
+
+    Ok(())
+}
+
+#[test]
+fn draining() -> tsukuyomi_server::Result<()> {
+    let app = App::create(
+        path!("/") //
+            .to(endpoint::reply("hello")),
+    )?;
+    let handle = app.clone();
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/")?;
+    assert_eq!(response.status(), 200);
+    assert!(!handle.is_draining());
+
+    handle.start_draining();
+    assert!(handle.is_draining());
+
+    let response = server.perform("/")?;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.header(header::CONNECTION)?, "close");
+
+    Ok(())
+}
+
+#[test]
+fn draining_custom_response() -> tsukuyomi_server::Result<()> {
+    let app = App::create(chain![
+        config::draining_response(|_| {
+            http::Response::builder()
+                .status(StatusCode::SERVICE_UNAVAILABLE)
+                .header(header::RETRY_AFTER, "10")
+                .body("draining".into())
+                .unwrap()
+        }),
+        path!("/") //
+            .to(endpoint::reply("hello")),
+    ])?;
+    app.start_draining();
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/")?;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.header(header::CONNECTION)?, "close");
+    assert_eq!(response.header(header::RETRY_AFTER)?, "10");
+    assert_eq!(response.body().to_utf8()?, "draining");
+
+    Ok(())
+}
+
+#[test]
+fn error_renderer() -> tsukuyomi_server::Result<()> {
+    let app = App::create(chain![
+        config::error_renderer(|err, input| {
+            let status = err.into_response(input.request).status();
+            http::Response::builder()
+                .status(status)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(
+                    format!(
+                        r#"{{"status":{},"path":"{}"}}"#,
+                        status.as_u16(),
+                        input.request.uri().path()
+                    )
+                    .into(),
+                )
+                .unwrap()
+        }),
+        path!("/") //
+            .to(endpoint::get().reply("hello")),
+        path!("/conflict") //
+            .to(endpoint::get().call(|| -> tsukuyomi::Result<&'static str> {
+                Err(tsukuyomi::error::from_status(StatusCode::CONFLICT))
+            })),
+        path!("/private") //
+            .to(endpoint::get().call(|| -> tsukuyomi::Result<&'static str> {
+                Err(tsukuyomi::error::unauthorized_basic("private"))
+            })),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/")?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.body().to_utf8()?, "hello");
+
+    let response = server.perform("/missing")?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(response.header(header::CONTENT_TYPE)?, "application/json");
+    assert_eq!(
+        response.body().to_utf8()?,
+        r#"{"status":404,"path":"/missing"}"#
+    );
+
+    let response = server.perform("/conflict")?;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    assert_eq!(response.header(header::CONTENT_TYPE)?, "application/json");
+    assert_eq!(
+        response.body().to_utf8()?,
+        r#"{"status":409,"path":"/conflict"}"#
+    );
+
+    // the header fields provided by the error are preserved.
+    let response = server.perform("/private")?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(response.header(header::CONTENT_TYPE)?, "application/json");
+    assert_eq!(
+        response.header(header::WWW_AUTHENTICATE)?,
+        "Basic realm=\"private\""
+    );
+
+    Ok(())
+}
+
+#[test]
+fn not_found_handler() -> tsukuyomi_server::Result<()> {
+    use tsukuyomi::{future::oneshot, handler::handler};
+
+    let app = App::create(chain![
+        config::not_found_handler(handler(
+            || {
+                oneshot(|_| {
+                    http::Response::builder()
+                        .status(StatusCode::NOT_FOUND)
+                        .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
+                        .body("<h1>Not Found</h1>")
+                        .map_err(tsukuyomi::error::internal_server_error)
+                })
+            },
+            None,
+        )),
+        path!("/") //
+            .to(endpoint::get().reply("index")),
+        mount("/admin").with(config::default_handler(handler(
+            || oneshot(|_| Ok::<_, tsukuyomi::Error>("admin.html")),
+            None,
+        ))),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/")?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.body().to_utf8()?, "index");
+
+    let response = server.perform("/missing")?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        response.header(header::CONTENT_TYPE)?,
+        "text/html; charset=utf-8"
+    );
+    assert_eq!(response.body().to_utf8()?, "<h1>Not Found</h1>");
+
+    // the default handler of the scope takes precedence.
+    let response = server.perform("/admin/missing")?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.body().to_utf8()?, "admin.html");
+
+    Ok(())
+}
+
+#[test]
+fn error_with_headers() -> tsukuyomi_server::Result<()> {
+    #[derive(Debug)]
+    struct Unauthorized;
+
+    impl std::fmt::Display for Unauthorized {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("unauthorized")
+        }
+    }
+
+    impl tsukuyomi::HttpError for Unauthorized {
+        type Body = &'static str;
+
+        fn into_response(self, _: &Request<()>) -> http::Response<Self::Body> {
+            http::Response::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .body("unauthorized")
+                .unwrap()
+        }
+
+        fn headers(&self) -> Option<http::HeaderMap> {
+            let mut headers = http::HeaderMap::new();
+            headers.insert(
+                header::WWW_AUTHENTICATE,
+                header::HeaderValue::from_static("Basic"),
+            );
+            Some(headers)
+        }
+    }
+
+    let app = App::create(
+        path!("/") //
+            .to(endpoint::get()
+                .call(|| -> tsukuyomi::Result<&'static str> { Err(Unauthorized.into()) })),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/")?;
+    assert_eq!(response.status(), 401);
+    assert_eq!(response.header(header::WWW_AUTHENTICATE)?, "Basic");
+    assert_eq!(response.body().to_utf8()?, "unauthorized");
+
+    Ok(())
+}
+
+#[test]
+fn error_from_status() -> tsukuyomi_server::Result<()> {
+    use tsukuyomi::error;
+
+    let app = App::create(chain![
+        path!("/conflict") //
+            .to(endpoint::get().call(|| -> tsukuyomi::Result<&'static str> {
+                Err(error::from_status(StatusCode::CONFLICT))
+            })),
+        path!("/legal") //
+            .to(endpoint::get().call(|| -> tsukuyomi::Result<&'static str> {
+                Err(error::from_status_with(
+                    StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS,
+                    "unavailable in your country",
+                ))
+            })),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/conflict")?;
+    assert_eq!(response.status(), 409);
+    assert!(response.body().to_bytes().is_empty());
+
+    let response = server.perform("/legal")?;
+    assert_eq!(response.status(), 451);
+    assert_eq!(response.body().to_utf8()?, "unavailable in your country");
+
+    Ok(())
+}
+
+#[test]
+fn request_extensions() -> tsukuyomi_server::Result<()> {
+    use tsukuyomi_service::Service;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct PeerInfo(&'static str);
+
+    let app = App::create(
+        path!("/") //
+            .to(endpoint::get()
+                .extract(extractor::ready(|input| {
+                    Ok::<_, tsukuyomi::Error>((input.extensions().get::<PeerInfo>().cloned(),))
+                }))
+                .call(|info: Option<PeerInfo>| info.map_or("none", |info| info.0))),
+    )?
+    .with_modify_service(tsukuyomi_service::modify_service(
+        |mut service: tsukuyomi::app::AppService<_>, _: ()| {
+            Ok::<_, std::io::Error>(tsukuyomi_service::service_fn(
+                move |mut request: Request<hyper::Body>| {
+                    request.extensions_mut().insert(PeerInfo("alice"));
+                    service.call(request)
+                },
+            ))
+        },
+    ));
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "alice");
+
+    Ok(())
+}
+
+#[test]
+fn expect_continue() -> tsukuyomi_server::Result<()> {
+    use {
+        futures01::{sync::oneshot, Future},
+        std::{
+            io::{Read, Write},
+            net::{TcpListener, TcpStream},
+        },
+        tsukuyomi_server::Server,
+    };
+
+    fn read_head(stream: &mut TcpStream) -> std::io::Result<String> {
+        let mut head = Vec::new();
+        let mut buf = [0u8; 1];
+        while !head.ends_with(b"\r\n\r\n") {
+            stream.read_exact(&mut buf)?;
+            head.push(buf[0]);
+        }
+        Ok(String::from_utf8_lossy(&head).into_owned())
+    }
+
+    let app = App::create(
+        path!("/upload") //
+            .to(endpoint::post()
+                .extract(extractor::body::plain::<String>())
+                .call(|body: String| body))
+            .modify(tsukuyomi::modifiers::content_length_limit(16)),
+    )?;
+
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+    let (tx_shutdown, rx_shutdown) = oneshot::channel::<()>();
+    let server = std::thread::spawn(move || {
+        Server::new(app)
+            .bind(listener)
+            .run_until(rx_shutdown.map_err(|_| ()))
+    });
+
+    // the interim response is sent before reading the body.
+    let mut stream = TcpStream::connect(addr)?;
+    stream.write_all(
+        b"POST /upload HTTP/1.1\r\n\
+          Host: localhost\r\n\
+          Content-Length: 5\r\n\
+          Expect: 100-continue\r\n\
+          Connection: close\r\n\
+          \r\n",
+    )?;
+    let head = read_head(&mut stream)?;
+    assert!(head.starts_with("HTTP/1.1 100 Continue\r\n"), "{}", head);
+    stream.write_all(b"hello")?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert!(response.ends_with("\r\n\r\nhello"), "{}", response);
+
+    // the request rejected by the modifier receives the final response without the body.
+    let mut stream = TcpStream::connect(addr)?;
+    stream.write_all(
+        b"POST /upload HTTP/1.1\r\n\
+          Host: localhost\r\n\
+          Content-Length: 1024\r\n\
+          Expect: 100-continue\r\n\
+          Connection: close\r\n\
+          \r\n",
+    )?;
+    let head = read_head(&mut stream)?;
+    assert!(
+        head.starts_with("HTTP/1.1 417 Expectation Failed\r\n"),
+        "{}",
+        head
+    );
+    drop(stream);
+
+    let _ = tx_shutdown.send(());
+    server.join().expect("the server thread panicked")?;
+
+    Ok(())
+}
+
+#[test]
+fn unsupported_expectation() -> tsukuyomi_server::Result<()> {
+    let app = App::create(
+        path!("/") //
+            .to(endpoint::post().reply("index")),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform(Request::post("/").header("expect", "foo"))?;
+    assert_eq!(response.status(), StatusCode::EXPECTATION_FAILED);
+
+    let response = server.perform(Request::post("/").header("expect", "100-continue"))?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    Ok(())
+}
+
+#[test]
+fn head_falls_back_to_get() -> tsukuyomi_server::Result<()> {
+    let app = App::create(chain![
+        path!("/") //
+            .to(endpoint::get().reply("hello")),
+        path!("/post") //
+            .to(endpoint::post().reply("posted")),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform(Request::head("/"))?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.header(header::CONTENT_LENGTH)?, "5");
+    assert_eq!(
+        response.header(header::CONTENT_TYPE)?,
+        "text/plain; charset=utf-8"
+    );
+    assert!(response.body().to_bytes().is_empty());
+
+    let response = server.perform(Request::head("/post"))?;
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+    Ok(())
+}
+
+#[test]
+fn auto_options() -> tsukuyomi_server::Result<()> {
+    let app = App::create(chain![
+        config::auto_options(true),
+        path!("/items") //
+            .guard(|input| input.request.method() == Method::POST)
+            .to(endpoint::post().reply("created")),
+        path!("/items") //
+            .to(endpoint::allow_only("GET, DELETE")?.reply("items")),
+        path!("/custom") //
+            .to(endpoint::allow_only("GET, OPTIONS")?.reply("custom")),
+        path!("/any") //
+            .to(endpoint::any().reply("any")),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform(Request::options("/items"))?;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert_eq!(
+        response.header(header::ALLOW)?,
+        "POST, GET, DELETE, HEAD, OPTIONS"
+    );
+    assert!(response.body().to_bytes().is_empty());
+
+    let response = server.perform(Request::post("/items"))?;
+    assert_eq!(response.body().to_utf8()?, "created");
+
+    let response = server.perform(Request::options("/custom"))?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.body().to_utf8()?, "custom");
+
+    let response = server.perform(Request::options("/any"))?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.body().to_utf8()?, "any");
+
+    let response = server.perform(Request::options("/missing"))?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    Ok(())
+}
+
+#[test]
+fn auto_options_disabled() -> tsukuyomi_server::Result<()> {
+    let app = App::create(
+        path!("/items") //
+            .to(endpoint::get().reply("items")),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform(Request::options("/items"))?;
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+    Ok(())
+}
+
+#[test]
+fn merge() -> tsukuyomi_server::Result<()> {
+    struct Greeting(&'static str);
+
+    fn greeting() -> impl tsukuyomi::extractor::Extractor<
+        Output = (&'static str,),
+        Error = tsukuyomi::Error,
+        Extract = impl tsukuyomi::future::TryFuture<Ok = (&'static str,), Error = tsukuyomi::Error>
+                      + Send
+                      + 'static,
+    > {
+        extractor::ready(|input| {
+            let greeting = input.states.try_get::<Greeting>()?;
+            Ok::<_, tsukuyomi::Error>((greeting.0,))
+        })
+    }
+
+    let users = App::create(chain![
+        path!("/") //
+            .to(endpoint::get().reply("users")),
+        path!("/:id") //
+            .to(endpoint::get()
+                .extract(greeting())
+                .call(|id: u32, greeting: &str| format!("{} user {}", greeting, id))),
+        path!("*") //
+            .to(endpoint::call(|| "users fallback")),
+    ])?;
+
+    let admin = App::create(chain![
+        config::state(Greeting("welcome")),
+        mount("/items").with(
+            path!("/:id") //
+                .to(endpoint::get()
+                    .extract(greeting())
+                    .call(|id: u32, greeting: &str| format!("{} item {}", greeting, id))),
+        ),
+    ])?;
+
+    let app = App::create(chain![
+        config::state(Greeting("hello")),
+        path!("/") //
+            .to(endpoint::get().reply("index")),
+        config::merge("/users", users),
+        config::merge("/admin", admin),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/")?;
+    assert_eq!(response.body().to_utf8()?, "index");
+
+    let response = server.perform("/users")?;
+    assert_eq!(response.body().to_utf8()?, "users");
+
+    let response = server.perform("/users/42")?;
+    assert_eq!(response.body().to_utf8()?, "hello user 42");
+
+    let response = server.perform("/users/foo/bar")?;
+    assert_eq!(response.body().to_utf8()?, "users fallback");
+
+    let response = server.perform("/admin/items/1")?;
+    assert_eq!(response.body().to_utf8()?, "welcome item 1");
+
+    let response = server.perform("/foo")?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    Ok(())
+}
+
+#[test]
+fn merge_conflicting_routes() -> tsukuyomi_server::Result<()> {
+    let create_app = || {
+        App::create(
+            path!("/:id") //
+                .to(endpoint::get().call(|id: u32| format!("{}", id))),
+        )
+    };
+
+    assert!(App::create(chain![
+        config::merge("/users", create_app()?),
+        config::merge("/users", create_app()?),
+    ])
+    .is_err());
+
+    assert!(App::create(chain![
+        path!("/users/me") //
+            .to(endpoint::get().reply("me")),
+        config::merge("/users", create_app()?),
+    ])
+    .is_err());
+
+    let app = create_app()?;
+    let _cloned = app.clone();
+    assert!(App::create(config::merge("/users", app)).is_err());
+
+    Ok(())
+}
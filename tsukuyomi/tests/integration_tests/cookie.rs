@@ -48,3 +48,74 @@ fn disable_manage_cookies() -> tsukuyomi_server::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn cookie_extractors() -> tsukuyomi_server::Result<()> {
+    use tsukuyomi::extractor;
+
+    let app = App::create(chain![
+        path!("/typed") //
+            .to(endpoint::get()
+                .extract(extractor::cookie::typed("id"))
+                .call(|id: u32| format!("{}", id))),
+        path!("/optional") //
+            .to(endpoint::get()
+                .extract(extractor::cookie::optional("id"))
+                .call(|id: Option<u32>| format!("{:?}", id))),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform(http::Request::get("/typed").header("cookie", "id=42"))?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "42");
+
+    let response = server.perform("/typed")?;
+    assert_eq!(response.status(), 400);
+
+    let response = server.perform(http::Request::get("/typed").header("cookie", "id=foo"))?;
+    assert_eq!(response.status(), 400);
+
+    let response = server.perform(http::Request::get("/optional").header("cookie", "id=42"))?;
+    assert_eq!(response.body().to_utf8()?, "Some(42)");
+
+    let response = server.perform("/optional")?;
+    assert_eq!(response.body().to_utf8()?, "None");
+
+    Ok(())
+}
+
+#[cfg(feature = "secure")]
+#[test]
+fn signed_cookie_extractor() -> tsukuyomi_server::Result<()> {
+    use {
+        cookie::{CookieJar, Key},
+        tsukuyomi::{config, extractor},
+    };
+
+    let key = Key::generate();
+    let signed_value = {
+        let mut jar = CookieJar::new();
+        jar.signed(&key).add(Cookie::new("id", "42"));
+        jar.get("id").expect("should be added").value().to_owned()
+    };
+
+    let app = App::create(chain![
+        path!("/") //
+            .to(endpoint::get()
+                .extract(extractor::cookie::signed("id"))
+                .call(|id: u32| format!("{}", id))),
+        config::state(key),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform(
+        http::Request::get("/").header("cookie", format!("id={}", signed_value).as_str()),
+    )?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "42");
+
+    let response = server.perform(http::Request::get("/").header("cookie", "id=42"))?;
+    assert_eq!(response.status(), 400);
+
+    Ok(())
+}
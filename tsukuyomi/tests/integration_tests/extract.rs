@@ -39,6 +39,27 @@ fn params() -> tsukuyomi_server::Result<()> {
     Ok(())
 }
 
+#[test]
+fn catch_all_bytes() -> tsukuyomi_server::Result<()> {
+    let app = App::create(chain![
+        path!("/bytes/*path") //
+            .to(endpoint::call(|path: Vec<u8>| format!("{:?}", path))),
+        path!("/string/*path") //
+            .to(endpoint::call(|path: String| path)),
+    ])?;
+
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/bytes/caf%E9")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "[99, 97, 102, 233]");
+
+    let response = server.perform("/string/caf%E9")?;
+    assert_eq!(response.status(), 400);
+
+    Ok(())
+}
+
 #[test]
 fn route_macros() -> tsukuyomi_server::Result<()> {
     let app = App::create(chain![
@@ -149,7 +170,7 @@ fn json_body() -> tsukuyomi_server::Result<()> {
 
     // missing content-type
     let response = server.perform(Request::post("/").body(&br#"{"id":23, "name":"bob"}"#[..]))?;
-    assert_eq!(response.status(), 400);
+    assert_eq!(response.status(), 415);
 
     // invalid content-type
     let response = server.perform(
@@ -157,7 +178,37 @@ fn json_body() -> tsukuyomi_server::Result<()> {
             .header("content-type", "application/graphql")
             .body(&br#"{"id":23, "name":"bob"}"#[..]),
     )?;
-    assert_eq!(response.status(), 400);
+    assert_eq!(response.status(), 415);
+
+    // content-type with the suffix `+json`
+    let response = server.perform(
+        Request::post("/")
+            .header("content-type", "application/vnd.api+json; charset=utf-8")
+            .body(&br#"{"id":23, "name":"bob"}"#[..]),
+    )?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "23,bob");
+
+    // UTF-16 encoded body
+    let body: Vec<u8> = r#"{"id":23, "name":"bob"}"#
+        .encode_utf16()
+        .flat_map(|u| vec![u as u8, (u >> 8) as u8])
+        .collect();
+    let response = server.perform(
+        Request::post("/")
+            .header("content-type", "application/json; charset=utf-16le")
+            .body(body),
+    )?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "23,bob");
+
+    // unsupported charset
+    let response = server.perform(
+        Request::post("/")
+            .header("content-type", "application/json; charset=euc-jp")
+            .body(&br#"{"id":23, "name":"bob"}"#[..]),
+    )?;
+    assert_eq!(response.status(), 415);
 
     // invalid data
     let response = server.perform(
@@ -197,7 +248,7 @@ fn urlencoded_body() -> tsukuyomi_server::Result<()> {
 
     // missing content-type
     let response = server.perform(Request::post("/").body(BODY))?;
-    assert_eq!(response.status(), 400);
+    assert_eq!(response.status(), 415);
 
     // invalid content-type
     let response = server.perform(
@@ -205,7 +256,7 @@ fn urlencoded_body() -> tsukuyomi_server::Result<()> {
             .header("content-type", "application/graphql")
             .body(BODY),
     )?;
-    assert_eq!(response.status(), 400);
+    assert_eq!(response.status(), 415);
 
     // invalid data
     let response = server.perform(
@@ -218,6 +269,61 @@ fn urlencoded_body() -> tsukuyomi_server::Result<()> {
     Ok(())
 }
 
+#[test]
+fn urlencoded_body_limit_and_optional() -> tsukuyomi_server::Result<()> {
+    #[derive(Debug, serde::Deserialize)]
+    struct Params {
+        id: u32,
+    }
+
+    let app = App::create(chain![
+        path!("/limited") //
+            .to(endpoint::post()
+                .extract(extractor::body::urlencoded::with_limit(8))
+                .call(|params: Params| format!("{}", params.id))),
+        path!("/optional") //
+            .to(endpoint::post()
+                .extract(extractor::body::urlencoded::optional())
+                .call(|params: Option<Params>| match params {
+                    Some(params) => format!("{}", params.id),
+                    None => "none".into(),
+                })),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform(
+        Request::post("/limited")
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body("id=23"),
+    )?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "23");
+
+    let response = server.perform(
+        Request::post("/limited")
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body("id=23&name=bob"),
+    )?;
+    assert_eq!(response.status(), 413);
+
+    let response = server.perform(
+        Request::post("/optional")
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body("id=42"),
+    )?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "42");
+
+    let response = server.perform(Request::post("/optional").body(""))?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "none");
+
+    let response = server.perform(Request::post("/optional").body("id=42"))?;
+    assert_eq!(response.status(), 415);
+
+    Ok(())
+}
+
 #[test]
 fn local_data() -> tsukuyomi_server::Result<()> {
     use {
@@ -422,7 +528,702 @@ fn either_or() -> tsukuyomi_server::Result<()> {
             .header("content-type", "text/plain; charset=utf-8")
             .body(&b"///invalid string"[..]),
     )?;
+    assert_eq!(response.status(), 415);
+
+    Ok(())
+}
+
+#[test]
+fn strict_query() -> tsukuyomi_server::Result<()> {
+    #[derive(Debug, serde::Deserialize)]
+    struct Params {
+        id: u32,
+        #[serde(default)]
+        name: Option<String>,
+    }
+
+    let app = App::create(
+        path!("/") //
+            .to(endpoint::get()
+                .extract(extractor::query::strict())
+                .call(|params: Params| format!("{},{:?}", params.id, params.name))),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/?id=23&name=bob")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "23,Some(\"bob\")");
+
+    let response = server.perform("/?id=23")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "23,None");
+
+    let response = server.perform("/?id=23&nmae=bob")?;
+    assert_eq!(response.status(), 400);
+
+    let response = server.perform("/")?;
+    assert_eq!(response.status(), 400);
+
+    Ok(())
+}
+
+#[test]
+fn strict_query_unknown_field() -> tsukuyomi_server::Result<()> {
+    use tsukuyomi::extractor::query::ExtractQueryError;
+
+    #[derive(Debug, serde::Deserialize)]
+    struct Params {
+        #[allow(dead_code)]
+        id: u32,
+    }
+
+    let app = App::create(
+        path!("/") //
+            .to(endpoint::get()
+                .extract(extractor::query::strict().map_err(|err: tsukuyomi::Error| {
+                    match err.downcast_ref::<ExtractQueryError>() {
+                        Some(ExtractQueryError::UnknownField { name }) => {
+                            tsukuyomi::error::bad_request(format!("unexpected key: {}", name))
+                        }
+                        _ => err,
+                    }
+                }))
+                .call(|_: Params| "ok")),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/?id=23&nmae=bob")?;
+    assert_eq!(response.status(), 400);
+    assert_eq!(response.body().to_utf8()?, "unexpected key: nmae");
+
+    let response = server.perform("/?id=foo")?;
+    assert_eq!(response.status(), 400);
+    assert!(response
+        .body()
+        .to_utf8()?
+        .starts_with("the query is invalid"));
+
+    Ok(())
+}
+
+#[test]
+fn json_stream() -> tsukuyomi_server::Result<()> {
+    use {
+        futures01::{Future, Stream},
+        tsukuyomi::extractor::body::JsonStream,
+    };
+
+    #[derive(Debug, serde::Deserialize)]
+    struct Item {
+        id: u32,
+    }
+
+    let app = App::create(chain![
+        path!("/") //
+            .to(endpoint::post()
+                .extract(extractor::body::json_stream())
+                .call_async(|items: JsonStream<Item>| {
+                    items
+                        .map(|item| item.id.to_string())
+                        .collect()
+                        .map(|ids| ids.join(","))
+                })),
+        path!("/strings") //
+            .to(endpoint::post()
+                .extract(extractor::body::json_stream_with_limit(16))
+                .call_async(|items: JsonStream<String>| {
+                    items.collect().map(|items| items.join("|"))
+                })),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform(
+        Request::post("/")
+            .header("content-type", "application/json")
+            .body(&b" [ {\"id\": 1}, {\"id\":2} ,{\"id\": 42}] "[..]),
+    )?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "1,2,42");
+
+    let response = server.perform(
+        Request::post("/")
+            .header("content-type", "application/json")
+            .body(tsukuyomi_server::test::body_stream(
+                futures01::stream::iter_ok::<_, std::io::Error>(vec![
+                    "[{\"id\"",
+                    ": 1}, {\"i",
+                    "d\": 2}]",
+                ]),
+            )),
+    )?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "1,2");
+
+    let response = server.perform(
+        Request::post("/")
+            .header("content-type", "application/json")
+            .body(&b"[]"[..]),
+    )?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "");
+
+    let response = server.perform(
+        Request::post("/")
+            .header("content-type", "application/json")
+            .body(&b"[{\"id\": 1}, {\"id\": 2}"[..]),
+    )?;
+    assert_eq!(response.status(), 400);
+
+    let response = server.perform(
+        Request::post("/")
+            .header("content-type", "application/json")
+            .body(&b"{\"id\": 1}"[..]),
+    )?;
+    assert_eq!(response.status(), 400);
+
+    // trailing characters after the end of array.
+    let response = server.perform(
+        Request::post("/")
+            .header("content-type", "application/json")
+            .body(&b"[{\"id\": 1}] {\"id\": 2}"[..]),
+    )?;
+    assert_eq!(response.status(), 400);
+
+    // the brackets and escaped quotes in strings, split into several chunks.
+    let response = server.perform(
+        Request::post("/strings")
+            .header("content-type", "application/json")
+            .body(tsukuyomi_server::test::body_stream(
+                futures01::stream::iter_ok::<_, std::io::Error>(vec![
+                    "[\"a],\\",
+                    "\"b\", \"[c",
+                    "]\"]",
+                ]),
+            )),
+    )?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "a],\"b|[c]");
+
+    // the element exceeds the limit.
+    let response = server.perform(
+        Request::post("/strings")
+            .header("content-type", "application/json")
+            .body(&b"[\"short\", \"too long string value\"]"[..]),
+    )?;
+    assert_eq!(response.status(), 413);
+
+    Ok(())
+}
+
+#[test]
+fn multi_query() -> tsukuyomi_server::Result<()> {
+    #[derive(Debug, serde::Deserialize)]
+    struct Filter {
+        name: String,
+        #[serde(default)]
+        max: Option<u32>,
+    }
+
+    #[derive(Debug, serde::Deserialize)]
+    struct Params {
+        #[serde(default)]
+        tag: Vec<String>,
+        #[serde(default)]
+        id: Vec<u32>,
+        filter: Option<Filter>,
+        q: Option<String>,
+    }
+
+    let app = App::create(
+        path!("/") //
+            .to(endpoint::get()
+                .extract(extractor::query::multi())
+                .call(|params: Params| format!("{:?}", params))),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/?tag=a&tag=b&id[]=1&id[]=2")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.body().to_utf8()?,
+        r#"Params { tag: ["a", "b"], id: [1, 2], filter: None, q: None }"#
+    );
+
+    let response = server.perform("/?tag=a&q=")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.body().to_utf8()?,
+        r#"Params { tag: ["a"], id: [], filter: None, q: Some("") }"#
+    );
+
+    let response = server.perform("/?filter[name]=x&filter[max]=10")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.body().to_utf8()?,
+        r#"Params { tag: [], id: [], filter: Some(Filter { name: "x", max: Some(10) }), q: None }"#
+    );
+
+    let response = server.perform("/?id=foo")?;
+    assert_eq!(response.status(), 400);
+
+    let response = server.perform("/?filter=x&filter[name]=y")?;
+    assert_eq!(response.status(), 400);
+
+    Ok(())
+}
+
+#[test]
+fn header_exact_and_optional() -> tsukuyomi_server::Result<()> {
+    let app = App::create(
+        path!("/") //
+            .to(endpoint::get()
+                .extract(extractor::header::exact("x-api-version".parse().unwrap()))
+                .extract(extractor::header::optional("x-page".parse().unwrap()))
+                .call(|version: u32, page: Option<u32>| format!("{},{:?}", version, page))),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform(Request::get("/").header("x-api-version", "2"))?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "2,None");
+
+    let response = server.perform(
+        Request::get("/")
+            .header("x-api-version", "2")
+            .header("x-page", "3"),
+    )?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "2,Some(3)");
+
+    let response = server.perform("/")?;
+    assert_eq!(response.status(), 400);
+    assert_eq!(
+        response.body().to_utf8()?,
+        "missing header field: x-api-version"
+    );
+
+    let response = server.perform(
+        Request::get("/")
+            .header("x-api-version", "2")
+            .header("x-page", "foo"),
+    )?;
+    assert_eq!(response.status(), 400);
+
+    Ok(())
+}
+
+#[test]
+fn multipart() -> tsukuyomi_server::Result<()> {
+    use {
+        futures01::{Future, Stream},
+        tsukuyomi::extractor::body::{Multipart, MultipartConfig},
+    };
+
+    let config = MultipartConfig {
+        max_field_size: Some(16),
+        ..Default::default()
+    };
+    let app = App::create(
+        path!("/") //
+            .to(endpoint::post()
+                .extract(extractor::body::multipart_with_config(config))
+                .call_async(|multipart: Multipart| {
+                    multipart
+                        .and_then(|field| {
+                            let name = field.name().to_owned();
+                            let filename = field.filename().unwrap_or("-").to_owned();
+                            let content_type = field
+                                .content_type()
+                                .map_or_else(|| "-".into(), |mime| mime.to_string());
+                            field.concat2().map(move |data| {
+                                format!(
+                                    "{}:{}:{}:{}",
+                                    name,
+                                    filename,
+                                    content_type,
+                                    String::from_utf8_lossy(&*data)
+                                )
+                            })
+                        })
+                        .collect()
+                        .map(|fields| fields.join(","))
+                })),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let body = "preamble\r\n\
+                --boundary\r\n\
+                Content-Disposition: form-data; name=\"text\"\r\n\
+                \r\n\
+                hello\r\n\
+                --boundary\r\n\
+                Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\
+                Content-Type: text/plain\r\n\
+                \r\n\
+                line1\r\nline2\r\n\
+                --boundary--\r\n";
+    let response = server.perform(
+        Request::post("/")
+            .header("content-type", "multipart/form-data; boundary=boundary")
+            .body(body),
+    )?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.body().to_utf8()?,
+        "text:-:-:hello,file:a.txt:text/plain:line1\r\nline2"
+    );
+
+    let body = "--boundary\r\n\
+                Content-Disposition: form-data; name=\"text\"\r\n\
+                \r\n\
+                too large content of field\r\n\
+                --boundary--\r\n";
+    let response = server.perform(
+        Request::post("/")
+            .header("content-type", "multipart/form-data; boundary=boundary")
+            .body(body),
+    )?;
+    assert_eq!(response.status(), 413);
+
+    let response = server.perform(
+        Request::post("/")
+            .header("content-type", "multipart/form-data")
+            .body(""),
+    )?;
+    assert_eq!(response.status(), 400);
+
+    let response = server.perform(
+        Request::post("/")
+            .header("content-type", "application/json")
+            .body("{}"),
+    )?;
+    assert_eq!(response.status(), 415);
+
+    let response = server.perform(Request::post("/").body(""))?;
+    assert_eq!(response.status(), 415);
+
+    Ok(())
+}
+
+#[test]
+fn multipart_spool() -> tsukuyomi_server::Result<()> {
+    use {
+        futures01::{Future, Stream},
+        std::io::Read,
+        tsukuyomi::extractor::body::{Multipart, MultipartConfig, Spooled},
+    };
+
+    let config = MultipartConfig {
+        spool_threshold: Some(8),
+        ..Default::default()
+    };
+    let app = App::create(
+        path!("/") //
+            .to(endpoint::post()
+                .extract(extractor::body::multipart_with_config(config))
+                .call_async(|multipart: Multipart| {
+                    multipart
+                        .and_then(|field| {
+                            let name = field.name().to_owned();
+                            field.spool().map(move |spooled| match spooled {
+                                Spooled::Memory(data) => {
+                                    format!("{}:memory:{}", name, String::from_utf8_lossy(&*data))
+                                }
+                                Spooled::File(mut file) => {
+                                    let path = file.path().to_owned();
+                                    let mut content = String::new();
+                                    file.read_to_string(&mut content).unwrap();
+                                    drop(file);
+                                    assert!(!path.exists());
+                                    format!("{}:file:{}", name, content)
+                                }
+                            })
+                        })
+                        .collect()
+                        .map(|fields| fields.join(","))
+                })),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let body = "--boundary\r\n\
+                Content-Disposition: form-data; name=\"small\"\r\n\
+                \r\n\
+                hello\r\n\
+                --boundary\r\n\
+                Content-Disposition: form-data; name=\"large\"; filename=\"a.txt\"\r\n\
+                \r\n\
+                the content written out to a file\r\n\
+                --boundary--\r\n";
+    let response = server.perform(
+        Request::post("/")
+            .header("content-type", "multipart/form-data; boundary=boundary")
+            .body(body),
+    )?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.body().to_utf8()?,
+        "small:memory:hello,large:file:the content written out to a file"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn temp_file() -> tsukuyomi_server::Result<()> {
+    use {std::io::Read, tsukuyomi::extractor::body::TempFile};
+
+    let app = App::create(chain![
+        path!("/") //
+            .to(endpoint::post().extract(extractor::body::temp_file()).call(
+                |mut file: TempFile| {
+                    let mut content = String::new();
+                    file.read_to_string(&mut content).unwrap();
+                    assert!(file.path().exists());
+                    content
+                }
+            )),
+        path!("/limited") //
+            .to(endpoint::post()
+                .extract(extractor::body::temp_file_with_limit(8))
+                .call(|_file: TempFile| "")),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform(Request::post("/").body("the uploaded content"))?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "the uploaded content");
+
+    let response = server.perform(Request::post("/limited").body("too large content"))?;
+    assert_eq!(response.status(), 413);
+
+    Ok(())
+}
+
+#[cfg(feature = "debug-extractor")]
+#[test]
+fn chain_error_position() -> tsukuyomi_server::Result<()> {
+    let fail = || extractor::ready(|_| Err::<(), _>(tsukuyomi::error::bad_request("oops")));
+    let ok = || extractor::ready(|_| Ok::<(), tsukuyomi::util::Never>(()));
+
+    let app = App::create(chain![
+        path!("/flat") //
+            .to(endpoint::get()
+                .extract(extractor::value(1u32).and(fail()).fallible())
+                .call(|res: Result<u32, tsukuyomi::Error>| match res {
+                    Ok(..) => "ok".to_string(),
+                    Err(err) => err.to_string(),
+                })),
+        path!("/nested") //
+            .to(endpoint::get()
+                .extract(extractor::value(1u32).and(fail()).and(ok()).fallible())
+                .call(|res: Result<u32, tsukuyomi::Error>| match res {
+                    Ok(..) => "ok".to_string(),
+                    Err(err) => err.to_string(),
+                })),
+        path!("/named") //
+            .to(endpoint::get()
+                .extract(extractor::value(1u32).and(fail().named("query")).fallible())
+                .call(|res: Result<u32, tsukuyomi::Error>| match res {
+                    Ok(..) => "ok".to_string(),
+                    Err(err) => err.to_string(),
+                })),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/flat")?;
+    assert_eq!(response.body().to_utf8()?, "extractor #2 failed: oops");
+
+    let response = server.perform("/nested")?;
+    assert_eq!(response.body().to_utf8()?, "extractor #1.2 failed: oops");
+
+    let response = server.perform("/named")?;
+    assert_eq!(
+        response.body().to_utf8()?,
+        "extractor #2 (query) failed: oops"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn named_error() -> tsukuyomi_server::Result<()> {
+    let fail = || extractor::ready(|_| Err::<(u32,), _>(tsukuyomi::error::bad_request("oops")));
+
+    let app = App::create(chain![
+        path!("/") //
+            .to(endpoint::get()
+                .extract(fail().named("query"))
+                .call(|n: u32| n.to_string())),
+        path!("/message") //
+            .to(endpoint::get()
+                .extract(fail().named("query").fallible())
+                .call(|res: Result<u32, tsukuyomi::Error>| match res {
+                    Ok(..) => "ok".to_string(),
+                    Err(err) => err.to_string(),
+                })),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    // the response is the same as the original error.
+    let response = server.perform("/")?;
+    assert_eq!(response.status(), 400);
+    assert_eq!(response.body().to_utf8()?, "oops");
+
+    let response = server.perform("/message")?;
+    assert_eq!(response.body().to_utf8()?, "extractor (query) failed: oops");
+
+    Ok(())
+}
+
+#[test]
+fn and_then() -> tsukuyomi_server::Result<()> {
+    #[derive(Debug, serde::Deserialize)]
+    struct Params {
+        id: u32,
+    }
+
+    let extractor = extractor::query()
+        .or(extractor::body::urlencoded())
+        .and_then(|params: Params| {
+            if params.id > 0 {
+                Ok(params.id)
+            } else {
+                Err(tsukuyomi::error::bad_request("id must be positive"))
+            }
+        });
+
+    let app = App::create(
+        path!("/") //
+            .to(endpoint::allow_only("GET, POST")?
+                .extract(extractor)
+                .call(|id: u32| format!("{}", id))),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/?id=23")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "23");
+
+    let response = server.perform(
+        Request::post("/")
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body("id=42"),
+    )?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "42");
+
+    let response = server.perform("/?id=0")?;
     assert_eq!(response.status(), 400);
 
     Ok(())
 }
+
+#[test]
+fn state() -> tsukuyomi_server::Result<()> {
+    use tsukuyomi::config;
+
+    #[derive(Clone)]
+    struct Name(&'static str);
+
+    let app = App::create(chain![
+        path!("/") //
+            .to(endpoint::get()
+                .extract(extractor::state())
+                .call(|name: Name| name.0)),
+        mount("/sub").with(chain![
+            path!("/") //
+                .to(endpoint::get()
+                    .extract(extractor::state())
+                    .call(|name: Name| name.0)),
+            path!("/missing") //
+                .to(endpoint::get()
+                    .extract(extractor::state())
+                    .call(|n: u32| format!("{}", n))),
+            config::state(Name("sub")),
+        ]),
+        mount("/inherited").with(
+            path!("/") //
+                .to(endpoint::get()
+                    .extract(extractor::state())
+                    .call(|name: Name| name.0)),
+        ),
+        config::state(Name("root")),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/")?;
+    assert_eq!(response.body().to_utf8()?, "root");
+
+    let response = server.perform("/sub")?;
+    assert_eq!(response.body().to_utf8()?, "sub");
+
+    let response = server.perform("/inherited")?;
+    assert_eq!(response.body().to_utf8()?, "root");
+
+    let response = server.perform("/sub/missing")?;
+    assert_eq!(response.status(), 500);
+
+    Ok(())
+}
+
+#[test]
+fn pool_acquire_timeout() -> tsukuyomi_server::Result<()> {
+    use {
+        std::{
+            sync::Mutex,
+            time::{Duration, Instant},
+        },
+        tsukuyomi::extractor::pool::Pool,
+    };
+
+    /// A pool that never gets the acquired resources back.
+    struct Tickets(Mutex<Vec<u32>>);
+
+    impl Pool for Tickets {
+        type Resource = u32;
+
+        fn get_timeout(&self, timeout: Duration) -> tsukuyomi::Result<Option<u32>> {
+            match self.0.lock().unwrap().pop() {
+                Some(ticket) => Ok(Some(ticket)),
+                None => {
+                    std::thread::sleep(timeout);
+                    Ok(None)
+                }
+            }
+        }
+    }
+
+    let timeout = Duration::from_millis(50);
+    let app = App::create(chain![
+        mount("/pooled").with(chain![
+            tsukuyomi::config::state(Tickets(Mutex::new(vec![1, 2]))),
+            path!("/") //
+                .to(endpoint::get()
+                    .extract(extractor::pool::acquire::<Tickets>(timeout))
+                    .call(|ticket: u32| format!("ticket {}", ticket))),
+        ]),
+        path!("/unregistered") //
+            .to(endpoint::get()
+                .extract(extractor::pool::acquire::<Tickets>(timeout))
+                .call(|ticket: u32| format!("ticket {}", ticket))),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/pooled")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "ticket 2");
+
+    let response = server.perform("/pooled")?;
+    assert_eq!(response.body().to_utf8()?, "ticket 1");
+
+    // the pool is exhausted.
+    let start = Instant::now();
+    let response = server.perform("/pooled")?;
+    assert_eq!(response.status(), 503);
+    assert!(start.elapsed() >= timeout);
+
+    let response = server.perform("/unregistered")?;
+    assert_eq!(response.status(), 500);
+
+    Ok(())
+}
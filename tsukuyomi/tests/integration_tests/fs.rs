@@ -1,9 +1,34 @@
-use tsukuyomi::{
-    config::prelude::*, //
-    fs::{NamedFile, Staticfiles},
-    App,
+use {
+    http::{header, StatusCode},
+    std::{fs, path::PathBuf},
+    tsukuyomi::{
+        config::prelude::*, //
+        fs::{CacheControl, Disposition, EtagMode, NamedFile, OpenConfig, Staticfiles},
+        App,
+    },
+    tsukuyomi_server::test::ResponseExt,
 };
 
+/// Creates a directory tree for testing:
+///
+/// ```text
+/// <root>/
+///   secret.txt
+///   public/
+///     static/
+///       hello.txt
+/// ```
+fn create_test_dir(name: &str) -> std::io::Result<PathBuf> {
+    let root = std::env::temp_dir().join(format!("tsukuyomi-test-{}-{}", name, std::process::id()));
+    if root.exists() {
+        fs::remove_dir_all(&root)?;
+    }
+    fs::create_dir_all(root.join("public/static"))?;
+    fs::write(root.join("secret.txt"), "secret")?;
+    fs::write(root.join("public/static/hello.txt"), "hello")?;
+    Ok(root)
+}
+
 #[test]
 #[ignore]
 fn compiletest() -> tsukuyomi::app::Result<()> {
@@ -20,3 +45,575 @@ fn compiletest() -> tsukuyomi::app::Result<()> {
 fn compiletest_staticfiles() -> tsukuyomi::app::Result<()> {
     App::create(Staticfiles::new("./public")).map(drop)
 }
+
+#[test]
+#[ignore]
+fn compiletest_staticfiles_index_file() -> tsukuyomi::app::Result<()> {
+    App::create(Staticfiles::new("./public").index_file("index.html")).map(drop)
+}
+
+#[test]
+fn staticfiles_index_file() -> tsukuyomi_server::Result<()> {
+    let root = create_test_dir("index-file")?;
+    fs::create_dir_all(root.join("public/docs/guide"))?;
+    fs::write(root.join("public/docs/index.html"), "docs")?;
+    fs::write(root.join("public/docs/guide/index.html"), "guide")?;
+
+    let app = App::create(Staticfiles::new(root.join("public")).index_file("index.html"))?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/docs/")?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.body().to_utf8()?, "docs");
+
+    let response = server.perform("/docs/guide")?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.body().to_utf8()?, "guide");
+
+    let response = server.perform("/static/hello.txt")?;
+    assert_eq!(response.body().to_utf8()?, "hello");
+
+    // the directory without the index file.
+    let response = server.perform("/static/")?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // no index file is configured.
+    let app = App::create(Staticfiles::new(root.join("public")))?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/docs/")?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    fs::remove_dir_all(&root)?;
+    Ok(())
+}
+
+#[test]
+fn staticfiles_path_traversal() -> tsukuyomi_server::Result<()> {
+    let root = create_test_dir("path-traversal")?;
+
+    let app = App::create(Staticfiles::new(root.join("public")))?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/static/hello.txt")?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.body().to_utf8()?, "hello");
+
+    let response = server.perform("/static/missing.txt")?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = server.perform("/static/../../secret.txt")?;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = server.perform("/static/..%2f..%2fsecret.txt")?;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = server.perform("/static/%2e%2e/%2e%2e/secret.txt")?;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    fs::remove_dir_all(&root)?;
+    Ok(())
+}
+
+#[test]
+fn staticfiles_catch_all() -> tsukuyomi_server::Result<()> {
+    let root = create_test_dir("catch-all")?;
+    fs::create_dir_all(root.join("public/docs"))?;
+    fs::write(root.join("public/docs/index.html"), "docs")?;
+
+    let app = App::create(
+        mount("/files").with(Staticfiles::new(root.join("public")).catch_all()), //
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/files/static/hello.txt")?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.body().to_utf8()?, "hello");
+
+    let response = server.perform("/files/docs/")?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.body().to_utf8()?, "docs");
+
+    let response = server.perform("/files/static/")?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = server.perform("/files/..%2fsecret.txt")?;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    fs::remove_dir_all(&root)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn staticfiles_symlink_outside_of_root() -> tsukuyomi_server::Result<()> {
+    let root = create_test_dir("symlink")?;
+    std::os::unix::fs::symlink(
+        root.join("secret.txt"),
+        root.join("public/static/secret.txt"),
+    )?;
+
+    let app = App::create(Staticfiles::new(root.join("public")))?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/static/secret.txt")?;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    fs::remove_dir_all(&root)?;
+    Ok(())
+}
+
+#[test]
+fn named_file_content_disposition() -> tsukuyomi_server::Result<()> {
+    let root = create_test_dir("content-disposition")?;
+    let path = root.join("public/static/hello.txt");
+
+    let app =
+        App::create(chain![
+            path!("/inline") //
+                .to(endpoint::get().reply(
+                    NamedFile::open(path.clone()).set_content_disposition(Disposition::Inline),
+                )),
+            path!("/attachment") //
+                .to(endpoint::get().reply(
+                    NamedFile::open(path.clone())
+                        .set_content_disposition(Disposition::Attachment { filename: None },)
+                )),
+            path!("/report") //
+                .to(
+                    endpoint::get().reply(NamedFile::open(path.clone()).set_content_disposition(
+                        Disposition::Attachment {
+                            filename: Some("報告書.csv".into()),
+                        },
+                    ))
+                ),
+        ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/inline")?;
+    assert_eq!(response.header(header::CONTENT_DISPOSITION)?, "inline");
+
+    let response = server.perform("/attachment")?;
+    assert_eq!(
+        response.header(header::CONTENT_DISPOSITION)?,
+        "attachment; filename=\"hello.txt\""
+    );
+
+    let response = server.perform("/report")?;
+    assert_eq!(
+        response.header(header::CONTENT_DISPOSITION)?,
+        "attachment; filename=\"___.csv\"; filename*=UTF-8''%E5%A0%B1%E5%91%8A%E6%9B%B8.csv"
+    );
+
+    fs::remove_dir_all(&root)?;
+    Ok(())
+}
+
+#[test]
+fn staticfiles_attachment() -> tsukuyomi_server::Result<()> {
+    let root = create_test_dir("staticfiles-attachment")?;
+
+    let app = App::create(
+        Staticfiles::new(root.join("public")).open_config(OpenConfig {
+            content_disposition: Some(Disposition::Attachment { filename: None }),
+            ..Default::default()
+        }),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/static/hello.txt")?;
+    assert_eq!(
+        response.header(header::CONTENT_DISPOSITION)?,
+        "attachment; filename=\"hello.txt\""
+    );
+
+    fs::remove_dir_all(&root)?;
+    Ok(())
+}
+
+#[test]
+fn staticfiles_cache_control() -> tsukuyomi_server::Result<()> {
+    use std::time::Duration;
+
+    let root = create_test_dir("cache-control")?;
+
+    for (cache_control, expected, validators) in vec![
+        (None, "public", true),
+        (
+            Some(CacheControl::Public {
+                max_age: Some(Duration::from_secs(3600)),
+            }),
+            "public, max-age=3600",
+            true,
+        ),
+        (
+            Some(CacheControl::Immutable { validators: true }),
+            "public, max-age=31536000, immutable",
+            true,
+        ),
+        (
+            Some(CacheControl::Immutable { validators: false }),
+            "public, max-age=31536000, immutable",
+            false,
+        ),
+    ] {
+        let staticfiles = Staticfiles::new(root.join("public"));
+        let staticfiles = match cache_control {
+            Some(cache_control) => staticfiles.cache_control(cache_control),
+            None => staticfiles,
+        };
+        let app = App::create(staticfiles)?;
+        let mut server = tsukuyomi_server::test::server(app)?;
+
+        let response = server.perform("/static/hello.txt")?;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.header(header::CACHE_CONTROL)?, expected);
+        assert_eq!(response.headers().contains_key(header::ETAG), validators);
+        assert_eq!(
+            response.headers().contains_key(header::LAST_MODIFIED),
+            validators
+        );
+    }
+
+    fs::remove_dir_all(&root)?;
+    Ok(())
+}
+
+#[test]
+fn named_file_etag() -> tsukuyomi_server::Result<()> {
+    use http::Request;
+
+    let root = create_test_dir("etag")?;
+    // the dedicated file for this test, whose contents are rewritten below.
+    let path = root.join("public/static/etag.txt");
+    fs::write(&path, "hello")?;
+
+    let app = App::create(chain![
+        Staticfiles::new(root.join("public")).open_config(OpenConfig {
+            etag: EtagMode::StrongHash,
+            ..Default::default()
+        }),
+        path!("/weak") //
+            .to(endpoint::get().reply(NamedFile::open(root.join("secret.txt")))),
+        path!("/none") //
+            .to(endpoint::get().reply(NamedFile::open_with_config(
+                root.join("secret.txt"),
+                OpenConfig {
+                    etag: EtagMode::None,
+                    ..Default::default()
+                },
+            ))),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    // strong ETag, computed by the stable hash of the contents.
+    let response = server.perform("/static/etag.txt")?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let etag = response.header(header::ETAG)?.to_str()?.to_owned();
+    assert_eq!(etag, "\"5-a430d84680aabd0b\"");
+
+    // the cached hash is used while the modification time and the size are unchanged.
+    let mtime = filetime::FileTime::from_last_modification_time(&fs::metadata(&path)?);
+    fs::write(&path, "HELLO")?;
+    filetime::set_file_times(&path, mtime, mtime)?;
+    let response = server.perform("/static/etag.txt")?;
+    assert_eq!(response.header(header::ETAG)?, etag.as_str());
+
+    // the hash is recomputed when the file is modified.
+    fs::write(&path, "world!")?;
+    let response = server.perform("/static/etag.txt")?;
+    assert_ne!(response.header(header::ETAG)?, etag.as_str());
+
+    fs::write(&path, "hello")?;
+    let response = server.perform("/static/etag.txt")?;
+    assert_eq!(response.header(header::ETAG)?, etag.as_str());
+
+    // If-None-Match uses the weak comparison.
+    for value in &[
+        etag.clone(),
+        format!("W/{}", etag),
+        format!("\"foo\", {}", etag),
+        "*".to_owned(),
+    ] {
+        let response = server.perform(
+            Request::get("/static/etag.txt") //
+                .header(header::IF_NONE_MATCH, value.as_str()),
+        )?;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED, "{}", value);
+    }
+    let response = server.perform(
+        Request::get("/static/etag.txt") //
+            .header(header::IF_NONE_MATCH, "\"foo\""),
+    )?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // weak ETag
+    let response = server.perform("/weak")?;
+    let etag = response.header(header::ETAG)?.to_str()?.to_owned();
+    assert!(etag.starts_with("W/"), "{}", etag);
+    let response = server.perform(
+        Request::get("/weak") //
+            .header(header::IF_NONE_MATCH, &etag[2..]),
+    )?;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    // no ETag
+    let response = server.perform("/none")?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!response.headers().contains_key(header::ETAG));
+
+    fs::remove_dir_all(&root)?;
+    Ok(())
+}
+
+#[test]
+fn named_file_non_cacheable_method() -> tsukuyomi_server::Result<()> {
+    use http::Request;
+
+    let root = create_test_dir("non-cacheable")?;
+
+    let app = App::create(
+        path!("/secret") //
+            .to(endpoint::any().reply(NamedFile::open(root.join("secret.txt")))),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/secret")?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().contains_key(header::CACHE_CONTROL));
+    let etag = response.header(header::ETAG)?.to_str()?.to_owned();
+
+    let response = server.perform(
+        Request::get("/secret") //
+            .header(header::IF_NONE_MATCH, etag.as_str()),
+    )?;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    // the responses to POST are not cacheable, so the validators are ignored.
+    let response = server.perform(
+        Request::post("/secret") //
+            .header(header::IF_NONE_MATCH, etag.as_str()),
+    )?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.body().to_utf8()?, "secret");
+    assert!(!response.headers().contains_key(header::CACHE_CONTROL));
+    assert!(!response.headers().contains_key(header::ETAG));
+    assert!(!response.headers().contains_key(header::LAST_MODIFIED));
+
+    fs::remove_dir_all(&root)?;
+    Ok(())
+}
+
+#[test]
+fn named_file_range() -> tsukuyomi_server::Result<()> {
+    use http::Request;
+
+    let root = create_test_dir("range")?;
+
+    let app = App::create(Staticfiles::new(root.join("public")))?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/static/hello.txt")?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.header(header::ACCEPT_RANGES)?, "bytes");
+
+    for &(range, content_range, body) in &[
+        ("bytes=1-3", "bytes 1-3/5", "ell"),
+        ("bytes=2-", "bytes 2-4/5", "llo"),
+        ("bytes=-2", "bytes 3-4/5", "lo"),
+        ("bytes=0-100", "bytes 0-4/5", "hello"),
+    ] {
+        let response = server.perform(
+            Request::get("/static/hello.txt") //
+                .header(header::RANGE, range),
+        )?;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT, "{}", range);
+        assert_eq!(response.header(header::CONTENT_RANGE)?, content_range);
+        assert_eq!(response.body().to_utf8()?, body);
+    }
+
+    let response = server.perform(
+        Request::get("/static/hello.txt") //
+            .header(header::RANGE, "bytes=5-"),
+    )?;
+    assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(response.header(header::CONTENT_RANGE)?, "bytes */5");
+
+    // multiple ranges are not supported.
+    let response = server.perform(
+        Request::get("/static/hello.txt") //
+            .header(header::RANGE, "bytes=0-1,3-4"),
+    )?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.body().to_utf8()?, "hello");
+
+    fs::remove_dir_all(&root)?;
+    Ok(())
+}
+
+#[test]
+fn named_file_if_range() -> tsukuyomi_server::Result<()> {
+    use http::Request;
+
+    let root = create_test_dir("if-range")?;
+
+    let app = App::create(chain![
+        Staticfiles::new(root.join("public")).open_config(OpenConfig {
+            etag: EtagMode::StrongHash,
+            ..Default::default()
+        }),
+        path!("/weak") //
+            .to(endpoint::get().reply(NamedFile::open(root.join("secret.txt")))),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/static/hello.txt")?;
+    let etag = response.header(header::ETAG)?.to_str()?.to_owned();
+    let last_modified = response.header(header::LAST_MODIFIED)?.to_str()?.to_owned();
+
+    // If-Range matches the current representation.
+    for if_range in &[etag.as_str(), last_modified.as_str()] {
+        let response = server.perform(
+            Request::get("/static/hello.txt")
+                .header(header::RANGE, "bytes=1-3")
+                .header(header::IF_RANGE, *if_range),
+        )?;
+        assert_eq!(
+            response.status(),
+            StatusCode::PARTIAL_CONTENT,
+            "{}",
+            if_range
+        );
+        assert_eq!(response.body().to_utf8()?, "ell");
+    }
+
+    // If-Range does not match, and the entire representation is returned.
+    for if_range in &["\"foo\"", "Thu, 01 Jan 1970 00:00:00 GMT"] {
+        let response = server.perform(
+            Request::get("/static/hello.txt")
+                .header(header::RANGE, "bytes=1-3")
+                .header(header::IF_RANGE, *if_range),
+        )?;
+        assert_eq!(response.status(), StatusCode::OK, "{}", if_range);
+        assert!(!response.headers().contains_key(header::CONTENT_RANGE));
+        assert_eq!(response.body().to_utf8()?, "hello");
+    }
+
+    // the weak entity tags never match.
+    let response = server.perform("/weak")?;
+    let etag = response.header(header::ETAG)?.to_str()?.to_owned();
+    let response = server.perform(
+        Request::get("/weak")
+            .header(header::RANGE, "bytes=1-3")
+            .header(header::IF_RANGE, etag.as_str()),
+    )?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.body().to_utf8()?, "secret");
+
+    fs::remove_dir_all(&root)?;
+    Ok(())
+}
+
+#[test]
+fn named_file_content_type() -> tsukuyomi_server::Result<()> {
+    let root = create_test_dir("content-type")?;
+    for name in &[
+        "index.html",
+        "style.css",
+        "app.js",
+        "image.png",
+        "data.unknown",
+    ] {
+        fs::write(root.join("public/static").join(name), "")?;
+    }
+
+    let app = App::create(chain![
+        Staticfiles::new(root.join("public")),
+        path!("/override") //
+            .to(endpoint::get().reply(NamedFile::open_with_config(
+                root.join("public/static/data.unknown"),
+                OpenConfig {
+                    content_type: Some(mime::TEXT_CSV),
+                    ..Default::default()
+                },
+            ))),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    for &(path, expected) in &[
+        ("/static/index.html", "text/html"),
+        ("/static/style.css", "text/css"),
+        ("/static/app.js", "application/javascript"),
+        ("/static/image.png", "image/png"),
+        ("/static/data.unknown", "application/octet-stream"),
+        ("/override", "text/csv"),
+    ] {
+        let response = server.perform(path)?;
+        assert_eq!(response.header(header::CONTENT_TYPE)?, expected);
+    }
+
+    fs::remove_dir_all(&root)?;
+    Ok(())
+}
+
+#[test]
+fn named_file_last_modified() -> tsukuyomi_server::Result<()> {
+    use filetime::FileTime;
+
+    let root = create_test_dir("last-modified")?;
+    let path = root.join("public/static/hello.txt");
+    let mtime = FileTime::from_unix_time(784_111_777, 0);
+    filetime::set_file_times(&path, mtime, mtime)?;
+
+    let app = App::create(Staticfiles::new(root.join("public")))?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    // IMF-fixdate, regardless of the local time zone.
+    let response = server.perform("/static/hello.txt")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.header(header::LAST_MODIFIED)?,
+        "Sun, 06 Nov 1994 08:49:37 GMT"
+    );
+
+    fs::remove_dir_all(&root)?;
+    Ok(())
+}
+
+#[test]
+fn named_file_if_modified_since_with_fixed_clock() -> tsukuyomi_server::Result<()> {
+    use {
+        http::Request,
+        std::time::{Duration, UNIX_EPOCH},
+        tsukuyomi::input::clock::FixedClock,
+    };
+
+    let root = create_test_dir("fixed-clock")?;
+
+    let app = || App::create(Staticfiles::new(root.join("public")));
+
+    let mut server = tsukuyomi_server::test::server(app()?)?;
+    let response = server.perform("/static/hello.txt")?;
+    assert_eq!(response.status(), 200);
+    let last_modified = response.header(header::LAST_MODIFIED)?.clone();
+
+    let response = server.perform(
+        Request::get("/static/hello.txt") //
+            .header(header::IF_MODIFIED_SINCE, last_modified.clone()),
+    )?;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    // the clock is earlier than the modification time of the file, and hence
+    // the value of If-Modified-Since is considered to be invalid.
+    let now = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    let mut server = tsukuyomi_server::test::server(app()?)?.extension(FixedClock(now));
+    let response = server.perform(
+        Request::get("/static/hello.txt") //
+            .header(header::IF_MODIFIED_SINCE, last_modified),
+    )?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "hello");
+
+    fs::remove_dir_all(&root)?;
+    Ok(())
+}
@@ -1,6 +1,61 @@
 mod app;
+mod auth;
 mod cookie;
 mod extract;
 mod fs;
 mod macros;
 mod modifier;
+mod output;
+
+use {
+    lazy_static::lazy_static,
+    std::sync::{Mutex, Once},
+};
+
+/// A logger that captures the messages emitted during the tests.
+///
+/// The records with the level `Trace` are disabled, in order to test the behavior
+/// with the disabled levels.
+struct CapturedLogger(Mutex<Vec<String>>);
+
+impl log::Log for CapturedLogger {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        metadata.level() <= log::Level::Debug
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        if self.enabled(record.metadata()) {
+            self.0
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .push(record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+lazy_static! {
+    static ref LOGGER: CapturedLogger = CapturedLogger(Mutex::new(vec![]));
+}
+
+/// Installs the capturing logger, if not installed yet.
+///
+/// Since the logger is global in the process, it is shared among all tests in this
+/// binary and the captured messages may contain the ones from the other tests.
+fn install_logger() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        log::set_logger(&*LOGGER).expect("the logger has already been installed");
+        log::set_max_level(log::LevelFilter::Trace);
+    });
+}
+
+/// Returns the log messages captured so far.
+fn captured_logs() -> Vec<String> {
+    LOGGER
+        .0
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
@@ -1,10 +1,14 @@
 use {
+    http::{Request, Response},
     std::sync::{Arc, Mutex},
     tsukuyomi::{
         config::prelude::*, //
+        future::{Poll, TryFuture},
         handler::{AllowedMethods, Handler, ModifyHandler},
         App,
+        Input,
     },
+    tsukuyomi_server::test::ResponseExt,
 };
 
 #[derive(Clone)]
@@ -125,7 +129,7 @@ fn scoped_modifier() -> tsukuyomi_server::Result<()> {
     let mut server = tsukuyomi_server::test::server(app)?;
 
     let _ = server.perform("/path1")?;
-    assert_eq!(*marker.lock().unwrap(), vec!["M2", "M1"]);
+    assert_eq!(*marker.lock().unwrap(), vec!["M1", "M2"]);
 
     marker.lock().unwrap().clear();
     let _ = server.perform("/path2")?;
@@ -167,11 +171,654 @@ fn nested_modifiers() -> tsukuyomi_server::Result<()> {
     let mut server = tsukuyomi_server::test::server(app)?;
 
     let _ = server.perform("/path/to")?;
-    assert_eq!(*marker.lock().unwrap(), vec!["M2", "M1"]);
+    assert_eq!(*marker.lock().unwrap(), vec!["M1", "M2"]);
 
     marker.lock().unwrap().clear();
     let _ = server.perform("/path/to/a")?;
-    assert_eq!(*marker.lock().unwrap(), vec!["M3", "M2", "M1"]);
+    assert_eq!(*marker.lock().unwrap(), vec!["M1", "M2", "M3"]);
+
+    Ok(())
+}
+
+#[derive(Clone)]
+struct OrderedModifier {
+    marker: Arc<Mutex<Vec<String>>>,
+    level: usize,
+}
+
+impl<H: Handler> ModifyHandler<H> for OrderedModifier {
+    type Output = H::Output;
+    type Handler = OrderedHandler<H>;
+
+    fn modify(&self, inner: H) -> Self::Handler {
+        OrderedHandler {
+            inner,
+            modifier: self.clone(),
+        }
+    }
+}
+
+struct OrderedHandler<H> {
+    inner: H,
+    modifier: OrderedModifier,
+}
+
+impl<H> Handler for OrderedHandler<H>
+where
+    H: Handler,
+{
+    type Output = H::Output;
+    type Error = H::Error;
+    type Handle = OrderedHandle<H::Handle>;
+
+    fn allowed_methods(&self) -> Option<&AllowedMethods> {
+        self.inner.allowed_methods()
+    }
+
+    fn handle(&self) -> Self::Handle {
+        let modifier = self.modifier.clone();
+        modifier
+            .marker
+            .lock()
+            .unwrap()
+            .push(format!("before {}", modifier.level));
+        OrderedHandle {
+            inner: self.inner.handle(),
+            modifier,
+        }
+    }
+}
+
+struct OrderedHandle<H> {
+    inner: H,
+    modifier: OrderedModifier,
+}
+
+impl<H> TryFuture for OrderedHandle<H>
+where
+    H: TryFuture,
+{
+    type Ok = H::Ok;
+    type Error = H::Error;
+
+    fn poll_ready(&mut self, input: &mut Input<'_>) -> Poll<Self::Ok, Self::Error> {
+        let output = futures01::try_ready!(self.inner.poll_ready(input));
+        self.modifier
+            .marker
+            .lock()
+            .unwrap()
+            .push(format!("after {}", self.modifier.level));
+        Ok(output.into())
+    }
+}
+
+#[test]
+fn modifier_ordering() -> tsukuyomi_server::Result<()> {
+    let marker = Arc::new(Mutex::new(vec![]));
+    let modifier = |level: usize| OrderedModifier {
+        marker: marker.clone(),
+        level,
+    };
+
+    let app = App::create(
+        mount("/a")
+            .with(
+                mount("/b")
+                    .with(
+                        mount("/c")
+                            .with(path!("/").to(endpoint::reply("")))
+                            .modify(modifier(3)),
+                    )
+                    .modify(modifier(2)),
+            )
+            .modify(modifier(1)),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let _ = server.perform("/a/b/c")?;
+    assert_eq!(
+        *marker.lock().unwrap(),
+        vec!["before 1", "before 2", "before 3", "after 3", "after 2", "after 1"]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn instrumented() -> tsukuyomi_server::Result<()> {
+    let instrumented = tsukuyomi::modifiers::instrumented();
+
+    let app = App::create(chain![
+        path!("/:id") //
+            .to(endpoint::get().call(|id: u32| format!("{}", id)))
+            .modify(instrumented.clone()),
+        path!("/other/path") //
+            .to(endpoint::get().reply("other")),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let metrics = instrumented.metrics();
+    assert_eq!(metrics.completed(), 0);
+    assert_eq!(metrics.mean_time(), None);
+
+    let _ = server.perform("/1")?;
+    let _ = server.perform("/2")?;
+    let _ = server.perform("/other/path")?;
+    assert_eq!(metrics.completed(), 2);
+    assert_eq!(metrics.failed(), 0);
+    assert_eq!(metrics.in_flight(), 0);
+
+    let response = server.perform("/foo")?;
+    assert_eq!(response.status(), 400);
+    assert_eq!(metrics.completed(), 3);
+    assert_eq!(metrics.failed(), 1);
+    assert!(metrics.max_time() <= metrics.total_time());
+    assert!(metrics.mean_time().is_some());
+
+    Ok(())
+}
+
+#[test]
+fn logger() -> tsukuyomi_server::Result<()> {
+    super::install_logger();
+
+    let logger = tsukuyomi::modifiers::logger().format(|record| {
+        format!(
+            "logger-test: {} {} {}",
+            record.method(),
+            record.path(),
+            record.status().as_u16()
+        )
+    });
+
+    // the messages are not formatted if the level is disabled.
+    let disabled_count = Arc::new(Mutex::new(0));
+    let disabled_logger = tsukuyomi::modifiers::logger()
+        .level(log::Level::Trace)
+        .format({
+            let disabled_count = disabled_count.clone();
+            move |_| {
+                *disabled_count.lock().unwrap() += 1;
+                String::new()
+            }
+        });
+
+    let app = App::create(chain![
+        path!("/:id") //
+            .to(endpoint::get().call(|id: u32| format!("{}", id)))
+            .modify(logger),
+        path!("/quiet/logger") //
+            .to(endpoint::get().reply("disabled"))
+            .modify(disabled_logger),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/42")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "42");
+
+    let response = server.perform("/foo")?;
+    assert_eq!(response.status(), 400);
+
+    let logs: Vec<_> = super::captured_logs()
+        .into_iter()
+        .filter(|message| message.starts_with("logger-test: "))
+        .collect();
+    assert_eq!(
+        logs,
+        vec!["logger-test: GET /42 200", "logger-test: GET /foo 400"]
+    );
+
+    let response = server.perform("/quiet/logger")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(*disabled_count.lock().unwrap(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn content_length_limit() -> tsukuyomi_server::Result<()> {
+    let app = App::create(
+        path!("/") //
+            .to(endpoint::post()
+                .extract(tsukuyomi::extractor::body::plain())
+                .call(|body: String| body))
+            .modify(tsukuyomi::modifiers::content_length_limit(16)),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform(
+        Request::post("/")
+            .header("content-length", "11")
+            .body("hello world"),
+    )?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "hello world");
+
+    // the Content-Length exceeds the limit.
+    let response = server.perform(
+        Request::post("/")
+            .header("content-length", "43")
+            .body("The quick brown fox jumps over the lazy dog"),
+    )?;
+    assert_eq!(response.status(), 413);
+
+    // the received body exceeds the limit without Content-Length.
+    let response =
+        server.perform(Request::post("/").body("The quick brown fox jumps over the lazy dog"))?;
+    assert_eq!(response.status(), 413);
+
+    let response = server.perform(Request::post("/").body("hello world"))?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "hello world");
+
+    // the streaming body exceeds the limit in the middle of transfer.
+    let response = server.perform(Request::post("/").body(tsukuyomi_server::test::body_stream(
+        futures01::stream::iter_ok::<_, std::io::Error>(vec![
+            "The quick brown fox ",
+            "jumps over ",
+            "the lazy dog",
+        ]),
+    )))?;
+    assert_eq!(response.status(), 413);
+
+    Ok(())
+}
+
+#[test]
+fn security_headers() -> tsukuyomi_server::Result<()> {
+    let app = App::create(chain![
+        path!("/") //
+            .to(endpoint::get().reply("index"))
+            .modify(tsukuyomi::modifiers::security_headers()),
+        path!("/custom") //
+            .to(endpoint::get().call(|| {
+                Response::builder()
+                    .header("x-frame-options", "DENY")
+                    .body("custom")
+                    .unwrap()
+            }))
+            .modify(
+                tsukuyomi::modifiers::security_headers()
+                    .disable_referrer_policy()
+                    .strict_transport_security(std::time::Duration::from_secs(3600), true),
+            ),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.header("x-content-type-options")?, "nosniff");
+    assert_eq!(response.header("x-frame-options")?, "SAMEORIGIN");
+    assert_eq!(
+        response.header("referrer-policy")?,
+        "strict-origin-when-cross-origin"
+    );
+    assert!(!response.headers().contains_key("strict-transport-security"));
+    assert!(!response.headers().contains_key("content-security-policy"));
+
+    let response = server.perform("/custom")?;
+    assert_eq!(response.header("x-frame-options")?, "DENY");
+    assert!(!response.headers().contains_key("referrer-policy"));
+    assert_eq!(
+        response.header("strict-transport-security")?,
+        "max-age=3600; includeSubDomains"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn problem_json() -> tsukuyomi_server::Result<()> {
+    #[derive(Debug)]
+    struct OutOfCredit {
+        balance: u32,
+    }
+
+    impl std::fmt::Display for OutOfCredit {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("your current balance is insufficient")
+        }
+    }
+
+    impl tsukuyomi::error::HttpError for OutOfCredit {
+        type Body = ();
+
+        fn into_response(self, _: &Request<()>) -> Response<Self::Body> {
+            Response::builder()
+                .status(403)
+                .header("x-balance", self.balance.to_string().as_str())
+                .body(())
+                .unwrap()
+        }
+
+        fn problem_details(&self, members: &mut serde_json::Map<String, serde_json::Value>) {
+            members.insert("title".into(), "You do not have enough credit.".into());
+            members.insert("balance".into(), self.balance.into());
+        }
+    }
+
+    let app = App::create(
+        chain![
+            path!("/:id") //
+                .to(endpoint::get().call(|id: u32| format!("{}", id))),
+            path!("/purchase") //
+                .to(
+                    endpoint::post().call(|| -> tsukuyomi::Result<&'static str> {
+                        Err(OutOfCredit { balance: 30 }.into())
+                    })
+                ),
+        ]
+        .modify(tsukuyomi::modifiers::problem_json()),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/42")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "42");
+
+    let response = server.perform("/foo")?;
+    assert_eq!(response.status(), 400);
+    assert_eq!(response.header("content-type")?, "application/problem+json");
+    let problem: serde_json::Value = serde_json::from_slice(&*response.body().to_bytes())?;
+    assert_eq!(problem["status"], 400);
+    assert_eq!(problem["title"], "Bad Request");
+    assert!(problem["detail"].is_string());
+
+    let response = server.perform(Request::post("/purchase"))?;
+    assert_eq!(response.status(), 403);
+    assert_eq!(response.header("content-type")?, "application/problem+json");
+    assert_eq!(response.header("x-balance")?, "30");
+    let problem: serde_json::Value = serde_json::from_slice(&*response.body().to_bytes())?;
+    assert_eq!(problem["status"], 403);
+    assert_eq!(problem["title"], "You do not have enough credit.");
+    assert_eq!(problem["detail"], "your current balance is insufficient");
+    assert_eq!(problem["balance"], 30);
+
+    Ok(())
+}
+
+#[test]
+fn rate_limit() -> tsukuyomi_server::Result<()> {
+    use std::{
+        net::SocketAddr,
+        time::{Duration, SystemTime},
+    };
+
+    #[derive(Clone)]
+    struct ManualClock(Arc<Mutex<SystemTime>>);
+
+    impl tsukuyomi::input::clock::Clock for ManualClock {
+        fn now(&self) -> SystemTime {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    let clock = ManualClock(Arc::new(Mutex::new(SystemTime::now())));
+
+    let app = App::create(chain![
+        tsukuyomi::config::clock(clock.clone()),
+        path!("/") //
+            .to(endpoint::get().reply("index"))
+            .modify(tsukuyomi::modifiers::rate_limit(1, Duration::from_secs(10))),
+        path!("/api") //
+            .to(endpoint::get().reply("api"))
+            .modify(
+                tsukuyomi::modifiers::rate_limit(1, Duration::from_secs(10))
+                    .burst(2)
+                    .key(|input| {
+                        input
+                            .request
+                            .headers()
+                            .get("x-api-key")
+                            .and_then(|h| h.to_str().ok())
+                            .map(ToOwned::to_owned)
+                    }),
+            ),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let addr: SocketAddr = "127.0.0.1:12345".parse().unwrap();
+    let response = server.perform(Request::get("/").extension(addr))?;
+    assert_eq!(response.status(), 200);
+
+    let response = server.perform(Request::get("/").extension(addr))?;
+    assert_eq!(response.status(), 429);
+    assert_eq!(response.header("retry-after")?, "10");
+
+    let other: SocketAddr = "127.0.0.2:12345".parse().unwrap();
+    let response = server.perform(Request::get("/").extension(other))?;
+    assert_eq!(response.status(), 200);
+
+    // the requests without the key are not limited.
+    let response = server.perform("/")?;
+    assert_eq!(response.status(), 200);
+    let response = server.perform("/")?;
+    assert_eq!(response.status(), 200);
+
+    for _ in 0..2 {
+        let response = server.perform(Request::get("/api").header("x-api-key", "foo"))?;
+        assert_eq!(response.status(), 200);
+    }
+    let response = server.perform(Request::get("/api").header("x-api-key", "foo"))?;
+    assert_eq!(response.status(), 429);
+
+    *clock.0.lock().unwrap() += Duration::from_secs(5);
+    let response = server.perform(Request::get("/api").header("x-api-key", "foo"))?;
+    assert_eq!(response.status(), 429);
+    assert_eq!(response.header("retry-after")?, "5");
+
+    *clock.0.lock().unwrap() += Duration::from_secs(5);
+    let response = server.perform(Request::get("/api").header("x-api-key", "foo"))?;
+    assert_eq!(response.status(), 200);
+    let response = server.perform(Request::get("/").extension(addr))?;
+    assert_eq!(response.status(), 200);
+
+    Ok(())
+}
+
+#[test]
+#[should_panic(expected = "the number of requests must be greater than zero")]
+fn rate_limit_with_zero_requests() {
+    let _ = tsukuyomi::modifiers::rate_limit(0, std::time::Duration::from_secs(10));
+}
+
+#[test]
+#[should_panic(expected = "the period must be greater than zero")]
+fn rate_limit_with_zero_period() {
+    let _ = tsukuyomi::modifiers::rate_limit(1, std::time::Duration::from_secs(0));
+}
+
+#[test]
+#[should_panic(expected = "the burst size must be greater than zero")]
+fn rate_limit_with_zero_burst() {
+    let _ = tsukuyomi::modifiers::rate_limit(1, std::time::Duration::from_secs(10)).burst(0);
+}
+
+#[test]
+fn request_id() -> tsukuyomi_server::Result<()> {
+    let records = Arc::new(Mutex::new(vec![]));
+    let logger = tsukuyomi::modifiers::logger().format({
+        let records = records.clone();
+        move |record| {
+            let msg = format!("{} {:?}", record.path(), record.request_id());
+            records.lock().unwrap().push(msg.clone());
+            msg
+        }
+    });
+
+    let app = App::create(
+        path!("/") //
+            .to(endpoint::get()
+                .extract(tsukuyomi::extractor::ready(|input| {
+                    Ok::<_, tsukuyomi::Error>((input.request_id().unwrap_or("").to_owned(),))
+                }))
+                .call(|request_id: String| request_id))
+            .modify(logger)
+            .modify(tsukuyomi::modifiers::request_id()),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    // propagates the existing identifier.
+    let response = server.perform(Request::get("/").header("x-request-id", "abc123"))?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.header("x-request-id")?, "abc123");
+    assert_eq!(response.body().to_utf8()?, "abc123");
+
+    // generates a new identifier.
+    let response = server.perform("/")?;
+    let request_id = response.header("x-request-id")?.to_str()?.to_owned();
+    assert!(request_id.parse::<uuid::Uuid>().is_ok(), "{}", request_id);
+    assert_eq!(response.body().to_utf8()?, request_id.as_str());
+
+    assert_eq!(
+        *records.lock().unwrap(),
+        vec![
+            "/ Some(\"abc123\")".to_owned(),
+            format!("/ Some({:?})", request_id),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn request_id_custom() -> tsukuyomi_server::Result<()> {
+    let app = App::create(
+        path!("/") //
+            .to(endpoint::get().reply("hello"))
+            .modify(
+                tsukuyomi::modifiers::request_id()
+                    .header_name(http::header::HeaderName::from_static("x-correlation-id"))
+                    .generator(|| "generated".into()),
+            ),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/")?;
+    assert_eq!(response.header("x-correlation-id")?, "generated");
+    assert!(!response.headers().contains_key("x-request-id"));
+
+    let response = server.perform(Request::get("/").header("x-correlation-id", "abc123"))?;
+    assert_eq!(response.header("x-correlation-id")?, "abc123");
+
+    Ok(())
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn trace() -> tsukuyomi_server::Result<()> {
+    use {
+        std::{
+            fmt,
+            sync::atomic::{AtomicUsize, Ordering},
+        },
+        tracing::{
+            field::{Field, Visit},
+            span, Event, Metadata, Subscriber,
+        },
+    };
+
+    #[derive(Default)]
+    struct Recorder {
+        next_id: AtomicUsize,
+        current: Mutex<Vec<u64>>,
+        records: Arc<Mutex<Vec<String>>>,
+    }
+
+    struct Fields<'a>(&'a mut Vec<String>);
+
+    impl<'a> Visit for Fields<'a> {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0.push(format!("{}={:?}", field.name(), value));
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, attrs: &span::Attributes<'_>) -> span::Id {
+            let id = self.next_id.fetch_add(1, Ordering::SeqCst) as u64 + 1;
+            let mut fields = vec![];
+            attrs.record(&mut Fields(&mut fields));
+            self.records.lock().unwrap().push(format!(
+                "new_span({}) {} {}",
+                id,
+                attrs.metadata().name(),
+                fields.join(" ")
+            ));
+            span::Id::from_u64(id)
+        }
+
+        fn record(&self, id: &span::Id, values: &span::Record<'_>) {
+            let mut fields = vec![];
+            values.record(&mut Fields(&mut fields));
+            self.records.lock().unwrap().push(format!(
+                "record({}) {}",
+                id.into_u64(),
+                fields.join(" ")
+            ));
+        }
+
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = vec![];
+            event.record(&mut Fields(&mut fields));
+            let current = self.current.lock().unwrap().last().cloned();
+            self.records
+                .lock()
+                .unwrap()
+                .push(format!("event({:?}) {}", current, fields.join(" ")));
+        }
+
+        fn enter(&self, id: &span::Id) {
+            self.current.lock().unwrap().push(id.into_u64());
+        }
+
+        fn exit(&self, _: &span::Id) {
+            self.current.lock().unwrap().pop();
+        }
+    }
+
+    let recorder = Recorder::default();
+    let records = recorder.records.clone();
+
+    let app = tsukuyomi::app::LocalApp::create(
+        path!("/:id") //
+            .to(endpoint::get().call(|id: u32| {
+                tracing::info!("in handler");
+                format!("{}", id)
+            }))
+            .modify(tsukuyomi::modifiers::trace()),
+    )?;
+    let mut server = tsukuyomi_server::test::local_server(app)?;
+
+    let response = tracing::subscriber::with_default(recorder, || server.perform("/42"))?;
+    assert_eq!(response.status(), 200);
+
+    let records = records.lock().unwrap();
+    assert_eq!(records.len(), 5, "{:?}", *records);
+    assert_eq!(
+        records[0],
+        "new_span(1) request method=GET path=/42 route=/:id"
+    );
+    assert_eq!(records[1], "event(Some(1)) message=in handler");
+    assert_eq!(records[2], "record(1) status=200");
+    assert!(
+        records[3].starts_with("record(1) latency="),
+        "{}",
+        records[3]
+    );
+    assert!(records[4].starts_with("event(Some(1))"), "{}", records[4]);
+    assert!(
+        records[4].contains("message=request completed"),
+        "{}",
+        records[4]
+    );
+    assert!(records[4].contains("status=200"), "{}", records[4]);
 
     Ok(())
 }
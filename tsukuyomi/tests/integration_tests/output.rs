@@ -0,0 +1,554 @@
+use {
+    http::{header, Request},
+    std::io::Read,
+    tsukuyomi::{
+        config::prelude::*, //
+        output::{self, Compressed},
+        App,
+    },
+    tsukuyomi_server::test::ResponseExt,
+};
+
+#[test]
+fn compressed() -> tsukuyomi_server::Result<()> {
+    let body = "Hello, Tsukuyomi. ".repeat(64);
+
+    let app = App::create(
+        path!("/") //
+            .to(endpoint::get().reply(Compressed::new(body.clone()))),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform(Request::get("/").header(header::ACCEPT_ENCODING, "gzip"))?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.header(header::CONTENT_ENCODING)?, "gzip");
+    assert_eq!(response.header(header::VARY)?, "accept-encoding");
+    let compressed = response.body().to_bytes();
+    assert!(compressed.len() < body.len());
+    let mut decoded = String::new();
+    flate2::read::GzDecoder::new(&*compressed).read_to_string(&mut decoded)?;
+    assert_eq!(decoded, body);
+
+    let response =
+        server.perform(Request::get("/").header(header::ACCEPT_ENCODING, "deflate, gzip;q=0"))?;
+    assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+    assert_eq!(response.header(header::VARY)?, "accept-encoding");
+    assert_eq!(response.body().to_utf8()?, body);
+
+    let response =
+        server.perform(Request::get("/").header(header::ACCEPT_ENCODING, "gzip;q=0, *"))?;
+    assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+
+    let response = server.perform(Request::get("/").header(header::ACCEPT_ENCODING, "*"))?;
+    assert_eq!(response.header(header::CONTENT_ENCODING)?, "gzip");
+
+    let response = server.perform("/")?;
+    assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+    assert_eq!(response.body().to_utf8()?, body);
+
+    Ok(())
+}
+
+#[test]
+fn no_content() -> tsukuyomi_server::Result<()> {
+    let app = App::create(chain![
+        path!("/unit") //
+            .to(endpoint::post().call(|| ())),
+        path!("/no_content") //
+            .to(endpoint::post().call(output::no_content)),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    for uri in &["/unit", "/no_content"] {
+        let response = server.perform(Request::post(*uri))?;
+        assert_eq!(response.status(), 204);
+        assert!(!response.headers().contains_key(header::CONTENT_TYPE));
+        assert!(!response.headers().contains_key(header::CONTENT_LENGTH));
+        assert_eq!(response.body().to_bytes().len(), 0);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn compressed_range() -> tsukuyomi_server::Result<()> {
+    let body = "Hello, Tsukuyomi. ".repeat(64);
+
+    let app = App::create(chain![
+        path!("/full") //
+            .to(endpoint::get().call({
+                let body = body.clone();
+                move || {
+                    Compressed::new(
+                        http::Response::builder()
+                            .header(header::ACCEPT_RANGES, "bytes")
+                            .body(body.clone())
+                            .unwrap(),
+                    )
+                }
+            })),
+        path!("/partial") //
+            .to(endpoint::get().call({
+                let body = body.clone();
+                move || {
+                    Compressed::new(
+                        http::Response::builder()
+                            .status(206)
+                            .header(header::ACCEPT_RANGES, "bytes")
+                            .header(
+                                header::CONTENT_RANGE,
+                                format!("bytes 0-9/{}", body.len()).as_str(),
+                            )
+                            .body(body[0..10].to_owned())
+                            .unwrap(),
+                    )
+                }
+            })),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform(Request::get("/full").header(header::ACCEPT_ENCODING, "gzip"))?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.header(header::CONTENT_ENCODING)?, "gzip");
+    assert!(!response.headers().contains_key(header::ACCEPT_RANGES));
+
+    let response = server.perform(
+        Request::get("/partial")
+            .header(header::ACCEPT_ENCODING, "gzip")
+            .header(header::RANGE, "bytes=0-9"),
+    )?;
+    assert_eq!(response.status(), 206);
+    assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+    assert_eq!(response.header(header::ACCEPT_RANGES)?, "bytes");
+    assert_eq!(
+        response.header(header::CONTENT_RANGE)?,
+        format!("bytes 0-9/{}", body.len()).as_str()
+    );
+    assert_eq!(response.body().to_utf8()?, &body[0..10]);
+
+    Ok(())
+}
+
+#[test]
+fn json() -> tsukuyomi_server::Result<()> {
+    let app = App::create(chain![
+        path!("/value") //
+            .to(endpoint::get().call(|| serde_json::json!({ "name": "alice" }))),
+        path!("/created") //
+            .to(endpoint::post().call(|| {
+                output::json(serde_json::json!({ "id": 42 })).status(http::StatusCode::CREATED)
+            })),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/value")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.header(header::CONTENT_TYPE)?, "application/json");
+    assert_eq!(response.body().to_utf8()?, r#"{"name":"alice"}"#);
+
+    let response = server.perform(Request::post("/created"))?;
+    assert_eq!(response.status(), 201);
+    assert_eq!(response.header(header::CONTENT_TYPE)?, "application/json");
+    assert_eq!(response.body().to_utf8()?, r#"{"id":42}"#);
+
+    Ok(())
+}
+
+#[test]
+fn response_ext_json_and_header_typed() -> tsukuyomi_server::Result<()> {
+    #[derive(Debug, serde::Deserialize, PartialEq)]
+    struct User {
+        name: String,
+    }
+
+    let app = App::create(chain![
+        path!("/json") //
+            .to(endpoint::get().call(|| serde_json::json!({ "name": "alice" }))),
+        path!("/text") //
+            .to(endpoint::get().reply("alice")),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/json")?;
+    assert_eq!(
+        response.json::<User>()?,
+        User {
+            name: "alice".into()
+        }
+    );
+    assert_eq!(
+        response.header_typed::<mime::Mime, _>(header::CONTENT_TYPE)?,
+        mime::APPLICATION_JSON
+    );
+
+    let response = server.perform("/text")?;
+    assert!(response.json::<User>().is_err());
+    assert!(response
+        .header_typed::<u64, _>(header::CONTENT_TYPE)
+        .is_err());
+    assert!(response.header_typed::<u64, _>(header::LOCATION).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn with_status() -> tsukuyomi_server::Result<()> {
+    use tsukuyomi::output::{IntoResponse, IntoResponseExt, WithStatus};
+
+    #[derive(serde::Serialize, IntoResponse)]
+    #[response(preset = "tsukuyomi::output::preset::Json")]
+    struct User {
+        name: String,
+    }
+
+    let app = App::create(chain![
+        path!("/users") //
+            .to(endpoint::post().call(|| {
+                User {
+                    name: "alice".into(),
+                }
+                .with_status(http::StatusCode::CREATED)
+            })),
+        path!("/accepted") //
+            .to(endpoint::post().call(|| {
+                WithStatus(
+                    http::StatusCode::ACCEPTED,
+                    http::Response::builder()
+                        .header("x-custom", "foo")
+                        .body("accepted")
+                        .unwrap(),
+                )
+            })),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform(Request::post("/users"))?;
+    assert_eq!(response.status(), 201);
+    assert_eq!(response.header(header::CONTENT_TYPE)?, "application/json");
+    assert_eq!(response.body().to_utf8()?, r#"{"name":"alice"}"#);
+
+    let response = server.perform(Request::post("/accepted"))?;
+    assert_eq!(response.status(), 202);
+    assert_eq!(response.header("x-custom")?, "foo");
+    assert_eq!(response.body().to_utf8()?, "accepted");
+
+    Ok(())
+}
+
+#[test]
+fn stream() -> tsukuyomi_server::Result<()> {
+    let app = App::create(
+        path!("/") //
+            .to(endpoint::get().call(|| {
+                let chunks = vec!["hello, ", "streaming ", "world"];
+                output::stream(futures01::stream::iter_ok::<_, std::io::Error>(chunks))
+                    .content_type("text/plain; charset=utf-8")
+                    .unwrap()
+            })),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.header(header::CONTENT_TYPE)?,
+        "text/plain; charset=utf-8"
+    );
+    assert!(!response.headers().contains_key(header::CONTENT_LENGTH));
+    assert_eq!(response.body().chunks().len(), 3);
+    assert_eq!(response.body().to_utf8()?, "hello, streaming world");
+
+    Ok(())
+}
+
+#[test]
+fn stream_error() -> tsukuyomi_server::Result<()> {
+    let app = App::create(
+        path!("/") //
+            .to(endpoint::get().call(|| {
+                output::stream(futures01::stream::iter_result(vec![
+                    Ok("hello"),
+                    Err(std::io::Error::new(std::io::ErrorKind::Other, "read error")),
+                ]))
+            })),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    // the client observes the error rather than the truncated body.
+    assert!(server.perform("/").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn stream_error_aborts_connection() -> tsukuyomi_server::Result<()> {
+    use {
+        futures01::{sync::oneshot, Future},
+        std::{
+            io::Write,
+            net::{TcpListener, TcpStream},
+        },
+        tsukuyomi_server::Server,
+    };
+
+    super::install_logger();
+
+    let app = App::create(
+        path!("/") //
+            .to(endpoint::get().call(|| {
+                output::stream(futures01::stream::iter_result(vec![
+                    Ok("hello"),
+                    Err(std::io::Error::new(std::io::ErrorKind::Other, "read error")),
+                ]))
+            })),
+    )?;
+
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+    let (tx_shutdown, rx_shutdown) = oneshot::channel::<()>();
+    let server = std::thread::spawn(move || {
+        Server::new(app)
+            .bind(listener)
+            .run_until(rx_shutdown.map_err(|_| ()))
+    });
+
+    let mut stream = TcpStream::connect(addr)?;
+    stream.write_all(
+        b"GET / HTTP/1.1\r\n\
+          Host: localhost\r\n\
+          \r\n",
+    )?;
+
+    // read until the connection is closed by the server (or reset).
+    let mut response = Vec::new();
+    let mut buf = [0u8; 1024];
+    loop {
+        match stream.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => response.extend_from_slice(&buf[..n]),
+            Err(ref err) if err.kind() == std::io::ErrorKind::ConnectionReset => break,
+            Err(err) => return Err(err.into()),
+        }
+    }
+    let response = String::from_utf8_lossy(&response);
+
+    // The connection is closed without sending the last chunk, so the client can
+    // distinguish the aborted response from a complete one. Note that the parts of
+    // response buffered before the error may not be flushed.
+    assert!(
+        response.is_empty() || response.starts_with("HTTP/1.1 200 OK\r\n"),
+        "{}",
+        response
+    );
+    assert!(!response.contains("\r\n0\r\n\r\n"), "{}", response);
+
+    let _ = tx_shutdown.send(());
+    server.join().expect("the server thread panicked")?;
+
+    // the error is logged.
+    let logs = super::captured_logs();
+    assert!(
+        logs.iter().any(
+            |message| message == "aborting the response body due to a stream error: read error"
+        ),
+        "{:?}",
+        logs
+    );
+
+    Ok(())
+}
+
+#[test]
+fn server_sent_events() -> tsukuyomi_server::Result<()> {
+    use tsukuyomi::output::sse::{Event, EventStream};
+
+    let app = App::create(
+        path!("/events") //
+            .to(endpoint::get().call(|| {
+                let events = vec![
+                    Event::data("hello"),
+                    Event::data("multi\nline")
+                        .event("update")
+                        .id("2")
+                        .retry(std::time::Duration::from_secs(3)),
+                ];
+                EventStream::new(futures01::stream::iter_ok::<_, std::io::Error>(events))
+            })),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/events")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.header(header::CONTENT_TYPE)?, "text/event-stream");
+    assert_eq!(response.header(header::CACHE_CONTROL)?, "no-cache");
+    assert_eq!(
+        response.body().to_utf8()?,
+        "data: hello\n\nevent: update\nid: 2\nretry: 3000\ndata: multi\ndata: line\n\n"
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "rmp-serde")]
+#[test]
+fn msgpack_preset() -> tsukuyomi_server::Result<()> {
+    use tsukuyomi::output::IntoResponse;
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize, IntoResponse)]
+    #[response(preset = "tsukuyomi::output::preset::MsgPack")]
+    struct User {
+        id: u32,
+        name: String,
+    }
+
+    let app = App::create(
+        path!("/user") //
+            .to(endpoint::get().call(|| User {
+                id: 42,
+                name: "alice".into(),
+            })),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/user")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.header(header::CONTENT_TYPE)?,
+        "application/msgpack"
+    );
+    let user: User = rmp_serde::from_slice(&*response.body().to_bytes())?;
+    assert_eq!(
+        user,
+        User {
+            id: 42,
+            name: "alice".into(),
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn negotiated_preset() -> tsukuyomi_server::Result<()> {
+    use tsukuyomi::output::IntoResponse;
+
+    #[derive(serde::Serialize, IntoResponse)]
+    #[response(preset = "tsukuyomi::output::preset::Negotiated")]
+    struct User {
+        name: String,
+    }
+
+    let app = App::create(
+        path!("/user") //
+            .to(endpoint::get().call(|| User {
+                name: "alice".into(),
+            })),
+    )?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/user")?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.header(header::CONTENT_TYPE)?, "application/json");
+    assert_eq!(response.body().to_utf8()?, r#"{"name":"alice"}"#);
+
+    let response = server
+        .perform(Request::get("/user").header(header::ACCEPT, "text/html, application/*;q=0.8"))?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.header(header::CONTENT_TYPE)?, "application/json");
+
+    let response = server.perform(Request::get("/user").header(header::ACCEPT, "text/html"))?;
+    assert_eq!(response.status(), 406);
+
+    let response = server
+        .perform(Request::get("/user").header(header::ACCEPT, "*/*, application/json;q=0"))?;
+    #[cfg(not(feature = "rmp-serde"))]
+    assert_eq!(response.status(), 406);
+    #[cfg(feature = "rmp-serde")]
+    assert_eq!(
+        response.header(header::CONTENT_TYPE)?,
+        "application/msgpack"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn redirect_to_route() -> tsukuyomi_server::Result<()> {
+    use tsukuyomi::output::redirect;
+
+    let app = App::create(chain![
+        path!("/old/:name") //
+            .to(endpoint::get().call(|name: String| {
+                redirect::to_route("/users/:name/posts", &[name])
+                    .map(|redirect| redirect.append_query("page=1"))
+            })),
+        path!("/broken") //
+            .to(endpoint::get().call(|| redirect::to_route("/users/:name/posts", &[] as &[&str]))),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let response = server.perform("/old/alice%20smith")?;
+    assert_eq!(response.status(), 301);
+    assert_eq!(
+        response.header(header::LOCATION)?,
+        "/users/alice%20smith/posts?page=1"
+    );
+
+    let response = server.perform("/broken")?;
+    assert_eq!(response.status(), 500);
+
+    Ok(())
+}
+
+#[test]
+fn perform_follow_redirects() -> tsukuyomi_server::Result<()> {
+    use tsukuyomi::output::redirect;
+
+    let app = App::create(chain![
+        path!("/") //
+            .to(endpoint::get().reply(redirect::to("/index.html"))),
+        path!("/index.html") //
+            .to(endpoint::get().reply("index")),
+        path!("/form") //
+            .to(endpoint::post().reply(redirect::see_other("static/done.html"))),
+        path!("/static/done.html") //
+            .to(endpoint::get().reply("done")),
+        path!("/loop") //
+            .to(endpoint::get().reply(redirect::found("/loop"))),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let (response, statuses) = server.perform_follow("/", 5)?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "index");
+    assert_eq!(statuses, vec![http::StatusCode::MOVED_PERMANENTLY]);
+
+    let (response, statuses) = server.perform_follow(Request::post("/form").body("data"), 5)?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().to_utf8()?, "done");
+    assert_eq!(statuses, vec![http::StatusCode::SEE_OTHER]);
+
+    let (response, statuses) = server.perform_follow("/index.html", 5)?;
+    assert_eq!(response.status(), 200);
+    assert!(statuses.is_empty());
+
+    assert!(server.perform_follow("/loop", 5).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn redirect_try_new() {
+    use tsukuyomi::output::redirect::Redirect;
+
+    assert!(Redirect::try_new(http::StatusCode::SEE_OTHER, "/").is_ok());
+    assert!(Redirect::try_new(http::StatusCode::OK, "/").is_err());
+    assert!(Redirect::try_new(http::StatusCode::NOT_FOUND, "/").is_err());
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "the status code must be a redirection")]
+fn redirect_new_with_non_redirection() {
+    let _ = tsukuyomi::output::redirect::Redirect::new(http::StatusCode::OK, "/");
+}
@@ -0,0 +1,149 @@
+//! The tests for the cleanup of temporary files created during the extraction.
+//!
+//! These tests are placed in the separate binary, in order to observe the temporary
+//! files created by this process without interference from the other tests.
+
+use {
+    futures01::{sync::oneshot, Future, Stream},
+    http::Request,
+    std::{fs, io, path::PathBuf, thread, time::Duration},
+    tsukuyomi::{
+        config::prelude::*, //
+        extractor::{
+            self,
+            body::{Multipart, MultipartConfig, TempFile},
+        },
+        App,
+    },
+    tsukuyomi_server::test::BodyStream,
+};
+
+fn temp_files() -> io::Result<Vec<PathBuf>> {
+    let prefix = format!("tsukuyomi-tmp-{}-", std::process::id());
+    let mut files = vec![];
+    for entry in fs::read_dir(std::env::temp_dir())? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with(&prefix) {
+            files.push(entry.path());
+        }
+    }
+    Ok(files)
+}
+
+/// Creates a request body that transfers the specified chunks, and then fails
+/// after the temporary file is observed, as if the client disconnected.
+///
+/// The returned handle resolves to the temporary files observed in the middle
+/// of the transfer.
+fn body_disconnected_after_spooling(
+    chunks: Vec<&'static str>,
+) -> (
+    BodyStream<impl Stream<Item = &'static str, Error = io::Error> + Send + 'static>,
+    thread::JoinHandle<io::Result<Vec<PathBuf>>>,
+) {
+    let (tx, rx) = oneshot::channel::<()>();
+    let body = tsukuyomi_server::test::body_stream(
+        futures01::stream::iter_ok(chunks).chain(
+            rx.then(|_| {
+                Err::<&'static str, _>(io::Error::new(
+                    io::ErrorKind::ConnectionAborted,
+                    "the client has disconnected",
+                ))
+            })
+            .into_stream(),
+        ),
+    );
+    let observer = thread::spawn(move || {
+        let mut files = vec![];
+        for _ in 0..500 {
+            files = temp_files()?;
+            if !files.is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = tx.send(());
+        Ok(files)
+    });
+    (body, observer)
+}
+
+const MULTIPART_CHUNKS: &[&str] = &[
+    "--boundary\r\n\
+     Content-Disposition: form-data; name=\"large\"; filename=\"a.txt\"\r\n\
+     \r\n",
+    "the content written out to a file",
+];
+
+#[test]
+fn remove_temp_files_on_disconnect() -> tsukuyomi_server::Result<()> {
+    let config = MultipartConfig {
+        spool_threshold: Some(8),
+        ..Default::default()
+    };
+    let keep_config = MultipartConfig {
+        keep_partial_files: true,
+        ..config.clone()
+    };
+    let app = App::create(chain![
+        path!("/temp_file") //
+            .to(endpoint::post()
+                .extract(extractor::body::temp_file())
+                .call(|_file: TempFile| "unreachable")),
+        path!("/multipart") //
+            .to(endpoint::post()
+                .extract(extractor::body::multipart_with_config(config))
+                .call_async(|multipart: Multipart| {
+                    multipart
+                        .and_then(|field| field.spool())
+                        .collect()
+                        .map(|_| "unreachable")
+                })),
+        path!("/multipart_keep") //
+            .to(endpoint::post()
+                .extract(extractor::body::multipart_with_config(keep_config))
+                .call_async(|multipart: Multipart| {
+                    multipart
+                        .and_then(|field| field.spool())
+                        .collect()
+                        .map(|_| "unreachable")
+                })),
+    ])?;
+    let mut server = tsukuyomi_server::test::server(app)?;
+
+    let (body, observer) =
+        body_disconnected_after_spooling(vec!["the content ", "written out to a file"]);
+    let response = server.perform(Request::post("/temp_file").body(body))?;
+    assert_ne!(response.status(), 200);
+    assert_eq!(observer.join().expect("the observer panicked")?.len(), 1);
+    assert_eq!(temp_files()?, Vec::<PathBuf>::new());
+
+    let (body, observer) = body_disconnected_after_spooling(MULTIPART_CHUNKS.to_vec());
+    let response = server.perform(
+        Request::post("/multipart")
+            .header("content-type", "multipart/form-data; boundary=boundary")
+            .body(body),
+    )?;
+    assert_ne!(response.status(), 200);
+    assert_eq!(observer.join().expect("the observer panicked")?.len(), 1);
+    assert_eq!(temp_files()?, Vec::<PathBuf>::new());
+
+    // the partial file is left behind if configured.
+    let (body, observer) = body_disconnected_after_spooling(MULTIPART_CHUNKS.to_vec());
+    let response = server.perform(
+        Request::post("/multipart_keep")
+            .header("content-type", "multipart/form-data; boundary=boundary")
+            .body(body),
+    )?;
+    assert_ne!(response.status(), 200);
+    let spooled = observer.join().expect("the observer panicked")?;
+    assert_eq!(spooled.len(), 1);
+    assert_eq!(temp_files()?, spooled);
+    // the tail of content is not written yet, since it may be a part of the delimiter.
+    let content = fs::read_to_string(&spooled[0])?;
+    assert!(!content.is_empty());
+    assert!("the content written out to a file".starts_with(&*content));
+    fs::remove_file(&spooled[0])?;
+
+    Ok(())
+}
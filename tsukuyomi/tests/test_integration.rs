@@ -48,3 +48,35 @@ fn test_current_thread() -> tsukuyomi_server::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_current_thread_shared_state() -> tsukuyomi_server::Result<()> {
+    use {
+        std::{cell::RefCell, rc::Rc},
+        tsukuyomi::{app::LocalApp, config::prelude::*},
+    };
+
+    let visited = Rc::new(RefCell::new(vec![]));
+
+    let app = LocalApp::create(
+        path!("/:name") //
+            .to(endpoint::get().call({
+                let visited = visited.clone();
+                move |name: String| {
+                    visited.borrow_mut().push(name.clone());
+                    format!("Hello, {}", name)
+                }
+            })),
+    )?;
+
+    let mut server = tsukuyomi_server::test::local_server(app)?;
+
+    let response = server.perform("/alice")?;
+    assert_eq!(response.body().to_utf8()?, "Hello, alice");
+    let response = server.perform("/bob")?;
+    assert_eq!(response.body().to_utf8()?, "Hello, bob");
+
+    assert_eq!(*visited.borrow(), vec!["alice", "bob"]);
+
+    Ok(())
+}